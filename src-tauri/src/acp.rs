@@ -0,0 +1,1017 @@
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::timeout;
+use uuid::Uuid;
+
+use crate::acp_client::{client_capabilities, AcpClient, ClientApproval};
+use crate::acp_messages::{MessageFolder, MESSAGE_COMPLETED_METHOD, MESSAGE_DELTA_METHOD};
+use crate::approvals::{auto_decide_subject, AUTO_APPROVAL_METHOD};
+use crate::attachments::{acp_prompt, resolve_attachments, PromptCapabilities};
+use crate::audit::{audit_session_ended, record_audit, AuditActor, AuditEntry};
+use crate::backend::{
+    get_backend_as, AgentBackend, AgentEvent, BackendFuture, BackendHealth, MessageRequest,
+    SharedHost,
+};
+use crate::codex::build_codex_path_env;
+use crate::opencode::{opencode_acp_command, run_models, run_session_list};
+use crate::prompt_queue::turn_finished;
+use crate::protocol::ApprovalDecision;
+use crate::rpc::{check_response, JsonRpcClient, RpcError};
+use crate::sandbox::sandbox_for_access_mode;
+use crate::state::AppState;
+use crate::transcripts::{record_transcript, TranscriptSource};
+use crate::types::{
+    AcpAgentConfig, AppSettings, BackendType, OpenCodePromptHandle, OpenCodeSessionInfo,
+    WorkspaceEntry,
+};
+use crate::usage::{record_usage, UsageRecord};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(120);
+/// A prompt fails only after the agent has been silent this long; turns that
+/// keep streaming updates, or wait on the user, may run for as long as they need.
+const PROMPT_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+const PROMPT_IDLE_CHECK: Duration = Duration::from_secs(15);
+
+/// A stdio JSON-RPC connection to an Agent Client Protocol agent: OpenCode's
+/// `opencode acp`, or any ACP-compatible CLI configured on the workspace.
+pub(crate) struct AcpSession {
+    pub(crate) entry: WorkspaceEntry,
+    pub(crate) backend: BackendType,
+    pub(crate) label: String,
+    pub(crate) child: Mutex<Child>,
+    pub(crate) rpc: JsonRpcClient,
+    pub(crate) stdout_reader: Mutex<BufReader<ChildStdout>>,
+    host: SharedHost,
+    this: Weak<AcpSession>,
+    /// When the agent last wrote to stdout; drives the prompt idle timeout.
+    last_activity: Mutex<Instant>,
+    client: AcpClient,
+    messages: Mutex<MessageFolder>,
+    /// `session/request_permission` requests, and client writes and commands,
+    /// awaiting the user's decision.
+    pending_permissions: Mutex<HashMap<u64, PendingPermission>>,
+    /// What the agent accepts besides text, from `initialize`.
+    prompt_capabilities: Mutex<PromptCapabilities>,
+    /// The model `session/new` reported for each session, which prompts
+    /// without a `modelId` run on.
+    session_models: Mutex<HashMap<String, String>>,
+}
+
+struct PendingPermission {
+    session_id: String,
+    options: Vec<Value>,
+    /// Set for our own prompts about `fs/*` and `terminal/*` requests, which
+    /// get the decision instead of the agent.
+    decided: Option<oneshot::Sender<bool>>,
+}
+
+impl AcpSession {
+    /// Sends a request and returns its `result`.
+    pub(crate) async fn request(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        self.request_with_timeout(method, params, REQUEST_TIMEOUT)
+            .await
+    }
+
+    async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Value,
+        request_timeout: Duration,
+    ) -> Result<Value, RpcError> {
+        let response = self
+            .rpc
+            .request(method, params, Some(request_timeout))
+            .await?;
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Waits for a `session/prompt` result for as long as the agent keeps
+    /// talking, cancelling the turn once it has been idle too long. The idle
+    /// clock stands still while a permission prompt for the session is open.
+    async fn await_prompt(
+        &self,
+        id: u64,
+        session_id: &str,
+        mut rx: oneshot::Receiver<Result<Value, RpcError>>,
+    ) -> Result<Value, RpcError> {
+        loop {
+            match timeout(PROMPT_IDLE_CHECK, &mut rx).await {
+                Ok(Ok(response)) => {
+                    let response = check_response(response?)?;
+                    return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+                }
+                Ok(Err(_)) => {
+                    return Err(RpcError::Disconnected {
+                        message: format!("{} process ended", self.label),
+                    })
+                }
+                Err(_) => {
+                    if self.awaiting_user(session_id).await {
+                        *self.last_activity.lock().await = Instant::now();
+                        continue;
+                    }
+                    if self.last_activity.lock().await.elapsed() < PROMPT_IDLE_TIMEOUT {
+                        continue;
+                    }
+                    self.rpc.pending().remove(id).await;
+                    return Err(RpcError::Timeout {
+                        method: "session/prompt".to_string(),
+                        id,
+                        after_ms: PROMPT_IDLE_TIMEOUT.as_millis() as u64,
+                    });
+                }
+            }
+        }
+    }
+
+    async fn awaiting_user(&self, session_id: &str) -> bool {
+        self.pending_permissions
+            .lock()
+            .await
+            .values()
+            .any(|permission| permission.session_id == session_id)
+    }
+
+    /// Emits the completion event for a prompt started by `send_message`.
+    async fn finish_prompt(
+        &self,
+        prompt_id: String,
+        session_id: String,
+        model: Option<String>,
+        result: Result<Value, RpcError>,
+    ) {
+        let (stop_reason, error) = match result {
+            Ok(result) => {
+                let usage = UsageRecord::from_acp_result(
+                    &self.entry.id,
+                    self.backend.clone(),
+                    &session_id,
+                    model.as_deref(),
+                    &result,
+                );
+                if let Some(record) = usage {
+                    record_usage(self.host.as_ref(), record).await;
+                }
+                (
+                    result
+                        .get("stopReason")
+                        .and_then(|value| value.as_str())
+                        .map(|value| value.to_string()),
+                    None,
+                )
+            }
+            Err(error) => {
+                if matches!(error, RpcError::Timeout { .. }) {
+                    let _ = self
+                        .rpc
+                        .notify("session/cancel", Some(json!({ "sessionId": session_id })))
+                        .await;
+                }
+                (None, Some(error.to_string()))
+            }
+        };
+        let message = self
+            .messages
+            .lock()
+            .await
+            .finish(&session_id, stop_reason.clone());
+        self.host.emit(AgentEvent::new(
+            &self.entry.id,
+            self.backend.clone(),
+            MESSAGE_COMPLETED_METHOD,
+            json!({
+                "promptId": prompt_id,
+                "sessionId": session_id,
+                "stopReason": stop_reason,
+                "error": error,
+                "message": message
+            }),
+        ));
+        turn_finished(&self.host, &self.entry.id, &session_id).await;
+    }
+
+    /// Runs an `fs/*` or `terminal/*` request from the agent and sends the reply.
+    async fn answer_client_request(&self, id: u64, method: &str, params: Value) {
+        let result = match self.approve_client_request(id, method, &params).await {
+            Ok(()) => self.client.handle(method, params).await,
+            Err(error) => Err(error),
+        };
+        let sent = match result {
+            Ok(result) => self.rpc.respond(id, result).await,
+            Err(RpcError::Server { code, message, .. }) => {
+                self.rpc.respond_error(id, code, &message).await
+            }
+            Err(error) => self.rpc.respond_error(id, -32603, &error.to_string()).await,
+        };
+        if let Err(error) = sent {
+            eprintln!("Failed to answer {method}: {error}");
+        }
+    }
+
+    /// Puts writes and commands through the workspace's approval rules and
+    /// sandbox like Codex approvals, asking the user when neither decides.
+    async fn approve_client_request(
+        &self,
+        id: u64,
+        method: &str,
+        params: &Value,
+    ) -> Result<(), RpcError> {
+        let Some(approval) = self.client.approval(method, params).await? else {
+            return Ok(());
+        };
+        let state = self.host.app_state();
+        let workspace_id = &self.entry.id;
+        record_audit(
+            state,
+            AuditEntry::request(workspace_id, self.backend.clone(), id, method, Some(params)),
+        )
+        .await;
+        let rule = auto_decide_subject(
+            state,
+            workspace_id,
+            &approval.subject,
+            approval.exclusion_rules.clone(),
+        )
+        .await;
+        let allowed = match rule {
+            Some(decision) => {
+                let entry = decision.log_entry(id, method, Some(params));
+                record_transcript(
+                    state,
+                    workspace_id,
+                    TranscriptSource::Client,
+                    AUTO_APPROVAL_METHOD,
+                    Some(&entry),
+                )
+                .await;
+                record_audit(
+                    state,
+                    AuditEntry::response(
+                        workspace_id,
+                        self.backend.clone(),
+                        id,
+                        &decision.response(),
+                        AuditActor::Rule,
+                    ),
+                )
+                .await;
+                self.host.emit(AgentEvent::new(
+                    workspace_id,
+                    self.backend.clone(),
+                    AUTO_APPROVAL_METHOD,
+                    entry,
+                ));
+                decision.decision == ApprovalDecision::Accept
+            }
+            None if approval.trusted => {
+                record_audit(
+                    state,
+                    AuditEntry::response(
+                        workspace_id,
+                        self.backend.clone(),
+                        id,
+                        &json!({ "decision": "accept" }),
+                        AuditActor::Policy,
+                    ),
+                )
+                .await;
+                true
+            }
+            None => self.ask_permission(id, params, &approval).await,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(RpcError::Server {
+                code: -32603,
+                message: format!("{} was declined", approval.title),
+                data: None,
+            })
+        }
+    }
+
+    /// Shows a client request to the user as a permission prompt and waits for
+    /// the answer, which arrives through `respond`.
+    async fn ask_permission(&self, id: u64, params: &Value, approval: &ClientApproval) -> bool {
+        let session_id = approval.session_id.clone().unwrap_or_default();
+        let options = vec![
+            json!({ "optionId": "allow", "kind": "allow_once", "name": "Allow" }),
+            json!({ "optionId": "reject", "kind": "reject_once", "name": "Reject" }),
+        ];
+        let request = json!({
+            "sessionId": session_id,
+            "toolCall": {
+                "toolCallId": format!("client_{id}"),
+                "title": approval.title,
+                "kind": approval.kind,
+                "rawInput": params
+            },
+            "options": options
+        });
+        let (decided, rx) = oneshot::channel();
+        self.pending_permissions.lock().await.insert(
+            id,
+            PendingPermission {
+                session_id,
+                options,
+                decided: Some(decided),
+            },
+        );
+        let state = self.host.app_state();
+        record_transcript(
+            state,
+            &self.entry.id,
+            TranscriptSource::Server,
+            "session/request_permission",
+            Some(&request),
+        )
+        .await;
+        self.host.emit(AgentEvent {
+            id: Some(id),
+            ..AgentEvent::new(
+                &self.entry.id,
+                self.backend.clone(),
+                "session/request_permission",
+                request,
+            )
+        });
+        // Dropped (interrupt, shutdown) counts as declined.
+        rx.await.unwrap_or(false)
+    }
+}
+
+/// Whether a `permission_outcome` picked one of the `allow_*` options.
+fn permission_allows(options: &[Value], outcome: &Value) -> bool {
+    let Some(option_id) = outcome
+        .get("outcome")
+        .and_then(|outcome| outcome.get("optionId"))
+    else {
+        return false;
+    };
+    options
+        .iter()
+        .find(|option| option.get("optionId") == Some(option_id))
+        .and_then(|option| option.get("kind"))
+        .and_then(|kind| kind.as_str())
+        .is_some_and(|kind| kind.starts_with("allow"))
+}
+
+/// Maps a UI decision (`{ "decision": "accept" }`, as for Codex approvals) onto
+/// one of the agent's permission options. A full ACP `outcome` passes through.
+fn permission_outcome(options: &[Value], result: &Value) -> Value {
+    if result.get("outcome").is_some() {
+        return result.clone();
+    }
+    let preferred: &[&str] = match result.get("decision").and_then(|value| value.as_str()) {
+        Some("accept") => &["allow_once", "allow_always"],
+        Some("acceptForSession") => &["allow_always", "allow_once"],
+        Some("decline") => &["reject_once", "reject_always"],
+        _ => &[],
+    };
+    let selected = preferred.iter().find_map(|kind| {
+        options
+            .iter()
+            .find(|option| option.get("kind").and_then(|value| value.as_str()) == Some(kind))
+            .and_then(|option| option.get("optionId").cloned())
+    });
+    match selected {
+        Some(option_id) => json!({ "outcome": { "outcome": "selected", "optionId": option_id } }),
+        None => json!({ "outcome": { "outcome": "cancelled" } }),
+    }
+}
+
+impl AgentBackend for AcpSession {
+    fn connect(
+        entry: WorkspaceEntry,
+        settings: AppSettings,
+        host: SharedHost,
+    ) -> BackendFuture<'static, Arc<Self>> {
+        Box::pin(async move {
+            let backend = entry.backend.clone();
+            Ok(spawn_acp_session(entry, backend, &settings, host).await?)
+        })
+    }
+
+    fn kind(&self) -> BackendType {
+        self.backend.clone()
+    }
+
+    fn start_thread(&self) -> BackendFuture<'_, Value> {
+        Box::pin(async move {
+            let result = self
+                .request(
+                    "session/new",
+                    json!({
+                        "cwd": self.entry.path.clone(),
+                        "mcpServers": []
+                    }),
+                )
+                .await?;
+
+            #[derive(serde::Deserialize)]
+            struct SessionModels {
+                #[serde(rename = "currentModelId")]
+                current_model_id: String,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct NewSessionResult {
+                #[serde(rename = "sessionId")]
+                session_id: String,
+                #[serde(default)]
+                models: Option<SessionModels>,
+            }
+
+            let new_session: NewSessionResult = serde_json::from_value(result)
+                .map_err(|e| format!("Failed to parse session/new result: {e}"))?;
+            if let Some(models) = new_session.models {
+                self.session_models
+                    .lock()
+                    .await
+                    .insert(new_session.session_id.clone(), models.current_model_id);
+            }
+
+            serde_json::to_value(OpenCodeSessionInfo {
+                id: new_session.session_id,
+                title: Some("New Session".to_string()),
+                created_at: None,
+                updated_at: None,
+            })
+            .map_err(|e| e.to_string().into())
+        })
+    }
+
+    fn send_message<'a>(
+        &'a self,
+        state: &'a AppState,
+        request: MessageRequest,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            // The agent's `fs/*` and `terminal/*` requests during this turn
+            // are held to the same sandbox profile a Codex turn would use.
+            let access_mode = request
+                .access_mode
+                .clone()
+                .unwrap_or_else(|| "current".to_string());
+            let app_settings = state.app_settings.lock().await.clone();
+            let workspace_settings = state
+                .workspaces
+                .lock()
+                .await
+                .get(&self.entry.id)
+                .map(|entry| entry.settings.clone())
+                .unwrap_or_default();
+            let sandbox = sandbox_for_access_mode(
+                &app_settings,
+                &workspace_settings,
+                &access_mode,
+                &self.entry.path,
+            )?;
+            let attachments = resolve_attachments(&self.entry.path, &request.attachments).await?;
+            let capabilities = *self.prompt_capabilities.lock().await;
+            let mut params = json!({
+                "sessionId": request.thread_id,
+                "prompt": acp_prompt(request.text, &attachments, capabilities)?
+            });
+
+            if let Some(model) = &request.model {
+                params["modelId"] = json!(model);
+            }
+
+            record_transcript(
+                state,
+                &self.entry.id,
+                TranscriptSource::Client,
+                "session/prompt",
+                Some(&params),
+            )
+            .await;
+            let session = self
+                .this
+                .upgrade()
+                .ok_or_else(|| format!("{} session is shutting down", self.label))?;
+            self.client.set_sandbox(&request.thread_id, sandbox).await;
+            let (id, rx) = self.rpc.start_request("session/prompt", params).await?;
+            state
+                .prompt_queue
+                .lock()
+                .await
+                .turn_started(&self.entry.id, &request.thread_id);
+            let handle = OpenCodePromptHandle {
+                prompt_id: format!("prompt_{}", Uuid::new_v4()),
+                session_id: request.thread_id,
+            };
+            // The turn can take minutes; answer now and report completion as an event.
+            let prompt_id = handle.prompt_id.clone();
+            let session_id = handle.session_id.clone();
+            let model = match request.model {
+                Some(model) => Some(model),
+                None => self.session_models.lock().await.get(&session_id).cloned(),
+            };
+            tokio::spawn(async move {
+                let result = session.await_prompt(id, &session_id, rx).await;
+                session
+                    .finish_prompt(prompt_id, session_id, model, result)
+                    .await;
+            });
+            serde_json::to_value(handle).map_err(|e| e.to_string().into())
+        })
+    }
+
+    fn interrupt<'a>(
+        &'a self,
+        thread_id: &'a str,
+        _turn_id: Option<&'a str>,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            // `session/cancel` is a notification; the prompt then completes
+            // with the `cancelled` stop reason.
+            self.rpc
+                .notify("session/cancel", Some(json!({ "sessionId": thread_id })))
+                .await?;
+            // ACP requires outstanding permission prompts of a cancelled turn
+            // to be answered with `cancelled`.
+            // Our own prompts are declined by dropping their sender.
+            let cancelled: Vec<u64> = {
+                let mut pending = self.pending_permissions.lock().await;
+                let ids: Vec<u64> = pending
+                    .iter()
+                    .filter(|(_, permission)| permission.session_id == thread_id)
+                    .map(|(id, _)| *id)
+                    .collect();
+                ids.into_iter()
+                    .filter(|id| {
+                        pending
+                            .remove(id)
+                            .is_some_and(|permission| permission.decided.is_none())
+                    })
+                    .collect()
+            };
+            for id in cancelled {
+                let _ = self
+                    .rpc
+                    .respond(id, json!({ "outcome": { "outcome": "cancelled" } }))
+                    .await;
+            }
+            Ok(json!({}))
+        })
+    }
+
+    // ACP has no session or model listing; OpenCode exposes both through its CLI.
+    fn list_threads<'a>(
+        &'a self,
+        state: &'a AppState,
+        _cursor: Option<String>,
+        _limit: Option<u32>,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            if self.backend != BackendType::OpenCode {
+                return Ok(json!([]));
+            }
+            let sessions = run_session_list(&self.entry, state).await?;
+            serde_json::to_value(sessions).map_err(|e| e.to_string().into())
+        })
+    }
+
+    fn list_models<'a>(&'a self, state: &'a AppState) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            if self.backend != BackendType::OpenCode {
+                return Ok(json!([]));
+            }
+            let providers = run_models(&self.entry, state).await?;
+            serde_json::to_value(providers).map_err(|e| e.to_string().into())
+        })
+    }
+
+    fn respond<'a>(&'a self, request_id: u64, result: Value) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let permission = self.pending_permissions.lock().await.remove(&request_id);
+            let result = match permission {
+                Some(PendingPermission {
+                    options,
+                    decided: Some(decided),
+                    ..
+                }) => {
+                    let outcome = permission_outcome(&options, &result);
+                    let _ = decided.send(permission_allows(&options, &outcome));
+                    return Ok(());
+                }
+                Some(permission) => permission_outcome(&permission.options, &result),
+                None => result,
+            };
+            Ok(self.rpc.respond(request_id, result).await?)
+        })
+    }
+
+    fn shutdown(&self) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            self.client.release_all().await;
+            let mut child = self.child.lock().await;
+            let _ = child.kill().await;
+            Ok(())
+        })
+    }
+
+    fn health(&self) -> BackendFuture<'_, BackendHealth> {
+        Box::pin(async move {
+            let alive = matches!(self.child.lock().await.try_wait(), Ok(None));
+            Ok(BackendHealth {
+                backend: self.backend.clone(),
+                alive,
+                pending_requests: self.rpc.pending().snapshot().await.len(),
+                stderr_tail: Vec::new(),
+            })
+        })
+    }
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// The ACP agent that serves a workspace's sessions. ACP workspaces use their
+/// own backend; a Codex workspace's side agent is the configured ACP agent if
+/// there is one, OpenCode otherwise.
+pub(crate) fn acp_backend_for(entry: &WorkspaceEntry) -> BackendType {
+    match entry.backend {
+        BackendType::OpenCode | BackendType::Acp => entry.backend.clone(),
+        BackendType::Codex if entry.acp_agent.is_some() => BackendType::Acp,
+        BackendType::Codex => BackendType::OpenCode,
+    }
+}
+
+fn build_agent_command(config: &AcpAgentConfig) -> Result<Command, String> {
+    let program = config.command.trim();
+    if program.is_empty() {
+        return Err("The ACP agent command is empty.".to_string());
+    }
+    let mut command = Command::new(program);
+    command.args(&config.args);
+    if let Some(path_env) = build_codex_path_env(Some(program)) {
+        command.env("PATH", path_env);
+    }
+    Ok(command)
+}
+
+fn spawn_stdout_reader(session: Arc<AcpSession>, host: SharedHost) {
+    tokio::spawn(async move {
+        let workspace_id = session.entry.id.clone();
+        let mut reader = session.stdout_reader.lock().await;
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) => {
+                    break;
+                }
+                Ok(_) => {
+                    *session.last_activity.lock().await = Instant::now();
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+
+                    let Ok(msg) = serde_json::from_str::<Value>(trimmed) else {
+                        continue;
+                    };
+                    let id = msg.get("id").and_then(|v| v.as_u64());
+                    let Some(event) =
+                        AgentEvent::from_message(&workspace_id, session.backend.clone(), &msg)
+                    else {
+                        if let Some(id) = id {
+                            session.rpc.pending().resolve(id, msg).await;
+                        }
+                        continue;
+                    };
+                    record_transcript(
+                        host.app_state(),
+                        &workspace_id,
+                        TranscriptSource::Server,
+                        &event.method,
+                        event.params.as_ref(),
+                    )
+                    .await;
+                    match id {
+                        // Permission prompts go to the UI like Codex approvals.
+                        Some(id) if event.method == "session/request_permission" => {
+                            let params = event.params.clone().unwrap_or(Value::Null);
+                            let permission = PendingPermission {
+                                session_id: params
+                                    .get("sessionId")
+                                    .and_then(|value| value.as_str())
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                options: params
+                                    .get("options")
+                                    .and_then(|value| value.as_array())
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                decided: None,
+                            };
+                            session
+                                .pending_permissions
+                                .lock()
+                                .await
+                                .insert(id, permission);
+                            record_audit(
+                                host.app_state(),
+                                AuditEntry::request(
+                                    &workspace_id,
+                                    session.backend.clone(),
+                                    id,
+                                    &event.method,
+                                    event.params.as_ref(),
+                                ),
+                            )
+                            .await;
+                            host.emit(event);
+                        }
+                        // Client capabilities may block (`terminal/wait_for_exit`),
+                        // so each runs on its own task.
+                        Some(id) => {
+                            let session = session.clone();
+                            tokio::spawn(async move {
+                                let params = event.params.unwrap_or(Value::Null);
+                                session
+                                    .answer_client_request(id, &event.method, params)
+                                    .await;
+                            });
+                        }
+                        None => {
+                            let deltas = match (&event.method[..], &event.params) {
+                                ("session/update", Some(params)) => {
+                                    session.messages.lock().await.apply(params)
+                                }
+                                _ => Vec::new(),
+                            };
+                            host.emit(event);
+                            for delta in deltas {
+                                host.emit(AgentEvent::new(
+                                    &workspace_id,
+                                    session.backend.clone(),
+                                    MESSAGE_DELTA_METHOD,
+                                    serde_json::to_value(delta).unwrap_or(Value::Null),
+                                ));
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading stdout: {}", e);
+                    break;
+                }
+            }
+        }
+        drop(reader);
+        let reason = format!("{} process ended", session.label);
+        session.rpc.pending().fail_all(&reason).await;
+        session.client.release_all().await;
+        audit_session_ended(host.app_state(), &workspace_id, &session.backend).await;
+
+        // Forget the dead process so the next call spawns a fresh one.
+        host.app_state()
+            .sessions
+            .lock()
+            .await
+            .remove_if_current(&workspace_id, &session);
+        host.emit(AgentEvent::new(
+            &workspace_id,
+            session.backend.clone(),
+            "acp/disconnected",
+            json!({ "workspaceId": workspace_id, "error": reason }),
+        ));
+    });
+}
+
+fn spawn_stderr_reader(
+    stderr: ChildStderr,
+    host: SharedHost,
+    workspace_id: String,
+    backend: BackendType,
+) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stderr);
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) => {
+                    break;
+                }
+                Ok(_) => {
+                    let trimmed = line.trim_end();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    host.emit(AgentEvent::new(
+                        &workspace_id,
+                        backend.clone(),
+                        "acp/stderr",
+                        json!({ "message": trimmed }),
+                    ));
+                }
+                Err(_) => {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+async fn initialize_acp_session(session: &AcpSession) -> Result<(), String> {
+    let result = session
+        .request_with_timeout(
+            "initialize",
+            json!({
+                "protocolVersion": 1,
+                "clientInfo": {
+                    "name": "codex_monitor",
+                    "version": env!("CARGO_PKG_VERSION")
+                },
+                "clientCapabilities": client_capabilities()
+            }),
+            INITIALIZE_TIMEOUT,
+        )
+        .await
+        .map_err(|error| error.to_string())?;
+    *session.prompt_capabilities.lock().await = PromptCapabilities::from_initialize(&result);
+
+    Ok(())
+}
+
+/// Spawns the ACP agent serving `backend` in the workspace and performs the
+/// `initialize` handshake.
+pub(crate) async fn spawn_acp_session(
+    entry: WorkspaceEntry,
+    backend: BackendType,
+    settings: &AppSettings,
+    host: SharedHost,
+) -> Result<Arc<AcpSession>, String> {
+    let (mut command, label) = match backend {
+        BackendType::OpenCode => (
+            opencode_acp_command(&entry, settings.opencode_bin.clone()).await?,
+            "OpenCode".to_string(),
+        ),
+        BackendType::Acp => {
+            let config = entry
+                .acp_agent
+                .as_ref()
+                .ok_or("No ACP agent is configured for this workspace.")?;
+            (build_agent_command(config)?, config.display_name())
+        }
+        BackendType::Codex => return Err("Codex does not speak ACP.".to_string()),
+    };
+    command.current_dir(&entry.path);
+    command.stdin(std::process::Stdio::piped());
+    command.stdout(std::process::Stdio::piped());
+    command.stderr(std::process::Stdio::piped());
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {label}: {e}"))?;
+
+    let stdin = child.stdin.take().ok_or("Failed to capture stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+
+    if let Some(stderr) = child.stderr.take() {
+        spawn_stderr_reader(stderr, host.clone(), entry.id.clone(), backend.clone());
+    }
+
+    // Until a prompt names its access mode, fall back to the app default and
+    // then to read-only.
+    let default_sandbox = sandbox_for_access_mode(
+        settings,
+        &entry.settings,
+        &settings.default_access_mode,
+        &entry.path,
+    )
+    .or_else(|_| sandbox_for_access_mode(settings, &entry.settings, "read-only", &entry.path))?;
+    let session = Arc::new_cyclic(|this| AcpSession {
+        entry: entry.clone(),
+        backend: backend.clone(),
+        label,
+        child: Mutex::new(child),
+        rpc: JsonRpcClient::new(stdin, true),
+        stdout_reader: Mutex::new(BufReader::new(stdout)),
+        host: host.clone(),
+        this: this.clone(),
+        last_activity: Mutex::new(Instant::now()),
+        client: AcpClient::new(entry.path.clone(), default_sandbox),
+        messages: Mutex::new(MessageFolder::default()),
+        pending_permissions: Mutex::new(HashMap::new()),
+        prompt_capabilities: Mutex::new(PromptCapabilities::default()),
+        session_models: Mutex::new(HashMap::new()),
+    });
+
+    spawn_stdout_reader(session.clone(), host.clone());
+
+    if let Err(error) = initialize_acp_session(&session).await {
+        let mut child = session.child.lock().await;
+        let _ = child.kill().await;
+        return Err(format!(
+            "Failed to initialize {} ACP: {error}",
+            session.label
+        ));
+    }
+
+    host.emit(AgentEvent::new(
+        &entry.id,
+        backend,
+        "acp/connected",
+        json!({ "workspaceId": entry.id.clone(), "serverUrl": "acp://local" }),
+    ));
+
+    Ok(session)
+}
+
+/// Returns the workspace's running ACP agent, spawning it on first use.
+pub(crate) async fn get_or_spawn_acp_session(
+    workspace_id: &str,
+    host: &SharedHost,
+) -> Result<Arc<AcpSession>, String> {
+    let state = host.app_state();
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .ok_or("Workspace not found")?
+            .clone()
+    };
+    let backend = acp_backend_for(&entry);
+
+    if let Some(existing) = get_backend_as::<AcpSession>(state, workspace_id, backend.clone()).await
+    {
+        return Ok(existing);
+    }
+
+    let settings = state.app_settings.lock().await.clone();
+    let session = spawn_acp_session(entry, backend.clone(), &settings, host.clone()).await?;
+
+    let mut sessions = state.sessions.lock().await;
+    if let Some(existing) = sessions.get(workspace_id, &backend) {
+        drop(sessions);
+        let _ = session.shutdown().await;
+        return existing
+            .as_any()
+            .downcast::<AcpSession>()
+            .map_err(|_| "unexpected ACP session type".to_string());
+    }
+    sessions.insert(workspace_id, session.clone());
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acp_backend_for, permission_outcome};
+    use crate::types::{AcpAgentConfig, BackendType, WorkspaceEntry};
+    use serde_json::json;
+
+    #[test]
+    fn permission_decisions_pick_matching_options() {
+        let options = vec![
+            json!({ "optionId": "always", "kind": "allow_always" }),
+            json!({ "optionId": "once", "kind": "allow_once" }),
+            json!({ "optionId": "no", "kind": "reject_once" }),
+        ];
+        assert_eq!(
+            permission_outcome(&options, &json!({ "decision": "accept" })),
+            json!({ "outcome": { "outcome": "selected", "optionId": "once" } })
+        );
+        assert_eq!(
+            permission_outcome(&options, &json!({ "decision": "decline" })),
+            json!({ "outcome": { "outcome": "selected", "optionId": "no" } })
+        );
+        assert_eq!(
+            permission_outcome(&options[..1], &json!({ "decision": "decline" })),
+            json!({ "outcome": { "outcome": "cancelled" } })
+        );
+    }
+
+    #[test]
+    fn codex_workspaces_prefer_the_configured_agent() {
+        let mut entry: WorkspaceEntry =
+            serde_json::from_str(r#"{"id":"1","name":"Test","path":"/tmp","codexBin":null}"#)
+                .expect("workspace deserialize");
+        assert_eq!(acp_backend_for(&entry), BackendType::OpenCode);
+
+        entry.acp_agent = Some(AcpAgentConfig {
+            name: None,
+            command: "claude-code-acp".to_string(),
+            args: Vec::new(),
+        });
+        assert_eq!(acp_backend_for(&entry), BackendType::Acp);
+
+        entry.backend = BackendType::OpenCode;
+        assert_eq!(acp_backend_for(&entry), BackendType::OpenCode);
+    }
+}
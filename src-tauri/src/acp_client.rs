@@ -0,0 +1,556 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::task::Poll;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::{watch, Mutex, Notify};
+use uuid::Uuid;
+
+use crate::approvals::ApprovalSubject;
+use crate::protocol::{ApprovalPolicy, SandboxPolicy};
+use crate::rpc::RpcError;
+use crate::sandbox::ResolvedSandbox;
+use crate::types::ApprovalRule;
+use crate::utils::confine_path;
+
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// What we tell the agent we can do for it during `initialize`.
+pub(crate) fn client_capabilities() -> Value {
+    json!({
+        "fs": {
+            "readTextFile": true,
+            "writeTextFile": true
+        },
+        "terminal": true
+    })
+}
+
+fn rpc_error(code: i64, message: impl Into<String>) -> RpcError {
+    RpcError::Server {
+        code,
+        message: message.into(),
+        data: None,
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(method: &str, params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| rpc_error(INVALID_PARAMS, format!("invalid {method} params: {e}")))
+}
+
+/// Where the agent may read, write and start commands: the workspace plus the
+/// sandbox's writable roots.
+fn allowed_roots(workspace_path: &str, sandbox: &ResolvedSandbox) -> Vec<PathBuf> {
+    let mut roots = vec![PathBuf::from(workspace_path)];
+    if let SandboxPolicy::WorkspaceWrite { writable_roots, .. } = &sandbox.sandbox_policy {
+        roots.extend(writable_roots.iter().map(PathBuf::from));
+    }
+    roots
+}
+
+async fn confined(path: &str, roots: &[PathBuf]) -> Result<PathBuf, RpcError> {
+    confine_path(Path::new(path), roots)
+        .await
+        .map_err(|message| rpc_error(INVALID_PARAMS, message))
+}
+
+fn session_id(params: &Value) -> Option<&str> {
+    params.get("sessionId").and_then(|value| value.as_str())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadTextFileParams {
+    path: String,
+    line: Option<usize>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteTextFileParams {
+    path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnvVariable {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateTerminalParams {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: Vec<EnvVariable>,
+    cwd: Option<String>,
+    output_byte_limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalParams {
+    terminal_id: String,
+}
+
+/// Returns `limit` lines of `content` starting at the 1-based `line`.
+fn slice_lines(content: &str, line: Option<usize>, limit: Option<usize>) -> String {
+    if line.is_none() && limit.is_none() {
+        return content.to_string();
+    }
+    let start = line.unwrap_or(1).saturating_sub(1);
+    let lines = content.split_inclusive('\n').skip(start);
+    match limit {
+        Some(limit) => lines.take(limit).collect(),
+        None => lines.collect(),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TerminalExitStatus {
+    exit_code: Option<i32>,
+    signal: Option<String>,
+}
+
+impl TerminalExitStatus {
+    fn from_status(status: std::io::Result<std::process::ExitStatus>) -> Self {
+        let Ok(status) = status else {
+            return Self::default();
+        };
+        #[cfg(unix)]
+        let signal =
+            std::os::unix::process::ExitStatusExt::signal(&status).map(|signal| signal.to_string());
+        #[cfg(not(unix))]
+        let signal = None;
+        Self {
+            exit_code: status.code(),
+            signal,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TerminalOutput {
+    text: String,
+    truncated: bool,
+}
+
+impl TerminalOutput {
+    /// Appends `chunk`, dropping the oldest output to stay within `limit` bytes.
+    fn push(&mut self, chunk: &str, limit: Option<usize>) {
+        self.text.push_str(chunk);
+        let Some(limit) = limit else {
+            return;
+        };
+        if self.text.len() <= limit {
+            return;
+        }
+        let mut cut = self.text.len() - limit;
+        while !self.text.is_char_boundary(cut) {
+            cut += 1;
+        }
+        self.text.drain(..cut);
+        self.truncated = true;
+    }
+}
+
+/// Decodes the UTF-8 in `pending`, keeping an incomplete trailing sequence
+/// for the next chunk. Invalid bytes become U+FFFD.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    loop {
+        match std::str::from_utf8(pending) {
+            Ok(valid) => {
+                text.push_str(valid);
+                pending.clear();
+                return text;
+            }
+            Err(error) => {
+                let valid = error.valid_up_to();
+                text.push_str(&String::from_utf8_lossy(&pending[..valid]));
+                let Some(invalid) = error.error_len() else {
+                    pending.drain(..valid);
+                    return text;
+                };
+                text.push(char::REPLACEMENT_CHARACTER);
+                pending.drain(..valid + invalid);
+            }
+        }
+    }
+}
+
+/// A write or command the agent asked for, in the terms approval rules use.
+pub(crate) struct ClientApproval {
+    pub(crate) session_id: Option<String>,
+    pub(crate) subject: ApprovalSubject,
+    /// Decline rules for the sandbox's excluded paths.
+    pub(crate) exclusion_rules: Vec<ApprovalRule>,
+    /// Whether the sandbox lets the agent act without asking.
+    pub(crate) trusted: bool,
+    /// How the permission prompt describes the request.
+    pub(crate) title: String,
+    pub(crate) kind: &'static str,
+}
+
+/// A command the agent started through `terminal/create`.
+struct AcpTerminal {
+    output: Mutex<TerminalOutput>,
+    exit: watch::Receiver<Option<TerminalExitStatus>>,
+    kill: Notify,
+}
+
+impl AcpTerminal {
+    fn spawn(params: CreateTerminalParams, cwd: &Path) -> Result<Arc<Self>, RpcError> {
+        let mut command = Command::new(&params.command);
+        command.args(&params.args);
+        command.envs(params.env.iter().map(|var| (&var.name, &var.value)));
+        command.current_dir(cwd);
+        command.stdin(Stdio::null());
+        command.stdout(Stdio::piped());
+        command.stderr(Stdio::piped());
+        let mut child = command.spawn().map_err(|e| {
+            rpc_error(
+                INTERNAL_ERROR,
+                format!("Failed to run {}: {e}", params.command),
+            )
+        })?;
+
+        let (exit_tx, exit_rx) = watch::channel(None);
+        let terminal = Arc::new(Self {
+            output: Mutex::new(TerminalOutput::default()),
+            exit: exit_rx,
+            kill: Notify::new(),
+        });
+        let limit = params.output_byte_limit;
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(pump_output(stdout, terminal.clone(), limit));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(pump_output(stderr, terminal.clone(), limit));
+        }
+        tokio::spawn(wait_for_child(child, terminal.clone(), exit_tx));
+        Ok(terminal)
+    }
+
+    async fn output(&self) -> Value {
+        let output = self.output.lock().await;
+        json!({
+            "output": output.text,
+            "truncated": output.truncated,
+            "exitStatus": *self.exit.borrow()
+        })
+    }
+
+    async fn wait_for_exit(&self) -> TerminalExitStatus {
+        let mut exit = self.exit.clone();
+        let status = match exit.wait_for(|status| status.is_some()).await {
+            Ok(status) => status.clone(),
+            Err(_) => None,
+        };
+        status.unwrap_or_default()
+    }
+}
+
+async fn pump_output(
+    mut stream: impl AsyncRead + Unpin,
+    terminal: Arc<AcpTerminal>,
+    limit: Option<usize>,
+) {
+    let mut buffer = [0u8; 4096];
+    // A character may straddle two reads.
+    let mut pending = Vec::new();
+    loop {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                pending.extend_from_slice(&buffer[..read]);
+                let chunk = take_utf8(&mut pending);
+                terminal.output.lock().await.push(&chunk, limit);
+            }
+        }
+    }
+    if !pending.is_empty() {
+        let rest = String::from_utf8_lossy(&pending);
+        terminal.output.lock().await.push(&rest, limit);
+    }
+}
+
+/// Waits for the process to exit on its own or for `terminal/kill`.
+async fn wait_for_child(
+    mut child: Child,
+    terminal: Arc<AcpTerminal>,
+    exit_tx: watch::Sender<Option<TerminalExitStatus>>,
+) {
+    let exited = {
+        let mut wait = Box::pin(child.wait());
+        let mut killed = Box::pin(terminal.kill.notified());
+        poll_fn(|cx| {
+            if let Poll::Ready(status) = wait.as_mut().poll(cx) {
+                return Poll::Ready(Some(status));
+            }
+            if killed.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            Poll::Pending
+        })
+        .await
+    };
+    let status = match exited {
+        Some(status) => status,
+        None => {
+            let _ = child.kill().await;
+            child.wait().await
+        }
+    };
+    let _ = exit_tx.send(Some(TerminalExitStatus::from_status(status)));
+}
+
+/// Client-side state the agent drives through `fs/*` and `terminal/*` requests.
+///
+/// Paths are confined to the workspace and the writable roots of the sandbox
+/// the session's latest prompt ran under; writes and commands are approved by
+/// the caller (see [`AcpClient::approval`]) before [`AcpClient::handle`] runs them.
+pub(crate) struct AcpClient {
+    workspace_path: String,
+    /// For sessions that have not been prompted through us yet.
+    default_sandbox: ResolvedSandbox,
+    sandboxes: Mutex<HashMap<String, ResolvedSandbox>>,
+    terminals: Mutex<HashMap<String, Arc<AcpTerminal>>>,
+}
+
+impl AcpClient {
+    pub(crate) fn new(workspace_path: String, default_sandbox: ResolvedSandbox) -> Self {
+        Self {
+            workspace_path,
+            default_sandbox,
+            sandboxes: Mutex::new(HashMap::new()),
+            terminals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Applies `sandbox` to the session's later `fs/*` and `terminal/*` requests.
+    pub(crate) async fn set_sandbox(&self, session_id: &str, sandbox: ResolvedSandbox) {
+        self.sandboxes
+            .lock()
+            .await
+            .insert(session_id.to_string(), sandbox);
+    }
+
+    async fn sandbox(&self, params: &Value) -> ResolvedSandbox {
+        let sandboxes = self.sandboxes.lock().await;
+        session_id(params)
+            .and_then(|session_id| sandboxes.get(session_id))
+            .unwrap_or(&self.default_sandbox)
+            .clone()
+    }
+
+    async fn roots(&self, params: &Value) -> Vec<PathBuf> {
+        allowed_roots(&self.workspace_path, &self.sandbox(params).await)
+    }
+
+    /// What a write or command request needs approved, or `None` for requests
+    /// that only read. Fails for paths outside the sandbox and for writes
+    /// under a read-only one.
+    pub(crate) async fn approval(
+        &self,
+        method: &str,
+        params: &Value,
+    ) -> Result<Option<ClientApproval>, RpcError> {
+        let sandbox = self.sandbox(params).await;
+        let roots = allowed_roots(&self.workspace_path, &sandbox);
+        let (subject, title, kind) = match method {
+            "fs/write_text_file" => {
+                let request: WriteTextFileParams = parse_params(method, params.clone())?;
+                if sandbox.sandbox_policy == SandboxPolicy::ReadOnly {
+                    return Err(rpc_error(
+                        INVALID_PARAMS,
+                        format!("the {} sandbox does not allow writes", sandbox.profile),
+                    ));
+                }
+                let path = confined(&request.path, &roots).await?;
+                let path = path.to_string_lossy().to_string();
+                (
+                    ApprovalSubject::FileChange {
+                        paths: vec![path.clone()],
+                        grant_root: None,
+                    },
+                    format!("Write {path}"),
+                    "edit",
+                )
+            }
+            "terminal/create" => {
+                let request: CreateTerminalParams = parse_params(method, params.clone())?;
+                let cwd = request.cwd.as_deref().unwrap_or(&self.workspace_path);
+                let cwd = confined(cwd, &roots).await?;
+                let command = std::iter::once(request.command.as_str())
+                    .chain(request.args.iter().map(|arg| arg.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (
+                    ApprovalSubject::Command {
+                        command: command.clone(),
+                        cwd: Some(cwd.to_string_lossy().to_string()),
+                    },
+                    format!("Run {command}"),
+                    "execute",
+                )
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(ClientApproval {
+            session_id: session_id(params).map(|id| id.to_string()),
+            subject,
+            exclusion_rules: sandbox.exclusion_rules(),
+            trusted: sandbox.approval_policy == ApprovalPolicy::Never,
+            title,
+            kind,
+        }))
+    }
+
+    async fn terminal(&self, params: Value, method: &str) -> Result<Arc<AcpTerminal>, RpcError> {
+        let params: TerminalParams = parse_params(method, params)?;
+        self.terminals
+            .lock()
+            .await
+            .get(&params.terminal_id)
+            .cloned()
+            .ok_or_else(|| {
+                rpc_error(
+                    INVALID_PARAMS,
+                    format!("unknown terminal {}", params.terminal_id),
+                )
+            })
+    }
+
+    /// Answers an agent-to-client request; errors become JSON-RPC error responses.
+    pub(crate) async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "fs/read_text_file" => {
+                let roots = self.roots(&params).await;
+                let params: ReadTextFileParams = parse_params(method, params)?;
+                let path = confined(&params.path, &roots).await?;
+                let content = tokio::fs::read_to_string(&path).await.map_err(|e| {
+                    rpc_error(
+                        INTERNAL_ERROR,
+                        format!("Failed to read {}: {e}", path.display()),
+                    )
+                })?;
+                Ok(json!({ "content": slice_lines(&content, params.line, params.limit) }))
+            }
+            "fs/write_text_file" => {
+                let roots = self.roots(&params).await;
+                let params: WriteTextFileParams = parse_params(method, params)?;
+                let path = confined(&params.path, &roots).await?;
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                        rpc_error(
+                            INTERNAL_ERROR,
+                            format!("Failed to create {}: {e}", parent.display()),
+                        )
+                    })?;
+                }
+                tokio::fs::write(&path, params.content).await.map_err(|e| {
+                    rpc_error(
+                        INTERNAL_ERROR,
+                        format!("Failed to write {}: {e}", path.display()),
+                    )
+                })?;
+                Ok(json!({}))
+            }
+            "terminal/create" => {
+                let roots = self.roots(&params).await;
+                let params: CreateTerminalParams = parse_params(method, params)?;
+                let cwd = params.cwd.as_deref().unwrap_or(&self.workspace_path);
+                let cwd = confined(cwd, &roots).await?;
+                let terminal = AcpTerminal::spawn(params, &cwd)?;
+                let terminal_id = format!("term_{}", Uuid::new_v4());
+                self.terminals
+                    .lock()
+                    .await
+                    .insert(terminal_id.clone(), terminal);
+                Ok(json!({ "terminalId": terminal_id }))
+            }
+            "terminal/output" => Ok(self.terminal(params, method).await?.output().await),
+            "terminal/wait_for_exit" => {
+                let status = self.terminal(params, method).await?.wait_for_exit().await;
+                Ok(serde_json::to_value(status).unwrap_or(Value::Null))
+            }
+            "terminal/kill" => {
+                self.terminal(params, method).await?.kill.notify_one();
+                Ok(json!({}))
+            }
+            "terminal/release" => {
+                let params: TerminalParams = parse_params(method, params)?;
+                if let Some(terminal) = self.terminals.lock().await.remove(&params.terminal_id) {
+                    terminal.kill.notify_one();
+                }
+                Ok(json!({}))
+            }
+            _ => Err(rpc_error(
+                METHOD_NOT_FOUND,
+                format!("{method} is not supported"),
+            )),
+        }
+    }
+
+    /// Kills every terminal the agent left running.
+    pub(crate) async fn release_all(&self) {
+        for (_, terminal) in self.terminals.lock().await.drain() {
+            terminal.kill.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slice_lines, take_utf8, TerminalOutput};
+
+    #[test]
+    fn slice_lines_is_one_based_and_limited() {
+        let content = "one\ntwo\nthree\nfour";
+        assert_eq!(slice_lines(content, None, None), content);
+        assert_eq!(slice_lines(content, Some(2), Some(2)), "two\nthree\n");
+        assert_eq!(slice_lines(content, Some(4), None), "four");
+        assert_eq!(slice_lines(content, None, Some(1)), "one\n");
+        assert_eq!(slice_lines(content, Some(9), None), "");
+    }
+
+    #[test]
+    fn split_characters_wait_for_the_next_chunk() {
+        let bytes = "é€".as_bytes();
+        let mut pending = bytes[..1].to_vec();
+        assert_eq!(take_utf8(&mut pending), "");
+        pending.extend_from_slice(&bytes[1..3]);
+        assert_eq!(take_utf8(&mut pending), "é");
+        assert_eq!(pending, bytes[2..3]);
+        pending.extend_from_slice(&bytes[3..]);
+        pending.extend_from_slice(b"\xffok");
+        assert_eq!(take_utf8(&mut pending), "€\u{fffd}ok");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn terminal_output_keeps_the_tail_on_char_boundaries() {
+        let mut output = TerminalOutput::default();
+        output.push("abc", Some(4));
+        assert!(!output.truncated);
+        output.push("déf", Some(4));
+        assert_eq!(output.text, "déf");
+        assert!(output.truncated);
+    }
+}
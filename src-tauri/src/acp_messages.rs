@@ -0,0 +1,393 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::types::{OpenCodeFileDiff, OpenCodeMessage, OpenCodeMessagePart, OpenCodePlanEntry};
+
+/// Emitted once per `session/update` that changes the open message.
+pub(crate) const MESSAGE_DELTA_METHOD: &str = "acp/message/delta";
+/// Emitted when `session/prompt` returns, with the finished message.
+pub(crate) const MESSAGE_COMPLETED_METHOD: &str = "acp/message/completed";
+
+/// How a part of the open message changed.
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(tag = "op", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum PartDelta {
+    /// Text to append to the text or reasoning part at `index`.
+    Append { text: String },
+    /// The part at `index` as it now stands; a new index adds a part.
+    Upsert { part: OpenCodeMessagePart },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct OpenCodeMessageDelta {
+    pub(crate) session_id: String,
+    pub(crate) message_id: String,
+    pub(crate) role: String,
+    pub(crate) index: usize,
+    pub(crate) delta: PartDelta,
+}
+
+/// Folds ACP `session/update` notifications into one open message per session.
+#[derive(Default)]
+pub(crate) struct MessageFolder {
+    open: HashMap<String, OpenCodeMessage>,
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+fn chunk_text(update: &Value) -> Option<String> {
+    let content = update.get("content")?;
+    if content.get("type").and_then(|value| value.as_str()) != Some("text") {
+        return None;
+    }
+    str_field(content, "text").filter(|text| !text.is_empty())
+}
+
+impl MessageFolder {
+    /// Applies the `params` of a `session/update` and returns what changed.
+    pub(crate) fn apply(&mut self, params: &Value) -> Vec<OpenCodeMessageDelta> {
+        let Some(session_id) = str_field(params, "sessionId") else {
+            return Vec::new();
+        };
+        let Some(update) = params.get("update") else {
+            return Vec::new();
+        };
+        let kind = update
+            .get("sessionUpdate")
+            .and_then(|value| value.as_str())
+            .unwrap_or_default();
+        let delta = match kind {
+            "user_message_chunk" => {
+                chunk_text(update).map(|text| self.append_text(&session_id, "user", text, false))
+            }
+            "agent_message_chunk" => chunk_text(update)
+                .map(|text| self.append_text(&session_id, "assistant", text, false)),
+            "agent_thought_chunk" => chunk_text(update)
+                .map(|text| self.append_text(&session_id, "assistant", text, true)),
+            "tool_call" | "tool_call_update" => self.apply_tool_call(&session_id, update),
+            "plan" => Some(self.apply_plan(&session_id, update)),
+            _ => None,
+        };
+        delta.into_iter().collect()
+    }
+
+    /// Closes the session's open message, stamping the prompt's stop reason.
+    pub(crate) fn finish(
+        &mut self,
+        session_id: &str,
+        stop_reason: Option<String>,
+    ) -> OpenCodeMessage {
+        let mut message = self
+            .open
+            .remove(session_id)
+            .unwrap_or_else(|| new_message(session_id, "assistant"));
+        message.stop_reason = stop_reason;
+        message
+    }
+
+    /// The open message for `session_id`, starting a new one when the role changes.
+    fn message(&mut self, session_id: &str, role: &str) -> &mut OpenCodeMessage {
+        let stale = self
+            .open
+            .get(session_id)
+            .is_some_and(|message| message.role != role);
+        if stale {
+            self.open.remove(session_id);
+        }
+        self.open
+            .entry(session_id.to_string())
+            .or_insert_with(|| new_message(session_id, role))
+    }
+
+    fn append_text(
+        &mut self,
+        session_id: &str,
+        role: &str,
+        text: String,
+        reasoning: bool,
+    ) -> OpenCodeMessageDelta {
+        let message = self.message(session_id, role);
+        let existing = match message.parts.last_mut() {
+            Some(OpenCodeMessagePart::Text { text }) if !reasoning => Some(text),
+            Some(OpenCodeMessagePart::Reasoning { text }) if reasoning => Some(text),
+            _ => None,
+        };
+        let delta = match existing {
+            Some(existing) => {
+                existing.push_str(&text);
+                PartDelta::Append { text }
+            }
+            None => {
+                let part = if reasoning {
+                    OpenCodeMessagePart::Reasoning { text }
+                } else {
+                    OpenCodeMessagePart::Text { text }
+                };
+                message.parts.push(part.clone());
+                PartDelta::Upsert { part }
+            }
+        };
+        delta_for(message, message.parts.len() - 1, delta)
+    }
+
+    fn apply_tool_call(
+        &mut self,
+        session_id: &str,
+        update: &Value,
+    ) -> Option<OpenCodeMessageDelta> {
+        let tool_call_id = str_field(update, "toolCallId")?;
+        let message = self.message(session_id, "assistant");
+        let index = message
+            .parts
+            .iter()
+            .position(|part| {
+                matches!(part, OpenCodeMessagePart::ToolUse { id, .. } if *id == tool_call_id)
+            })
+            .unwrap_or_else(|| {
+                message.parts.push(OpenCodeMessagePart::ToolUse {
+                    id: tool_call_id.clone(),
+                    name: "Tool".to_string(),
+                    kind: None,
+                    input: Value::Null,
+                    status: "pending".to_string(),
+                    output: String::new(),
+                    diffs: Vec::new(),
+                });
+                message.parts.len() - 1
+            });
+        if let OpenCodeMessagePart::ToolUse {
+            name,
+            kind,
+            input,
+            status,
+            output,
+            diffs,
+            ..
+        } = &mut message.parts[index]
+        {
+            if let Some(title) = str_field(update, "title") {
+                *name = title;
+            }
+            if let Some(value) = str_field(update, "kind") {
+                *kind = Some(value);
+            }
+            if let Some(value) = update.get("rawInput").filter(|value| !value.is_null()) {
+                *input = value.clone();
+            }
+            if let Some(value) = str_field(update, "status") {
+                *status = value;
+            }
+            // ACP sends the whole content collection on every update.
+            if let Some(content) = update.get("content").and_then(|value| value.as_array()) {
+                let (text, files) = tool_content(content);
+                *output = text;
+                *diffs = files;
+            }
+        }
+        let part = message.parts[index].clone();
+        Some(delta_for(message, index, PartDelta::Upsert { part }))
+    }
+
+    fn apply_plan(&mut self, session_id: &str, update: &Value) -> OpenCodeMessageDelta {
+        let entries: Vec<OpenCodePlanEntry> = update
+            .get("entries")
+            .and_then(|value| value.as_array())
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| {
+                        Some(OpenCodePlanEntry {
+                            content: str_field(entry, "content")?,
+                            priority: str_field(entry, "priority"),
+                            status: str_field(entry, "status")
+                                .unwrap_or_else(|| "pending".to_string()),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let message = self.message(session_id, "assistant");
+        let part = OpenCodeMessagePart::Plan { entries };
+        // Each plan update replaces the previous plan.
+        let index = match message
+            .parts
+            .iter()
+            .position(|part| matches!(part, OpenCodeMessagePart::Plan { .. }))
+        {
+            Some(index) => {
+                message.parts[index] = part.clone();
+                index
+            }
+            None => {
+                message.parts.push(part.clone());
+                message.parts.len() - 1
+            }
+        };
+        delta_for(message, index, PartDelta::Upsert { part })
+    }
+}
+
+fn new_message(session_id: &str, role: &str) -> OpenCodeMessage {
+    OpenCodeMessage {
+        id: format!("msg_{}", Uuid::new_v4()),
+        session_id: session_id.to_string(),
+        role: role.to_string(),
+        parts: Vec::new(),
+        created_at: Some(now_millis()),
+        stop_reason: None,
+    }
+}
+
+fn delta_for(message: &OpenCodeMessage, index: usize, delta: PartDelta) -> OpenCodeMessageDelta {
+    OpenCodeMessageDelta {
+        session_id: message.session_id.clone(),
+        message_id: message.id.clone(),
+        role: message.role.clone(),
+        index,
+        delta,
+    }
+}
+
+/// Splits tool call content into its text output and file diffs.
+fn tool_content(content: &[Value]) -> (String, Vec<OpenCodeFileDiff>) {
+    let mut output = Vec::new();
+    let mut diffs = Vec::new();
+    for item in content {
+        match item.get("type").and_then(|value| value.as_str()) {
+            Some("content") => {
+                if let Some(text) = item
+                    .get("content")
+                    .and_then(|inner| str_field(inner, "text"))
+                {
+                    output.push(text);
+                }
+            }
+            Some("diff") => {
+                if let Some(path) = str_field(item, "path") {
+                    diffs.push(OpenCodeFileDiff {
+                        path,
+                        old_text: str_field(item, "oldText"),
+                        new_text: str_field(item, "newText").unwrap_or_default(),
+                    });
+                }
+            }
+            Some("terminal") => {
+                if let Some(terminal_id) = str_field(item, "terminalId") {
+                    output.push(format!("[terminal:{terminal_id}]"));
+                }
+            }
+            _ => {}
+        }
+    }
+    (output.join("\n"), diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageFolder, PartDelta};
+    use crate::types::OpenCodeMessagePart;
+    use serde_json::json;
+
+    fn update(update: serde_json::Value) -> serde_json::Value {
+        json!({ "sessionId": "ses_1", "update": update })
+    }
+
+    #[test]
+    fn chunks_append_to_the_current_part() {
+        let mut folder = MessageFolder::default();
+        let first = folder.apply(&update(json!({
+            "sessionUpdate": "agent_thought_chunk",
+            "content": { "type": "text", "text": "Thinking" }
+        })));
+        assert!(matches!(first[0].delta, PartDelta::Upsert { .. }));
+        folder.apply(&update(json!({
+            "sessionUpdate": "agent_message_chunk",
+            "content": { "type": "text", "text": "Hel" }
+        })));
+        let last = folder.apply(&update(json!({
+            "sessionUpdate": "agent_message_chunk",
+            "content": { "type": "text", "text": "lo" }
+        })));
+        assert_eq!(last[0].index, 1);
+        assert_eq!(
+            last[0].delta,
+            PartDelta::Append {
+                text: "lo".to_string()
+            }
+        );
+
+        let message = folder.finish("ses_1", Some("end_turn".to_string()));
+        assert_eq!(message.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(
+            message.parts,
+            vec![
+                OpenCodeMessagePart::Reasoning {
+                    text: "Thinking".to_string()
+                },
+                OpenCodeMessagePart::Text {
+                    text: "Hello".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tool_call_updates_merge_into_one_part() {
+        let mut folder = MessageFolder::default();
+        folder.apply(&update(json!({
+            "sessionUpdate": "tool_call",
+            "toolCallId": "call_1",
+            "title": "Read file",
+            "kind": "read",
+            "status": "pending"
+        })));
+        let deltas = folder.apply(&update(json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_1",
+            "status": "completed",
+            "content": [
+                { "type": "content", "content": { "type": "text", "text": "done" } },
+                { "type": "diff", "path": "/a.rs", "oldText": "a", "newText": "b" }
+            ]
+        })));
+        match &deltas[0].delta {
+            PartDelta::Upsert {
+                part:
+                    OpenCodeMessagePart::ToolUse {
+                        name,
+                        status,
+                        output,
+                        diffs,
+                        ..
+                    },
+            } => {
+                assert_eq!(name, "Read file");
+                assert_eq!(status, "completed");
+                assert_eq!(output, "done");
+                assert_eq!(diffs[0].path, "/a.rs");
+            }
+            other => panic!("unexpected delta: {other:?}"),
+        }
+        assert_eq!(deltas[0].index, 0);
+    }
+}
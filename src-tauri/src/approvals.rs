@@ -0,0 +1,362 @@
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::protocol::{ApprovalDecision, ApprovalResponse, KnownServerRequest};
+use crate::state::AppState;
+use crate::types::{ApprovalRule, ApprovalRuleTarget};
+use crate::utils::{normalize_path, resolve_path};
+
+/// Emitted (and journaled) whenever a rule answers an approval request.
+pub(crate) const AUTO_APPROVAL_METHOD: &str = "codex/approval/auto";
+
+/// Shell syntax that can chain a second command after an allowed prefix.
+const SHELL_CONTROL: &[&str] = &[";", "&", "|", "`", "$(", ">", "<", "\n"];
+
+/// What an approval request asks to do, in the terms rules are written in.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ApprovalSubject {
+    Command {
+        command: String,
+        cwd: Option<String>,
+    },
+    FileChange {
+        paths: Vec<String>,
+        grant_root: Option<String>,
+    },
+}
+
+/// A request answered by a rule instead of the UI.
+#[derive(Debug, Clone)]
+pub(crate) struct AutoDecision {
+    pub(crate) decision: ApprovalDecision,
+    pub(crate) rule_index: usize,
+    pub(crate) rule: ApprovalRule,
+}
+
+impl AutoDecision {
+    /// The result sent back to the app-server, in the shape the UI would send.
+    pub(crate) fn response(&self) -> Value {
+        serde_json::to_value(ApprovalResponse {
+            decision: self.decision,
+        })
+        .unwrap_or(Value::Null)
+    }
+
+    pub(crate) fn log_entry(&self, request_id: u64, method: &str, params: Option<&Value>) -> Value {
+        json!({
+            "threadId": params.and_then(|params| params.get("threadId")),
+            "requestId": request_id,
+            "method": method,
+            "decision": self.decision,
+            "ruleIndex": self.rule_index,
+            "rule": self.rule,
+            "params": params,
+        })
+    }
+}
+
+/// Looks up the workspace's rules and answers `request` if one matches.
+/// `extra_rules` (e.g. sandbox exclusions) are checked alongside them.
+pub(crate) async fn auto_decide(
+    state: &AppState,
+    workspace_id: &str,
+    request: &KnownServerRequest,
+    extra_rules: Vec<ApprovalRule>,
+) -> Option<AutoDecision> {
+    let subject = match request {
+        KnownServerRequest::CommandExecutionApproval(params) => ApprovalSubject::Command {
+            command: params.command.clone().unwrap_or_default(),
+            cwd: params.cwd.clone(),
+        },
+        KnownServerRequest::FileChangeApproval(params) => ApprovalSubject::FileChange {
+            paths: state.thread_store.lock().await.file_change_paths(
+                workspace_id,
+                &params.thread_id,
+                &params.item_id,
+            ),
+            grant_root: params.grant_root.clone(),
+        },
+    };
+    auto_decide_subject(state, workspace_id, &subject, extra_rules).await
+}
+
+/// [`auto_decide`] for a subject built elsewhere, e.g. an ACP agent's
+/// `fs/write_text_file` or `terminal/create`.
+pub(crate) async fn auto_decide_subject(
+    state: &AppState,
+    workspace_id: &str,
+    subject: &ApprovalSubject,
+    extra_rules: Vec<ApprovalRule>,
+) -> Option<AutoDecision> {
+    let (rules, workspace_path) = {
+        let workspaces = state.workspaces.lock().await;
+        let entry = workspaces.get(workspace_id)?;
+        let mut rules = entry.settings.approval_rules.clone();
+        rules.extend(extra_rules);
+        (rules, entry.path.clone())
+    };
+    if rules.is_empty() {
+        return None;
+    }
+    evaluate(&rules, Path::new(&workspace_path), subject).map(|(rule_index, rule)| AutoDecision {
+        decision: rule.decision,
+        rule_index,
+        rule: rule.clone(),
+    })
+}
+
+/// Decline rules are checked before accept rules; within each, the first match wins.
+pub(crate) fn evaluate<'a>(
+    rules: &'a [ApprovalRule],
+    workspace: &Path,
+    subject: &ApprovalSubject,
+) -> Option<(usize, &'a ApprovalRule)> {
+    let with_decision = |decision: ApprovalDecision| {
+        rules
+            .iter()
+            .enumerate()
+            .filter(move |(_, rule)| rule.decision == decision)
+    };
+    with_decision(ApprovalDecision::Decline)
+        .chain(with_decision(ApprovalDecision::Accept))
+        .find(|(_, rule)| rule_matches(rule, workspace, subject))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+fn rule_matches(rule: &ApprovalRule, workspace: &Path, subject: &ApprovalSubject) -> bool {
+    let approving = rule.decision == ApprovalDecision::Accept;
+    let target_matches = matches!(
+        (rule.target, subject),
+        (ApprovalRuleTarget::Any, _)
+            | (ApprovalRuleTarget::Command, ApprovalSubject::Command { .. })
+            | (
+                ApprovalRuleTarget::FileChange,
+                ApprovalSubject::FileChange { .. }
+            )
+    );
+    if !target_matches {
+        return false;
+    }
+    if let Some(prefix) = non_empty(&rule.command_prefix) {
+        let ApprovalSubject::Command { command, .. } = subject else {
+            return false;
+        };
+        // An approved prefix must not let a second command ride along.
+        if approving && SHELL_CONTROL.iter().any(|token| command.contains(token)) {
+            return false;
+        }
+        if !command_has_prefix(command, prefix) {
+            return false;
+        }
+    }
+    if let Some(pattern) = non_empty(&rule.path_glob) {
+        let ApprovalSubject::FileChange { paths, .. } = subject else {
+            return false;
+        };
+        if paths.is_empty() {
+            return false;
+        }
+        let matches = |path: &String| {
+            if Path::new(pattern).is_absolute() {
+                glob_match(pattern, &resolve_path(workspace, path).to_string_lossy())
+            } else {
+                glob_match(pattern, &workspace_relative(workspace, path))
+            }
+        };
+        // Approve only when every path is covered; deny when any path is.
+        let covered = if approving {
+            paths.iter().all(matches)
+        } else {
+            paths.iter().any(matches)
+        };
+        if !covered {
+            return false;
+        }
+    }
+    if let Some(cwd) = non_empty(&rule.cwd) {
+        let requested = match subject {
+            ApprovalSubject::Command { cwd, .. } => cwd.as_deref(),
+            ApprovalSubject::FileChange { grant_root, .. } => grant_root.as_deref(),
+        };
+        let requested = requested
+            .map(|dir| resolve_path(workspace, dir))
+            .unwrap_or_else(|| normalize_path(workspace));
+        if !requested.starts_with(resolve_path(workspace, cwd)) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Compares whole words, so `git` matches `git status` but not `gitk`.
+fn command_has_prefix(command: &str, prefix: &str) -> bool {
+    let mut words = command.split_whitespace();
+    prefix
+        .split_whitespace()
+        .all(|expected| words.next() == Some(expected))
+}
+
+fn workspace_relative(workspace: &Path, path: &str) -> String {
+    let resolved = resolve_path(workspace, path);
+    let relative = resolved
+        .strip_prefix(normalize_path(workspace))
+        .unwrap_or(&resolved);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `*` and `?` stay within one segment and `**` spans any number of them. A
+/// pattern without `/` matches the file name at any depth, like `.gitignore`.
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    let mut segments: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty()).collect();
+    if !pattern.contains('/') {
+        segments.insert(0, "**");
+    }
+    let path: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    match_segments(&segments, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    let pattern: Vec<Vec<char>> = pattern.iter().map(|part| part.chars().collect()).collect();
+    let path: Vec<Vec<char>> = path.iter().map(|part| part.chars().collect()).collect();
+    match_wildcards(
+        &pattern,
+        &path,
+        |part| part.as_slice() == ['*', '*'],
+        |part, segment| match_segment(part, segment),
+    )
+}
+
+fn match_segment(pattern: &[char], text: &[char]) -> bool {
+    match_wildcards(
+        pattern,
+        text,
+        |c| *c == '*',
+        |expected, c| *expected == '?' || expected == c,
+    )
+}
+
+/// Matches `text` against a pattern whose `is_many` items span any run of
+/// text, in `O(pattern × text)` time. On a mismatch only the latest
+/// wildcard takes one more item: anything an earlier wildcard could absorb
+/// the latest one can too, so backtracking further never finds a new match.
+fn match_wildcards<P, T>(
+    pattern: &[P],
+    text: &[T],
+    is_many: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // The latest wildcard and how much text it had absorbed up to.
+    let mut retry: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && is_many(&pattern[p]) {
+            retry = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && matches(&pattern[p], &text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((wildcard, absorbed)) = retry {
+            retry = Some((wildcard, absorbed + 1));
+            p = wildcard + 1;
+            t = absorbed + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_many)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, glob_match, ApprovalSubject};
+    use crate::protocol::ApprovalDecision;
+    use crate::types::{ApprovalRule, ApprovalRuleTarget};
+    use std::path::Path;
+
+    fn rule(decision: ApprovalDecision, target: ApprovalRuleTarget) -> ApprovalRule {
+        ApprovalRule {
+            name: None,
+            decision,
+            target,
+            command_prefix: None,
+            path_glob: None,
+            cwd: None,
+        }
+    }
+
+    fn command(command: &str, cwd: Option<&str>) -> ApprovalSubject {
+        ApprovalSubject::Command {
+            command: command.to_string(),
+            cwd: cwd.map(|cwd| cwd.to_string()),
+        }
+    }
+
+    #[test]
+    fn glob_match_handles_segments_and_basenames() {
+        assert!(glob_match("*.md", "docs/guide/intro.md"));
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/a/b/lib.rs"));
+        assert!(!glob_match("src/*.rs", "src/a/lib.rs"));
+        assert!(glob_match("tests/fixture_?.json", "tests/fixture_1.json"));
+        assert!(!glob_match("tests/fixture_?.json", "tests/fixture_10.json"));
+    }
+
+    #[test]
+    fn glob_match_stays_fast_on_pathological_patterns() {
+        let text = "a".repeat(200);
+        assert!(!glob_match(&format!("{}b", "a*".repeat(30)), &text));
+        assert!(glob_match(&format!("{}a", "a*".repeat(30)), &text));
+
+        let path = vec!["d"; 60].join("/");
+        assert!(!glob_match(&format!("{}x", "**/d/".repeat(20)), &path));
+        assert!(glob_match(&format!("{}d", "**/d/".repeat(20)), &path));
+        assert!(glob_match("**/**/d/**", &path));
+    }
+
+    #[test]
+    fn command_rules_match_whole_words_and_reject_chaining() {
+        let workspace = Path::new("/repo");
+        let mut allow = rule(ApprovalDecision::Accept, ApprovalRuleTarget::Command);
+        allow.command_prefix = Some("git status".to_string());
+        let rules = vec![allow];
+        assert!(evaluate(&rules, workspace, &command("git status --short", None)).is_some());
+        assert!(evaluate(&rules, workspace, &command("git statusx", None)).is_none());
+        assert!(evaluate(&rules, workspace, &command("git status && rm -rf /", None)).is_none());
+    }
+
+    #[test]
+    fn deny_rules_win_and_cwd_is_scoped_to_the_workspace() {
+        let workspace = Path::new("/repo");
+        let mut allow = rule(ApprovalDecision::Accept, ApprovalRuleTarget::Any);
+        allow.cwd = Some("app".to_string());
+        let mut deny = rule(ApprovalDecision::Decline, ApprovalRuleTarget::FileChange);
+        deny.path_glob = Some("*.lock".to_string());
+        let rules = vec![allow, deny];
+
+        let (index, _) = evaluate(&rules, workspace, &command("make", Some("/repo/app/web")))
+            .expect("cwd below app");
+        assert_eq!(index, 0);
+        assert!(evaluate(&rules, workspace, &command("make", Some("/repo"))).is_none());
+
+        let change = ApprovalSubject::FileChange {
+            paths: vec![
+                "/repo/app/main.rs".to_string(),
+                "/repo/Cargo.lock".to_string(),
+            ],
+            grant_root: Some("/repo/app".to_string()),
+        };
+        let (index, matched) = evaluate(&rules, workspace, &change).expect("lock file denied");
+        assert_eq!(index, 1);
+        assert_eq!(matched.decision, ApprovalDecision::Decline);
+    }
+}
@@ -0,0 +1,199 @@
+//! The app as a window onto a running daemon. While the daemon holds the data
+//! directory, every command the webview invokes is forwarded over the daemon
+//! socket and its agent events are re-emitted to the webview, so the app shows
+//! and drives the sessions the daemon keeps alive.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tauri::ipc::{Invoke, InvokeBody, InvokeError};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::unix::OwnedReadHalf;
+use tokio::net::UnixStream;
+use tokio::sync::{oneshot, Mutex};
+
+use crate::backend::AGENT_EVENT;
+use crate::daemon::SUBSCRIBE_METHOD;
+use crate::dispatch::LAGGED_METHOD;
+use crate::rpc::{JsonRpcClient, RpcError};
+
+/// A daemon that holds the lock may still be starting; it gets this long to
+/// open its socket.
+const CONNECT_ATTEMPTS: u32 = 25;
+const CONNECT_RETRY: Duration = Duration::from_millis(200);
+/// Pause between attempts to get back a daemon connection that closed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub(crate) struct DaemonLink {
+    path: PathBuf,
+    /// `None` while reconnecting.
+    client: Mutex<Option<Arc<JsonRpcClient>>>,
+}
+
+/// The daemon link, when this app runs attached to a daemon.
+pub(crate) fn daemon_link(invoke: &Invoke<Wry>) -> Option<Arc<DaemonLink>> {
+    let link = invoke
+        .message
+        .webview_ref()
+        .try_state::<Arc<DaemonLink>>()?;
+    Some(Arc::clone(&link))
+}
+
+fn connect_client(stream: UnixStream) -> (OwnedReadHalf, Arc<JsonRpcClient>) {
+    let (reader, writer) = stream.into_split();
+    (reader, Arc::new(JsonRpcClient::new(writer, true)))
+}
+
+impl DaemonLink {
+    /// Connects to the daemon on `path` and starts relaying its events to
+    /// `app`. Blocks briefly, so `setup` can decide before any window loads.
+    pub(crate) fn attach(app: AppHandle, path: PathBuf) -> Result<Arc<Self>, String> {
+        let stream = connect_blocking(&path)?;
+        stream
+            .set_nonblocking(true)
+            .map_err(|error| error.to_string())?;
+        let stream = UnixStream::from_std(stream).map_err(|error| error.to_string())?;
+        let (reader, client) = connect_client(stream);
+        let link = Arc::new(Self {
+            path,
+            client: Mutex::new(Some(Arc::clone(&client))),
+        });
+        tokio::spawn(Arc::clone(&link).relay(app, reader, client));
+        Ok(link)
+    }
+
+    /// Reads replies and events until the daemon hangs up, then reconnects.
+    /// Events sent in between are lost; the webview refetches on demand.
+    async fn relay(
+        self: Arc<Self>,
+        app: AppHandle,
+        mut reader: OwnedReadHalf,
+        mut client: Arc<JsonRpcClient>,
+    ) {
+        loop {
+            let subscriber = Arc::clone(&client);
+            tokio::spawn(async move {
+                if let Err(error) = subscriber.request(SUBSCRIBE_METHOD, json!({}), None).await {
+                    eprintln!("Failed to subscribe to daemon events: {error}");
+                }
+            });
+            read_messages(&app, &client, reader).await;
+            self.client.lock().await.take();
+            client
+                .pending()
+                .fail_all("The Codex Monitor daemon closed the connection")
+                .await;
+            eprintln!("Lost the daemon connection; reconnecting.");
+            let stream = loop {
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                if let Ok(stream) = UnixStream::connect(&self.path).await {
+                    break stream;
+                }
+            };
+            (reader, client) = connect_client(stream);
+            *self.client.lock().await = Some(Arc::clone(&client));
+        }
+    }
+
+    /// Answers a webview `invoke` through the daemon. Errors keep the shape
+    /// the local commands reject with: `{ message, rpc }` when the agent
+    /// failed, otherwise a string.
+    pub(crate) fn forward(self: Arc<Self>, invoke: Invoke<Wry>) {
+        let app = invoke.message.webview_ref().app_handle().clone();
+        let method = invoke.message.command().to_string();
+        let params = match invoke.message.payload() {
+            InvokeBody::Json(params) => params.clone(),
+            InvokeBody::Raw(_) => Value::Null,
+        };
+        invoke.resolver.respond_async(async move {
+            self.call(&app, &method, params)
+                .await
+                .map_err(InvokeError::from)
+        });
+    }
+
+    async fn call(&self, app: &AppHandle, method: &str, mut params: Value) -> Result<Value, Value> {
+        // The daemon has no window to ask where an export goes, so the folder
+        // is picked here and the daemon names the file.
+        if method == "export_thread" && params.get("path").is_none_or(Value::is_null) {
+            let Some(folder) = pick_export_folder(app).await? else {
+                return Ok(Value::Null);
+            };
+            if let Some(params) = params.as_object_mut() {
+                params.insert("path".to_string(), json!(folder));
+            }
+        }
+        let client = self
+            .client
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| json!("Not connected to the Codex Monitor daemon"))?;
+        match client.request(method, params, None).await {
+            Ok(response) => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+            Err(RpcError::Server { message, data, .. }) => Err(match data {
+                Some(rpc) => json!({ "message": message, "rpc": rpc }),
+                None => Value::String(message),
+            }),
+            Err(error) => Err(Value::String(error.to_string())),
+        }
+    }
+}
+
+/// Connects with plain blocking calls, retrying while the daemon starts up.
+fn connect_blocking(path: &Path) -> Result<std::os::unix::net::UnixStream, String> {
+    let mut attempt = 1;
+    loop {
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(stream) => return Ok(stream),
+            Err(error) if attempt >= CONNECT_ATTEMPTS => {
+                return Err(format!("No daemon on {} ({error})", path.display()))
+            }
+            Err(_) => {
+                attempt += 1;
+                std::thread::sleep(CONNECT_RETRY);
+            }
+        }
+    }
+}
+
+async fn read_messages(app: &AppHandle, client: &JsonRpcClient, reader: OwnedReadHalf) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        match message.get("method").and_then(Value::as_str) {
+            Some(AGENT_EVENT) => {
+                let params = message.get("params").cloned().unwrap_or(Value::Null);
+                let _ = app.emit(AGENT_EVENT, params);
+            }
+            Some(LAGGED_METHOD) => eprintln!("Missed agent events from the daemon: {line}"),
+            Some(_) => {}
+            None => {
+                if let Some(id) = message.get("id").and_then(Value::as_u64) {
+                    client.pending().resolve(id, message).await;
+                }
+            }
+        }
+    }
+}
+
+/// Asks which folder an export goes into; `None` when the dialog was dismissed.
+async fn pick_export_folder(app: &AppHandle) -> Result<Option<String>, Value> {
+    let (tx, rx) = oneshot::channel();
+    app.dialog().file().pick_folder(move |folder| {
+        let _ = tx.send(folder);
+    });
+    match rx.await.ok().flatten() {
+        Some(folder) => folder
+            .into_path()
+            .map(|path| Some(path.to_string_lossy().to_string()))
+            .map_err(|error| json!(error.to_string())),
+        None => Ok(None),
+    }
+}
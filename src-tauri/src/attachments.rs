@@ -0,0 +1,454 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use tokio::io::AsyncReadExt;
+use ts_rs::TS;
+
+use crate::protocol::UserInput;
+use crate::utils::{confine_path, normalize_path, resolve_path};
+
+const MAX_ATTACHMENTS: usize = 10;
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+/// Larger files are linked instead of embedded in ACP prompts.
+const MAX_EMBEDDED_FILE_BYTES: u64 = 1024 * 1024;
+const SNIFF_BYTES: usize = 4096;
+
+/// Something sent along with the text of a user message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum Attachment {
+    /// An image on disk, e.g. picked in the file dialog.
+    ImagePath { path: String },
+    /// Base64 image bytes, e.g. pasted from the clipboard.
+    ImageData {
+        data: String,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// A workspace file, relative to the workspace root as listed by `list_workspace_files`.
+    File { path: String },
+}
+
+/// An attachment that was read from disk, checked and typed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ResolvedAttachment {
+    Image {
+        mime_type: &'static str,
+        bytes: Vec<u8>,
+        path: Option<PathBuf>,
+    },
+    File {
+        path: PathBuf,
+        relative: String,
+        mime_type: &'static str,
+        size: u64,
+        /// `None` when the file is too large to embed.
+        contents: Option<Vec<u8>>,
+    },
+}
+
+/// The prompt content an ACP agent advertised in `initialize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PromptCapabilities {
+    pub(crate) image: bool,
+    pub(crate) embedded_context: bool,
+}
+
+impl PromptCapabilities {
+    pub(crate) fn from_initialize(result: &Value) -> Self {
+        let capabilities = &result["agentCapabilities"]["promptCapabilities"];
+        Self {
+            image: capabilities["image"].as_bool().unwrap_or(false),
+            embedded_context: capabilities["embeddedContext"].as_bool().unwrap_or(false),
+        }
+    }
+}
+
+fn image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Sniffs images and PDFs, then goes by extension; unknown text is `text/plain`.
+pub(crate) fn detect_mime(path: &Path, bytes: &[u8]) -> &'static str {
+    if let Some(mime) = image_mime(bytes) {
+        return mime;
+    }
+    if bytes.starts_with(b"%PDF-") {
+        return "application/pdf";
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let by_extension = match extension.as_str() {
+        "md" | "markdown" => Some("text/markdown"),
+        "json" => Some("application/json"),
+        "html" | "htm" => Some("text/html"),
+        "css" => Some("text/css"),
+        "js" | "mjs" | "cjs" | "jsx" => Some("text/javascript"),
+        "ts" | "tsx" => Some("text/x-typescript"),
+        "rs" => Some("text/x-rust"),
+        "py" => Some("text/x-python"),
+        "toml" => Some("application/toml"),
+        "yaml" | "yml" => Some("application/yaml"),
+        "xml" => Some("application/xml"),
+        "csv" => Some("text/csv"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    };
+    // A sniffed prefix may end inside a character.
+    let is_text = match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    };
+    by_extension.unwrap_or(if is_text {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    })
+}
+
+async fn read_head(path: &Path, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(len);
+    tokio::fs::File::open(path)
+        .await?
+        .take(len as u64)
+        .read_to_end(&mut head)
+        .await?;
+    Ok(head)
+}
+
+fn image_too_large(name: &str) -> String {
+    format!(
+        "{name} is larger than the {} MB image limit.",
+        MAX_IMAGE_BYTES / (1024 * 1024)
+    )
+}
+
+fn checked_image(
+    name: &str,
+    bytes: Vec<u8>,
+    path: Option<PathBuf>,
+) -> Result<ResolvedAttachment, String> {
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(image_too_large(name));
+    }
+    let mime_type = image_mime(&bytes)
+        .ok_or_else(|| format!("{name} is not a PNG, JPEG, GIF or WebP image."))?;
+    Ok(ResolvedAttachment::Image {
+        mime_type,
+        bytes,
+        path,
+    })
+}
+
+async fn resolve_attachment(
+    workspace: &Path,
+    attachment: &Attachment,
+) -> Result<ResolvedAttachment, String> {
+    match attachment {
+        Attachment::ImagePath { path } => {
+            let path = resolve_path(workspace, path);
+            let name = path.display().to_string();
+            let size = tokio::fs::metadata(&path)
+                .await
+                .map_err(|e| format!("Failed to read {name}: {e}"))?
+                .len();
+            if size > MAX_IMAGE_BYTES as u64 {
+                return Err(image_too_large(&name));
+            }
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|e| format!("Failed to read {name}: {e}"))?;
+            checked_image(&name, bytes, Some(path))
+        }
+        Attachment::ImageData { data, name } => {
+            let name = name.clone().unwrap_or_else(|| "Pasted image".to_string());
+            let bytes = STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("{name} is not valid base64: {e}"))?;
+            checked_image(&name, bytes, None)
+        }
+        Attachment::File { path: relative } => {
+            let root = normalize_path(workspace);
+            let path = resolve_path(workspace, relative);
+            if !path.starts_with(&root) {
+                return Err(format!("{relative} is outside the workspace."));
+            }
+            // Symlinks inside the workspace may still point out of it.
+            let target = confine_path(&path, std::slice::from_ref(&root))
+                .await
+                .map_err(|_| format!("{relative} is outside the workspace."))?;
+            let metadata = tokio::fs::metadata(&target)
+                .await
+                .map_err(|e| format!("Failed to read {relative}: {e}"))?;
+            if !metadata.is_file() {
+                return Err(format!("{relative} is not a file."));
+            }
+            let size = metadata.len();
+            let contents = if size <= MAX_EMBEDDED_FILE_BYTES {
+                Some(
+                    tokio::fs::read(&target)
+                        .await
+                        .map_err(|e| format!("Failed to read {relative}: {e}"))?,
+                )
+            } else {
+                None
+            };
+            let mime_type = match &contents {
+                Some(contents) => detect_mime(&path, contents),
+                None => detect_mime(
+                    &path,
+                    &read_head(&target, SNIFF_BYTES).await.unwrap_or_default(),
+                ),
+            };
+            Ok(ResolvedAttachment::File {
+                relative: path
+                    .strip_prefix(&root)
+                    .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_else(|_| relative.clone()),
+                path,
+                mime_type,
+                size,
+                contents,
+            })
+        }
+    }
+}
+
+/// Reads and validates attachments; relative paths resolve against the workspace.
+pub(crate) async fn resolve_attachments(
+    workspace_path: &str,
+    attachments: &[Attachment],
+) -> Result<Vec<ResolvedAttachment>, String> {
+    if attachments.len() > MAX_ATTACHMENTS {
+        return Err(format!(
+            "A message can carry at most {MAX_ATTACHMENTS} attachments."
+        ));
+    }
+    let workspace = Path::new(workspace_path);
+    let mut resolved = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        resolved.push(resolve_attachment(workspace, attachment).await?);
+    }
+    Ok(resolved)
+}
+
+fn data_url(mime_type: &str, bytes: &[u8]) -> String {
+    format!("data:{mime_type};base64,{}", STANDARD.encode(bytes))
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// Codex `turn/start` input. Files are mentioned by path; Codex reads them from the workspace.
+pub(crate) fn codex_inputs(text: String, attachments: &[ResolvedAttachment]) -> Vec<UserInput> {
+    let mut inputs = vec![UserInput::Text { text }];
+    for attachment in attachments {
+        inputs.push(match attachment {
+            ResolvedAttachment::Image {
+                path: Some(path), ..
+            } => UserInput::LocalImage {
+                path: path.to_string_lossy().to_string(),
+            },
+            ResolvedAttachment::Image {
+                mime_type, bytes, ..
+            } => UserInput::Image {
+                url: data_url(mime_type, bytes),
+            },
+            ResolvedAttachment::File { relative, .. } => UserInput::Text {
+                text: format!("@{relative}"),
+            },
+        });
+    }
+    inputs
+}
+
+/// ACP `session/prompt` content blocks. Files are embedded when the agent
+/// supports it and they are small enough, and linked otherwise.
+pub(crate) fn acp_prompt(
+    text: String,
+    attachments: &[ResolvedAttachment],
+    capabilities: PromptCapabilities,
+) -> Result<Vec<Value>, String> {
+    let mut blocks = vec![json!({ "type": "text", "text": text })];
+    for attachment in attachments {
+        let block = match attachment {
+            ResolvedAttachment::Image {
+                mime_type,
+                bytes,
+                path,
+            } => {
+                if !capabilities.image {
+                    return Err("This agent does not accept images.".to_string());
+                }
+                let mut block = json!({
+                    "type": "image",
+                    "mimeType": mime_type,
+                    "data": STANDARD.encode(bytes)
+                });
+                if let Some(path) = path {
+                    block["uri"] = json!(file_uri(path));
+                }
+                block
+            }
+            ResolvedAttachment::File {
+                path,
+                relative,
+                mime_type,
+                size,
+                contents,
+            } => match contents {
+                Some(contents) if capabilities.embedded_context => {
+                    let mut resource = json!({ "uri": file_uri(path), "mimeType": mime_type });
+                    match std::str::from_utf8(contents) {
+                        Ok(text) => resource["text"] = json!(text),
+                        Err(_) => resource["blob"] = json!(STANDARD.encode(contents)),
+                    }
+                    json!({ "type": "resource", "resource": resource })
+                }
+                _ => json!({
+                    "type": "resource_link",
+                    "uri": file_uri(path),
+                    "name": relative,
+                    "mimeType": mime_type,
+                    "size": size
+                }),
+            },
+        };
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acp_prompt, codex_inputs, resolve_attachments, Attachment, PromptCapabilities};
+    use crate::protocol::UserInput;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[tokio::test]
+    async fn attachments_become_codex_inputs_and_acp_blocks() {
+        let root = std::env::temp_dir().join(format!("attach-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("shot.png"), PNG).unwrap();
+        let workspace = root.to_string_lossy().to_string();
+
+        let attachments = vec![
+            Attachment::ImagePath {
+                path: "shot.png".to_string(),
+            },
+            Attachment::ImageData {
+                data: STANDARD.encode(PNG),
+                name: None,
+            },
+            Attachment::File {
+                path: "src/main.rs".to_string(),
+            },
+        ];
+        let resolved = resolve_attachments(&workspace, &attachments)
+            .await
+            .expect("resolve");
+        let inputs = codex_inputs("look".to_string(), &resolved);
+        assert!(matches!(&inputs[1], UserInput::LocalImage { path } if path.ends_with("shot.png")));
+        assert!(
+            matches!(&inputs[2], UserInput::Image { url } if url.starts_with("data:image/png;base64,"))
+        );
+        assert!(matches!(&inputs[3], UserInput::Text { text } if text == "@src/main.rs"));
+
+        let text_only = PromptCapabilities::default();
+        assert!(acp_prompt("look".to_string(), &resolved, text_only).is_err());
+        let capabilities = PromptCapabilities {
+            image: true,
+            embedded_context: true,
+        };
+        let blocks = acp_prompt("look".to_string(), &resolved, capabilities).expect("blocks");
+        assert_eq!(blocks[1]["mimeType"], "image/png");
+        assert_eq!(blocks[3]["resource"]["text"], "fn main() {}\n");
+        assert_eq!(blocks[3]["resource"]["mimeType"], "text/x-rust");
+
+        let capabilities = PromptCapabilities {
+            image: true,
+            embedded_context: false,
+        };
+        let blocks = acp_prompt("look".to_string(), &resolved, capabilities).expect("blocks");
+        assert_eq!(blocks[3]["type"], "resource_link");
+        assert_eq!(blocks[3]["name"], "src/main.rs");
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn rejects_non_images_and_paths_outside_the_workspace() {
+        let not_image = Attachment::ImageData {
+            data: STANDARD.encode(b"hello"),
+            name: Some("notes.txt".to_string()),
+        };
+        let error = resolve_attachments("/repo", &[not_image])
+            .await
+            .unwrap_err();
+        assert!(error.contains("notes.txt"));
+
+        let escape = Attachment::File {
+            path: "../secrets.env".to_string(),
+        };
+        assert_eq!(
+            resolve_attachments("/repo", &[escape]).await.unwrap_err(),
+            "../secrets.env is outside the workspace."
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn rejects_symlinks_out_of_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("attach-{}", uuid::Uuid::new_v4()));
+        let root = dir.join("workspace");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(dir.join("secrets.env"), "TOKEN=1\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("secrets.env"), root.join("secrets.env")).unwrap();
+        let workspace = root.to_string_lossy().to_string();
+
+        let linked = Attachment::File {
+            path: "secrets.env".to_string(),
+        };
+        assert_eq!(
+            resolve_attachments(&workspace, &[linked])
+                .await
+                .unwrap_err(),
+            "secrets.env is outside the workspace."
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::protocol::{ApprovalPolicy, SandboxPolicy};
+use crate::state::AppState;
+use crate::transcripts::{sanitize_id, transcript_thread_id};
+use crate::types::BackendType;
+
+const DEFAULT_QUERY_LIMIT: usize = 500;
+const MAX_QUERY_LIMIT: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum AuditKind {
+    /// An approval request the agent sent us.
+    Request,
+    /// Our answer to a request.
+    Response,
+    /// The access mode and sandbox a turn was started with.
+    TurnPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum AuditActor {
+    /// Answered from the UI.
+    User,
+    /// Answered by a workspace approval rule.
+    Rule,
+    /// Allowed without asking because the sandbox's approval policy is `never`.
+    Policy,
+    /// Answered over the remote API with the token named in `remoteToken`.
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct AuditEntry {
+    #[ts(type = "number")]
+    pub(crate) timestamp: u64,
+    pub(crate) workspace_id: String,
+    pub(crate) backend: BackendType,
+    pub(crate) kind: AuditKind,
+    #[serde(default)]
+    pub(crate) thread_id: Option<String>,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub(crate) request_id: Option<u64>,
+    #[serde(default)]
+    pub(crate) method: Option<String>,
+    /// `accept`/`decline` for Codex, the chosen option for ACP agents.
+    #[serde(default)]
+    pub(crate) decision: Option<String>,
+    #[serde(default)]
+    pub(crate) actor: Option<AuditActor>,
+    /// Label of the remote access token that answered.
+    #[serde(default)]
+    pub(crate) remote_token: Option<String>,
+    #[serde(default)]
+    pub(crate) access_mode: Option<String>,
+    #[serde(default)]
+    pub(crate) approval_policy: Option<ApprovalPolicy>,
+    #[serde(default)]
+    pub(crate) sandbox_policy: Option<SandboxPolicy>,
+    /// Request params or response result, as sent on the wire.
+    #[serde(default)]
+    pub(crate) details: Option<Value>,
+}
+
+impl AuditEntry {
+    fn new(workspace_id: &str, backend: BackendType, kind: AuditKind) -> Self {
+        Self {
+            timestamp: now_ms(),
+            workspace_id: workspace_id.to_string(),
+            backend,
+            kind,
+            thread_id: None,
+            turn_id: None,
+            request_id: None,
+            method: None,
+            decision: None,
+            actor: None,
+            remote_token: None,
+            access_mode: None,
+            approval_policy: None,
+            sandbox_policy: None,
+            details: None,
+        }
+    }
+
+    pub(crate) fn request(
+        workspace_id: &str,
+        backend: BackendType,
+        request_id: u64,
+        method: &str,
+        params: Option<&Value>,
+    ) -> Self {
+        Self {
+            thread_id: transcript_thread_id(params),
+            turn_id: params.and_then(|params| str_field(params, "turnId")),
+            request_id: Some(request_id),
+            method: Some(method.to_string()),
+            details: params.cloned(),
+            ..Self::new(workspace_id, backend, AuditKind::Request)
+        }
+    }
+
+    pub(crate) fn response(
+        workspace_id: &str,
+        backend: BackendType,
+        request_id: u64,
+        result: &Value,
+        actor: AuditActor,
+    ) -> Self {
+        Self {
+            request_id: Some(request_id),
+            decision: decision_of(result),
+            actor: Some(actor),
+            details: Some(result.clone()),
+            ..Self::new(workspace_id, backend, AuditKind::Response)
+        }
+    }
+
+    pub(crate) fn turn_policy(
+        workspace_id: &str,
+        thread_id: &str,
+        turn_id: Option<String>,
+        access_mode: &str,
+        approval_policy: ApprovalPolicy,
+        sandbox_policy: SandboxPolicy,
+    ) -> Self {
+        Self {
+            thread_id: Some(thread_id.to_string()),
+            turn_id,
+            access_mode: Some(access_mode.to_string()),
+            approval_policy: Some(approval_policy),
+            sandbox_policy: Some(sandbox_policy),
+            ..Self::new(workspace_id, BackendType::Codex, AuditKind::TurnPolicy)
+        }
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+/// Reads the decision out of a Codex (`decision`) or ACP (`outcome`) answer.
+fn decision_of(result: &Value) -> Option<String> {
+    if let Some(decision) = str_field(result, "decision") {
+        return Some(decision);
+    }
+    let outcome = result.get("outcome")?;
+    str_field(outcome, "optionId").or_else(|| str_field(outcome, "outcome"))
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuditQuery {
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) decision: Option<String>,
+    /// Inclusive lower bound, in milliseconds since the epoch.
+    pub(crate) since: Option<u64>,
+    /// Exclusive upper bound, in milliseconds since the epoch.
+    pub(crate) until: Option<u64>,
+    pub(crate) limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        let workspace_matches = self
+            .workspace_id
+            .as_ref()
+            .is_none_or(|workspace_id| entry.workspace_id == *workspace_id);
+        let thread_matches = self
+            .thread_id
+            .as_ref()
+            .is_none_or(|thread_id| entry.thread_id.as_ref() == Some(thread_id));
+        let decision_matches = self
+            .decision
+            .as_ref()
+            .is_none_or(|decision| entry.decision.as_ref() == Some(decision));
+        workspace_matches
+            && thread_matches
+            && decision_matches
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+    }
+}
+
+/// Where a request came from, remembered until it is answered.
+#[derive(Debug, Clone)]
+struct RequestContext {
+    thread_id: Option<String>,
+    turn_id: Option<String>,
+    method: Option<String>,
+}
+
+/// Append-only JSONL logs under `<app data>/audit/<workspace>.jsonl`.
+///
+/// Unlike transcripts these are never rotated or trimmed. File access is
+/// blocking; callers go through `spawn_blocking` (see [`record_audit`]).
+pub(crate) struct AuditLog {
+    root: PathBuf,
+    files: HashMap<String, File>,
+    open_requests: HashMap<(String, BackendType, u64), RequestContext>,
+}
+
+impl AuditLog {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            files: HashMap::new(),
+            open_requests: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, workspace_id: &str) -> PathBuf {
+        log_path(&self.root, workspace_id)
+    }
+
+    /// Forgets the unanswered requests of a backend whose process has exited;
+    /// their answers can no longer arrive.
+    pub(crate) fn session_ended(&mut self, workspace_id: &str, backend: &BackendType) {
+        self.open_requests
+            .retain(|(workspace, kind, _), _| workspace != workspace_id || kind != backend);
+    }
+
+    /// Appends `entry`; responses inherit the thread, turn and method of their request.
+    pub(crate) fn append(&mut self, mut entry: AuditEntry) -> Result<(), String> {
+        if let Some(request_id) = entry.request_id {
+            let key = (
+                entry.workspace_id.clone(),
+                entry.backend.clone(),
+                request_id,
+            );
+            match entry.kind {
+                AuditKind::Request => {
+                    self.open_requests.insert(
+                        key,
+                        RequestContext {
+                            thread_id: entry.thread_id.clone(),
+                            turn_id: entry.turn_id.clone(),
+                            method: entry.method.clone(),
+                        },
+                    );
+                }
+                AuditKind::Response => {
+                    if let Some(context) = self.open_requests.remove(&key) {
+                        entry.thread_id = entry.thread_id.or(context.thread_id);
+                        entry.turn_id = entry.turn_id.or(context.turn_id);
+                        entry.method = entry.method.or(context.method);
+                    }
+                }
+                AuditKind::TurnPolicy => {}
+            }
+        }
+
+        if !self.files.contains_key(&entry.workspace_id) {
+            fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(&entry.workspace_id))
+                .map_err(|e| e.to_string())?;
+            self.files.insert(entry.workspace_id.clone(), file);
+        }
+        let Some(file) = self.files.get_mut(&entry.workspace_id) else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+fn log_path(root: &Path, workspace_id: &str) -> PathBuf {
+    root.join(format!("{}.jsonl", sanitize_id(workspace_id)))
+}
+
+/// Matching entries under `root`, oldest first, keeping the most recent
+/// `limit`. Blocking; the logs are streamed so only matches are held.
+pub(crate) fn read_audit_log(root: &Path, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .clamp(1, MAX_QUERY_LIMIT);
+    let keep_newest = |entries: &mut Vec<AuditEntry>| {
+        entries.sort_by_key(|entry| entry.timestamp);
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+    };
+    let paths = match &query.workspace_id {
+        Some(workspace_id) => vec![log_path(root, workspace_id)],
+        None => match fs::read_dir(root) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+    let mut entries = Vec::new();
+    for path in paths {
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) else {
+                continue;
+            };
+            if query.matches(&entry) {
+                entries.push(entry);
+                if entries.len() >= 2 * limit {
+                    keep_newest(&mut entries);
+                }
+            }
+        }
+    }
+    keep_newest(&mut entries);
+    Ok(entries)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Drops the request context kept for a backend process that has exited.
+pub(crate) async fn audit_session_ended(
+    state: &AppState,
+    workspace_id: &str,
+    backend: &BackendType,
+) {
+    state
+        .audit_log
+        .lock()
+        .await
+        .session_ended(workspace_id, backend);
+}
+
+/// Appends to the audit log; failures are logged, not surfaced.
+pub(crate) async fn record_audit(state: &AppState, entry: AuditEntry) {
+    let log = Arc::clone(&state.audit_log);
+    let write = tokio::task::spawn_blocking(move || {
+        let mut log = log.blocking_lock();
+        let workspace_id = entry.workspace_id.clone();
+        if let Err(error) = log.append(entry) {
+            eprintln!(
+                "Failed to write audit log for {workspace_id} under {}: {error}",
+                log.root().display()
+            );
+        }
+    });
+    let _ = write.await;
+}
+
+pub(crate) async fn query_audit_log(
+    state: &AppState,
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+    decision: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, String> {
+    let query = AuditQuery {
+        workspace_id,
+        thread_id,
+        decision,
+        since,
+        until,
+        limit,
+    };
+    let root = state.audit_log.lock().await.root().to_path_buf();
+    tokio::task::spawn_blocking(move || read_audit_log(&root, &query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_audit_log, AuditActor, AuditEntry, AuditKind, AuditLog, AuditQuery};
+    use crate::types::BackendType;
+    use serde_json::json;
+
+    #[test]
+    fn responses_inherit_request_context_and_filter_by_decision() {
+        let root = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        let mut log = AuditLog::new(root.clone());
+        let params = json!({ "threadId": "thr_1", "turnId": "turn_1", "command": "rm -rf build" });
+        log.append(AuditEntry::request(
+            "ws",
+            BackendType::Codex,
+            4,
+            "item/commandExecution/requestApproval",
+            Some(&params),
+        ))
+        .expect("append request");
+        log.append(AuditEntry::response(
+            "ws",
+            BackendType::Codex,
+            4,
+            &json!({ "decision": "accept" }),
+            AuditActor::User,
+        ))
+        .expect("append response");
+
+        let accepted = read_audit_log(
+            &root,
+            &AuditQuery {
+                workspace_id: Some("ws".to_string()),
+                thread_id: Some("thr_1".to_string()),
+                decision: Some("accept".to_string()),
+                ..AuditQuery::default()
+            },
+        )
+        .expect("query");
+        assert_eq!(accepted.len(), 1);
+        assert_eq!(accepted[0].kind, AuditKind::Response);
+        assert_eq!(accepted[0].turn_id.as_deref(), Some("turn_1"));
+        assert_eq!(
+            accepted[0].method.as_deref(),
+            Some("item/commandExecution/requestApproval")
+        );
+
+        let everything = read_audit_log(&root, &AuditQuery::default()).expect("query all");
+        assert_eq!(everything.len(), 2);
+        let none = read_audit_log(
+            &root,
+            &AuditQuery {
+                since: Some(everything[1].timestamp + 1),
+                ..AuditQuery::default()
+            },
+        )
+        .expect("query range");
+        assert!(none.is_empty());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn ended_sessions_forget_their_open_requests() {
+        let root = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        let mut log = AuditLog::new(root.clone());
+        for (workspace, backend) in [("ws", BackendType::Codex), ("ws", BackendType::OpenCode)] {
+            log.append(AuditEntry::request(
+                workspace,
+                backend,
+                1,
+                "session/request_permission",
+                None,
+            ))
+            .expect("append request");
+        }
+        log.session_ended("ws", &BackendType::Codex);
+        assert_eq!(log.open_requests.len(), 1);
+        assert!(log
+            .open_requests
+            .contains_key(&("ws".to_string(), BackendType::OpenCode, 1)));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
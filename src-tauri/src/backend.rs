@@ -0,0 +1,450 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Emitter, Manager};
+use ts_rs::TS;
+
+use crate::acp::AcpSession;
+use crate::attachments::Attachment;
+use crate::audit::{record_audit, AuditActor, AuditEntry};
+use crate::codex::WorkspaceSession;
+use crate::rpc::CommandError;
+use crate::state::AppState;
+use crate::types::{AppSettings, BackendType, WorkspaceEntry};
+
+/// Tauri event carrying every [`AgentEvent`], whatever the backend.
+pub(crate) const AGENT_EVENT: &str = "agent-event";
+
+pub(crate) type BackendFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, CommandError>> + Send + 'a>>;
+
+/// Envelope for everything an agent process pushes to the UI: forwarded
+/// notifications, server requests (with their `id`), and lifecycle events.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct AgentEvent {
+    pub(crate) workspace_id: String,
+    pub(crate) backend: BackendType,
+    #[ts(type = "number | null")]
+    pub(crate) id: Option<u64>,
+    pub(crate) method: String,
+    pub(crate) params: Option<Value>,
+}
+
+impl AgentEvent {
+    pub(crate) fn new(
+        workspace_id: &str,
+        backend: BackendType,
+        method: &str,
+        params: Value,
+    ) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            backend,
+            id: None,
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+
+    /// Wraps a JSON-RPC notification or request read from an agent's stdout.
+    pub(crate) fn from_message(
+        workspace_id: &str,
+        backend: BackendType,
+        message: &Value,
+    ) -> Option<Self> {
+        let method = message.get("method")?.as_str()?;
+        Some(Self {
+            workspace_id: workspace_id.to_string(),
+            backend,
+            id: message.get("id").and_then(|id| id.as_u64()),
+            method: method.to_string(),
+            params: message.get("params").cloned(),
+        })
+    }
+}
+
+/// Where agent events go. Backend code emits through this instead of a Tauri
+/// handle so it also runs in the daemon and under plain tokio tests.
+pub(crate) trait EventSink: Send + Sync {
+    fn emit(&self, event: AgentEvent);
+}
+
+/// What long-running backend work (session readers, reconnects, the prompt
+/// queue) holds on to: the app state and a sink for its events.
+pub(crate) trait AgentHost: EventSink {
+    fn app_state(&self) -> &AppState;
+}
+
+pub(crate) type SharedHost = Arc<dyn AgentHost>;
+
+/// The Tauri app as a host: events reach the webview and daemon subscribers.
+#[cfg(feature = "gui")]
+impl EventSink for AppHandle {
+    fn emit(&self, event: AgentEvent) {
+        if let Some(state) = self.try_state::<AppState>() {
+            EventSink::emit(state.inner(), event.clone());
+        }
+        let _ = Emitter::emit(self, AGENT_EVENT, event);
+    }
+}
+
+#[cfg(feature = "gui")]
+impl AgentHost for AppHandle {
+    fn app_state(&self) -> &AppState {
+        Manager::state::<AppState>(self).inner()
+    }
+}
+
+#[cfg(feature = "gui")]
+pub(crate) fn app_host(app: AppHandle) -> SharedHost {
+    Arc::new(app)
+}
+
+/// The bare state as a host, with no window: events only reach subscribers
+/// of `AppState::events`.
+impl EventSink for AppState {
+    fn emit(&self, event: AgentEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl AgentHost for AppState {
+    fn app_state(&self) -> &AppState {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct BackendHealth {
+    pub(crate) backend: BackendType,
+    pub(crate) alive: bool,
+    pub(crate) pending_requests: usize,
+    pub(crate) stderr_tail: Vec<String>,
+}
+
+/// A user turn, in the terms every backend understands. `model` is passed
+/// through verbatim (OpenCode expects `provider/model`).
+#[derive(Debug, Clone)]
+pub(crate) struct MessageRequest {
+    pub(crate) thread_id: String,
+    pub(crate) text: String,
+    pub(crate) model: Option<String>,
+    pub(crate) effort: Option<String>,
+    pub(crate) access_mode: Option<String>,
+    pub(crate) attachments: Vec<Attachment>,
+}
+
+/// One connected agent process serving a workspace.
+///
+/// Operations return the backend's own JSON so the frontend keeps parsing
+/// the shapes it already knows; backend-specific calls downcast via `as_any`.
+pub(crate) trait AgentBackend: Send + Sync {
+    fn connect(
+        entry: WorkspaceEntry,
+        settings: AppSettings,
+        host: SharedHost,
+    ) -> BackendFuture<'static, Arc<Self>>
+    where
+        Self: Sized;
+
+    fn kind(&self) -> BackendType;
+
+    fn start_thread(&self) -> BackendFuture<'_, Value>;
+
+    fn send_message<'a>(
+        &'a self,
+        state: &'a AppState,
+        request: MessageRequest,
+    ) -> BackendFuture<'a, Value>;
+
+    fn interrupt<'a>(
+        &'a self,
+        thread_id: &'a str,
+        turn_id: Option<&'a str>,
+    ) -> BackendFuture<'a, Value>;
+
+    fn list_threads<'a>(
+        &'a self,
+        state: &'a AppState,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> BackendFuture<'a, Value>;
+
+    fn list_models<'a>(&'a self, state: &'a AppState) -> BackendFuture<'a, Value>;
+
+    /// Answers a request the agent sent us (an approval or permission prompt).
+    fn respond<'a>(&'a self, request_id: u64, result: Value) -> BackendFuture<'a, ()>;
+
+    /// Picks up changed app settings without reconnecting.
+    fn apply_settings<'a>(&'a self, _settings: &'a AppSettings) -> BackendFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn shutdown(&self) -> BackendFuture<'_, ()>;
+
+    fn health(&self) -> BackendFuture<'_, BackendHealth>;
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+/// Connected agents keyed by workspace and backend. A workspace normally has
+/// one, but a Codex workspace may also run an ACP agent next to it.
+#[derive(Default)]
+pub(crate) struct SessionRegistry {
+    sessions: HashMap<(String, BackendType), Arc<dyn AgentBackend>>,
+}
+
+impl SessionRegistry {
+    pub(crate) fn get(
+        &self,
+        workspace_id: &str,
+        backend: &BackendType,
+    ) -> Option<Arc<dyn AgentBackend>> {
+        self.sessions
+            .get(&(workspace_id.to_string(), backend.clone()))
+            .cloned()
+    }
+
+    pub(crate) fn contains(&self, workspace_id: &str, backend: &BackendType) -> bool {
+        self.get(workspace_id, backend).is_some()
+    }
+
+    pub(crate) fn insert(&mut self, workspace_id: &str, session: Arc<dyn AgentBackend>) {
+        self.sessions
+            .insert((workspace_id.to_string(), session.kind()), session);
+    }
+
+    pub(crate) fn remove(
+        &mut self,
+        workspace_id: &str,
+        backend: &BackendType,
+    ) -> Option<Arc<dyn AgentBackend>> {
+        self.sessions
+            .remove(&(workspace_id.to_string(), backend.clone()))
+    }
+
+    /// Removes `session` only if it is still the registered one, so a stale
+    /// process exiting cannot evict its replacement.
+    pub(crate) fn remove_if_current<T: AgentBackend>(
+        &mut self,
+        workspace_id: &str,
+        session: &Arc<T>,
+    ) -> bool {
+        let key = (workspace_id.to_string(), session.kind());
+        match self.sessions.get(&key) {
+            Some(current) if std::ptr::addr_eq(Arc::as_ptr(current), Arc::as_ptr(session)) => {
+                self.sessions.remove(&key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn remove_workspace(&mut self, workspace_id: &str) -> Vec<Arc<dyn AgentBackend>> {
+        let keys: Vec<_> = self
+            .sessions
+            .keys()
+            .filter(|(id, _)| id == workspace_id)
+            .cloned()
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| self.sessions.remove(&key))
+            .collect()
+    }
+
+    pub(crate) fn all(&self) -> Vec<Arc<dyn AgentBackend>> {
+        self.sessions.values().cloned().collect()
+    }
+}
+
+/// Spawns the agent process for `entry`. This is the only place that maps a
+/// `BackendType` to an implementation.
+pub(crate) async fn connect_backend(
+    entry: WorkspaceEntry,
+    host: &SharedHost,
+) -> Result<Arc<dyn AgentBackend>, String> {
+    let settings = host.app_state().app_settings.lock().await.clone();
+    let host = host.clone();
+    let backend: Arc<dyn AgentBackend> = match entry.backend {
+        BackendType::Codex => WorkspaceSession::connect(entry, settings, host)
+            .await
+            .map_err(|error| error.message)?,
+        BackendType::OpenCode | BackendType::Acp => AcpSession::connect(entry, settings, host)
+            .await
+            .map_err(|error| error.message)?,
+    };
+    Ok(backend)
+}
+
+/// The agent serving the workspace's configured backend.
+pub(crate) async fn get_backend(
+    state: &AppState,
+    workspace_id: &str,
+) -> Result<Arc<dyn AgentBackend>, String> {
+    let backend = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .map(|entry| entry.backend.clone())
+            .ok_or("workspace not found")?
+    };
+    state
+        .sessions
+        .lock()
+        .await
+        .get(workspace_id, &backend)
+        .ok_or_else(|| "workspace not connected".to_string())
+}
+
+/// Looks up the workspace's agent of the given backend as its concrete type.
+pub(crate) async fn get_backend_as<T: Any + Send + Sync>(
+    state: &AppState,
+    workspace_id: &str,
+    backend: BackendType,
+) -> Option<Arc<T>> {
+    let session = state.sessions.lock().await.get(workspace_id, &backend)?;
+    session.as_any().downcast::<T>().ok()
+}
+
+/// Drops every agent of the workspace from the registry and stops them.
+pub(crate) async fn shutdown_backend(state: &AppState, workspace_id: &str) {
+    let sessions = state.sessions.lock().await.remove_workspace(workspace_id);
+    for session in sessions {
+        let _ = session.shutdown().await;
+    }
+}
+
+pub(crate) async fn start_thread(
+    state: &AppState,
+    workspace_id: String,
+) -> Result<Value, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    backend.start_thread().await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn send_user_message(
+    state: &AppState,
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+) -> Result<Value, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    let request = MessageRequest {
+        thread_id,
+        text,
+        model,
+        effort,
+        access_mode,
+        attachments: attachments.unwrap_or_default(),
+    };
+    backend.send_message(state, request).await
+}
+
+pub(crate) async fn turn_interrupt(
+    state: &AppState,
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+) -> Result<Value, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    backend.interrupt(&thread_id, Some(&turn_id)).await
+}
+
+pub(crate) async fn list_threads(
+    state: &AppState,
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<Value, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    backend.list_threads(state, cursor, limit).await
+}
+
+pub(crate) async fn model_list(
+    state: &AppState,
+    workspace_id: String,
+) -> Result<Value, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    backend.list_models(state).await
+}
+
+/// Answers an agent request on the user's behalf and audits the answer, under
+/// `remote_token` when it came in over the remote API.
+pub(crate) async fn respond_to_request(
+    state: &AppState,
+    workspace_id: &str,
+    request_id: u64,
+    result: Value,
+    backend: Option<BackendType>,
+    remote_token: Option<&str>,
+) -> Result<(), CommandError> {
+    let session = match backend {
+        Some(backend) => state
+            .sessions
+            .lock()
+            .await
+            .get(workspace_id, &backend)
+            .ok_or_else(|| "workspace not connected".to_string())?,
+        None => get_backend(state, workspace_id).await?,
+    };
+    let actor = match remote_token {
+        Some(_) => AuditActor::Remote,
+        None => AuditActor::User,
+    };
+    let entry = AuditEntry {
+        remote_token: remote_token.map(str::to_string),
+        ..AuditEntry::response(workspace_id, session.kind(), request_id, &result, actor)
+    };
+    session.respond(request_id, result).await?;
+    record_audit(state, entry).await;
+    Ok(())
+}
+
+pub(crate) async fn backend_health(
+    state: &AppState,
+    workspace_id: String,
+) -> Result<BackendHealth, CommandError> {
+    let backend = get_backend(state, &workspace_id).await?;
+    backend.health().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AgentEvent;
+    use crate::types::BackendType;
+    use serde_json::json;
+
+    #[test]
+    fn agent_event_keeps_request_ids_and_params() {
+        let request = json!({
+            "id": 7,
+            "method": "item/commandExecution/requestApproval",
+            "params": { "threadId": "thr_1" }
+        });
+        let event = AgentEvent::from_message("ws", BackendType::Codex, &request).expect("event");
+        assert_eq!(event.id, Some(7));
+        assert_eq!(event.params, Some(json!({ "threadId": "thr_1" })));
+        assert_eq!(
+            serde_json::to_value(&event).unwrap()["workspaceId"],
+            json!("ws")
+        );
+
+        let response = json!({ "id": 8, "result": {} });
+        assert!(AgentEvent::from_message("ws", BackendType::OpenCode, &response).is_none());
+    }
+}
@@ -0,0 +1,10 @@
+#[cfg(unix)]
+fn main() {
+    std::process::exit(codex_monitor_lib::run_cli())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("The Codex Monitor command line client needs Unix domain sockets.");
+    std::process::exit(1);
+}
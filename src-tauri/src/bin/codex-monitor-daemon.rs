@@ -0,0 +1,10 @@
+#[cfg(unix)]
+fn main() {
+    codex_monitor_lib::run_daemon()
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("The Codex Monitor daemon needs Unix domain sockets.");
+    std::process::exit(1);
+}
@@ -0,0 +1,669 @@
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+use crate::acp_messages::MESSAGE_COMPLETED_METHOD;
+use crate::backend::AGENT_EVENT;
+use crate::daemon::{socket_path, SUBSCRIBE_METHOD};
+use crate::dispatch::LAGGED_METHOD;
+use crate::protocol::{KnownNotification, KnownThreadItem, ThreadItem, TurnStatus};
+
+const EXIT_OK: i32 = 0;
+/// The daemon answered with an error.
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+/// No daemon is listening on the socket.
+const EXIT_UNAVAILABLE: i32 = 3;
+const EXIT_TURN_FAILED: i32 = 4;
+const EXIT_TURN_INTERRUPTED: i32 = 5;
+
+const USAGE: &str = "\
+Usage: codex-monitor-cli [--socket PATH] <command>
+
+Commands:
+  workspaces                          List workspaces
+  connect <workspace>                 Start the workspace's agent
+  start-thread <workspace>            Start a thread and print it
+  send <workspace> <thread> <text>    Send a prompt and wait for the turn to end
+      [--model M] [--effort E] [--access-mode PROFILE] [--no-wait] [--follow]
+  git-status <workspace>              Print git status for a workspace or worktree
+  follow [<workspace>]                Print agent events until interrupted
+  call <method> [params-json]         Call any daemon method
+
+<workspace> is a workspace id, name or path. Output is one JSON value per line.
+Needs a running codex-monitor-daemon.";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Workspaces,
+    Connect {
+        workspace: String,
+    },
+    StartThread {
+        workspace: String,
+    },
+    Send {
+        workspace: String,
+        thread_id: String,
+        text: String,
+        model: Option<String>,
+        effort: Option<String>,
+        access_mode: Option<String>,
+        wait: bool,
+        follow: bool,
+    },
+    GitStatus {
+        workspace: String,
+    },
+    Follow {
+        workspace: Option<String>,
+    },
+    Call {
+        method: String,
+        params: Value,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Invocation {
+    socket: Option<PathBuf>,
+    command: Command,
+}
+
+fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let mut socket = None;
+    let mut positional = Vec::new();
+    let mut options: Vec<(String, Option<String>)> = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--socket" | "--model" | "--effort" | "--access-mode" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("{arg} needs a value"))?
+                    .clone();
+                if arg == "--socket" {
+                    socket = Some(PathBuf::from(value));
+                } else {
+                    options.push((arg.clone(), Some(value)));
+                }
+            }
+            "--no-wait" | "--follow" => options.push((arg.clone(), None)),
+            "-h" | "--help" => positional.insert(0, "help".to_string()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    let positional: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let command = match positional.as_slice() {
+        ["help", ..] => Command::Help,
+        ["workspaces"] => Command::Workspaces,
+        ["connect", workspace] => Command::Connect {
+            workspace: workspace.to_string(),
+        },
+        ["start-thread", workspace] => Command::StartThread {
+            workspace: workspace.to_string(),
+        },
+        ["send", workspace, thread_id, text] => Command::Send {
+            workspace: workspace.to_string(),
+            thread_id: thread_id.to_string(),
+            text: text.to_string(),
+            model: option("--model").flatten(),
+            effort: option("--effort").flatten(),
+            access_mode: option("--access-mode").flatten(),
+            wait: option("--no-wait").is_none(),
+            follow: option("--follow").is_some(),
+        },
+        ["git-status", workspace] => Command::GitStatus {
+            workspace: workspace.to_string(),
+        },
+        ["follow"] => Command::Follow { workspace: None },
+        ["follow", workspace] => Command::Follow {
+            workspace: Some(workspace.to_string()),
+        },
+        ["call", method] => Command::Call {
+            method: method.to_string(),
+            params: Value::Null,
+        },
+        ["call", method, params] => Command::Call {
+            method: method.to_string(),
+            params: serde_json::from_str(params)
+                .map_err(|error| format!("Invalid params JSON: {error}"))?,
+        },
+        [] => return Err("Missing command".to_string()),
+        [command, ..] => return Err(format!("Unexpected arguments for {command}")),
+    };
+    if !options.is_empty() && !matches!(command, Command::Send { .. }) {
+        return Err(
+            "--model, --effort, --access-mode, --no-wait and --follow only apply to send"
+                .to_string(),
+        );
+    }
+    Ok(Invocation { socket, command })
+}
+
+#[derive(Debug)]
+enum CliError {
+    Unavailable(String),
+    Failed(String),
+    /// The daemon dropped this many events because we read too slowly.
+    Lagged(u64),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Unavailable(_) => EXIT_UNAVAILABLE,
+            CliError::Failed(_) | CliError::Lagged(_) => EXIT_FAILED,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            CliError::Unavailable(message) | CliError::Failed(message) => message.clone(),
+            CliError::Lagged(skipped) => format!(
+                "Missed {skipped} events from the daemon, so the turn's outcome is unknown; \
+                 check the thread with resume_thread"
+            ),
+        }
+    }
+}
+
+/// A connection to the daemon. Notifications that arrive while waiting for a
+/// reply are kept for `next_event`.
+struct DaemonClient {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+    events: VecDeque<Value>,
+}
+
+impl DaemonClient {
+    async fn connect(path: &Path) -> Result<Self, CliError> {
+        let stream = UnixStream::connect(path).await.map_err(|error| {
+            CliError::Unavailable(format!(
+                "No daemon on {} ({error}). Start codex-monitor-daemon first.",
+                path.display()
+            ))
+        })?;
+        Ok(Self::from_stream(stream))
+    }
+
+    fn from_stream(stream: UnixStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+            events: VecDeque::new(),
+        }
+    }
+
+    async fn read_message(&mut self) -> Result<Value, CliError> {
+        let line = self
+            .lines
+            .next_line()
+            .await
+            .map_err(|error| CliError::Unavailable(error.to_string()))?
+            .ok_or_else(|| CliError::Unavailable("The daemon closed the connection".to_string()))?;
+        serde_json::from_str(&line).map_err(|error| CliError::Failed(error.to_string()))
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value, CliError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut line =
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|error| CliError::Unavailable(error.to_string()))?;
+        loop {
+            let message = self.read_message().await?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                self.events.push_back(message);
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string());
+                return Err(CliError::Failed(text));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// The next `agent-event` payload; fails with `CliError::Lagged` when the
+    /// daemon reports that it dropped events for this connection.
+    async fn next_event(&mut self) -> Result<Value, CliError> {
+        loop {
+            let message = match self.events.pop_front() {
+                Some(message) => message,
+                None => self.read_message().await?,
+            };
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+            match message.get("method").and_then(Value::as_str) {
+                Some(AGENT_EVENT) => return Ok(params),
+                Some(LAGGED_METHOD) => {
+                    let skipped = params.get("skipped").and_then(Value::as_u64);
+                    return Err(CliError::Lagged(skipped.unwrap_or_default()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Accepts a workspace id, name or path.
+    async fn resolve_workspace(&mut self, workspace: &str) -> Result<String, CliError> {
+        let workspaces = self.call("list_workspaces", Value::Null).await?;
+        let list = workspaces.as_array().cloned().unwrap_or_default();
+        for key in ["id", "name", "path"] {
+            let matches: Vec<&Value> = list
+                .iter()
+                .filter(|entry| entry.get(key).and_then(Value::as_str) == Some(workspace))
+                .collect();
+            match matches.as_slice() {
+                [] => continue,
+                [entry] => {
+                    return Ok(entry
+                        .get("id")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string())
+                }
+                _ => {
+                    return Err(CliError::Failed(format!(
+                        "More than one workspace is named {workspace}; use its id"
+                    )))
+                }
+            }
+        }
+        Err(CliError::Failed(format!(
+            "workspace not found: {workspace}"
+        )))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TurnOutcome {
+    Completed,
+    Interrupted,
+    Failed,
+}
+
+impl TurnOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            TurnOutcome::Completed => "completed",
+            TurnOutcome::Interrupted => "interrupted",
+            TurnOutcome::Failed => "failed",
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            TurnOutcome::Completed => EXIT_OK,
+            TurnOutcome::Interrupted => EXIT_TURN_INTERRUPTED,
+            TurnOutcome::Failed => EXIT_TURN_FAILED,
+        }
+    }
+}
+
+/// The turn `send` is waiting on, and what it has seen of it so far.
+struct PendingTurn {
+    thread_id: String,
+    turn_id: Option<String>,
+    last_message: Option<Value>,
+}
+
+impl PendingTurn {
+    /// Reads one agent event; returns the outcome once the turn has ended.
+    fn observe(&mut self, event: &Value) -> Option<TurnOutcome> {
+        let method = event.get("method").and_then(Value::as_str)?;
+        let params = event.get("params").cloned().unwrap_or(Value::Null);
+        match method {
+            "codex/disconnected" | "acp/disconnected" => return Some(TurnOutcome::Failed),
+            MESSAGE_COMPLETED_METHOD => {
+                if params.get("sessionId").and_then(Value::as_str) != Some(&self.thread_id) {
+                    return None;
+                }
+                self.last_message = params.get("message").cloned();
+                if params.get("error").is_some_and(|error| !error.is_null()) {
+                    return Some(TurnOutcome::Failed);
+                }
+                return match params.get("stopReason").and_then(Value::as_str) {
+                    Some("cancelled") => Some(TurnOutcome::Interrupted),
+                    Some("refusal") => Some(TurnOutcome::Failed),
+                    _ => Some(TurnOutcome::Completed),
+                };
+            }
+            _ => {}
+        }
+        let notification =
+            serde_json::from_value(json!({ "method": method, "params": params })).ok()?;
+        match notification {
+            KnownNotification::ItemCompleted(event) if event.thread_id == self.thread_id => {
+                if let ThreadItem::Known(KnownThreadItem::AgentMessage { .. }) = &event.item {
+                    self.last_message = serde_json::to_value(&event.item).ok();
+                }
+                None
+            }
+            KnownNotification::TurnCompleted(event) => {
+                let ours = match event.turn.thread_id.as_deref() {
+                    Some(thread_id) => thread_id == self.thread_id,
+                    None => self.turn_id.as_deref() == Some(event.turn.id.as_str()),
+                };
+                if !ours {
+                    return None;
+                }
+                Some(match event.turn.status {
+                    Some(TurnStatus::Interrupted) => TurnOutcome::Interrupted,
+                    Some(TurnStatus::Failed) => TurnOutcome::Failed,
+                    _ => TurnOutcome::Completed,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn turn_id_of(response: &Value) -> Option<String> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("turn")
+        .and_then(|turn| turn.get("id"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn print(value: &Value) {
+    println!("{value}");
+}
+
+async fn run_command(client: &mut DaemonClient, command: Command) -> Result<i32, CliError> {
+    match command {
+        Command::Help => {
+            println!("{USAGE}");
+        }
+        Command::Workspaces => print(&client.call("list_workspaces", Value::Null).await?),
+        Command::Connect { workspace } => {
+            let id = client.resolve_workspace(&workspace).await?;
+            client
+                .call("connect_workspace", json!({ "id": id }))
+                .await?;
+            print(&json!({ "workspaceId": id, "connected": true }));
+        }
+        Command::StartThread { workspace } => {
+            let id = client.resolve_workspace(&workspace).await?;
+            print(
+                &client
+                    .call("start_thread", json!({ "workspaceId": id }))
+                    .await?,
+            );
+        }
+        Command::GitStatus { workspace } => {
+            let id = client.resolve_workspace(&workspace).await?;
+            print(
+                &client
+                    .call("get_git_status", json!({ "workspaceId": id }))
+                    .await?,
+            );
+        }
+        Command::Call { method, params } => print(&client.call(&method, params).await?),
+        Command::Follow { workspace } => {
+            let workspace_id = match workspace {
+                Some(workspace) => Some(client.resolve_workspace(&workspace).await?),
+                None => None,
+            };
+            client
+                .call(SUBSCRIBE_METHOD, json!({ "workspaceId": workspace_id }))
+                .await?;
+            // Watching is best effort, so a gap is reported and skipped.
+            loop {
+                match client.next_event().await {
+                    Ok(event) => print(&event),
+                    Err(CliError::Lagged(skipped)) => {
+                        print(&json!({ "method": LAGGED_METHOD, "params": { "skipped": skipped } }))
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+        Command::Send {
+            workspace,
+            thread_id,
+            text,
+            model,
+            effort,
+            access_mode,
+            wait,
+            follow,
+        } => {
+            let id = client.resolve_workspace(&workspace).await?;
+            // Subscribe first so a fast turn cannot end before we listen.
+            if wait || follow {
+                client
+                    .call(SUBSCRIBE_METHOD, json!({ "workspaceId": id }))
+                    .await?;
+            }
+            let response = client
+                .call(
+                    "send_user_message",
+                    json!({
+                        "workspaceId": id,
+                        "threadId": thread_id,
+                        "text": text,
+                        "model": model,
+                        "effort": effort,
+                        "accessMode": access_mode,
+                    }),
+                )
+                .await?;
+            if !wait {
+                print(&response);
+                return Ok(EXIT_OK);
+            }
+            let mut turn = PendingTurn {
+                thread_id: thread_id.clone(),
+                turn_id: turn_id_of(&response),
+                last_message: None,
+            };
+            let outcome = loop {
+                let event = client.next_event().await?;
+                if follow {
+                    print(&event);
+                }
+                if let Some(outcome) = turn.observe(&event) {
+                    break outcome;
+                }
+            };
+            print(&json!({
+                "workspaceId": id,
+                "threadId": thread_id,
+                "turnId": turn.turn_id,
+                "status": outcome.label(),
+                "lastMessage": turn.last_message,
+            }));
+            return Ok(outcome.exit_code());
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+/// Runs the `codex-monitor-cli` command line client and returns its exit code.
+pub fn run_cli() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let invocation = match parse_args(&args) {
+        Ok(invocation) => invocation,
+        Err(error) => {
+            eprintln!("{}", json!({ "error": error }));
+            eprintln!("{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+    if invocation.command == Command::Help {
+        println!("{USAGE}");
+        return EXIT_OK;
+    }
+    let Some(path) = invocation.socket.or_else(socket_path) else {
+        eprintln!(
+            "{}",
+            json!({ "error": "Cannot locate the daemon socket; pass --socket" })
+        );
+        return EXIT_USAGE;
+    };
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            eprintln!("{}", json!({ "error": error.to_string() }));
+            return EXIT_FAILED;
+        }
+    };
+    let result = runtime.block_on(async {
+        let mut client = DaemonClient::connect(&path).await?;
+        run_command(&mut client, invocation.command).await
+    });
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", json!({ "error": error.message() }));
+            error.exit_code()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, CliError, Command, DaemonClient, PendingTurn, TurnOutcome};
+    use serde_json::json;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::UnixStream;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_send_with_options() {
+        let invocation = parse_args(&args(&[
+            "--socket",
+            "/tmp/cm.sock",
+            "send",
+            "repo",
+            "thr_1",
+            "run the tests",
+            "--model",
+            "gpt-5",
+            "--follow",
+        ]))
+        .unwrap();
+        assert_eq!(
+            invocation.socket.as_deref(),
+            Some(std::path::Path::new("/tmp/cm.sock"))
+        );
+        assert_eq!(
+            invocation.command,
+            Command::Send {
+                workspace: "repo".to_string(),
+                thread_id: "thr_1".to_string(),
+                text: "run the tests".to_string(),
+                model: Some("gpt-5".to_string()),
+                effort: None,
+                access_mode: None,
+                wait: true,
+                follow: true,
+            }
+        );
+        assert!(parse_args(&args(&["workspaces", "--model", "x"])).is_err());
+        assert!(parse_args(&args(&["git-status"])).is_err());
+        assert!(parse_args(&args(&["call", "list_workspaces", "{"])).is_err());
+    }
+
+    #[test]
+    fn pending_turn_maps_completion_events_to_outcomes() {
+        let mut turn = PendingTurn {
+            thread_id: "thr_1".to_string(),
+            turn_id: Some("turn_1".to_string()),
+            last_message: None,
+        };
+        let event = |method: &str, params: serde_json::Value| json!({ "workspaceId": "ws", "backend": "codex", "method": method, "params": params });
+        assert_eq!(
+            turn.observe(&event(
+                "item/completed",
+                json!({
+                    "threadId": "thr_1",
+                    "item": { "type": "agentMessage", "id": "item_1", "text": "done" }
+                })
+            )),
+            None
+        );
+        assert_eq!(turn.last_message.as_ref().unwrap()["text"], "done");
+        assert_eq!(
+            turn.observe(&event(
+                "turn/completed",
+                json!({ "turn": { "id": "turn_9", "threadId": "thr_2", "status": "failed" } })
+            )),
+            None
+        );
+        assert_eq!(
+            turn.observe(&event(
+                "turn/completed",
+                json!({ "turn": { "id": "turn_1", "status": "interrupted" } })
+            )),
+            Some(TurnOutcome::Interrupted)
+        );
+        assert_eq!(
+            turn.observe(&event(
+                "acp/message/completed",
+                json!({ "sessionId": "thr_1", "stopReason": "end_turn", "error": null })
+            )),
+            Some(TurnOutcome::Completed)
+        );
+        assert_eq!(
+            turn.observe(&event("codex/disconnected", json!({}))),
+            Some(TurnOutcome::Failed)
+        );
+    }
+
+    #[tokio::test]
+    async fn next_event_fails_when_the_daemon_dropped_events() {
+        let (ours, mut daemon) = UnixStream::pair().unwrap();
+        let mut client = DaemonClient::from_stream(ours);
+        daemon
+            .write_all(
+                concat!(
+                    r#"{"jsonrpc":"2.0","method":"agent-event","params":{"method":"turn/started"}}"#,
+                    "\n",
+                    r#"{"jsonrpc":"2.0","method":"events/lagged","params":{"skipped":3}}"#,
+                    "\n",
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let event = client.next_event().await.unwrap();
+        assert_eq!(event["method"], "turn/started");
+        assert!(matches!(
+            client.next_event().await,
+            Err(CliError::Lagged(3))
+        ));
+    }
+}
@@ -1,72 +1,332 @@
-use serde::Serialize;
-use serde_json::{json, Map, Value};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::io::ErrorKind;
 use std::path::Path;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use tauri::{AppHandle, Emitter, State};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::{oneshot, Mutex};
-use tokio::time::timeout;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout};
 
+use crate::approvals::{auto_decide, AutoDecision, AUTO_APPROVAL_METHOD};
+use crate::attachments::{codex_inputs, resolve_attachments};
+use crate::audit::{audit_session_ended, record_audit, AuditActor, AuditEntry};
+use crate::backend::{
+    get_backend_as, AgentBackend, AgentEvent, AgentHost, BackendFuture, BackendHealth,
+    MessageRequest, SharedHost,
+};
+use crate::prompt_queue::observe_notification;
+use crate::protocol::{
+    AppServerMessage, ApprovalPolicy, ClientInfo, InitializeParams, ReviewStartParams,
+    ReviewTarget, ServerNotification, ServerRequest, SkillsListParams, ThreadIdParams,
+    ThreadListParams, ThreadResponse, ThreadStartParams, TurnInterruptParams, TurnStartParams,
+};
+use crate::rate_limits::{observe_codex_rate_limits, poll_rate_limits};
+use crate::rpc::{CommandError, JsonRpcClient, PendingRequestInfo, RpcError};
+use crate::sandbox::sandbox_for_access_mode;
 use crate::state::AppState;
-use crate::types::WorkspaceEntry;
+use crate::transcripts::{record_transcript, TranscriptSource};
+use crate::types::{
+    AppSettings, ApprovalRule, BackendType, RequestTimeoutSettings, WorkspaceEntry,
+};
+use crate::usage::{observe_codex_usage, record_usage};
 
-#[derive(Serialize, Clone)]
-struct AppServerEvent {
-    workspace_id: String,
-    message: Value,
-}
+const CANCEL_REQUEST_METHOD: &str = "$/cancelRequest";
+const STDERR_TAIL_LINES: usize = 20;
+const RECONNECT_MAX_ATTEMPTS: u32 = 6;
+const RECONNECT_BASE_DELAY_MS: u64 = 500;
+const RECONNECT_MAX_DELAY_MS: u64 = 30_000;
 
 pub(crate) struct WorkspaceSession {
     pub(crate) entry: WorkspaceEntry,
     pub(crate) child: Mutex<Child>,
-    pub(crate) stdin: Mutex<ChildStdin>,
-    pub(crate) pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
-    pub(crate) next_id: AtomicU64,
+    pub(crate) rpc: JsonRpcClient,
+    pub(crate) request_timeouts: Mutex<RequestTimeoutSettings>,
+    pub(crate) stderr_tail: Mutex<VecDeque<String>>,
+    pub(crate) active_threads: Mutex<HashSet<String>>,
+    /// Decline rules from the excluded paths of each thread's latest sandbox profile.
+    pub(crate) thread_exclusions: Mutex<HashMap<String, Vec<ApprovalRule>>>,
+    /// The model the app-server reported for each thread on start or resume,
+    /// which turns started without a model run on.
+    pub(crate) thread_models: Mutex<HashMap<String, String>>,
 }
 
 impl WorkspaceSession {
-    async fn write_message(&self, value: Value) -> Result<(), String> {
-        let mut stdin = self.stdin.lock().await;
-        let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
-        line.push('\n');
-        stdin
-            .write_all(line.as_bytes())
-            .await
-            .map_err(|e| e.to_string())
+    async fn send_request(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let limit = self.request_timeouts.lock().await.for_method(method);
+        self.send_request_with_timeout(method, params, limit).await
+    }
+
+    async fn send_request_with_timeout(
+        &self,
+        method: &str,
+        params: Value,
+        limit: Option<Duration>,
+    ) -> Result<Value, RpcError> {
+        self.rpc.request(method, params, limit).await
     }
 
-    async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-        let (tx, rx) = oneshot::channel();
-        self.pending.lock().await.insert(id, tx);
-        self.write_message(json!({ "id": id, "method": method, "params": params }))
+    async fn cancel_request(&self, id: u64) -> Result<(), CommandError> {
+        self.rpc
+            .pending()
+            .cancel(id)
+            .await
+            .ok_or_else(|| format!("request {id} is not pending"))?;
+        self.send_notification(CANCEL_REQUEST_METHOD, Some(json!({ "id": id })))
             .await?;
-        rx.await.map_err(|_| "request canceled".to_string())
+        Ok(())
     }
 
-    async fn send_notification(&self, method: &str, params: Option<Value>) -> Result<(), String> {
-        let value = if let Some(params) = params {
-            json!({ "method": method, "params": params })
-        } else {
-            json!({ "method": method })
-        };
-        self.write_message(value).await
+    async fn send_notification(&self, method: &str, params: Option<Value>) -> Result<(), RpcError> {
+        self.rpc.notify(method, params).await
+    }
+
+    async fn send_response(&self, id: u64, result: Value) -> Result<(), RpcError> {
+        self.rpc.respond(id, result).await
+    }
+
+    async fn push_stderr_line(&self, line: &str) {
+        let mut tail = self.stderr_tail.lock().await;
+        if tail.len() >= STDERR_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
     }
 
-    async fn send_response(&self, id: u64, result: Value) -> Result<(), String> {
-        self.write_message(json!({ "id": id, "result": result }))
+    pub(crate) async fn read_rate_limits(&self) -> Result<Value, RpcError> {
+        self.send_request("account/rateLimits/read", Value::Null)
             .await
     }
+
+    async fn track_thread(&self, response: &Value) {
+        let Some(response) = thread_from_response(response) else {
+            return;
+        };
+        if let Some(model) = response.model {
+            self.thread_models
+                .lock()
+                .await
+                .insert(response.thread.id.clone(), model);
+        }
+        self.active_threads.lock().await.insert(response.thread.id);
+    }
 }
 
-fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
+impl AgentBackend for WorkspaceSession {
+    fn connect(
+        entry: WorkspaceEntry,
+        settings: AppSettings,
+        host: SharedHost,
+    ) -> BackendFuture<'static, Arc<Self>> {
+        respawn_session(entry, settings.codex_bin, settings.request_timeouts, host)
+    }
+
+    fn kind(&self) -> BackendType {
+        BackendType::Codex
+    }
+
+    fn start_thread(&self) -> BackendFuture<'_, Value> {
+        Box::pin(async move {
+            let params = to_params(&ThreadStartParams {
+                cwd: self.entry.path.clone(),
+                approval_policy: ApprovalPolicy::OnRequest,
+            })?;
+            let response = self.send_request("thread/start", params).await?;
+            self.track_thread(&response).await;
+            Ok(response)
+        })
+    }
+
+    fn send_message<'a>(
+        &'a self,
+        state: &'a AppState,
+        request: MessageRequest,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            // `access_mode` names a sandbox profile; the built-in names are the
+            // original three modes.
+            let access_mode = request
+                .access_mode
+                .unwrap_or_else(|| "current".to_string());
+            let app_settings = state.app_settings.lock().await.clone();
+            let workspace_settings = state
+                .workspaces
+                .lock()
+                .await
+                .get(&self.entry.id)
+                .map(|entry| entry.settings.clone())
+                .unwrap_or_default();
+            let sandbox = sandbox_for_access_mode(
+                &app_settings,
+                &workspace_settings,
+                &access_mode,
+                &self.entry.path,
+            )?;
+            let attachments = resolve_attachments(&self.entry.path, &request.attachments).await?;
+
+            let params = to_params(&TurnStartParams {
+                thread_id: request.thread_id.clone(),
+                input: codex_inputs(request.text, &attachments),
+                cwd: self.entry.path.clone(),
+                approval_policy: sandbox.approval_policy,
+                sandbox_policy: sandbox.sandbox_policy.clone(),
+                model: request.model.clone(),
+                effort: request.effort,
+            })?;
+            record_transcript(
+                state,
+                &self.entry.id,
+                TranscriptSource::Client,
+                "turn/start",
+                Some(&params),
+            )
+            .await;
+            // In place before the request goes out, since the turn's first
+            // approvals can arrive before its response does.
+            let previous = self
+                .thread_exclusions
+                .lock()
+                .await
+                .insert(request.thread_id.clone(), sandbox.exclusion_rules());
+            let response = match self.send_request("turn/start", params).await {
+                Ok(response) => response,
+                Err(error) => {
+                    let mut exclusions = self.thread_exclusions.lock().await;
+                    match previous {
+                        Some(rules) => exclusions.insert(request.thread_id.clone(), rules),
+                        None => exclusions.remove(&request.thread_id),
+                    };
+                    return Err(error.into());
+                }
+            };
+            let model = match request.model {
+                Some(model) => Some(model),
+                None => self
+                    .thread_models
+                    .lock()
+                    .await
+                    .get(&request.thread_id)
+                    .cloned(),
+            };
+            state
+                .usage
+                .lock()
+                .await
+                .note_model(&self.entry.id, &request.thread_id, model.as_deref());
+            let turn_id = response
+                .get("turn")
+                .and_then(|turn| turn.get("id"))
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string());
+            record_audit(
+                state,
+                AuditEntry::turn_policy(
+                    &self.entry.id,
+                    &request.thread_id,
+                    turn_id,
+                    &access_mode,
+                    sandbox.approval_policy,
+                    sandbox.sandbox_policy,
+                ),
+            )
+            .await;
+            Ok(response)
+        })
+    }
+
+    fn interrupt<'a>(
+        &'a self,
+        thread_id: &'a str,
+        turn_id: Option<&'a str>,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            let turn_id = turn_id.ok_or("turn id is required to interrupt a Codex turn")?;
+            let params = to_params(&TurnInterruptParams {
+                thread_id: thread_id.to_string(),
+                turn_id: turn_id.to_string(),
+            })?;
+            Ok(self.send_request("turn/interrupt", params).await?)
+        })
+    }
+
+    fn list_threads<'a>(
+        &'a self,
+        state: &'a AppState,
+        cursor: Option<String>,
+        limit: Option<u32>,
+    ) -> BackendFuture<'a, Value> {
+        Box::pin(async move {
+            let params = to_params(&ThreadListParams { cursor, limit })?;
+            let response = self.send_request("thread/list", params).await?;
+            state
+                .search_index
+                .lock()
+                .await
+                .index_codex_thread_list(&self.entry.id, &response);
+            Ok(response)
+        })
+    }
+
+    fn list_models<'a>(&'a self, _state: &'a AppState) -> BackendFuture<'a, Value> {
+        Box::pin(async move { Ok(self.send_request("model/list", json!({})).await?) })
+    }
+
+    fn respond<'a>(&'a self, request_id: u64, result: Value) -> BackendFuture<'a, ()> {
+        Box::pin(async move { Ok(self.send_response(request_id, result).await?) })
+    }
+
+    fn apply_settings<'a>(&'a self, settings: &'a AppSettings) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            *self.request_timeouts.lock().await = settings.request_timeouts.clone();
+            Ok(())
+        })
+    }
+
+    fn shutdown(&self) -> BackendFuture<'_, ()> {
+        Box::pin(async move {
+            let mut child = self.child.lock().await;
+            let _ = child.kill().await;
+            Ok(())
+        })
+    }
+
+    fn health(&self) -> BackendFuture<'_, BackendHealth> {
+        Box::pin(async move {
+            let alive = matches!(self.child.lock().await.try_wait(), Ok(None));
+            Ok(BackendHealth {
+                backend: BackendType::Codex,
+                alive,
+                pending_requests: self.rpc.pending().snapshot().await.len(),
+                stderr_tail: self.stderr_tail.lock().await.iter().cloned().collect(),
+            })
+        })
+    }
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+fn thread_from_response(response: &Value) -> Option<ThreadResponse> {
+    let result = response.get("result").unwrap_or(response);
+    ThreadResponse::deserialize(result).ok()
+}
+
+fn to_params<T: Serialize>(params: &T) -> Result<Value, String> {
+    serde_json::to_value(params).map_err(|e| e.to_string())
+}
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis((RECONNECT_BASE_DELAY_MS * factor).min(RECONNECT_MAX_DELAY_MS))
+}
+
+pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
     let mut paths: Vec<String> = env::var("PATH")
         .unwrap_or_default()
         .split(':')
@@ -177,7 +437,8 @@ async fn check_codex_installation(codex_bin: Option<String>) -> Result<Option<St
 pub(crate) async fn spawn_workspace_session(
     entry: WorkspaceEntry,
     default_codex_bin: Option<String>,
-    app_handle: AppHandle,
+    request_timeouts: RequestTimeoutSettings,
+    host: SharedHost,
 ) -> Result<Arc<WorkspaceSession>, String> {
     let codex_bin = entry
         .codex_bin
@@ -200,15 +461,18 @@ pub(crate) async fn spawn_workspace_session(
     let session = Arc::new(WorkspaceSession {
         entry: entry.clone(),
         child: Mutex::new(child),
-        stdin: Mutex::new(stdin),
-        pending: Mutex::new(HashMap::new()),
-        next_id: AtomicU64::new(1),
+        rpc: JsonRpcClient::new(stdin, false),
+        request_timeouts: Mutex::new(request_timeouts),
+        stderr_tail: Mutex::new(VecDeque::new()),
+        active_threads: Mutex::new(HashSet::new()),
+        thread_exclusions: Mutex::new(HashMap::new()),
+        thread_models: Mutex::new(HashMap::new()),
     });
 
     let session_clone = Arc::clone(&session);
     let workspace_id = entry.id.clone();
-    let app_handle_clone = app_handle.clone();
-    tauri::async_runtime::spawn(async move {
+    let host_clone = host.clone();
+    tokio::spawn(async move {
         let mut lines = BufReader::new(stdout).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             if line.trim().is_empty() {
@@ -217,80 +481,143 @@ pub(crate) async fn spawn_workspace_session(
             let value: Value = match serde_json::from_str(&line) {
                 Ok(value) => value,
                 Err(err) => {
-                    let payload = AppServerEvent {
-                        workspace_id: workspace_id.clone(),
-                        message: json!({
-                            "method": "codex/parseError",
-                            "params": { "error": err.to_string(), "raw": line },
-                        }),
-                    };
-                    let _ = app_handle_clone.emit("app-server-event", payload);
+                    host_clone.emit(AgentEvent::new(
+                        &workspace_id,
+                        BackendType::Codex,
+                        "codex/parseError",
+                        json!({ "error": err.to_string(), "raw": line }),
+                    ));
                     continue;
                 }
             };
 
-            let maybe_id = value.get("id").and_then(|id| id.as_u64());
-            let has_method = value.get("method").is_some();
-            let has_result_or_error =
-                value.get("result").is_some() || value.get("error").is_some();
-            if let Some(id) = maybe_id {
-                if has_result_or_error {
-                    if let Some(tx) = session_clone.pending.lock().await.remove(&id) {
-                        let _ = tx.send(value);
+            let message = AppServerMessage::classify(&value);
+            match &message {
+                AppServerMessage::Response { id } => {
+                    session_clone.rpc.pending().resolve(*id, value).await;
+                }
+                AppServerMessage::Request { .. } | AppServerMessage::Notification(_) => {
+                    let state = host_clone.app_state();
+                    if let AppServerMessage::Notification(ServerNotification::Known(
+                        notification,
+                    )) = &message
+                    {
+                        state
+                            .thread_store
+                            .lock()
+                            .await
+                            .apply(&workspace_id, notification);
+                        observe_notification(&host_clone, &workspace_id, notification).await;
+                        observe_codex_usage(host_clone.as_ref(), &workspace_id, notification).await;
+                        observe_codex_rate_limits(host_clone.as_ref(), &workspace_id, notification)
+                            .await;
+                    }
+                    if let Some(method) = value.get("method").and_then(|method| method.as_str()) {
+                        record_transcript(
+                            state,
+                            &workspace_id,
+                            TranscriptSource::Server,
+                            method,
+                            value.get("params"),
+                        )
+                        .await;
+                    }
+                    if let AppServerMessage::Request { id, request } = &message {
+                        let method = value
+                            .get("method")
+                            .and_then(|method| method.as_str())
+                            .unwrap_or_default();
+                        record_audit(
+                            state,
+                            AuditEntry::request(
+                                &workspace_id,
+                                BackendType::Codex,
+                                *id,
+                                method,
+                                value.get("params"),
+                            ),
+                        )
+                        .await;
+                        let exclusions = value
+                            .get("params")
+                            .and_then(|params| params.get("threadId"))
+                            .and_then(|thread_id| thread_id.as_str());
+                        let exclusions = match exclusions {
+                            Some(thread_id) => session_clone
+                                .thread_exclusions
+                                .lock()
+                                .await
+                                .get(thread_id)
+                                .cloned()
+                                .unwrap_or_default(),
+                            None => Vec::new(),
+                        };
+                        let decision = match request {
+                            ServerRequest::Known(request) => {
+                                auto_decide(state, &workspace_id, request, exclusions).await
+                            }
+                            ServerRequest::Unknown(_) => None,
+                        };
+                        if let Some(decision) = decision {
+                            answer_automatically(
+                                &session_clone,
+                                host_clone.as_ref(),
+                                *id,
+                                &value,
+                                decision,
+                            )
+                            .await;
+                            continue;
+                        }
+                    }
+                    if let Some(event) =
+                        AgentEvent::from_message(&workspace_id, BackendType::Codex, &value)
+                    {
+                        host_clone.emit(event);
                     }
-                } else if has_method {
-                    let payload = AppServerEvent {
-                        workspace_id: workspace_id.clone(),
-                        message: value,
-                    };
-                    let _ = app_handle_clone.emit("app-server-event", payload);
-                } else if let Some(tx) = session_clone.pending.lock().await.remove(&id) {
-                    let _ = tx.send(value);
                 }
-            } else if has_method {
-                let payload = AppServerEvent {
-                    workspace_id: workspace_id.clone(),
-                    message: value,
-                };
-                let _ = app_handle_clone.emit("app-server-event", payload);
+                AppServerMessage::Invalid => {}
             }
         }
+        handle_session_exit(session_clone, host_clone).await;
     });
 
+    let session_clone = Arc::clone(&session);
     let workspace_id = entry.id.clone();
-    let app_handle_clone = app_handle.clone();
-    tauri::async_runtime::spawn(async move {
+    let host_clone = host.clone();
+    tokio::spawn(async move {
         let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             if line.trim().is_empty() {
                 continue;
             }
-            let payload = AppServerEvent {
-                workspace_id: workspace_id.clone(),
-                message: json!({
-                    "method": "codex/stderr",
-                    "params": { "message": line },
-                }),
-            };
-            let _ = app_handle_clone.emit("app-server-event", payload);
+            session_clone.push_stderr_line(&line).await;
+            host_clone.emit(AgentEvent::new(
+                &workspace_id,
+                BackendType::Codex,
+                "codex/stderr",
+                json!({ "message": line }),
+            ));
         }
     });
 
-    let init_params = json!({
-        "clientInfo": {
-            "name": "codex_monitor",
-            "title": "CodexMonitor",
-            "version": "0.1.0"
-        }
-    });
-    let init_result = timeout(
-        Duration::from_secs(15),
-        session.send_request("initialize", init_params),
-    )
-    .await;
-    let init_response = match init_result {
-        Ok(response) => response,
-        Err(_) => {
+    let init_params = to_params(&InitializeParams {
+        client_info: ClientInfo {
+            name: "codex_monitor".to_string(),
+            title: "CodexMonitor".to_string(),
+            version: "0.1.0".to_string(),
+        },
+    })?;
+    let init_result = session
+        .send_request_with_timeout(
+            "initialize",
+            init_params,
+            Some(Duration::from_secs(15)),
+        )
+        .await;
+    match init_result {
+        Ok(_) => {}
+        Err(RpcError::Timeout { .. }) => {
             let mut child = session.child.lock().await;
             let _ = child.kill().await;
             return Err(
@@ -298,26 +625,253 @@ pub(crate) async fn spawn_workspace_session(
                     .to_string(),
             );
         }
+        Err(error) => return Err(error.to_string()),
+    }
+    session
+        .send_notification("initialized", None)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    host.emit(AgentEvent::new(
+        &entry.id,
+        BackendType::Codex,
+        "codex/connected",
+        json!({ "workspaceId": entry.id.clone() }),
+    ));
+
+    Ok(session)
+}
+
+/// Answers an approval request from the workspace's rules and logs the decision.
+async fn answer_automatically(
+    session: &WorkspaceSession,
+    host: &dyn AgentHost,
+    id: u64,
+    message: &Value,
+    decision: AutoDecision,
+) {
+    let state = host.app_state();
+    let workspace_id = &session.entry.id;
+    let method = message
+        .get("method")
+        .and_then(|method| method.as_str())
+        .unwrap_or_default();
+    let entry = decision.log_entry(id, method, message.get("params"));
+    if let Err(error) = session.send_response(id, decision.response()).await {
+        eprintln!("Failed to answer {method} ({id}) for {workspace_id}: {error}");
+        return;
+    }
+    record_transcript(
+        state,
+        workspace_id,
+        TranscriptSource::Client,
+        AUTO_APPROVAL_METHOD,
+        Some(&entry),
+    )
+    .await;
+    record_audit(
+        state,
+        AuditEntry::response(
+            workspace_id,
+            BackendType::Codex,
+            id,
+            &decision.response(),
+            AuditActor::Rule,
+        ),
+    )
+    .await;
+    host.emit(AgentEvent::new(
+        workspace_id,
+        BackendType::Codex,
+        AUTO_APPROVAL_METHOD,
+        entry,
+    ));
+}
+
+async fn handle_session_exit(session: Arc<WorkspaceSession>, host: SharedHost) {
+    let workspace_id = session.entry.id.clone();
+    let exit_code = {
+        let mut child = session.child.lock().await;
+        match timeout(Duration::from_secs(2), child.wait()).await {
+            Ok(Ok(status)) => status.code(),
+            _ => {
+                let _ = child.kill().await;
+                None
+            }
+        }
     };
-    init_response?;
-    session.send_notification("initialized", None).await?;
-
-    let payload = AppServerEvent {
-        workspace_id: entry.id.clone(),
-        message: json!({
-            "method": "codex/connected",
-            "params": { "workspaceId": entry.id.clone() }
-        }),
+    let reason = match exit_code {
+        Some(code) => format!("Codex app-server exited with status {code}."),
+        None => "Codex app-server exited.".to_string(),
     };
-    let _ = app_handle.emit("app-server-event", payload);
+    session.rpc.pending().fail_all(&reason).await;
 
-    Ok(session)
+    let state = host.app_state();
+    state
+        .prompt_queue
+        .lock()
+        .await
+        .workspace_stopped(&workspace_id);
+    audit_session_ended(state, &workspace_id, &BackendType::Codex).await;
+    let interrupted_usage = state.usage.lock().await.workspace_stopped(&workspace_id);
+    for record in interrupted_usage {
+        record_usage(host.as_ref(), record).await;
+    }
+    let was_active = {
+        state
+            .sessions
+            .lock()
+            .await
+            .remove_if_current(&workspace_id, &session)
+    };
+    // Sessions removed on purpose (workspace removal, reconnect) are not supervised.
+    if !was_active {
+        return;
+    }
+
+    let stderr_tail: Vec<String> = session.stderr_tail.lock().await.iter().cloned().collect();
+    host.emit(AgentEvent::new(
+        &workspace_id,
+        BackendType::Codex,
+        "codex/disconnected",
+        json!({
+            "workspaceId": workspace_id,
+            "exitCode": exit_code,
+            "reason": reason,
+            "stderr": stderr_tail,
+            "willReconnect": true,
+        }),
+    ));
+
+    let threads: Vec<String> = session.active_threads.lock().await.iter().cloned().collect();
+    tokio::spawn(reconnect_workspace(workspace_id, threads, host));
 }
 
-#[tauri::command]
-pub(crate) async fn codex_doctor(
+// Boxed so the stdout reader -> supervisor -> spawn cycle has a nameable future type.
+fn respawn_session(
+    entry: WorkspaceEntry,
+    default_codex_bin: Option<String>,
+    request_timeouts: RequestTimeoutSettings,
+    host: SharedHost,
+) -> BackendFuture<'static, Arc<WorkspaceSession>> {
+    Box::pin(async move {
+        Ok(spawn_workspace_session(entry, default_codex_bin, request_timeouts, host).await?)
+    })
+}
+
+async fn reconnect_workspace(workspace_id: String, threads: Vec<String>, host: SharedHost) {
+    let state = host.app_state();
+    let mut last_error = String::new();
+    for attempt in 1..=RECONNECT_MAX_ATTEMPTS {
+        let delay = reconnect_delay(attempt);
+        host.emit(AgentEvent::new(
+            &workspace_id,
+            BackendType::Codex,
+            "codex/reconnecting",
+            json!({
+                "workspaceId": workspace_id,
+                "attempt": attempt,
+                "delayMs": delay.as_millis() as u64,
+            }),
+        ));
+        sleep(delay).await;
+
+        let entry = {
+            let workspaces = state.workspaces.lock().await;
+            workspaces.get(&workspace_id).cloned()
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+        if state
+            .sessions
+            .lock()
+            .await
+            .contains(&workspace_id, &BackendType::Codex)
+        {
+            return;
+        }
+        let (default_bin, request_timeouts, resume_threads) = {
+            let settings = state.app_settings.lock().await;
+            (
+                settings.codex_bin.clone(),
+                settings.request_timeouts.clone(),
+                settings.resume_threads_on_reconnect,
+            )
+        };
+
+        let session = match respawn_session(
+            entry,
+            default_bin,
+            request_timeouts,
+            host.clone(),
+        )
+        .await
+        {
+            Ok(session) => session,
+            Err(error) => {
+                last_error = error.message;
+                continue;
+            }
+        };
+        {
+            let mut sessions = state.sessions.lock().await;
+            if sessions.contains(&workspace_id, &BackendType::Codex) {
+                drop(sessions);
+                let mut child = session.child.lock().await;
+                let _ = child.kill().await;
+                return;
+            }
+            sessions.insert(&workspace_id, session.clone());
+        }
+
+        if resume_threads {
+            for thread_id in &threads {
+                let params = match to_params(&ThreadIdParams {
+                    thread_id: thread_id.clone(),
+                }) {
+                    Ok(params) => params,
+                    Err(_) => continue,
+                };
+                match session.send_request("thread/resume", params).await {
+                    Ok(response) => session.track_thread(&response).await,
+                    Err(error) => host.emit(AgentEvent::new(
+                        &workspace_id,
+                        BackendType::Codex,
+                        "codex/resumeFailed",
+                        json!({ "threadId": thread_id, "error": error.to_string() }),
+                    )),
+                }
+            }
+        }
+        return;
+    }
+
+    host.emit(AgentEvent::new(
+        &workspace_id,
+        BackendType::Codex,
+        "codex/reconnectFailed",
+        json!({
+            "workspaceId": workspace_id,
+            "attempts": RECONNECT_MAX_ATTEMPTS,
+            "error": last_error,
+        }),
+    ));
+}
+
+async fn get_session(
+    state: &AppState,
+    workspace_id: &str,
+) -> Result<Arc<WorkspaceSession>, String> {
+    get_backend_as::<WorkspaceSession>(state, workspace_id, BackendType::Codex)
+        .await
+        .ok_or_else(|| "workspace not connected".to_string())
+}
+
+/// Checks that the Codex CLI, its app-server and Node all run.
+pub(crate) async fn codex_doctor_report(
     codex_bin: Option<String>,
-    state: State<'_, AppState>,
+    state: &AppState,
 ) -> Result<Value, String> {
     let default_bin = {
         let settings = state.app_settings.lock().await;
@@ -409,212 +963,131 @@ pub(crate) async fn codex_doctor(
     }))
 }
 
-#[tauri::command]
-pub(crate) async fn start_thread(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "cwd": session.entry.path,
-        "approvalPolicy": "on-request"
-    });
-    session.send_request("thread/start", params).await
-}
-
-#[tauri::command]
-pub(crate) async fn resume_thread(
-    workspace_id: String,
+/// Resumes a thread and returns the full `thread/resume` response, turns included.
+pub(crate) async fn fetch_thread(
+    state: &AppState,
+    workspace_id: &str,
     thread_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "threadId": thread_id
-    });
-    session.send_request("thread/resume", params).await
-}
-
-#[tauri::command]
-pub(crate) async fn list_threads(
-    workspace_id: String,
-    cursor: Option<String>,
-    limit: Option<u32>,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "cursor": cursor,
-        "limit": limit,
-    });
-    session.send_request("thread/list", params).await
-}
-
-#[tauri::command]
-pub(crate) async fn archive_thread(
-    workspace_id: String,
-    thread_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "threadId": thread_id
-    });
-    session.send_request("thread/archive", params).await
-}
-
-#[tauri::command]
-pub(crate) async fn send_user_message(
-    workspace_id: String,
-    thread_id: String,
-    text: String,
-    model: Option<String>,
-    effort: Option<String>,
-    access_mode: Option<String>,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
-    let sandbox_policy = match access_mode.as_str() {
-        "full-access" => json!({
-            "type": "dangerFullAccess"
-        }),
-        "read-only" => json!({
-            "type": "readOnly"
-        }),
-        _ => json!({
-            "type": "workspaceWrite",
-            "writableRoots": [session.entry.path],
-            "networkAccess": true
-        }),
-    };
-
-    let approval_policy = if access_mode == "full-access" {
-        "never"
-    } else {
-        "on-request"
-    };
-
-    let params = json!({
-        "threadId": thread_id,
-        "input": [{ "type": "text", "text": text }],
-        "cwd": session.entry.path,
-        "approvalPolicy": approval_policy,
-        "sandboxPolicy": sandbox_policy,
-        "model": model,
-        "effort": effort,
-    });
-    session.send_request("turn/start", params).await
+) -> Result<Value, CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    let params = to_params(&ThreadIdParams { thread_id })?;
+    let response = session.send_request("thread/resume", params).await?;
+    session.track_thread(&response).await;
+    state
+        .search_index
+        .lock()
+        .await
+        .index_codex_thread(workspace_id, &response);
+    Ok(response)
 }
 
-#[tauri::command]
-pub(crate) async fn turn_interrupt(
-    workspace_id: String,
+pub(crate) async fn archive_codex_thread(
+    state: &AppState,
+    workspace_id: &str,
     thread_id: String,
-    turn_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "threadId": thread_id,
-        "turnId": turn_id,
-    });
-    session.send_request("turn/interrupt", params).await
+) -> Result<Value, CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    let params = to_params(&ThreadIdParams {
+        thread_id: thread_id.clone(),
+    })?;
+    let response = session.send_request("thread/archive", params).await?;
+    session.active_threads.lock().await.remove(&thread_id);
+    state
+        .thread_store
+        .lock()
+        .await
+        .remove_thread(workspace_id, &thread_id);
+    Ok(response)
 }
 
-#[tauri::command]
-pub(crate) async fn start_review(
-    workspace_id: String,
+pub(crate) async fn start_codex_review(
+    state: &AppState,
+    workspace_id: &str,
     thread_id: String,
-    target: Value,
+    target: ReviewTarget,
     delivery: Option<String>,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let mut params = Map::new();
-    params.insert("threadId".to_string(), json!(thread_id));
-    params.insert("target".to_string(), target);
-    if let Some(delivery) = delivery {
-        params.insert("delivery".to_string(), json!(delivery));
-    }
+) -> Result<Value, CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    let params = to_params(&ReviewStartParams {
+        thread_id,
+        target,
+        delivery,
+    })?;
+    record_transcript(
+        state,
+        workspace_id,
+        TranscriptSource::Client,
+        "review/start",
+        Some(&params),
+    )
+    .await;
     session
-        .send_request("review/start", Value::Object(params))
+        .send_request("review/start", params)
         .await
+        .map_err(CommandError::from)
 }
 
-#[tauri::command]
-pub(crate) async fn model_list(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({});
-    session.send_request("model/list", params).await
+/// Reads the account's rate limits through the workspace's app-server.
+pub(crate) async fn read_account_rate_limits(
+    host: &dyn AgentHost,
+    workspace_id: &str,
+) -> Result<Value, CommandError> {
+    let session = get_session(host.app_state(), workspace_id).await?;
+    Ok(poll_rate_limits(host, &session).await?)
 }
 
-#[tauri::command]
-pub(crate) async fn account_rate_limits(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
+pub(crate) async fn list_codex_skills(
+    state: &AppState,
+    workspace_id: &str,
+) -> Result<Value, CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    let params = to_params(&SkillsListParams {
+        cwd: session.entry.path.clone(),
+    })?;
     session
-        .send_request("account/rateLimits/read", Value::Null)
+        .send_request("skills/list", params)
         .await
+        .map_err(CommandError::from)
 }
 
-#[tauri::command]
-pub(crate) async fn skills_list(
-    workspace_id: String,
-    state: State<'_, AppState>,
-) -> Result<Value, String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    let params = json!({
-        "cwd": session.entry.path
-    });
-    session.send_request("skills/list", params).await
+pub(crate) async fn cancel_codex_request(
+    state: &AppState,
+    workspace_id: &str,
+    request_id: u64,
+) -> Result<(), CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    session.cancel_request(request_id).await
 }
 
-#[tauri::command]
-pub(crate) async fn respond_to_server_request(
-    workspace_id: String,
-    request_id: u64,
-    result: Value,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    let sessions = state.sessions.lock().await;
-    let session = sessions
-        .get(&workspace_id)
-        .ok_or("workspace not connected")?;
-    session.send_response(request_id, result).await
+pub(crate) async fn pending_codex_requests(
+    state: &AppState,
+    workspace_id: &str,
+) -> Result<Vec<PendingRequestInfo>, CommandError> {
+    let session = get_session(state, workspace_id).await?;
+    Ok(session.rpc.pending().snapshot().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reconnect_delay, thread_from_response};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn reconnect_delay_backs_off_and_caps() {
+        assert_eq!(reconnect_delay(1), Duration::from_millis(500));
+        assert_eq!(reconnect_delay(2), Duration::from_millis(1000));
+        assert_eq!(reconnect_delay(4), Duration::from_millis(4000));
+        assert_eq!(reconnect_delay(20), Duration::from_millis(30_000));
+    }
+
+    #[test]
+    fn thread_from_response_reads_result_thread() {
+        let response =
+            json!({ "id": 3, "result": { "thread": { "id": "thr_1" }, "model": "gpt-5-codex" } });
+        let thread = thread_from_response(&response).expect("thread");
+        assert_eq!(thread.thread.id, "thr_1");
+        assert_eq!(thread.model.as_deref(), Some("gpt-5-codex"));
+        assert!(thread_from_response(&json!({ "id": 4, "result": {} })).is_none());
+    }
 }
@@ -0,0 +1,656 @@
+//! The Tauri commands the webview invokes. Each one unwraps Tauri's `State`
+//! or `AppHandle` and calls its module's core function, which the daemon and
+//! remote access servers reach through `dispatch.rs` instead.
+
+use serde_json::Value;
+use std::path::PathBuf;
+
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use crate::attachments::Attachment;
+use crate::audit::{self, AuditEntry};
+use crate::backend::{self, app_host, BackendHealth};
+use crate::codex;
+use crate::export::{self, ExportFormat, ThreadExport};
+use crate::git;
+use crate::opencode;
+use crate::prompt_queue::{self, PromptSchedule, QueuedPrompt};
+use crate::protocol::ReviewTarget;
+use crate::rate_limits::{self, CachedRateLimits};
+use crate::remote::{self, RemoteServerStatus};
+use crate::rpc::{CommandError, PendingRequestInfo};
+use crate::search::{self, SearchHit};
+use crate::settings;
+use crate::state::AppState;
+use crate::thread_store::{self, ThreadSnapshot};
+use crate::transcripts::{self, TranscriptPage};
+use crate::types::{
+    AcpAgentConfig, AppSettings, BackendType, GitFileDiff, GitHubIssuesResponse, GitLogResponse,
+    OpenCodePromptHandle, OpenCodeProviderInfo, OpenCodeSessionInfo, RemoteScope, RemoteToken,
+    WorkspaceInfo, WorkspaceSettings,
+};
+use crate::usage::{self, UsageGroup, UsageSummary};
+use crate::workspaces;
+
+#[tauri::command]
+pub(crate) async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+    settings::get_app_settings(&state).await
+}
+
+#[tauri::command]
+pub(crate) async fn update_app_settings(
+    settings: AppSettings,
+    app: AppHandle,
+) -> Result<AppSettings, String> {
+    settings::update_app_settings(&app_host(app), settings).await
+}
+
+#[tauri::command]
+pub(crate) async fn codex_doctor(
+    codex_bin: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Value, String> {
+    codex::codex_doctor_report(codex_bin, &state).await
+}
+
+#[tauri::command]
+pub(crate) async fn resume_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    codex::fetch_thread(&state, &workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn archive_thread(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    codex::archive_codex_thread(&state, &workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn start_review(
+    workspace_id: String,
+    thread_id: String,
+    target: ReviewTarget,
+    delivery: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    codex::start_codex_review(&state, &workspace_id, thread_id, target, delivery).await
+}
+
+#[tauri::command]
+pub(crate) async fn account_rate_limits(
+    workspace_id: String,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    codex::read_account_rate_limits(&app, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn skills_list(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    codex::list_codex_skills(&state, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_request(
+    workspace_id: String,
+    request_id: u64,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    codex::cancel_codex_request(&state, &workspace_id, request_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_pending_requests(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PendingRequestInfo>, CommandError> {
+    codex::pending_codex_requests(&state, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_workspaces(
+    state: State<'_, AppState>,
+) -> Result<Vec<WorkspaceInfo>, String> {
+    workspaces::list_workspaces(&state).await
+}
+
+#[tauri::command]
+pub(crate) async fn add_workspace(
+    path: String,
+    codex_bin: Option<String>,
+    opencode_bin: Option<String>,
+    backend: Option<String>,
+    acp_agent: Option<AcpAgentConfig>,
+    app: AppHandle,
+) -> Result<WorkspaceInfo, String> {
+    workspaces::add_workspace(
+        &app_host(app),
+        path,
+        codex_bin,
+        opencode_bin,
+        backend,
+        acp_agent,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn add_worktree(
+    parent_id: String,
+    branch: String,
+    app: AppHandle,
+) -> Result<WorkspaceInfo, String> {
+    workspaces::add_worktree(&app_host(app), parent_id, branch).await
+}
+
+#[tauri::command]
+pub(crate) async fn remove_workspace(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    workspaces::remove_workspace(&state, id).await
+}
+
+#[tauri::command]
+pub(crate) async fn remove_worktree(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    workspaces::remove_worktree(&state, id).await
+}
+
+#[tauri::command]
+pub(crate) async fn update_workspace_settings(
+    id: String,
+    settings: WorkspaceSettings,
+    state: State<'_, AppState>,
+) -> Result<WorkspaceInfo, String> {
+    workspaces::update_workspace_settings(&state, id, settings).await
+}
+
+#[tauri::command]
+pub(crate) async fn update_workspace_codex_bin(
+    id: String,
+    codex_bin: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<WorkspaceInfo, String> {
+    workspaces::update_workspace_codex_bin(&state, id, codex_bin).await
+}
+
+#[tauri::command]
+pub(crate) async fn update_workspace_acp_agent(
+    id: String,
+    acp_agent: Option<AcpAgentConfig>,
+    state: State<'_, AppState>,
+) -> Result<WorkspaceInfo, String> {
+    workspaces::update_workspace_acp_agent(&state, id, acp_agent).await
+}
+
+#[tauri::command]
+pub(crate) async fn connect_workspace(id: String, app: AppHandle) -> Result<(), String> {
+    workspaces::connect_saved_workspace(&id, &app_host(app)).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_workspace_files(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    workspaces::list_workspace_files(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn start_thread(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    backend::start_thread(&state, workspace_id).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn send_user_message(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    backend::send_user_message(
+        &state,
+        workspace_id,
+        thread_id,
+        text,
+        model,
+        effort,
+        access_mode,
+        attachments,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn turn_interrupt(
+    workspace_id: String,
+    thread_id: String,
+    turn_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    backend::turn_interrupt(&state, workspace_id, thread_id, turn_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_threads(
+    workspace_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    backend::list_threads(&state, workspace_id, cursor, limit).await
+}
+
+#[tauri::command]
+pub(crate) async fn model_list(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Value, CommandError> {
+    backend::model_list(&state, workspace_id).await
+}
+
+/// Answers an agent request. `backend` picks the side agent of a Codex
+/// workspace; it defaults to the workspace's own backend.
+#[tauri::command]
+pub(crate) async fn respond_to_server_request(
+    workspace_id: String,
+    request_id: u64,
+    result: Value,
+    backend: Option<BackendType>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    backend::respond_to_request(&state, &workspace_id, request_id, result, backend, None).await
+}
+
+#[tauri::command]
+pub(crate) async fn backend_health(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<BackendHealth, CommandError> {
+    backend::backend_health(&state, workspace_id).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn enqueue_prompt(
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    schedule: Option<PromptSchedule>,
+    app: AppHandle,
+) -> Result<QueuedPrompt, String> {
+    prompt_queue::enqueue_prompt(
+        &app_host(app),
+        workspace_id,
+        thread_id,
+        text,
+        model,
+        effort,
+        access_mode,
+        attachments,
+        schedule,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn list_queued_prompts(
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    prompt_queue::list_queued_prompts(&state, workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn reorder_queued_prompts(
+    workspace_id: String,
+    thread_id: String,
+    prompt_ids: Vec<String>,
+    app: AppHandle,
+) -> Result<Vec<QueuedPrompt>, String> {
+    prompt_queue::reorder_queued_prompts(&app_host(app), workspace_id, thread_id, prompt_ids).await
+}
+
+#[tauri::command]
+pub(crate) async fn set_queued_prompt_paused(
+    prompt_id: String,
+    paused: bool,
+    app: AppHandle,
+) -> Result<QueuedPrompt, String> {
+    prompt_queue::set_queued_prompt_paused(&app_host(app), prompt_id, paused).await
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_queued_prompt(prompt_id: String, app: AppHandle) -> Result<(), String> {
+    prompt_queue::cancel_queued_prompt(&app_host(app), prompt_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_thread_snapshot(
+    workspace_id: String,
+    thread_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<ThreadSnapshot>, String> {
+    thread_store::get_thread_snapshot(&state, workspace_id, thread_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn query_audit_log(
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+    decision: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<AuditEntry>, String> {
+    audit::query_audit_log(
+        &state,
+        workspace_id,
+        thread_id,
+        decision,
+        since,
+        until,
+        limit,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn get_usage_summary(
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    group_by: UsageGroup,
+    utc_offset_minutes: Option<i32>,
+    state: State<'_, AppState>,
+) -> Result<UsageSummary, String> {
+    usage::get_usage_summary(
+        &state,
+        workspace_id,
+        thread_id,
+        since,
+        until,
+        group_by,
+        utc_offset_minutes,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn read_transcript(
+    workspace_id: String,
+    thread_id: String,
+    cursor: Option<u64>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<TranscriptPage, String> {
+    transcripts::read_transcript(&state, workspace_id, thread_id, cursor, limit).await
+}
+
+#[tauri::command]
+pub(crate) async fn search_conversations(
+    query: String,
+    workspace_id: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchHit>, String> {
+    search::search_conversations(&state, query, workspace_id, limit).await
+}
+
+/// Asks where to save an export; `None` when the dialog was dismissed.
+async fn pick_export_path(
+    app: &AppHandle,
+    export: &ThreadExport,
+    format: ExportFormat,
+) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = oneshot::channel();
+    app.dialog()
+        .file()
+        .add_filter(format.label(), &[format.extension()])
+        .set_file_name(export::default_file_name(export, format))
+        .save_file(move |file| {
+            let _ = tx.send(file);
+        });
+    match rx.await.ok().flatten() {
+        Some(file) => file.into_path().map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Exports a thread (Codex) or session (OpenCode); returns the written path, or
+/// `None` when the save dialog was dismissed.
+#[tauri::command]
+pub(crate) async fn export_thread(
+    workspace_id: String,
+    thread_id: String,
+    format: ExportFormat,
+    path: Option<String>,
+    app: AppHandle,
+) -> Result<Option<String>, String> {
+    let host = app_host(app.clone());
+    if let Some(path) = path {
+        return export::export_thread(&host, workspace_id, thread_id, format, path)
+            .await
+            .map(Some);
+    }
+    let export = export::build_export(host.app_state(), &host, &workspace_id, &thread_id).await?;
+    let Some(target) = pick_export_path(&app, &export, format).await? else {
+        return Ok(None);
+    };
+    export::write_export(&export, format, &target)
+        .await
+        .map(Some)
+}
+
+#[tauri::command]
+pub(crate) async fn get_git_status(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    git::get_git_status(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_git_diffs(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<GitFileDiff>, String> {
+    git::get_git_diffs(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_git_log(
+    workspace_id: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<GitLogResponse, String> {
+    git::get_git_log(&state, workspace_id, limit).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_git_remote(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    git::get_git_remote(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_github_issues(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<GitHubIssuesResponse, String> {
+    git::get_github_issues(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_git_branches(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    git::list_git_branches(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn checkout_git_branch(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    git::checkout_git_branch(&state, workspace_id, name).await
+}
+
+#[tauri::command]
+pub(crate) async fn create_git_branch(
+    workspace_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    git::create_git_branch(&state, workspace_id, name).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_cached_rate_limits(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<CachedRateLimits>, String> {
+    rate_limits::get_cached_rate_limits(&state, workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_remote_server_status(
+    state: State<'_, AppState>,
+) -> Result<RemoteServerStatus, String> {
+    remote::get_remote_server_status(&state).await
+}
+
+/// Makes a new token; it takes effect once saved into the settings.
+#[tauri::command]
+pub(crate) async fn generate_remote_token(
+    label: String,
+    scope: RemoteScope,
+) -> Result<RemoteToken, String> {
+    remote::generate_remote_token(label, scope)
+}
+
+#[tauri::command]
+pub(crate) async fn opencode_doctor(
+    opencode_bin: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Value, String> {
+    opencode::opencode_doctor_report(opencode_bin, &state).await
+}
+
+#[tauri::command]
+pub(crate) async fn list_opencode_sessions(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<OpenCodeSessionInfo>, String> {
+    opencode::opencode_sessions(&state, &workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn create_opencode_session(
+    workspace_id: String,
+    app: AppHandle,
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    opencode::create_opencode_session(&app_host(app), workspace_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_opencode_session(
+    workspace_id: String,
+    session_id: String,
+    app: AppHandle,
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    opencode::get_opencode_session(&app_host(app), workspace_id, session_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn load_opencode_session(
+    workspace_id: String,
+    session_id: String,
+    app: AppHandle,
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    opencode::load_opencode_session(&app_host(app), workspace_id, session_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn delete_opencode_session(
+    workspace_id: String,
+    session_id: String,
+    app: AppHandle,
+) -> Result<(), CommandError> {
+    opencode::delete_opencode_session(&app_host(app), workspace_id, session_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_opencode_messages(
+    workspace_id: String,
+    session_id: String,
+    app: AppHandle,
+) -> Result<Value, CommandError> {
+    opencode::fetch_opencode_messages(&workspace_id, &session_id, &app_host(app)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn send_opencode_message(
+    workspace_id: String,
+    session_id: String,
+    text: String,
+    provider_id: Option<String>,
+    model_id: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    app: AppHandle,
+) -> Result<OpenCodePromptHandle, CommandError> {
+    opencode::send_opencode_message(
+        &app_host(app),
+        workspace_id,
+        session_id,
+        text,
+        provider_id,
+        model_id,
+        attachments,
+    )
+    .await
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_opencode_operation(
+    workspace_id: String,
+    session_id: String,
+    app: AppHandle,
+) -> Result<(), CommandError> {
+    opencode::cancel_opencode_operation(&app_host(app), workspace_id, session_id).await
+}
+
+#[tauri::command]
+pub(crate) async fn get_opencode_providers(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<OpenCodeProviderInfo>, String> {
+    opencode::opencode_providers(&state, &workspace_id).await
+}
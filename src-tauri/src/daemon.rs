@@ -0,0 +1,237 @@
+use serde_json::{json, Value};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+
+use crate::backend::SharedHost;
+use crate::dispatch::{
+    dispatch, error_object, next_event_notification, rpc_error, Params, INVALID_REQUEST,
+    PARSE_ERROR,
+};
+use crate::rpc::RpcError;
+use crate::state::default_data_dir;
+
+/// Overrides where the daemon listens (and where clients connect).
+pub(crate) const SOCKET_ENV: &str = "CODEX_MONITOR_SOCKET";
+/// Holds the socket, so its permissions decide who may connect.
+const SOCKET_DIR: &str = "daemon";
+const SOCKET_FILE: &str = "daemon.sock";
+/// Replies and events waiting to be written to one client. A subscriber that
+/// lets this fill up is disconnected instead of buffered without bound.
+const OUTGOING_BUFFER: usize = 256;
+pub(crate) const SUBSCRIBE_METHOD: &str = "subscribe";
+pub(crate) const UNSUBSCRIBE_METHOD: &str = "unsubscribe";
+
+/// `$CODEX_MONITOR_SOCKET`, or `daemon/daemon.sock` in the data directory.
+pub(crate) fn socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    Some(default_data_dir()?.join(SOCKET_DIR).join(SOCKET_FILE))
+}
+
+/// One client socket. Replies and events share a single writer so lines
+/// never interleave.
+struct Connection {
+    host: SharedHost,
+    out: mpsc::Sender<Value>,
+    /// Tells the writer to hang up because a subscriber fell too far behind.
+    overflowed: Arc<Notify>,
+    subscription: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Connection {
+    async fn handle_line(&self, line: &str) {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(error) => {
+                self.send_error(Value::Null, &rpc_error(PARSE_ERROR, error.to_string()))
+                    .await;
+                return;
+            }
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            self.send_error(
+                id.unwrap_or(Value::Null),
+                &rpc_error(INVALID_REQUEST, "Missing method"),
+            )
+            .await;
+            return;
+        };
+        let params = message.get("params").cloned();
+        let result = match method {
+            SUBSCRIBE_METHOD => self.subscribe(params).await,
+            UNSUBSCRIBE_METHOD => self.unsubscribe().await,
+            _ => dispatch(&self.host, method, params, None).await,
+        };
+        // Notifications (no `id`) run but get no reply.
+        let Some(id) = id else {
+            return;
+        };
+        match result {
+            Ok(result) => {
+                let _ = self
+                    .out
+                    .send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+                    .await;
+            }
+            Err(error) => self.send_error(id, &error).await,
+        }
+    }
+
+    async fn send_error(&self, id: Value, error: &RpcError) {
+        let _ = self
+            .out
+            .send(json!({ "jsonrpc": "2.0", "id": id, "error": error_object(error) }))
+            .await;
+    }
+
+    /// Streams agent events as `agent-event` notifications, optionally only
+    /// those of `workspaceId`. Subscribing again replaces the filter.
+    async fn subscribe(&self, params: Option<Value>) -> Result<Value, RpcError> {
+        let workspace_id: Option<String> = Params::new(params)?.get("workspaceId")?;
+        let mut events = self.host.app_state().events.subscribe();
+        let out = self.out.clone();
+        let overflowed = Arc::clone(&self.overflowed);
+        let task = tokio::spawn(async move {
+            while let Some(message) =
+                next_event_notification(&mut events, workspace_id.as_deref()).await
+            {
+                match out.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        overflowed.notify_one();
+                        break;
+                    }
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+        });
+        if let Some(previous) = self.subscription.lock().await.replace(task) {
+            previous.abort();
+        }
+        Ok(json!({ "subscribed": true }))
+    }
+
+    async fn unsubscribe(&self) -> Result<Value, RpcError> {
+        let subscription = self.subscription.lock().await.take();
+        if let Some(task) = &subscription {
+            task.abort();
+        }
+        Ok(json!({ "subscribed": false, "wasSubscribed": subscription.is_some() }))
+    }
+}
+
+async fn handle_connection(host: SharedHost, stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let (out, mut outgoing) = mpsc::channel::<Value>(OUTGOING_BUFFER);
+    let overflowed = Arc::new(Notify::new());
+    let hang_up = Arc::clone(&overflowed);
+    tokio::spawn(async move {
+        loop {
+            let message = tokio::select! {
+                message = outgoing.recv() => message,
+                // Closing tells the client it missed events; it can reconnect.
+                _ = hang_up.notified() => None,
+            };
+            let Some(message) = message else {
+                break;
+            };
+            let mut line = message.to_string();
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+    let connection = Arc::new(Connection {
+        host,
+        out,
+        overflowed,
+        subscription: Mutex::new(None),
+    });
+    let mut lines = BufReader::new(reader).lines();
+    // Each request runs on its own task so a long `send_user_message` does
+    // not hold up the rest. A half-closed socket keeps receiving replies and
+    // events until the client goes away.
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let connection = Arc::clone(&connection);
+        tokio::spawn(async move {
+            connection.handle_line(&line).await;
+        });
+    }
+}
+
+/// Makes sure only this user can enter the socket's directory. Our own
+/// default directory is tightened; a `$CODEX_MONITOR_SOCKET` directory that
+/// others can enter is refused rather than changed.
+fn private_dir(dir: &Path, ours: bool) -> Result<(), String> {
+    if !dir.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .map_err(|error| error.to_string())?;
+    }
+    let mode = std::fs::metadata(dir)
+        .map_err(|error| error.to_string())?
+        .permissions()
+        .mode();
+    if mode & 0o077 == 0 {
+        return Ok(());
+    }
+    if !ours {
+        return Err(format!(
+            "{} is open to other users; keep the socket in a directory with mode 0700",
+            dir.display()
+        ));
+    }
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|error| error.to_string())
+}
+
+/// Binds `path`, replacing a stale socket left by a daemon that crashed.
+async fn bind(path: &Path, ours: bool) -> Result<UnixListener, String> {
+    if let Some(parent) = path.parent() {
+        private_dir(parent, ours)?;
+    }
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(format!(
+                "Another daemon is already listening on {}",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path).map_err(|error| error.to_string())?;
+    }
+    let listener = UnixListener::bind(path).map_err(|error| error.to_string())?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|error| error.to_string())?;
+    Ok(listener)
+}
+
+/// Serves the command set on the daemon socket until the process exits.
+pub(crate) async fn serve(host: SharedHost) -> Result<(), String> {
+    let ours = std::env::var_os(SOCKET_ENV).is_none();
+    let path = socket_path().ok_or("Cannot locate the data directory; set HOME")?;
+    let listener = bind(&path, ours).await?;
+    eprintln!("Codex Monitor daemon listening on {}", path.display());
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(host.clone(), stream));
+            }
+            Err(error) => eprintln!("Daemon failed to accept a connection: {error}"),
+        }
+    }
+}
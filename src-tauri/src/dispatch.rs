@@ -0,0 +1,482 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::backend::{AgentEvent, SharedHost, AGENT_EVENT};
+use crate::rpc::{CommandError, RpcError};
+use crate::{
+    audit, backend, codex, export, git, opencode, prompt_queue, rate_limits, remote, search,
+    settings, thread_store, transcripts, usage, workspaces,
+};
+
+/// Sent when a subscriber fell behind and missed `skipped` events.
+pub(crate) const LAGGED_METHOD: &str = "events/lagged";
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const COMMAND_FAILED: i64 = -32000;
+
+pub(crate) fn rpc_error(code: i64, message: impl Into<String>) -> RpcError {
+    RpcError::Server {
+        code,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// The JSON-RPC `error` object for a failed call.
+pub(crate) fn error_object(error: &RpcError) -> Value {
+    match error {
+        RpcError::Server {
+            code,
+            message,
+            data,
+        } => json!({ "code": code, "message": message, "data": data }),
+        other => json!({ "code": COMMAND_FAILED, "message": other.to_string() }),
+    }
+}
+
+/// Named command arguments, spelled the way the frontend passes them to `invoke`.
+pub(crate) struct Params(Map<String, Value>);
+
+impl Params {
+    pub(crate) fn new(params: Option<Value>) -> Result<Self, RpcError> {
+        match params {
+            None | Some(Value::Null) => Ok(Self(Map::new())),
+            Some(Value::Object(map)) => Ok(Self(map)),
+            Some(_) => Err(rpc_error(INVALID_PARAMS, "params must be an object")),
+        }
+    }
+
+    /// A missing key reads as `null`, so optional arguments can be left out.
+    pub(crate) fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T, RpcError> {
+        let value = self.0.get(key).cloned().unwrap_or(Value::Null);
+        serde_json::from_value(value)
+            .map_err(|error| rpc_error(INVALID_PARAMS, format!("{key}: {error}")))
+    }
+}
+
+/// A command's result as a JSON-RPC reply. Agent errors ride along in `data`
+/// the way the app's frontend receives them in `CommandError::rpc`.
+fn reply<T: Serialize, E: Into<CommandError>>(result: Result<T, E>) -> Result<Value, RpcError> {
+    let value = result.map_err(|error| {
+        let error = error.into();
+        RpcError::Server {
+            code: COMMAND_FAILED,
+            message: error.message,
+            data: error.rpc.and_then(|rpc| serde_json::to_value(rpc).ok()),
+        }
+    })?;
+    serde_json::to_value(value).map_err(|error| rpc_error(COMMAND_FAILED, error.to_string()))
+}
+
+/// Runs the command named `method` through its core function. Commands that
+/// need a window (the export save dialog) require their path argument instead.
+/// `remote_token` labels calls made with a remote access token, so the audit
+/// log records which token answered a request.
+pub(crate) async fn dispatch(
+    host: &SharedHost,
+    method: &str,
+    params: Option<Value>,
+    remote_token: Option<&str>,
+) -> Result<Value, RpcError> {
+    let params = Params::new(params)?;
+    let state = host.app_state();
+    match method {
+        "get_app_settings" => reply(settings::get_app_settings(state).await),
+        "update_app_settings" => {
+            reply(settings::update_app_settings(host, params.get("settings")?).await)
+        }
+        "codex_doctor" => reply(codex::codex_doctor_report(params.get("codexBin")?, state).await),
+        "list_workspaces" => reply(workspaces::list_workspaces(state).await),
+        "add_workspace" => reply(
+            workspaces::add_workspace(
+                host,
+                params.get("path")?,
+                params.get("codexBin")?,
+                params.get("opencodeBin")?,
+                params.get("backend")?,
+                params.get("acpAgent")?,
+            )
+            .await,
+        ),
+        "add_worktree" => reply(
+            workspaces::add_worktree(host, params.get("parentId")?, params.get("branch")?).await,
+        ),
+        "remove_workspace" => reply(workspaces::remove_workspace(state, params.get("id")?).await),
+        "remove_worktree" => reply(workspaces::remove_worktree(state, params.get("id")?).await),
+        "update_workspace_settings" => reply(
+            workspaces::update_workspace_settings(
+                state,
+                params.get("id")?,
+                params.get("settings")?,
+            )
+            .await,
+        ),
+        "update_workspace_codex_bin" => reply(
+            workspaces::update_workspace_codex_bin(
+                state,
+                params.get("id")?,
+                params.get("codexBin")?,
+            )
+            .await,
+        ),
+        "update_workspace_acp_agent" => reply(
+            workspaces::update_workspace_acp_agent(
+                state,
+                params.get("id")?,
+                params.get("acpAgent")?,
+            )
+            .await,
+        ),
+        "connect_workspace" => {
+            reply(workspaces::connect_saved_workspace(&params.get::<String>("id")?, host).await)
+        }
+        "list_workspace_files" => {
+            reply(workspaces::list_workspace_files(state, params.get("workspaceId")?).await)
+        }
+        "start_thread" => reply(backend::start_thread(state, params.get("workspaceId")?).await),
+        "send_user_message" => reply(
+            backend::send_user_message(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("text")?,
+                params.get("model")?,
+                params.get("effort")?,
+                params.get("accessMode")?,
+                params.get("attachments")?,
+            )
+            .await,
+        ),
+        "turn_interrupt" => reply(
+            backend::turn_interrupt(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("turnId")?,
+            )
+            .await,
+        ),
+        "list_threads" => reply(
+            backend::list_threads(
+                state,
+                params.get("workspaceId")?,
+                params.get("cursor")?,
+                params.get("limit")?,
+            )
+            .await,
+        ),
+        "model_list" => reply(backend::model_list(state, params.get("workspaceId")?).await),
+        "respond_to_server_request" => reply(
+            backend::respond_to_request(
+                state,
+                &params.get::<String>("workspaceId")?,
+                params.get("requestId")?,
+                params.get("result")?,
+                params.get("backend")?,
+                remote_token,
+            )
+            .await,
+        ),
+        "backend_health" => reply(backend::backend_health(state, params.get("workspaceId")?).await),
+        "enqueue_prompt" => reply(
+            prompt_queue::enqueue_prompt(
+                host,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("text")?,
+                params.get("model")?,
+                params.get("effort")?,
+                params.get("accessMode")?,
+                params.get("attachments")?,
+                params.get("schedule")?,
+            )
+            .await,
+        ),
+        "list_queued_prompts" => reply(
+            prompt_queue::list_queued_prompts(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+            )
+            .await,
+        ),
+        "reorder_queued_prompts" => reply(
+            prompt_queue::reorder_queued_prompts(
+                host,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("promptIds")?,
+            )
+            .await,
+        ),
+        "set_queued_prompt_paused" => reply(
+            prompt_queue::set_queued_prompt_paused(
+                host,
+                params.get("promptId")?,
+                params.get("paused")?,
+            )
+            .await,
+        ),
+        "cancel_queued_prompt" => {
+            reply(prompt_queue::cancel_queued_prompt(host, params.get("promptId")?).await)
+        }
+        "start_review" => reply(
+            codex::start_codex_review(
+                state,
+                &params.get::<String>("workspaceId")?,
+                params.get("threadId")?,
+                params.get("target")?,
+                params.get("delivery")?,
+            )
+            .await,
+        ),
+        "cancel_request" => reply(
+            codex::cancel_codex_request(
+                state,
+                &params.get::<String>("workspaceId")?,
+                params.get("requestId")?,
+            )
+            .await,
+        ),
+        "list_pending_requests" => {
+            reply(codex::pending_codex_requests(state, &params.get::<String>("workspaceId")?).await)
+        }
+        "resume_thread" => reply(
+            codex::fetch_thread(
+                state,
+                &params.get::<String>("workspaceId")?,
+                params.get("threadId")?,
+            )
+            .await,
+        ),
+        "archive_thread" => reply(
+            codex::archive_codex_thread(
+                state,
+                &params.get::<String>("workspaceId")?,
+                params.get("threadId")?,
+            )
+            .await,
+        ),
+        "get_thread_snapshot" => reply(
+            thread_store::get_thread_snapshot(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+            )
+            .await,
+        ),
+        "query_audit_log" => reply(
+            audit::query_audit_log(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("decision")?,
+                params.get("since")?,
+                params.get("until")?,
+                params.get("limit")?,
+            )
+            .await,
+        ),
+        "get_usage_summary" => reply(
+            usage::get_usage_summary(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("since")?,
+                params.get("until")?,
+                params.get("groupBy")?,
+                params.get("utcOffsetMinutes")?,
+            )
+            .await,
+        ),
+        "read_transcript" => reply(
+            transcripts::read_transcript(
+                state,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("cursor")?,
+                params.get("limit")?,
+            )
+            .await,
+        ),
+        "search_conversations" => reply(
+            search::search_conversations(
+                state,
+                params.get("query")?,
+                params.get("workspaceId")?,
+                params.get("limit")?,
+            )
+            .await,
+        ),
+        "export_thread" => reply(
+            export::export_thread(
+                host,
+                params.get("workspaceId")?,
+                params.get("threadId")?,
+                params.get("format")?,
+                params.get("path")?,
+            )
+            .await,
+        ),
+        "get_git_status" => reply(git::get_git_status(state, params.get("workspaceId")?).await),
+        "get_git_diffs" => reply(git::get_git_diffs(state, params.get("workspaceId")?).await),
+        "get_git_log" => {
+            reply(git::get_git_log(state, params.get("workspaceId")?, params.get("limit")?).await)
+        }
+        "get_git_remote" => reply(git::get_git_remote(state, params.get("workspaceId")?).await),
+        "get_github_issues" => {
+            reply(git::get_github_issues(state, params.get("workspaceId")?).await)
+        }
+        "list_git_branches" => {
+            reply(git::list_git_branches(state, params.get("workspaceId")?).await)
+        }
+        "checkout_git_branch" => reply(
+            git::checkout_git_branch(state, params.get("workspaceId")?, params.get("name")?).await,
+        ),
+        "create_git_branch" => reply(
+            git::create_git_branch(state, params.get("workspaceId")?, params.get("name")?).await,
+        ),
+        "account_rate_limits" => reply(
+            codex::read_account_rate_limits(host.as_ref(), &params.get::<String>("workspaceId")?)
+                .await,
+        ),
+        "get_cached_rate_limits" => {
+            reply(rate_limits::get_cached_rate_limits(state, params.get("workspaceId")?).await)
+        }
+        "get_remote_server_status" => reply(remote::get_remote_server_status(state).await),
+        "generate_remote_token" => reply(remote::generate_remote_token(
+            params.get("label")?,
+            params.get("scope")?,
+        )),
+        "skills_list" => {
+            reply(codex::list_codex_skills(state, &params.get::<String>("workspaceId")?).await)
+        }
+        "opencode_doctor" => {
+            reply(opencode::opencode_doctor_report(params.get("opencodeBin")?, state).await)
+        }
+        "list_opencode_sessions" => {
+            reply(opencode::opencode_sessions(state, &params.get::<String>("workspaceId")?).await)
+        }
+        "create_opencode_session" => {
+            reply(opencode::create_opencode_session(host, params.get("workspaceId")?).await)
+        }
+        "get_opencode_session" => reply(
+            opencode::get_opencode_session(
+                host,
+                params.get("workspaceId")?,
+                params.get("sessionId")?,
+            )
+            .await,
+        ),
+        "load_opencode_session" => reply(
+            opencode::load_opencode_session(
+                host,
+                params.get("workspaceId")?,
+                params.get("sessionId")?,
+            )
+            .await,
+        ),
+        "delete_opencode_session" => reply(
+            opencode::delete_opencode_session(
+                host,
+                params.get("workspaceId")?,
+                params.get("sessionId")?,
+            )
+            .await,
+        ),
+        "get_opencode_messages" => reply(
+            opencode::fetch_opencode_messages(
+                &params.get::<String>("workspaceId")?,
+                &params.get::<String>("sessionId")?,
+                host,
+            )
+            .await,
+        ),
+        "send_opencode_message" => reply(
+            opencode::send_opencode_message(
+                host,
+                params.get("workspaceId")?,
+                params.get("sessionId")?,
+                params.get("text")?,
+                params.get("providerId")?,
+                params.get("modelId")?,
+                params.get("attachments")?,
+            )
+            .await,
+        ),
+        "cancel_opencode_operation" => reply(
+            opencode::cancel_opencode_operation(
+                host,
+                params.get("workspaceId")?,
+                params.get("sessionId")?,
+            )
+            .await,
+        ),
+        "get_opencode_providers" => {
+            reply(opencode::opencode_providers(state, &params.get::<String>("workspaceId")?).await)
+        }
+        _ => Err(rpc_error(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {method}"),
+        )),
+    }
+}
+
+/// Waits for the next agent event (of `workspace_id` only, if given) as a
+/// JSON-RPC notification; `None` once the app is shutting down.
+pub(crate) async fn next_event_notification(
+    events: &mut broadcast::Receiver<AgentEvent>,
+    workspace_id: Option<&str>,
+) -> Option<Value> {
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                if workspace_id.is_some_and(|id| id != event.workspace_id) {
+                    continue;
+                }
+                return Some(json!({ "jsonrpc": "2.0", "method": AGENT_EVENT, "params": event }));
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                return Some(json!({
+                    "jsonrpc": "2.0",
+                    "method": LAGGED_METHOD,
+                    "params": { "skipped": skipped },
+                }))
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error_object, Params, INVALID_PARAMS};
+    use crate::rpc::RpcError;
+    use serde_json::json;
+
+    #[test]
+    fn params_treat_missing_keys_as_null() {
+        let params = Params::new(Some(json!({ "workspaceId": "ws", "limit": 5 }))).unwrap();
+        assert_eq!(params.get::<String>("workspaceId").unwrap(), "ws");
+        assert_eq!(params.get::<Option<u32>>("limit").unwrap(), Some(5));
+        assert_eq!(params.get::<Option<String>>("cursor").unwrap(), None);
+
+        let error = params.get::<String>("threadId").unwrap_err();
+        let object = error_object(&error);
+        assert_eq!(object["code"], INVALID_PARAMS);
+        assert!(object["message"].as_str().unwrap().starts_with("threadId"));
+
+        assert!(Params::new(None).is_ok());
+        assert!(Params::new(Some(json!(["ws"]))).is_err());
+        let other = error_object(&RpcError::Disconnected {
+            message: "gone".to_string(),
+        });
+        assert_eq!(other["message"], "gone");
+    }
+}
@@ -0,0 +1,993 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::acp_messages::MessageFolder;
+use crate::backend::SharedHost;
+use crate::opencode::fetch_opencode_messages;
+use crate::protocol::{KnownThreadItem, PlanStep, ThreadItem};
+use crate::state::AppState;
+use crate::transcripts::{read_journal, TranscriptEntry};
+use crate::types::{BackendType, OpenCodeMessage, OpenCodeMessagePart};
+use crate::utils::civil_from_days;
+
+/// Bumped whenever the JSON export changes shape.
+const EXPORT_SCHEMA_VERSION: u32 = 1;
+const TITLE_MAX_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub(crate) enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "HTML",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum ExportEntry {
+    UserMessage {
+        text: String,
+    },
+    AgentMessage {
+        text: String,
+    },
+    Reasoning {
+        summary: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Command {
+        command: String,
+        cwd: Option<String>,
+        exit_code: Option<i32>,
+        output: Option<String>,
+    },
+    FileChange {
+        path: String,
+        diff: Option<String>,
+    },
+    ToolCall {
+        name: String,
+        status: Option<String>,
+        input: Option<Value>,
+        output: Option<String>,
+    },
+    Plan {
+        explanation: Option<String>,
+        steps: Vec<PlanStep>,
+    },
+}
+
+/// The stable JSON export; Markdown and HTML are rendered from the same data.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadExport {
+    pub(crate) schema_version: u32,
+    pub(crate) backend: String,
+    pub(crate) workspace_id: String,
+    pub(crate) workspace_name: String,
+    pub(crate) thread_id: String,
+    pub(crate) title: Option<String>,
+    #[ts(type = "number")]
+    pub(crate) exported_at: u64,
+    pub(crate) entries: Vec<ExportEntry>,
+}
+
+impl ThreadExport {
+    fn display_title(&self) -> String {
+        self.title
+            .clone()
+            .unwrap_or_else(|| format!("Thread {}", self.thread_id))
+    }
+}
+
+/// The thread's preview and items as its Codex transcript recorded them:
+/// `item/completed` items, with `turn/start` prompts standing in for user
+/// messages the app-server did not echo as items.
+fn codex_transcript_items(transcript: &[TranscriptEntry]) -> (Option<String>, Vec<ThreadItem>) {
+    let mut preview = None;
+    let mut items = Vec::new();
+    let mut prompt: Option<ThreadItem> = None;
+    for entry in transcript {
+        let Some(params) = entry.params.as_ref() else {
+            continue;
+        };
+        match entry.method.as_str() {
+            "thread/started" => {
+                preview = params
+                    .get("thread")
+                    .and_then(|thread| thread.get("preview"))
+                    .and_then(|preview| preview.as_str())
+                    .filter(|preview| !preview.trim().is_empty())
+                    .map(|preview| preview.to_string())
+                    .or(preview);
+            }
+            "turn/start" => {
+                items.extend(prompt.take());
+                let content = params
+                    .get("input")
+                    .and_then(|input| input.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                prompt = Some(ThreadItem::Known(KnownThreadItem::UserMessage {
+                    id: format!("prompt-{}", entry.seq),
+                    content,
+                }));
+            }
+            "item/completed" => {
+                let Some(item) = params
+                    .get("item")
+                    .and_then(|item| ThreadItem::deserialize(item).ok())
+                else {
+                    continue;
+                };
+                if matches!(item, ThreadItem::Known(KnownThreadItem::UserMessage { .. })) {
+                    prompt = None;
+                } else {
+                    items.extend(prompt.take());
+                }
+                items.push(item);
+            }
+            _ => {}
+        }
+    }
+    items.extend(prompt);
+    (preview, items)
+}
+
+/// An ACP session's messages as its transcript recorded them, folded the way
+/// they were streamed.
+fn acp_transcript_messages(
+    session_id: &str,
+    transcript: &[TranscriptEntry],
+) -> Vec<OpenCodeMessage> {
+    let mut folder = MessageFolder::default();
+    let mut messages = Vec::new();
+    let mut open = false;
+    for entry in transcript {
+        let Some(params) = entry.params.as_ref() else {
+            continue;
+        };
+        match entry.method.as_str() {
+            "session/prompt" => {
+                if std::mem::take(&mut open) {
+                    messages.push(folder.finish(session_id, None));
+                }
+                let text = params
+                    .get("prompt")
+                    .and_then(|prompt| prompt.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| part.get("text").and_then(|text| text.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                messages.push(OpenCodeMessage {
+                    id: format!("prompt-{}", entry.seq),
+                    session_id: session_id.to_string(),
+                    role: "user".to_string(),
+                    parts: vec![OpenCodeMessagePart::Text { text }],
+                    created_at: Some(entry.timestamp as i64),
+                    stop_reason: None,
+                });
+            }
+            "session/update" => {
+                // User chunks only replay history on `session/load`; the
+                // prompts themselves were recorded above.
+                let kind = params
+                    .get("update")
+                    .and_then(|update| update.get("sessionUpdate"))
+                    .and_then(|kind| kind.as_str());
+                if kind != Some("user_message_chunk") && !folder.apply(params).is_empty() {
+                    open = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if open {
+        messages.push(folder.finish(session_id, None));
+    }
+    messages
+}
+
+fn first_prompt_title(entries: &[ExportEntry]) -> Option<String> {
+    entries.iter().find_map(|entry| match entry {
+        ExportEntry::UserMessage { text } => text
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| line.trim().chars().take(TITLE_MAX_CHARS).collect()),
+        _ => None,
+    })
+}
+
+fn codex_entries(items: Vec<ThreadItem>) -> Vec<ExportEntry> {
+    let mut entries = Vec::new();
+    for item in items {
+        let ThreadItem::Known(item) = item else {
+            continue;
+        };
+        match item {
+            KnownThreadItem::UserMessage { content, .. } => {
+                let text = content
+                    .iter()
+                    .filter_map(|part| part.get("text").and_then(|text| text.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                entries.push(ExportEntry::UserMessage { text });
+            }
+            KnownThreadItem::AgentMessage { text, .. } => {
+                entries.push(ExportEntry::AgentMessage { text });
+            }
+            KnownThreadItem::Reasoning { summary, .. } => {
+                let summary = summary.join("\n\n");
+                if !summary.trim().is_empty() {
+                    entries.push(ExportEntry::Reasoning { summary });
+                }
+            }
+            KnownThreadItem::CommandExecution {
+                command,
+                cwd,
+                aggregated_output,
+                exit_code,
+                ..
+            } => entries.push(ExportEntry::Command {
+                command,
+                cwd,
+                exit_code,
+                output: aggregated_output,
+            }),
+            KnownThreadItem::FileChange { changes, .. } => {
+                entries.extend(changes.into_iter().map(|change| ExportEntry::FileChange {
+                    path: change.path,
+                    diff: change.diff,
+                }));
+            }
+            KnownThreadItem::McpToolCall {
+                server,
+                tool,
+                status,
+                ..
+            } => {
+                let name = [server, tool]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("/");
+                entries.push(ExportEntry::ToolCall {
+                    name,
+                    status,
+                    input: None,
+                    output: None,
+                });
+            }
+        }
+    }
+    entries
+}
+
+fn opencode_entries(result: &Value) -> (Option<String>, Vec<ExportEntry>) {
+    let messages = result
+        .get("messages")
+        .unwrap_or(result)
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut entries: Vec<ExportEntry> = Vec::new();
+    for message in &messages {
+        let is_user = message.get("role").and_then(|role| role.as_str()) == Some("user");
+        let parts = message
+            .get("parts")
+            .and_then(|parts| parts.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for part in parts {
+            let text = part
+                .get("text")
+                .or_else(|| part.get("content"))
+                .and_then(|text| text.as_str())
+                .map(|text| text.to_string());
+            match part.get("type").and_then(|kind| kind.as_str()) {
+                Some("text") => {
+                    let text = text.unwrap_or_default();
+                    entries.push(if is_user {
+                        ExportEntry::UserMessage { text }
+                    } else {
+                        ExportEntry::AgentMessage { text }
+                    });
+                }
+                Some("reasoning") => entries.push(ExportEntry::Reasoning {
+                    summary: text.unwrap_or_default(),
+                }),
+                Some("tool_use") => entries.push(ExportEntry::ToolCall {
+                    name: part
+                        .get("name")
+                        .or_else(|| part.get("toolName"))
+                        .and_then(|name| name.as_str())
+                        .unwrap_or("tool")
+                        .to_string(),
+                    status: part
+                        .get("status")
+                        .and_then(|status| status.as_str())
+                        .map(|status| status.to_string()),
+                    input: part.get("input").cloned(),
+                    output: part
+                        .get("output")
+                        .and_then(|output| output.as_str())
+                        .filter(|output| !output.is_empty())
+                        .map(|output| output.to_string()),
+                }),
+                Some("tool_result") => {
+                    let attached = match entries.last_mut() {
+                        Some(ExportEntry::ToolCall { output, .. }) if output.is_none() => {
+                            *output = text.clone();
+                            true
+                        }
+                        _ => false,
+                    };
+                    if !attached {
+                        entries.push(ExportEntry::ToolCall {
+                            name: "tool".to_string(),
+                            status: None,
+                            input: None,
+                            output: text,
+                        });
+                    }
+                }
+                Some("patch") => {
+                    let files = part
+                        .get("files")
+                        .and_then(|files| files.as_array())
+                        .cloned()
+                        .unwrap_or_default();
+                    entries.extend(files.iter().filter_map(|file| file.as_str()).map(|path| {
+                        ExportEntry::FileChange {
+                            path: path.to_string(),
+                            diff: None,
+                        }
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+    (first_prompt_title(&entries), entries)
+}
+
+pub(crate) async fn build_export(
+    state: &AppState,
+    host: &SharedHost,
+    workspace_id: &str,
+    thread_id: &str,
+) -> Result<ThreadExport, String> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?
+    };
+    let transcript = {
+        let root = state.transcripts_dir.clone();
+        let (workspace_id, thread_id) = (workspace_id.to_string(), thread_id.to_string());
+        tokio::task::spawn_blocking(move || {
+            read_journal(&root, &workspace_id, &thread_id, None, usize::MAX)
+        })
+        .await
+        .map_err(|e| e.to_string())??
+        .entries
+    };
+    let snapshot = state
+        .thread_store
+        .lock()
+        .await
+        .snapshot(workspace_id, thread_id);
+    let (backend, (title, mut entries)) = match entry.backend {
+        BackendType::Codex => {
+            let (preview, mut items) = codex_transcript_items(&transcript);
+            if items.is_empty() {
+                // Threads from before transcripts were kept have only live state.
+                items = snapshot
+                    .as_ref()
+                    .map(|snapshot| snapshot.items.clone())
+                    .unwrap_or_default();
+            }
+            if items.is_empty() {
+                return Err("This thread has no recorded history to export yet.".to_string());
+            }
+            let entries = codex_entries(items);
+            let title = preview.or_else(|| first_prompt_title(&entries));
+            ("codex", (title, entries))
+        }
+        BackendType::OpenCode | BackendType::Acp => {
+            let messages = acp_transcript_messages(thread_id, &transcript);
+            let result = if messages.is_empty() && entry.backend == BackendType::OpenCode {
+                // OpenCode keeps its own history for sessions we never recorded.
+                fetch_opencode_messages(workspace_id, thread_id, host)
+                    .await
+                    .map_err(|error| error.message)?
+            } else {
+                json!(messages)
+            };
+            let label = if entry.backend == BackendType::Acp {
+                "acp"
+            } else {
+                "opencode"
+            };
+            (label, opencode_entries(&result))
+        }
+    };
+    if let Some(plan) = snapshot.and_then(|snapshot| snapshot.plan) {
+        entries.push(ExportEntry::Plan {
+            explanation: plan.explanation,
+            steps: plan.plan,
+        });
+    }
+    Ok(ThreadExport {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        backend: backend.to_string(),
+        workspace_id: workspace_id.to_string(),
+        workspace_name: entry.name,
+        thread_id: thread_id.to_string(),
+        title,
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0),
+        entries,
+    })
+}
+
+/// Formats epoch milliseconds as `YYYY-MM-DD HH:MM UTC`.
+fn format_timestamp(millis: u64) -> String {
+    let secs = millis / 1000;
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+}
+
+/// A backtick fence longer than any run of backticks inside `content`.
+fn fence(content: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for ch in content.chars() {
+        if ch == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn fenced_block(language: &str, content: &str) -> String {
+    let fence = fence(content);
+    format!("{fence}{language}\n{}\n{fence}\n\n", content.trim_end())
+}
+
+fn plan_marker(status: &str) -> &'static str {
+    match status.replace(['_', '-', ' '], "").to_lowercase().as_str() {
+        "completed" => "[x]",
+        "inprogress" => "[~]",
+        _ => "[ ]",
+    }
+}
+
+fn render_markdown(export: &ThreadExport) -> String {
+    let mut out = format!("# {}\n\n", export.display_title());
+    out.push_str(&format!(
+        "- Workspace: {}\n- Thread: `{}`\n- Backend: {}\n- Exported: {}\n\n",
+        export.workspace_name,
+        export.thread_id,
+        export.backend,
+        format_timestamp(export.exported_at)
+    ));
+    for entry in &export.entries {
+        match entry {
+            ExportEntry::UserMessage { text } => {
+                out.push_str(&format!("## User\n\n{}\n\n", text.trim_end()));
+            }
+            ExportEntry::AgentMessage { text } => {
+                out.push_str(&format!("## Agent\n\n{}\n\n", text.trim_end()));
+            }
+            ExportEntry::Reasoning { summary } => {
+                out.push_str("> **Reasoning**\n>\n");
+                for line in summary.trim_end().lines() {
+                    out.push_str(&format!("> {line}\n"));
+                }
+                out.push('\n');
+            }
+            ExportEntry::Command {
+                command,
+                cwd,
+                exit_code,
+                output,
+            } => {
+                let mut heading = "**Command**".to_string();
+                if let Some(cwd) = cwd {
+                    heading.push_str(&format!(" in `{cwd}`"));
+                }
+                if let Some(code) = exit_code {
+                    heading.push_str(&format!(" (exit {code})"));
+                }
+                out.push_str(&format!("{heading}\n\n"));
+                let body = match output {
+                    Some(output) if !output.trim().is_empty() => {
+                        format!("$ {command}\n{output}")
+                    }
+                    _ => format!("$ {command}"),
+                };
+                out.push_str(&fenced_block("sh", &body));
+            }
+            ExportEntry::FileChange { path, diff } => {
+                out.push_str(&format!("**Changed** `{path}`\n\n"));
+                if let Some(diff) = diff.as_deref().filter(|diff| !diff.trim().is_empty()) {
+                    out.push_str(&fenced_block("diff", diff));
+                }
+            }
+            ExportEntry::ToolCall {
+                name,
+                status,
+                input,
+                output,
+            } => {
+                let status = status
+                    .as_deref()
+                    .map(|status| format!(" ({status})"))
+                    .unwrap_or_default();
+                out.push_str(&format!("**Tool** `{name}`{status}\n\n"));
+                if let Some(input) = input {
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    out.push_str(&fenced_block("json", &input));
+                }
+                if let Some(output) = output.as_deref().filter(|output| !output.trim().is_empty()) {
+                    out.push_str(&fenced_block("text", output));
+                }
+            }
+            ExportEntry::Plan { explanation, steps } => {
+                out.push_str("## Plan\n\n");
+                if let Some(explanation) = explanation {
+                    out.push_str(&format!("{}\n\n", explanation.trim_end()));
+                }
+                for step in steps {
+                    out.push_str(&format!("- {} {}\n", plan_marker(&step.status), step.step));
+                }
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn render_diff_html(diff: &str) -> String {
+    let lines: Vec<String> = diff
+        .lines()
+        .map(|line| {
+            let class = if line.starts_with("+++") || line.starts_with("---") {
+                "meta"
+            } else if line.starts_with('+') {
+                "add"
+            } else if line.starts_with('-') {
+                "del"
+            } else if line.starts_with("@@") {
+                "hunk"
+            } else {
+                "ctx"
+            };
+            format!("<span class=\"{class}\">{}</span>", escape_html(line))
+        })
+        .collect();
+    format!("<pre class=\"diff\">{}</pre>", lines.join("\n"))
+}
+
+const HTML_STYLE: &str = "\
+body{font:14px/1.55 -apple-system,BlinkMacSystemFont,\"Segoe UI\",sans-serif;max-width:880px;margin:32px auto;padding:0 20px;color:#1f2328;background:#fff}\
+header{border-bottom:1px solid #d0d7de;margin-bottom:24px}\
+header dl{display:grid;grid-template-columns:max-content 1fr;gap:2px 12px;color:#59636e}\
+section{margin:16px 0;padding:12px 16px;border-radius:8px;border:1px solid #d0d7de}\
+section.user{background:#f6f8fa}\
+section.reasoning{color:#59636e;font-style:italic}\
+h2{font-size:12px;text-transform:uppercase;letter-spacing:.06em;color:#59636e;margin:0 0 8px}\
+.text{white-space:pre-wrap;word-wrap:break-word}\
+pre{background:#f6f8fa;padding:10px;border-radius:6px;overflow:auto;font:12px/1.45 ui-monospace,SFMono-Regular,Menlo,monospace}\
+.diff .add{color:#116329;background:#dafbe1;display:block}\
+.diff .del{color:#82071e;background:#ffebe9;display:block}\
+.diff .hunk{color:#0550ae;display:block}\
+.diff .meta{color:#59636e;display:block}\
+.diff .ctx{display:block}\
+ul.plan{list-style:none;padding-left:0}\
+@media (prefers-color-scheme:dark){body{background:#0d1117;color:#e6edf3}section,header{border-color:#30363d}section.user,pre{background:#161b22}.diff .add{background:#033a16;color:#aff5b4}.diff .del{background:#67060c;color:#ffdcd7}}";
+
+fn render_html(export: &ThreadExport) -> String {
+    let title = escape_html(&export.display_title());
+    let mut body = String::new();
+    for entry in &export.entries {
+        let section = match entry {
+            ExportEntry::UserMessage { text } => format!(
+                "<section class=\"user\"><h2>User</h2><div class=\"text\">{}</div></section>",
+                escape_html(text)
+            ),
+            ExportEntry::AgentMessage { text } => format!(
+                "<section class=\"agent\"><h2>Agent</h2><div class=\"text\">{}</div></section>",
+                escape_html(text)
+            ),
+            ExportEntry::Reasoning { summary } => format!(
+                "<section class=\"reasoning\"><h2>Reasoning</h2><div class=\"text\">{}</div></section>",
+                escape_html(summary)
+            ),
+            ExportEntry::Command {
+                command,
+                cwd,
+                exit_code,
+                output,
+            } => {
+                let mut meta = String::new();
+                if let Some(cwd) = cwd {
+                    meta.push_str(&format!(" in <code>{}</code>", escape_html(cwd)));
+                }
+                if let Some(code) = exit_code {
+                    meta.push_str(&format!(" &middot; exit {code}"));
+                }
+                format!(
+                    "<section class=\"command\"><h2>Command{meta}</h2><pre>$ {}\n{}</pre></section>",
+                    escape_html(command),
+                    escape_html(output.as_deref().unwrap_or_default())
+                )
+            }
+            ExportEntry::FileChange { path, diff } => format!(
+                "<section class=\"file-change\"><h2>Changed <code>{}</code></h2>{}</section>",
+                escape_html(path),
+                diff.as_deref().map(render_diff_html).unwrap_or_default()
+            ),
+            ExportEntry::ToolCall {
+                name,
+                status,
+                input,
+                output,
+            } => {
+                let input = input
+                    .as_ref()
+                    .map(|input| {
+                        format!(
+                            "<pre>{}</pre>",
+                            escape_html(&serde_json::to_string_pretty(input).unwrap_or_default())
+                        )
+                    })
+                    .unwrap_or_default();
+                let output = output
+                    .as_deref()
+                    .map(|output| format!("<pre>{}</pre>", escape_html(output)))
+                    .unwrap_or_default();
+                format!(
+                    "<section class=\"tool\"><h2>Tool <code>{}</code> {}</h2>{input}{output}</section>",
+                    escape_html(name),
+                    escape_html(status.as_deref().unwrap_or_default())
+                )
+            }
+            ExportEntry::Plan { explanation, steps } => {
+                let explanation = explanation
+                    .as_deref()
+                    .map(|text| format!("<div class=\"text\">{}</div>", escape_html(text)))
+                    .unwrap_or_default();
+                let steps: String = steps
+                    .iter()
+                    .map(|step| {
+                        format!(
+                            "<li>{} {}</li>",
+                            plan_marker(&step.status),
+                            escape_html(&step.step)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "<section class=\"plan\"><h2>Plan</h2>{explanation}<ul class=\"plan\">{steps}</ul></section>"
+                )
+            }
+        };
+        body.push_str(&section);
+        body.push('\n');
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<header><h1>{title}</h1><dl><dt>Workspace</dt><dd>{}</dd><dt>Thread</dt><dd><code>{}</code></dd><dt>Backend</dt><dd>{}</dd><dt>Exported</dt><dd>{}</dd></dl></header>\n{body}</body>\n</html>\n",
+        escape_html(&export.workspace_name),
+        escape_html(&export.thread_id),
+        escape_html(&export.backend),
+        format_timestamp(export.exported_at)
+    )
+}
+
+fn render(export: &ThreadExport, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Markdown => Ok(render_markdown(export)),
+        ExportFormat::Json => serde_json::to_string_pretty(export).map_err(|e| e.to_string()),
+        ExportFormat::Html => Ok(render_html(export)),
+    }
+}
+
+pub(crate) fn default_file_name(export: &ThreadExport, format: ExportFormat) -> String {
+    let slug: String = export
+        .title
+        .as_deref()
+        .unwrap_or_default()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let stem = if slug.is_empty() {
+        export.thread_id.clone()
+    } else {
+        slug.chars().take(60).collect()
+    };
+    format!("{stem}.{}", format.extension())
+}
+
+/// Renders `export` into `target`; returns the written path.
+pub(crate) async fn write_export(
+    export: &ThreadExport,
+    format: ExportFormat,
+    target: &Path,
+) -> Result<String, String> {
+    let contents = render(export, format)?;
+    tokio::fs::write(target, contents)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// Exports a thread (Codex) or session (OpenCode) to `path`, or under its
+/// default file name when `path` is a directory; returns the written path.
+pub(crate) async fn export_thread(
+    host: &SharedHost,
+    workspace_id: String,
+    thread_id: String,
+    format: ExportFormat,
+    path: String,
+) -> Result<String, String> {
+    let export = build_export(host.app_state(), host, &workspace_id, &thread_id).await?;
+    let mut target = PathBuf::from(path);
+    if tokio::fs::metadata(&target)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        target.push(default_file_name(&export, format));
+    }
+    write_export(&export, format, &target).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        acp_transcript_messages, codex_entries, codex_transcript_items, default_file_name, fence,
+        format_timestamp, opencode_entries, render_html, render_markdown, ExportEntry,
+        ExportFormat, ThreadExport,
+    };
+    use crate::protocol::ThreadItem;
+    use crate::transcripts::{TranscriptEntry, TranscriptSource};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    fn export(entries: Vec<ExportEntry>) -> ThreadExport {
+        ThreadExport {
+            schema_version: 1,
+            backend: "codex".to_string(),
+            workspace_id: "ws".to_string(),
+            workspace_name: "Repo".to_string(),
+            thread_id: "thr_1".to_string(),
+            title: Some("Fix <migration>".to_string()),
+            exported_at: 1_700_000_000_000,
+            entries,
+        }
+    }
+
+    fn transcript(entries: &[(&str, Value)]) -> Vec<TranscriptEntry> {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(index, (method, params))| TranscriptEntry {
+                seq: index as u64 + 1,
+                timestamp: 1_700_000_000_000,
+                source: TranscriptSource::Server,
+                method: method.to_string(),
+                params: Some(params.clone()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn codex_items_become_entries() {
+        let items = json!([
+            { "type": "userMessage", "id": "u", "content": [{ "type": "text", "text": "Fix it" }] },
+            { "type": "reasoning", "id": "r", "summary": [] },
+            { "type": "commandExecution", "id": "c", "command": "ls", "exitCode": 0 },
+            { "type": "fileChange", "id": "f", "changes": [
+                { "path": "a.rs", "diff": "+x" }, { "path": "b.rs" }
+            ]},
+            { "type": "somethingNew", "id": "n" }
+        ]);
+        let items = Vec::<ThreadItem>::deserialize(&items).expect("items");
+        let entries = codex_entries(items);
+        let kinds: Vec<&str> = entries
+            .iter()
+            .map(|entry| match entry {
+                ExportEntry::UserMessage { .. } => "user",
+                ExportEntry::Command { .. } => "command",
+                ExportEntry::FileChange { .. } => "file",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["user", "command", "file", "file"]);
+    }
+
+    #[test]
+    fn codex_transcripts_keep_prompts_without_echoed_items() {
+        let (preview, items) = codex_transcript_items(&transcript(&[
+            (
+                "thread/started",
+                json!({ "thread": { "id": "thr_1", "preview": "Fix it" } }),
+            ),
+            (
+                "turn/start",
+                json!({ "threadId": "thr_1", "input": [{ "type": "text", "text": "First" }] }),
+            ),
+            (
+                "item/completed",
+                json!({ "threadId": "thr_1", "item": { "type": "agentMessage", "id": "a", "text": "Done" } }),
+            ),
+            (
+                "turn/start",
+                json!({ "threadId": "thr_1", "input": [{ "type": "text", "text": "Second" }] }),
+            ),
+            (
+                "item/completed",
+                json!({ "threadId": "thr_1", "item": { "type": "userMessage", "id": "u", "content": [{ "type": "text", "text": "Second" }] } }),
+            ),
+        ]));
+        assert_eq!(preview.as_deref(), Some("Fix it"));
+        let entries = codex_entries(items);
+        assert!(matches!(&entries[..], [
+            ExportEntry::UserMessage { text: first },
+            ExportEntry::AgentMessage { .. },
+            ExportEntry::UserMessage { text: second },
+        ] if first == "First" && second == "Second"));
+    }
+
+    #[test]
+    fn acp_transcripts_fold_into_messages() {
+        let update = |update: Value| json!({ "sessionId": "ses_1", "update": update });
+        let messages = acp_transcript_messages(
+            "ses_1",
+            &transcript(&[
+                (
+                    "session/prompt",
+                    json!({ "sessionId": "ses_1", "prompt": [{ "type": "text", "text": "Run tests" }] }),
+                ),
+                (
+                    "session/update",
+                    update(
+                        json!({ "sessionUpdate": "agent_message_chunk", "content": { "type": "text", "text": "Run" } }),
+                    ),
+                ),
+                (
+                    "session/update",
+                    update(
+                        json!({ "sessionUpdate": "agent_message_chunk", "content": { "type": "text", "text": "ning" } }),
+                    ),
+                ),
+                (
+                    "session/update",
+                    update(
+                        json!({ "sessionUpdate": "tool_call", "toolCallId": "t1", "title": "bash", "status": "completed",
+                "content": [{ "type": "content", "content": { "type": "text", "text": "ok" } }] }),
+                    ),
+                ),
+            ]),
+        );
+        let (title, entries) = opencode_entries(&json!(messages));
+        assert_eq!(title.as_deref(), Some("Run tests"));
+        assert!(matches!(&entries[1], ExportEntry::AgentMessage { text } if text == "Running"));
+        assert!(matches!(
+            &entries[2],
+            ExportEntry::ToolCall { name, output: Some(output), .. } if name == "bash" && output == "ok"
+        ));
+    }
+
+    #[test]
+    fn opencode_tool_results_attach_to_calls() {
+        let (title, entries) = opencode_entries(&json!([
+            { "role": "user", "parts": [{ "type": "text", "text": "\nRun tests\nplease" }] },
+            { "role": "assistant", "parts": [
+                { "type": "tool_use", "id": "t1", "name": "bash", "input": { "cmd": "cargo test" } },
+                { "type": "tool_result", "tool_use_id": "t1", "content": "ok" }
+            ]}
+        ]));
+        assert_eq!(title.as_deref(), Some("Run tests"));
+        assert!(matches!(
+            &entries[1],
+            ExportEntry::ToolCall { name, output: Some(output), .. } if name == "bash" && output == "ok"
+        ));
+    }
+
+    #[test]
+    fn renderers_escape_and_fence_content() {
+        let export = export(vec![
+            ExportEntry::AgentMessage {
+                text: "Use ```rust``` blocks".to_string(),
+            },
+            ExportEntry::FileChange {
+                path: "src/a.rs".to_string(),
+                diff: Some("@@ -1 +1 @@\n-old\n+new".to_string()),
+            },
+        ]);
+        let markdown = render_markdown(&export);
+        assert!(markdown.starts_with("# Fix <migration>\n"));
+        assert!(markdown.contains("- Exported: 2023-11-14 22:13 UTC"));
+        assert!(markdown.contains("```diff\n@@ -1 +1 @@"));
+
+        let html = render_html(&export);
+        assert!(html.contains("<title>Fix &lt;migration&gt;</title>"));
+        assert!(html.contains("<span class=\"add\">+new</span>"));
+
+        assert_eq!(fence("a ```` b"), "`````");
+        assert_eq!(
+            default_file_name(&export, ExportFormat::Html),
+            "fix-migration.html"
+        );
+    }
+
+    #[test]
+    fn timestamps_format_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29 00:00 UTC");
+    }
+}
@@ -1,6 +1,5 @@
 use git2::{BranchType, DiffOptions, Repository, Sort, Status, StatusOptions, Tree};
 use serde_json::json;
-use tauri::State;
 use tokio::process::Command;
 
 use crate::state::AppState;
@@ -96,10 +95,9 @@ fn parse_github_repo(remote_url: &str) -> Option<String> {
     }
 }
 
-#[tauri::command]
 pub(crate) async fn get_git_status(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -194,10 +192,9 @@ pub(crate) async fn get_git_status(
     }))
 }
 
-#[tauri::command]
 pub(crate) async fn get_git_diffs(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<Vec<GitFileDiff>, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -258,11 +255,10 @@ pub(crate) async fn get_git_diffs(
     Ok(results)
 }
 
-#[tauri::command]
 pub(crate) async fn get_git_log(
+    state: &AppState,
     workspace_id: String,
     limit: Option<usize>,
-    state: State<'_, AppState>,
 ) -> Result<GitLogResponse, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -365,10 +361,9 @@ pub(crate) async fn get_git_log(
     })
 }
 
-#[tauri::command]
 pub(crate) async fn get_git_remote(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<Option<String>, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -395,10 +390,9 @@ pub(crate) async fn get_git_remote(
     Ok(remote.url().map(|url| url.to_string()))
 }
 
-#[tauri::command]
 pub(crate) async fn get_github_issues(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<GitHubIssuesResponse, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -485,10 +479,9 @@ pub(crate) async fn get_github_issues(
     Ok(GitHubIssuesResponse { total, issues })
 }
 
-#[tauri::command]
 pub(crate) async fn list_git_branches(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -514,15 +507,14 @@ pub(crate) async fn list_git_branches(
             .unwrap_or(0);
         branches.push(BranchInfo { name, last_commit });
     }
-    branches.sort_by(|a, b| b.last_commit.cmp(&a.last_commit));
+    branches.sort_by_key(|branch| std::cmp::Reverse(branch.last_commit));
     Ok(json!({ "branches": branches }))
 }
 
-#[tauri::command]
 pub(crate) async fn checkout_git_branch(
+    state: &AppState,
     workspace_id: String,
     name: String,
-    state: State<'_, AppState>,
 ) -> Result<(), String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -533,11 +525,10 @@ pub(crate) async fn checkout_git_branch(
     checkout_branch(&repo, &name).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
 pub(crate) async fn create_git_branch(
+    state: &AppState,
     workspace_id: String,
     name: String,
-    state: State<'_, AppState>,
 ) -> Result<(), String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -1,18 +1,63 @@
+use std::sync::Arc;
+
+#[cfg(feature = "gui")]
 use tauri::menu::{Menu, MenuItemBuilder, PredefinedMenuItem, Submenu};
+#[cfg(feature = "gui")]
 use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
 
+use crate::backend::SharedHost;
+
+mod acp;
+mod acp_client;
+mod acp_messages;
+mod approvals;
+#[cfg(all(unix, feature = "gui"))]
+mod attach;
+mod attachments;
+mod audit;
+mod backend;
+#[cfg(unix)]
+mod cli;
 mod codex;
+#[cfg(feature = "gui")]
+mod commands;
+#[cfg(unix)]
+mod daemon;
+mod dispatch;
+mod export;
 mod git;
 mod opencode;
+mod prompt_queue;
+mod protocol;
+mod rate_limits;
+mod remote;
+mod rpc;
+mod sandbox;
+mod search;
 mod settings;
 mod state;
 mod storage;
+#[cfg(feature = "mock-agents")]
+#[doc(hidden)]
+pub mod testing;
+mod thread_store;
+mod transcripts;
 mod types;
+mod usage;
 mod utils;
 mod workspaces;
 
+#[cfg(unix)]
+pub use cli::run_cli;
+
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Tauri and the backend share one runtime, entered here so `setup` can
+    // spawn the background workers.
+    let runtime = runtime();
+    tauri::async_runtime::set(runtime.handle().clone());
+    let _entered = runtime.enter();
     tauri::Builder::default()
         .enable_macos_default_menu(false)
         .menu(|handle| {
@@ -114,8 +159,18 @@ pub fn run() {
             }
         })
         .setup(|app| {
-            let state = state::AppState::load(&app.handle());
-            app.manage(state);
+            let data_dir = state::data_dir(app.handle());
+            match state::try_lock_data_dir(&data_dir) {
+                Ok(Some(lock)) => {
+                    app.manage(lock);
+                    manage_state(app.handle());
+                }
+                Ok(None) => attach_to_daemon(app.handle(), &data_dir),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
             #[cfg(desktop)]
             app.handle()
                 .plugin(tauri_plugin_updater::Builder::new().build())?;
@@ -124,49 +179,152 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
-        .invoke_handler(tauri::generate_handler![
-            settings::get_app_settings,
-            settings::update_app_settings,
-            codex::codex_doctor,
-            workspaces::list_workspaces,
-            workspaces::add_workspace,
-            workspaces::add_worktree,
-            workspaces::remove_workspace,
-            workspaces::remove_worktree,
-            workspaces::update_workspace_settings,
-            workspaces::update_workspace_codex_bin,
-            codex::start_thread,
-            codex::send_user_message,
-            codex::turn_interrupt,
-            codex::start_review,
-            codex::respond_to_server_request,
-            codex::resume_thread,
-            codex::list_threads,
-            codex::archive_thread,
-            workspaces::connect_workspace,
-            git::get_git_status,
-            git::get_git_diffs,
-            git::get_git_log,
-            git::get_git_remote,
-            git::get_github_issues,
-            workspaces::list_workspace_files,
-            git::list_git_branches,
-            git::checkout_git_branch,
-            git::create_git_branch,
-            codex::model_list,
-            codex::account_rate_limits,
-            codex::skills_list,
-            opencode::opencode_doctor,
-            opencode::list_opencode_sessions,
-            opencode::create_opencode_session,
-            opencode::get_opencode_session,
-            opencode::load_opencode_session,
-            opencode::delete_opencode_session,
-            opencode::get_opencode_messages,
-            opencode::send_opencode_message,
-            opencode::cancel_opencode_operation,
-            opencode::get_opencode_providers
-        ])
-        .run(tauri::generate_context!())
+        .invoke_handler(move |invoke| {
+            #[cfg(unix)]
+            if let Some(link) = attach::daemon_link(&invoke) {
+                link.forward(invoke);
+                return true;
+            }
+            run_command(invoke)
+        })
+        .run(context())
         .expect("error while running tauri application");
 }
+
+/// Runs the app's own commands; `run` forwards to the daemon instead when
+/// attached to one.
+#[cfg(feature = "gui")]
+fn run_command(invoke: tauri::ipc::Invoke<tauri::Wry>) -> bool {
+    let handle = tauri::generate_handler![
+        commands::get_app_settings,
+        commands::update_app_settings,
+        commands::codex_doctor,
+        commands::list_workspaces,
+        commands::add_workspace,
+        commands::add_worktree,
+        commands::remove_workspace,
+        commands::remove_worktree,
+        commands::update_workspace_settings,
+        commands::update_workspace_codex_bin,
+        commands::update_workspace_acp_agent,
+        commands::start_thread,
+        commands::send_user_message,
+        commands::turn_interrupt,
+        commands::enqueue_prompt,
+        commands::list_queued_prompts,
+        commands::reorder_queued_prompts,
+        commands::set_queued_prompt_paused,
+        commands::cancel_queued_prompt,
+        commands::start_review,
+        commands::respond_to_server_request,
+        commands::cancel_request,
+        commands::list_pending_requests,
+        commands::resume_thread,
+        commands::list_threads,
+        commands::archive_thread,
+        commands::get_thread_snapshot,
+        commands::query_audit_log,
+        commands::get_usage_summary,
+        commands::read_transcript,
+        commands::search_conversations,
+        commands::export_thread,
+        commands::connect_workspace,
+        commands::get_git_status,
+        commands::get_git_diffs,
+        commands::get_git_log,
+        commands::get_git_remote,
+        commands::get_github_issues,
+        commands::list_workspace_files,
+        commands::list_git_branches,
+        commands::checkout_git_branch,
+        commands::create_git_branch,
+        commands::model_list,
+        commands::backend_health,
+        commands::account_rate_limits,
+        commands::get_cached_rate_limits,
+        commands::get_remote_server_status,
+        commands::generate_remote_token,
+        commands::skills_list,
+        commands::opencode_doctor,
+        commands::list_opencode_sessions,
+        commands::create_opencode_session,
+        commands::get_opencode_session,
+        commands::load_opencode_session,
+        commands::delete_opencode_session,
+        commands::get_opencode_messages,
+        commands::send_opencode_message,
+        commands::cancel_opencode_operation,
+        commands::get_opencode_providers
+    ];
+    handle(invoke)
+}
+
+/// Runs without windows or Tauri, serving the command set on a Unix socket
+/// (see `daemon.rs`) so agents keep running while no GUI is open. It holds
+/// the data directory, so the app refuses to start while it runs.
+#[cfg(unix)]
+pub fn run_daemon() {
+    let served = runtime().block_on(async {
+        let data_dir =
+            state::default_data_dir().ok_or("Cannot locate the data directory; set HOME")?;
+        let _lock = state::lock_data_dir(&data_dir)?;
+        let host: SharedHost = Arc::new(state::AppState::load_from(&data_dir));
+        start_workers(&host);
+        daemon::serve(host).await
+    });
+    if let Err(error) = served {
+        eprintln!("Failed to start the daemon: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("error while starting the async runtime")
+}
+
+/// The daemon holds the data directory, so show its sessions instead of
+/// loading a second copy of the state. Another app window holding it has no
+/// socket to attach to.
+#[cfg(feature = "gui")]
+fn attach_to_daemon(app: &tauri::AppHandle, data_dir: &std::path::Path) {
+    #[cfg(unix)]
+    {
+        let attached = daemon::socket_path()
+            .ok_or_else(|| "Cannot locate the daemon socket".to_string())
+            .and_then(|path| attach::DaemonLink::attach(app.clone(), path));
+        match attached {
+            Ok(link) => {
+                app.manage(link);
+                return;
+            }
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+    eprintln!(
+        "{} is in use by another Codex Monitor app.",
+        data_dir.display()
+    );
+    std::process::exit(1);
+}
+
+#[cfg(feature = "gui")]
+fn manage_state(app: &tauri::AppHandle) {
+    app.manage(state::AppState::load(app));
+    start_workers(&backend::app_host(app.clone()));
+}
+
+/// Background workers shared by the app and the daemon.
+fn start_workers(host: &SharedHost) {
+    prompt_queue::start_scheduler(host.clone());
+    rate_limits::start_rate_limit_watcher(host.clone());
+    search::start_search_indexer(host.clone());
+    remote::start_remote_access(host.clone());
+}
+
+#[cfg(feature = "gui")]
+fn context() -> tauri::Context {
+    tauri::generate_context!()
+}
@@ -1,45 +1,21 @@
-use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
 use std::io::ErrorKind;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
 use std::time::Duration;
 
-use tauri::{AppHandle, Emitter, State};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStderr, ChildStdout, Command};
-use tokio::sync::{oneshot, Mutex};
+use tokio::process::Command;
 use tokio::time::timeout;
 
+use crate::acp::{acp_backend_for, get_or_spawn_acp_session};
+use crate::attachments::Attachment;
+use crate::backend::{AgentBackend, MessageRequest, SharedHost};
+use crate::rpc::CommandError;
 use crate::state::AppState;
-use crate::types::{OpenCodeProviderInfo, OpenCodeProviderModel, OpenCodeSessionInfo, WorkspaceEntry};
-
-#[derive(Serialize, Clone)]
-struct OpenCodeEvent {
-    #[serde(rename = "workspaceId")]
-    workspace_id: String,
-    method: String,
-    params: Option<Value>,
-}
-
-#[derive(Serialize, Clone)]
-struct WorkspaceEvent {
-    workspace_id: String,
-    event_type: String,
-    server_url: Option<String>,
-    error: Option<String>,
-}
-
-pub(crate) struct OpenCodeSession {
-    pub(crate) entry: WorkspaceEntry,
-    pub(crate) child: Mutex<Child>,
-    pub(crate) stdin: Mutex<ChildStdin>,
-    pub(crate) stdout_reader: Mutex<BufReader<ChildStdout>>,
-    pub(crate) next_id: AtomicU64,
-    pub(crate) pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
-}
+use crate::types::{
+    BackendType, OpenCodePromptHandle, OpenCodeProviderInfo, OpenCodeProviderModel,
+    OpenCodeSessionInfo, WorkspaceEntry,
+};
 
 fn build_opencode_command(opencode_bin: Option<String>) -> Command {
     let default_bin = opencode_bin
@@ -132,171 +108,11 @@ async fn check_opencode_installation(opencode_bin: Option<String>) -> Result<Opt
     Ok(if version.is_empty() { None } else { Some(version) })
 }
 
-async fn send_jsonrpc_request_with_timeout(
-    session: &Arc<OpenCodeSession>,
-    method: &str,
-    params: Value,
-    request_timeout: Duration,
-) -> Result<Value, String> {
-    let id = session.next_id.fetch_add(1, Ordering::SeqCst);
-    let request = json!({
-        "jsonrpc": "2.0",
-        "method": method,
-        "params": params,
-        "id": id
-    });
-
-    let (tx, rx) = oneshot::channel();
-    {
-        let mut pending = session.pending.lock().await;
-        pending.insert(id, tx);
-    }
-
-    {
-        let mut stdin = session.stdin.lock().await;
-        let request_str = format!("{}\n", request.to_string());
-        stdin
-            .write_all(request_str.as_bytes())
-            .await
-            .map_err(|e| format!("Failed to write to stdin: {}", e))?;
-        stdin
-            .flush()
-            .await
-            .map_err(|e| format!("Failed to flush stdin: {}", e))?;
-    }
-
-    match timeout(request_timeout, rx).await {
-        Ok(Ok(response)) => {
-            if let Some(error) = response.get("error") {
-                let message = error
-                    .get("message")
-                    .and_then(|m| m.as_str())
-                    .unwrap_or("Unknown error");
-                return Err(message.to_string());
-            }
-            Ok(response.get("result").cloned().unwrap_or(Value::Null))
-        }
-        Ok(Err(_)) => Err("Response channel closed".to_string()),
-        Err(_) => {
-            session.pending.lock().await.remove(&id);
-            Err("Request timed out".to_string())
-        }
-    }
-}
-
-async fn send_jsonrpc_request(
-    session: &Arc<OpenCodeSession>,
-    method: &str,
-    params: Value,
-) -> Result<Value, String> {
-    send_jsonrpc_request_with_timeout(session, method, params, Duration::from_secs(30)).await
-}
-
-fn spawn_stdout_reader(session: Arc<OpenCodeSession>, app: AppHandle, workspace_id: String) {
-    tokio::spawn(async move {
-        let mut reader = session.stdout_reader.lock().await;
-        let mut line = String::new();
-
-        loop {
-            line.clear();
-            match reader.read_line(&mut line).await {
-                Ok(0) => {
-                    break;
-                }
-                Ok(_) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
-                        continue;
-                    }
-
-                    if let Ok(msg) = serde_json::from_str::<Value>(trimmed) {
-                        if let Some(id) = msg.get("id").and_then(|v| v.as_u64()) {
-                            if let Some(tx) = session.pending.lock().await.remove(&id) {
-                                let _ = tx.send(msg);
-                            }
-                        } else if let Some(method) = msg.get("method").and_then(|v| v.as_str()) {
-                            let event_payload = OpenCodeEvent {
-                                workspace_id: workspace_id.clone(),
-                                method: method.to_string(),
-                                params: msg.get("params").cloned(),
-                            };
-                            let _ = app.emit("opencode-event", event_payload);
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error reading stdout: {}", e);
-                    break;
-                }
-            }
-        }
-
-        let payload = WorkspaceEvent {
-            workspace_id: workspace_id.clone(),
-            event_type: "disconnected".to_string(),
-            server_url: None,
-            error: Some("OpenCode process ended".to_string()),
-        };
-        let _ = app.emit("workspace-event", payload);
-    });
-}
-
-fn spawn_stderr_reader(stderr: ChildStderr, app: AppHandle, workspace_id: String) {
-    tokio::spawn(async move {
-        let mut reader = BufReader::new(stderr);
-        let mut line = String::new();
-
-        loop {
-            line.clear();
-            match reader.read_line(&mut line).await {
-                Ok(0) => {
-                    break;
-                }
-                Ok(_) => {
-                    let trimmed = line.trim_end();
-                    if trimmed.is_empty() {
-                        continue;
-                    }
-                    let _ = app.emit(
-                        "opencode-stderr",
-                        json!({
-                            "workspaceId": workspace_id,
-                            "line": trimmed
-                        }),
-                    );
-                }
-                Err(_) => {
-                    break;
-                }
-            }
-        }
-    });
-}
-
-async fn initialize_acp_session(session: &Arc<OpenCodeSession>) -> Result<(), String> {
-    let _ = send_jsonrpc_request_with_timeout(
-        session,
-        "initialize",
-        json!({
-            "protocolVersion": 1,
-            "clientInfo": {
-                "name": "codex_monitor",
-                "version": env!("CARGO_PKG_VERSION")
-            },
-            "clientCapabilities": {}
-        }),
-        Duration::from_secs(120),
-    )
-    .await?;
-
-    Ok(())
-}
-
-pub(crate) async fn spawn_opencode_session(
-    entry: WorkspaceEntry,
+/// The `opencode acp` command for the workspace, after checking the CLI runs.
+pub(crate) async fn opencode_acp_command(
+    entry: &WorkspaceEntry,
     default_opencode_bin: Option<String>,
-    app_handle: AppHandle,
-) -> Result<Arc<OpenCodeSession>, String> {
+) -> Result<Command, String> {
     let opencode_bin = entry
         .opencode_bin
         .clone()
@@ -306,93 +122,13 @@ pub(crate) async fn spawn_opencode_session(
 
     let mut command = build_opencode_command(opencode_bin);
     command.arg("acp");
-    command.current_dir(&entry.path);
-    command.stdin(std::process::Stdio::piped());
-    command.stdout(std::process::Stdio::piped());
-    command.stderr(std::process::Stdio::piped());
-
-    let mut child = command.spawn().map_err(|e| format!("Failed to spawn opencode: {}", e))?;
-
-    let stdin = child
-        .stdin
-        .take()
-        .ok_or("Failed to capture stdin")?;
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or("Failed to capture stdout")?;
-
-    if let Some(stderr) = child.stderr.take() {
-        spawn_stderr_reader(stderr, app_handle.clone(), entry.id.clone());
-    }
-
-    let session = Arc::new(OpenCodeSession {
-        entry: entry.clone(),
-        child: Mutex::new(child),
-        stdin: Mutex::new(stdin),
-        stdout_reader: Mutex::new(BufReader::new(stdout)),
-        next_id: AtomicU64::new(1),
-        pending: Mutex::new(HashMap::new()),
-    });
-
-    spawn_stdout_reader(session.clone(), app_handle.clone(), entry.id.clone());
-
-    if let Err(error) = initialize_acp_session(&session).await {
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
-        return Err(format!("Failed to initialize OpenCode ACP: {error}"));
-    }
-
-    let payload = WorkspaceEvent {
-        workspace_id: entry.id.clone(),
-        event_type: "connected".to_string(),
-        server_url: Some("acp://local".to_string()),
-        error: None,
-    };
-    let _ = app_handle.emit("workspace-event", payload);
-
-    Ok(session)
+    Ok(command)
 }
 
-async fn get_or_spawn_acp_session(
-    workspace_id: &str,
-    state: &AppState,
-    app: &AppHandle,
-) -> Result<Arc<OpenCodeSession>, String> {
-    {
-        let sessions = state.opencode_sessions.lock().await;
-        if let Some(existing) = sessions.get(workspace_id) {
-            return Ok(existing.clone());
-        }
-    }
-
-    let entry = {
-        let workspaces = state.workspaces.lock().await;
-        workspaces
-            .get(workspace_id)
-            .ok_or("Workspace not found")?
-            .clone()
-    };
-
-    let default_bin = {
-        let settings = state.app_settings.lock().await;
-        settings.opencode_bin.clone()
-    };
-
-    let session = spawn_opencode_session(entry, default_bin, app.clone()).await?;
-
-    let mut sessions = state.opencode_sessions.lock().await;
-    if let Some(existing) = sessions.get(workspace_id) {
-        return Ok(existing.clone());
-    }
-    sessions.insert(workspace_id.to_string(), session.clone());
-    Ok(session)
-}
-
-#[tauri::command]
-pub(crate) async fn opencode_doctor(
+/// Checks the OpenCode CLI and its `acp` subcommand for the doctor panel.
+pub(crate) async fn opencode_doctor_report(
     opencode_bin: Option<String>,
-    state: State<'_, AppState>,
+    state: &AppState,
 ) -> Result<Value, String> {
     let default_bin = {
         let settings = state.app_settings.lock().await;
@@ -426,18 +162,30 @@ pub(crate) async fn opencode_doctor(
     }))
 }
 
-#[tauri::command]
-pub(crate) async fn list_opencode_sessions(
-    workspace_id: String,
-    state: State<'_, AppState>,
+/// The workspace's OpenCode sessions; empty for other ACP agents.
+pub(crate) async fn opencode_sessions(
+    state: &AppState,
+    workspace_id: &str,
 ) -> Result<Vec<OpenCodeSessionInfo>, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
-        .get(&workspace_id)
+        .get(workspace_id)
         .ok_or("Workspace not found")?
         .clone();
     drop(workspaces);
+    // A configured ACP agent has no session listing of its own.
+    if acp_backend_for(&entry) != BackendType::OpenCode {
+        return Ok(Vec::new());
+    }
 
+    run_session_list(&entry, state).await
+}
+
+/// Lists sessions through `opencode session list`, which works without a running agent.
+pub(crate) async fn run_session_list(
+    entry: &WorkspaceEntry,
+    state: &AppState,
+) -> Result<Vec<OpenCodeSessionInfo>, String> {
     let default_bin = {
         let settings = state.app_settings.lock().await;
         settings.opencode_bin.clone()
@@ -468,164 +216,152 @@ pub(crate) async fn list_opencode_sessions(
 
     let sessions: Vec<OpenCodeSessionInfo> = serde_json::from_str(&stdout)
         .unwrap_or_else(|_| Vec::new());
+    state
+        .search_index
+        .lock()
+        .await
+        .index_opencode_sessions(&entry.id, &sessions);
 
     Ok(sessions)
 }
 
-#[tauri::command]
 pub(crate) async fn create_opencode_session(
+    host: &SharedHost,
     workspace_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<OpenCodeSessionInfo, String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
-    let cwd = session.entry.path.clone();
-
-    let result = send_jsonrpc_request(&session, "session/new", json!({
-        "cwd": cwd,
-        "mcpServers": []
-    })).await?;
-
-    #[derive(serde::Deserialize)]
-    struct NewSessionResult {
-        #[serde(rename = "sessionId")]
-        session_id: String,
-    }
-
-    let new_session: NewSessionResult = serde_json::from_value(result)
-        .map_err(|e| format!("Failed to parse session/new result: {e}"))?;
-
-    Ok(OpenCodeSessionInfo {
-        id: new_session.session_id,
-        title: Some("New Session".to_string()),
-        created_at: None,
-        updated_at: None,
-    })
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    let session = get_or_spawn_acp_session(&workspace_id, host).await?;
+    let result = session.start_thread().await?;
+    serde_json::from_value::<OpenCodeSessionInfo>(result)
+        .map_err(|e| format!("Failed to parse session: {}", e).into())
 }
 
-#[tauri::command]
 pub(crate) async fn get_opencode_session(
+    host: &SharedHost,
     workspace_id: String,
     session_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<OpenCodeSessionInfo, String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    let session = get_or_spawn_acp_session(&workspace_id, host).await?;
 
-    let result = send_jsonrpc_request(&session, "session/get", json!({
+    let result = session.request("session/get", json!({
         "sessionId": session_id
     })).await?;
 
     serde_json::from_value::<OpenCodeSessionInfo>(result)
-        .map_err(|e| format!("Failed to parse session: {}", e))
+        .map_err(|e| format!("Failed to parse session: {}", e).into())
 }
 
-#[tauri::command]
 pub(crate) async fn load_opencode_session(
+    host: &SharedHost,
     workspace_id: String,
     session_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<OpenCodeSessionInfo, String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
+) -> Result<OpenCodeSessionInfo, CommandError> {
+    let session = get_or_spawn_acp_session(&workspace_id, host).await?;
 
-    let result = send_jsonrpc_request(&session, "session/load", json!({
+    let result = session.request("session/load", json!({
         "sessionId": session_id
     })).await?;
 
     serde_json::from_value::<OpenCodeSessionInfo>(result)
-        .map_err(|e| format!("Failed to parse loaded session: {}", e))
+        .map_err(|e| format!("Failed to parse loaded session: {}", e).into())
 }
 
-#[tauri::command]
 pub(crate) async fn delete_opencode_session(
+    host: &SharedHost,
     workspace_id: String,
     session_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<(), String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
+) -> Result<(), CommandError> {
+    let session = get_or_spawn_acp_session(&workspace_id, host).await?;
 
-    send_jsonrpc_request(&session, "session/delete", json!({
+    session.request("session/delete", json!({
         "sessionId": session_id
     })).await?;
 
     Ok(())
 }
 
-#[tauri::command]
-pub(crate) async fn get_opencode_messages(
-    workspace_id: String,
-    session_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<Value, String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
+/// Loads the message history of an OpenCode session, spawning the agent if needed.
+pub(crate) async fn fetch_opencode_messages(
+    workspace_id: &str,
+    session_id: &str,
+    host: &SharedHost,
+) -> Result<Value, CommandError> {
+    let session = get_or_spawn_acp_session(workspace_id, host).await?;
 
-    let result = send_jsonrpc_request(&session, "message/list", json!({
+    let result = session.request("message/list", json!({
         "sessionId": session_id
     })).await?;
+    host.app_state()
+        .search_index
+        .lock()
+        .await
+        .index_opencode_messages(workspace_id, session_id, &result);
 
     Ok(result)
 }
 
-#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn send_opencode_message(
+    host: &SharedHost,
     workspace_id: String,
     session_id: String,
     text: String,
     provider_id: Option<String>,
     model_id: Option<String>,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<(), String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
-
-    let mut params = json!({
-        "sessionId": session_id,
-        "prompt": [{
-            "type": "text",
-            "text": text
-        }]
-    });
-
-    if let (Some(provider), Some(model)) = (provider_id, model_id) {
-        params["modelId"] = json!(format!("{}/{}", provider, model));
-    }
-
-    let _ = send_jsonrpc_request(&session, "session/prompt", params).await?;
-
-    Ok(())
+    attachments: Option<Vec<Attachment>>,
+) -> Result<OpenCodePromptHandle, CommandError> {
+    let host = host.clone();
+    let session = get_or_spawn_acp_session(&workspace_id, &host).await?;
+    let model = match (provider_id, model_id) {
+        (Some(provider), Some(model)) => Some(format!("{}/{}", provider, model)),
+        _ => None,
+    };
+    let request = MessageRequest {
+        thread_id: session_id,
+        text,
+        model,
+        effort: None,
+        access_mode: None,
+        attachments: attachments.unwrap_or_default(),
+    };
+    let handle = session.send_message(host.app_state(), request).await?;
+    serde_json::from_value(handle)
+        .map_err(|e| format!("Failed to parse prompt handle: {}", e).into())
 }
 
-#[tauri::command]
 pub(crate) async fn cancel_opencode_operation(
+    host: &SharedHost,
     workspace_id: String,
     session_id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<(), String> {
-    let session = get_or_spawn_acp_session(&workspace_id, state.inner(), &app).await?;
-
-    send_jsonrpc_request(&session, "session/cancel", json!({
-        "sessionId": session_id
-    })).await?;
+) -> Result<(), CommandError> {
+    let session = get_or_spawn_acp_session(&workspace_id, host).await?;
+    session.interrupt(&session_id, None).await?;
 
     Ok(())
 }
 
-#[tauri::command]
-pub(crate) async fn get_opencode_providers(
-    workspace_id: String,
-    state: State<'_, AppState>,
+/// The providers and models OpenCode offers; empty for other ACP agents.
+pub(crate) async fn opencode_providers(
+    state: &AppState,
+    workspace_id: &str,
 ) -> Result<Vec<OpenCodeProviderInfo>, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
-        .get(&workspace_id)
+        .get(workspace_id)
         .ok_or("Workspace not found")?
         .clone();
     drop(workspaces);
+    if acp_backend_for(&entry) != BackendType::OpenCode {
+        return Ok(Vec::new());
+    }
+
+    run_models(&entry, state).await
+}
 
+/// Groups `opencode models` output (`provider/model` per line) by provider.
+pub(crate) async fn run_models(
+    entry: &WorkspaceEntry,
+    state: &AppState,
+) -> Result<Vec<OpenCodeProviderInfo>, String> {
     let default_bin = {
         let settings = state.app_settings.lock().await;
         settings.opencode_bin.clone()
@@ -0,0 +1,595 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::attachments::Attachment;
+use crate::backend::{get_backend, AgentEvent, EventSink, MessageRequest, SharedHost};
+use crate::protocol::KnownNotification;
+use crate::state::AppState;
+
+pub(crate) const QUEUE_UPDATED_METHOD: &str = "queue/updated";
+pub(crate) const QUEUE_DISPATCHED_METHOD: &str = "queue/dispatched";
+
+const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+const MIN_INTERVAL_SECS: u64 = 60;
+
+/// When a queued prompt may be sent. Prompts only go out while their thread is idle.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum PromptSchedule {
+    /// As soon as the current turn finishes.
+    #[default]
+    Next,
+    /// Once, at `runAt` (ms since the epoch) or later.
+    At {
+        #[serde(rename = "runAt")]
+        #[ts(type = "number")]
+        run_at: u64,
+    },
+    /// Repeatedly, every `intervalSecs`, starting at `nextRunAt`.
+    Every {
+        #[serde(rename = "intervalSecs")]
+        #[ts(type = "number")]
+        interval_secs: u64,
+        #[serde(rename = "nextRunAt")]
+        #[ts(type = "number")]
+        next_run_at: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct QueuedPrompt {
+    pub(crate) id: String,
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) text: String,
+    #[serde(default)]
+    pub(crate) model: Option<String>,
+    #[serde(default)]
+    pub(crate) effort: Option<String>,
+    #[serde(default)]
+    pub(crate) access_mode: Option<String>,
+    #[serde(default)]
+    pub(crate) attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub(crate) schedule: PromptSchedule,
+    #[serde(default)]
+    pub(crate) paused: bool,
+    /// Why the last dispatch failed; failed prompts are paused until resumed.
+    #[serde(default)]
+    pub(crate) last_error: Option<String>,
+    #[ts(type = "number")]
+    pub(crate) created_at: u64,
+}
+
+impl QueuedPrompt {
+    fn is_due(&self, now: u64) -> bool {
+        if self.paused {
+            return false;
+        }
+        match self.schedule {
+            PromptSchedule::Next => true,
+            PromptSchedule::At { run_at } => now >= run_at,
+            PromptSchedule::Every { next_run_at, .. } => now >= next_run_at,
+        }
+    }
+
+    fn in_thread(&self, workspace_id: &str, thread_id: &str) -> bool {
+        self.workspace_id == workspace_id && self.thread_id == thread_id
+    }
+}
+
+/// Per-thread prompt queues, persisted as one JSON file. Threads with a turn
+/// in flight are tracked here so the next prompt waits for it.
+pub(crate) struct PromptQueue {
+    path: PathBuf,
+    prompts: Vec<QueuedPrompt>,
+    busy_threads: HashSet<(String, String)>,
+}
+
+impl PromptQueue {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let prompts = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            prompts,
+            busy_threads: HashSet::new(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(&self.prompts).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn list(
+        &self,
+        workspace_id: Option<&str>,
+        thread_id: Option<&str>,
+    ) -> Vec<QueuedPrompt> {
+        self.prompts
+            .iter()
+            .filter(|prompt| workspace_id.is_none_or(|id| prompt.workspace_id == id))
+            .filter(|prompt| thread_id.is_none_or(|id| prompt.thread_id == id))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn push(&mut self, prompt: QueuedPrompt) -> Result<(), String> {
+        self.prompts.push(prompt);
+        self.save()
+    }
+
+    /// Puts the thread's prompts in the order of `ids`; unlisted ones keep
+    /// their relative order after them.
+    pub(crate) fn reorder(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        ids: &[String],
+    ) -> Result<(), String> {
+        let (mut thread, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.prompts)
+            .into_iter()
+            .partition(|prompt| prompt.in_thread(workspace_id, thread_id));
+        if let Some(id) = ids
+            .iter()
+            .find(|id| !thread.iter().any(|prompt| &prompt.id == *id))
+        {
+            self.prompts = rest.into_iter().chain(thread).collect();
+            return Err(format!("Queued prompt {id} is not in this thread."));
+        }
+        thread.sort_by_key(|prompt| {
+            ids.iter()
+                .position(|id| id == &prompt.id)
+                .unwrap_or(ids.len())
+        });
+        self.prompts = rest.into_iter().chain(thread).collect();
+        self.save()
+    }
+
+    pub(crate) fn set_paused(&mut self, id: &str, paused: bool) -> Result<QueuedPrompt, String> {
+        let prompt = self
+            .prompts
+            .iter_mut()
+            .find(|prompt| prompt.id == id)
+            .ok_or("queued prompt not found")?;
+        prompt.paused = paused;
+        if !paused {
+            prompt.last_error = None;
+        }
+        let prompt = prompt.clone();
+        self.save()?;
+        Ok(prompt)
+    }
+
+    pub(crate) fn cancel(&mut self, id: &str) -> Result<QueuedPrompt, String> {
+        let index = self
+            .prompts
+            .iter()
+            .position(|prompt| prompt.id == id)
+            .ok_or("queued prompt not found")?;
+        let prompt = self.prompts.remove(index);
+        self.save()?;
+        Ok(prompt)
+    }
+
+    pub(crate) fn turn_started(&mut self, workspace_id: &str, thread_id: &str) {
+        self.busy_threads
+            .insert((workspace_id.to_string(), thread_id.to_string()));
+    }
+
+    pub(crate) fn turn_finished(&mut self, workspace_id: &str, thread_id: &str) {
+        self.busy_threads
+            .remove(&(workspace_id.to_string(), thread_id.to_string()));
+    }
+
+    /// Forgets in-flight turns of an agent that went away.
+    pub(crate) fn workspace_stopped(&mut self, workspace_id: &str) {
+        self.busy_threads.retain(|(id, _)| id != workspace_id);
+    }
+
+    /// The first due prompt of every idle thread, in queue order.
+    pub(crate) fn due(&self, now: u64) -> Vec<QueuedPrompt> {
+        let mut seen = HashSet::new();
+        self.prompts
+            .iter()
+            .filter(|prompt| prompt.is_due(now))
+            .filter(|prompt| {
+                let thread = (prompt.workspace_id.clone(), prompt.thread_id.clone());
+                !self.busy_threads.contains(&thread) && seen.insert(thread)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Claims a due prompt for sending: marks its thread busy and removes it,
+    /// or moves a repeating prompt to its next run.
+    pub(crate) fn take(&mut self, id: &str, now: u64) -> Option<QueuedPrompt> {
+        let index = self.prompts.iter().position(|prompt| prompt.id == id)?;
+        let prompt = &self.prompts[index];
+        let thread = (prompt.workspace_id.clone(), prompt.thread_id.clone());
+        if !prompt.is_due(now) || self.busy_threads.contains(&thread) {
+            return None;
+        }
+        self.busy_threads.insert(thread);
+        let taken = match &mut self.prompts[index].schedule {
+            PromptSchedule::Every {
+                interval_secs,
+                next_run_at,
+            } => {
+                *next_run_at = now + *interval_secs * 1000;
+                self.prompts[index].clone()
+            }
+            _ => self.prompts.remove(index),
+        };
+        let _ = self.save();
+        Some(taken)
+    }
+
+    /// Puts a prompt that failed to send back at the front of its thread, paused.
+    pub(crate) fn requeue_failed(&mut self, mut prompt: QueuedPrompt, error: String) {
+        self.turn_finished(&prompt.workspace_id, &prompt.thread_id);
+        prompt.paused = true;
+        prompt.last_error = Some(error);
+        match self.prompts.iter().position(|entry| entry.id == prompt.id) {
+            Some(index) => {
+                self.prompts[index].paused = true;
+                self.prompts[index].last_error = prompt.last_error;
+            }
+            None => {
+                let index = self
+                    .prompts
+                    .iter()
+                    .position(|entry| entry.in_thread(&prompt.workspace_id, &prompt.thread_id))
+                    .unwrap_or(self.prompts.len());
+                self.prompts.insert(index, prompt);
+            }
+        }
+        let _ = self.save();
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+async fn emit_queue_event(
+    state: &AppState,
+    events: &dyn EventSink,
+    workspace_id: &str,
+    method: &str,
+    params: serde_json::Value,
+) {
+    let backend = state
+        .workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| entry.backend.clone())
+        .unwrap_or_default();
+    events.emit(AgentEvent::new(workspace_id, backend, method, params));
+}
+
+async fn emit_queue_updated(
+    state: &AppState,
+    events: &dyn EventSink,
+    workspace_id: &str,
+    thread_id: &str,
+) {
+    let prompts = state
+        .prompt_queue
+        .lock()
+        .await
+        .list(Some(workspace_id), Some(thread_id));
+    emit_queue_event(
+        state,
+        events,
+        workspace_id,
+        QUEUE_UPDATED_METHOD,
+        json!({ "threadId": thread_id, "prompts": prompts }),
+    )
+    .await;
+}
+
+/// Sends the next due prompt of every idle thread whose agent is connected.
+pub(crate) async fn dispatch_due_prompts(state: &AppState, events: &dyn EventSink) {
+    let now = now_ms();
+    let candidates = state.prompt_queue.lock().await.due(now);
+    for candidate in candidates {
+        let Ok(backend) = get_backend(state, &candidate.workspace_id).await else {
+            continue;
+        };
+        let Some(prompt) = state.prompt_queue.lock().await.take(&candidate.id, now) else {
+            continue;
+        };
+        emit_queue_updated(state, events, &prompt.workspace_id, &prompt.thread_id).await;
+        emit_queue_event(
+            state,
+            events,
+            &prompt.workspace_id,
+            QUEUE_DISPATCHED_METHOD,
+            json!({ "threadId": prompt.thread_id, "prompt": prompt }),
+        )
+        .await;
+        let request = MessageRequest {
+            thread_id: prompt.thread_id.clone(),
+            text: prompt.text.clone(),
+            model: prompt.model.clone(),
+            effort: prompt.effort.clone(),
+            access_mode: prompt.access_mode.clone(),
+            attachments: prompt.attachments.clone(),
+        };
+        if let Err(error) = backend.send_message(state, request).await {
+            let (workspace_id, thread_id) = (prompt.workspace_id.clone(), prompt.thread_id.clone());
+            state
+                .prompt_queue
+                .lock()
+                .await
+                .requeue_failed(prompt, error.message);
+            emit_queue_updated(state, events, &workspace_id, &thread_id).await;
+        }
+    }
+}
+
+/// Dispatches off the caller's task; agent readers must not wait on their own requests.
+pub(crate) fn spawn_dispatch(host: &SharedHost) {
+    let host = host.clone();
+    tokio::spawn(async move {
+        dispatch_due_prompts(host.app_state(), host.as_ref()).await;
+    });
+}
+
+/// Wakes the queue every second for scheduled prompts.
+pub(crate) fn start_scheduler(host: SharedHost) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULER_TICK).await;
+            dispatch_due_prompts(host.app_state(), host.as_ref()).await;
+        }
+    });
+}
+
+/// Marks the thread idle and sends whatever is queued behind the turn.
+pub(crate) async fn turn_finished(host: &SharedHost, workspace_id: &str, thread_id: &str) {
+    host.app_state()
+        .prompt_queue
+        .lock()
+        .await
+        .turn_finished(workspace_id, thread_id);
+    spawn_dispatch(host);
+}
+
+/// Tracks Codex turns, including ones started outside the queue.
+pub(crate) async fn observe_notification(
+    host: &SharedHost,
+    workspace_id: &str,
+    notification: &KnownNotification,
+) {
+    match notification {
+        KnownNotification::TurnStarted(event) => {
+            if let Some(thread_id) = event.turn.thread_id.as_deref() {
+                host.app_state()
+                    .prompt_queue
+                    .lock()
+                    .await
+                    .turn_started(workspace_id, thread_id);
+            }
+        }
+        KnownNotification::TurnCompleted(event) => {
+            if let Some(thread_id) = event.turn.thread_id.as_deref() {
+                turn_finished(host, workspace_id, thread_id).await;
+            }
+        }
+        _ => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn enqueue_prompt(
+    host: &SharedHost,
+    workspace_id: String,
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    schedule: Option<PromptSchedule>,
+) -> Result<QueuedPrompt, String> {
+    let state = host.app_state();
+    if text.trim().is_empty() {
+        return Err("Queued prompts need text.".to_string());
+    }
+    if !state.workspaces.lock().await.contains_key(&workspace_id) {
+        return Err("workspace not found".to_string());
+    }
+    let schedule = schedule.unwrap_or_default();
+    if let PromptSchedule::Every { interval_secs, .. } = schedule {
+        if interval_secs < MIN_INTERVAL_SECS {
+            return Err(format!(
+                "Repeating prompts need an interval of at least {MIN_INTERVAL_SECS} seconds."
+            ));
+        }
+    }
+    let prompt = QueuedPrompt {
+        id: Uuid::new_v4().to_string(),
+        workspace_id,
+        thread_id,
+        text,
+        model,
+        effort,
+        access_mode,
+        attachments: attachments.unwrap_or_default(),
+        schedule,
+        paused: false,
+        last_error: None,
+        created_at: now_ms(),
+    };
+    state.prompt_queue.lock().await.push(prompt.clone())?;
+    emit_queue_updated(
+        state,
+        host.as_ref(),
+        &prompt.workspace_id,
+        &prompt.thread_id,
+    )
+    .await;
+    spawn_dispatch(host);
+    Ok(prompt)
+}
+
+pub(crate) async fn list_queued_prompts(
+    state: &AppState,
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    let queue = state.prompt_queue.lock().await;
+    Ok(queue.list(workspace_id.as_deref(), thread_id.as_deref()))
+}
+
+pub(crate) async fn reorder_queued_prompts(
+    host: &SharedHost,
+    workspace_id: String,
+    thread_id: String,
+    prompt_ids: Vec<String>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    let state = host.app_state();
+    let prompts = {
+        let mut queue = state.prompt_queue.lock().await;
+        queue.reorder(&workspace_id, &thread_id, &prompt_ids)?;
+        queue.list(Some(&workspace_id), Some(&thread_id))
+    };
+    emit_queue_updated(state, host.as_ref(), &workspace_id, &thread_id).await;
+    Ok(prompts)
+}
+
+pub(crate) async fn set_queued_prompt_paused(
+    host: &SharedHost,
+    prompt_id: String,
+    paused: bool,
+) -> Result<QueuedPrompt, String> {
+    let state = host.app_state();
+    let prompt = state
+        .prompt_queue
+        .lock()
+        .await
+        .set_paused(&prompt_id, paused)?;
+    emit_queue_updated(
+        state,
+        host.as_ref(),
+        &prompt.workspace_id,
+        &prompt.thread_id,
+    )
+    .await;
+    if !paused {
+        spawn_dispatch(host);
+    }
+    Ok(prompt)
+}
+
+pub(crate) async fn cancel_queued_prompt(
+    host: &SharedHost,
+    prompt_id: String,
+) -> Result<(), String> {
+    let state = host.app_state();
+    let prompt = state.prompt_queue.lock().await.cancel(&prompt_id)?;
+    emit_queue_updated(
+        state,
+        host.as_ref(),
+        &prompt.workspace_id,
+        &prompt.thread_id,
+    )
+    .await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PromptQueue, PromptSchedule, QueuedPrompt};
+
+    fn prompt(id: &str, thread_id: &str, schedule: PromptSchedule) -> QueuedPrompt {
+        QueuedPrompt {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            thread_id: thread_id.to_string(),
+            text: format!("prompt {id}"),
+            model: None,
+            effort: None,
+            access_mode: None,
+            attachments: Vec::new(),
+            schedule,
+            paused: false,
+            last_error: None,
+            created_at: 0,
+        }
+    }
+
+    fn ids(prompts: &[QueuedPrompt]) -> Vec<&str> {
+        prompts.iter().map(|prompt| prompt.id.as_str()).collect()
+    }
+
+    #[test]
+    fn queue_waits_for_turns_and_keeps_schedules() {
+        let path = std::env::temp_dir().join(format!("queue-{}.json", uuid::Uuid::new_v4()));
+        let mut queue = PromptQueue::load(path.clone());
+        queue
+            .push(prompt("later", "a", PromptSchedule::At { run_at: 5_000 }))
+            .unwrap();
+        queue
+            .push(prompt("first", "a", PromptSchedule::Next))
+            .unwrap();
+        queue
+            .push(prompt("second", "a", PromptSchedule::Next))
+            .unwrap();
+        let every = PromptSchedule::Every {
+            interval_secs: 60,
+            next_run_at: 0,
+        };
+        queue.push(prompt("poll", "b", every)).unwrap();
+
+        queue.turn_started("ws", "a");
+        assert_eq!(ids(&queue.due(1_000)), vec!["poll"]);
+        let poll = queue.take("poll", 1_000).expect("due");
+        assert_eq!(
+            queue.list(None, Some("b"))[0].schedule,
+            PromptSchedule::Every {
+                interval_secs: 60,
+                next_run_at: 61_000
+            }
+        );
+        assert!(queue.take("poll", 1_000).is_none());
+        queue.requeue_failed(poll, "workspace not connected".to_string());
+        assert!(queue.list(None, Some("b"))[0].paused);
+
+        queue.turn_finished("ws", "a");
+        assert_eq!(ids(&queue.due(1_000)), vec!["first"]);
+        assert_eq!(ids(&queue.due(6_000)), vec!["later"]);
+
+        queue.reorder("ws", "a", &["second".to_string()]).unwrap();
+        assert_eq!(
+            ids(&queue.list(None, Some("a"))),
+            vec!["second", "later", "first"]
+        );
+        assert!(queue.reorder("ws", "a", &["poll".to_string()]).is_err());
+
+        let reloaded = PromptQueue::load(path.clone());
+        assert_eq!(reloaded.list(None, None).len(), 4);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,648 @@
+//! Typed model of the Codex app-server protocol.
+//!
+//! Only the requests CodexMonitor sends and the messages it reacts to are modelled.
+//! Anything else deserializes into an `Unknown` variant that keeps the raw JSON, so a
+//! newer app-server never breaks event forwarding. TypeScript bindings are written to
+//! `src/protocol/` by `cargo test`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+// ---------------------------------------------------------------------------
+// Requests
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ClientInfo {
+    pub(crate) name: String,
+    pub(crate) title: String,
+    pub(crate) version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct InitializeParams {
+    pub(crate) client_info: ClientInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export)]
+pub(crate) enum ApprovalPolicy {
+    /// The app-server spells this one `untrusted`; the alias keeps settings
+    /// saved with the old name loading.
+    #[serde(rename = "untrusted", alias = "unless-trusted")]
+    UnlessTrusted,
+    OnFailure,
+    OnRequest,
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum SandboxPolicy {
+    DangerFullAccess,
+    ReadOnly,
+    #[serde(rename_all = "camelCase")]
+    WorkspaceWrite {
+        writable_roots: Vec<String>,
+        network_access: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum UserInput {
+    Text {
+        text: String,
+    },
+    /// A remote or `data:` URL.
+    Image {
+        url: String,
+    },
+    LocalImage {
+        path: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadStartParams {
+    pub(crate) cwd: String,
+    pub(crate) approval_policy: ApprovalPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadIdParams {
+    pub(crate) thread_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadListParams {
+    pub(crate) cursor: Option<String>,
+    pub(crate) limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnStartParams {
+    pub(crate) thread_id: String,
+    pub(crate) input: Vec<UserInput>,
+    pub(crate) cwd: String,
+    pub(crate) approval_policy: ApprovalPolicy,
+    pub(crate) sandbox_policy: SandboxPolicy,
+    pub(crate) model: Option<String>,
+    pub(crate) effort: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnInterruptParams {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum ReviewTarget {
+    UncommittedChanges,
+    BaseBranch {
+        branch: String,
+    },
+    Commit {
+        sha: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[ts(optional)]
+        title: Option<String>,
+    },
+    Custom {
+        instructions: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ReviewStartParams {
+    pub(crate) thread_id: String,
+    pub(crate) target: ReviewTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub(crate) delivery: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct SkillsListParams {
+    pub(crate) cwd: String,
+}
+
+// ---------------------------------------------------------------------------
+// Responses
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct Thread {
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) preview: Option<String>,
+    #[serde(default)]
+    pub(crate) created_at: Option<i64>,
+}
+
+/// Result of `thread/start` and `thread/resume`.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadResponse {
+    pub(crate) thread: Thread,
+    /// The model the thread's turns use unless `turn/start` names one.
+    #[serde(default)]
+    pub(crate) model: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum ApprovalDecision {
+    Accept,
+    Decline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ApprovalResponse {
+    pub(crate) decision: ApprovalDecision,
+}
+
+// ---------------------------------------------------------------------------
+// Notifications
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum TurnStatus {
+    InProgress,
+    Completed,
+    Interrupted,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct Turn {
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) thread_id: Option<String>,
+    #[serde(default)]
+    pub(crate) status: Option<TurnStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct FileUpdateChange {
+    pub(crate) path: String,
+    #[serde(default)]
+    pub(crate) kind: Option<Value>,
+    #[serde(default)]
+    pub(crate) diff: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum KnownThreadItem {
+    #[serde(rename_all = "camelCase")]
+    UserMessage {
+        id: String,
+        #[serde(default)]
+        content: Vec<Value>,
+    },
+    #[serde(rename_all = "camelCase")]
+    AgentMessage {
+        id: String,
+        #[serde(default)]
+        text: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Reasoning {
+        id: String,
+        #[serde(default)]
+        summary: Vec<String>,
+        #[serde(default)]
+        content: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CommandExecution {
+        id: String,
+        command: String,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default)]
+        status: Option<String>,
+        #[serde(default)]
+        aggregated_output: Option<String>,
+        #[serde(default)]
+        exit_code: Option<i32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    FileChange {
+        id: String,
+        #[serde(default)]
+        changes: Vec<FileUpdateChange>,
+        #[serde(default)]
+        status: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    McpToolCall {
+        id: String,
+        #[serde(default)]
+        server: Option<String>,
+        #[serde(default)]
+        tool: Option<String>,
+        #[serde(default)]
+        status: Option<String>,
+    },
+}
+
+/// A thread item; unrecognised item types are preserved verbatim.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(untagged)]
+#[ts(export)]
+pub(crate) enum ThreadItem {
+    Known(KnownThreadItem),
+    Unknown(Value),
+}
+
+impl ThreadItem {
+    pub(crate) fn id(&self) -> Option<&str> {
+        match self {
+            ThreadItem::Known(item) => Some(match item {
+                KnownThreadItem::UserMessage { id, .. }
+                | KnownThreadItem::AgentMessage { id, .. }
+                | KnownThreadItem::Reasoning { id, .. }
+                | KnownThreadItem::CommandExecution { id, .. }
+                | KnownThreadItem::FileChange { id, .. }
+                | KnownThreadItem::McpToolCall { id, .. } => id.as_str(),
+            }),
+            ThreadItem::Unknown(value) => value.get("id").and_then(|id| id.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct PlanStep {
+    pub(crate) step: String,
+    pub(crate) status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnError {
+    pub(crate) message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ItemDelta {
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) item_id: String,
+    pub(crate) delta: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ItemEvent {
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) item: ThreadItem,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnEvent {
+    pub(crate) turn: Turn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadEvent {
+    pub(crate) thread: Thread,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnPlanUpdated {
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    #[serde(default)]
+    pub(crate) explanation: Option<String>,
+    #[serde(default)]
+    pub(crate) plan: Vec<PlanStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TurnDiffUpdated {
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) diff: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadTokenUsageUpdated {
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) token_usage: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct AccountRateLimitsUpdated {
+    pub(crate) rate_limits: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ErrorNotification {
+    #[serde(default)]
+    pub(crate) thread_id: Option<String>,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) error: TurnError,
+    #[serde(default)]
+    pub(crate) will_retry: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "method", content = "params")]
+#[ts(export)]
+pub(crate) enum KnownNotification {
+    #[serde(rename = "thread/started")]
+    ThreadStarted(ThreadEvent),
+    #[serde(rename = "turn/started")]
+    TurnStarted(TurnEvent),
+    #[serde(rename = "turn/completed")]
+    TurnCompleted(TurnEvent),
+    #[serde(rename = "item/started")]
+    ItemStarted(ItemEvent),
+    #[serde(rename = "item/completed")]
+    ItemCompleted(ItemEvent),
+    #[serde(rename = "item/agentMessage/delta")]
+    AgentMessageDelta(ItemDelta),
+    #[serde(rename = "item/reasoning/summaryTextDelta")]
+    ReasoningSummaryTextDelta(ItemDelta),
+    #[serde(rename = "item/reasoning/textDelta")]
+    ReasoningTextDelta(ItemDelta),
+    #[serde(rename = "item/commandExecution/outputDelta")]
+    CommandExecutionOutputDelta(ItemDelta),
+    #[serde(rename = "item/fileChange/outputDelta")]
+    FileChangeOutputDelta(ItemDelta),
+    #[serde(rename = "turn/plan/updated")]
+    TurnPlanUpdated(TurnPlanUpdated),
+    #[serde(rename = "turn/diff/updated")]
+    TurnDiffUpdated(TurnDiffUpdated),
+    #[serde(rename = "thread/tokenUsage/updated")]
+    ThreadTokenUsageUpdated(ThreadTokenUsageUpdated),
+    #[serde(rename = "account/rateLimits/updated")]
+    AccountRateLimitsUpdated(AccountRateLimitsUpdated),
+    #[serde(rename = "error")]
+    Error(ErrorNotification),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub(crate) struct UnknownMessage {
+    pub(crate) method: String,
+    #[serde(default)]
+    pub(crate) params: Option<Value>,
+}
+
+/// A server notification; methods this module does not model are kept as-is.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(untagged)]
+#[ts(export)]
+pub(crate) enum ServerNotification {
+    Known(KnownNotification),
+    Unknown(UnknownMessage),
+}
+
+// ---------------------------------------------------------------------------
+// Server-initiated requests
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct CommandExecutionApprovalParams {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) item_id: String,
+    #[serde(default)]
+    pub(crate) reason: Option<String>,
+    #[serde(default)]
+    pub(crate) command: Option<String>,
+    #[serde(default)]
+    pub(crate) cwd: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct FileChangeApprovalParams {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    pub(crate) item_id: String,
+    #[serde(default)]
+    pub(crate) reason: Option<String>,
+    #[serde(default)]
+    pub(crate) grant_root: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "method", content = "params")]
+#[ts(export)]
+pub(crate) enum KnownServerRequest {
+    #[serde(rename = "item/commandExecution/requestApproval")]
+    CommandExecutionApproval(CommandExecutionApprovalParams),
+    #[serde(rename = "item/fileChange/requestApproval")]
+    FileChangeApproval(FileChangeApprovalParams),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(untagged)]
+#[ts(export)]
+pub(crate) enum ServerRequest {
+    Known(KnownServerRequest),
+    Unknown(UnknownMessage),
+}
+
+/// One line read from the app-server's stdout, classified by JSON-RPC shape.
+#[derive(Debug, Clone)]
+pub(crate) enum AppServerMessage {
+    Response { id: u64 },
+    Request { id: u64, request: ServerRequest },
+    Notification(ServerNotification),
+    Invalid,
+}
+
+impl AppServerMessage {
+    pub(crate) fn classify(value: &Value) -> Self {
+        let id = value.get("id").and_then(|id| id.as_u64());
+        let has_method = value.get("method").is_some();
+        let has_result_or_error = value.get("result").is_some() || value.get("error").is_some();
+        match id {
+            Some(id) if has_method && !has_result_or_error => {
+                match ServerRequest::deserialize(value) {
+                    Ok(request) => AppServerMessage::Request { id, request },
+                    Err(_) => AppServerMessage::Invalid,
+                }
+            }
+            Some(id) => AppServerMessage::Response { id },
+            None if has_method => match ServerNotification::deserialize(value) {
+                Ok(notification) => AppServerMessage::Notification(notification),
+                Err(_) => AppServerMessage::Invalid,
+            },
+            None => AppServerMessage::Invalid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AppServerMessage, ApprovalPolicy, KnownNotification, KnownServerRequest, KnownThreadItem,
+        SandboxPolicy, ServerNotification, ServerRequest, ThreadItem,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn sandbox_policy_serializes_with_type_tag() {
+        let policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec!["/repo".to_string()],
+            network_access: true,
+        };
+        assert_eq!(
+            serde_json::to_value(policy).expect("serialize"),
+            json!({ "type": "workspaceWrite", "writableRoots": ["/repo"], "networkAccess": true })
+        );
+    }
+
+    #[test]
+    fn approval_policy_uses_app_server_names() {
+        assert_eq!(
+            serde_json::to_value(ApprovalPolicy::UnlessTrusted).expect("serialize"),
+            json!("untrusted")
+        );
+        assert_eq!(
+            serde_json::to_value(ApprovalPolicy::OnRequest).expect("serialize"),
+            json!("on-request")
+        );
+        assert_eq!(
+            serde_json::from_value::<ApprovalPolicy>(json!("unless-trusted")).expect("alias"),
+            ApprovalPolicy::UnlessTrusted
+        );
+    }
+
+    #[test]
+    fn classify_parses_known_notifications() {
+        let message = AppServerMessage::classify(&json!({
+            "method": "item/completed",
+            "params": {
+                "threadId": "thr_1",
+                "item": { "type": "agentMessage", "id": "item_1", "text": "done" }
+            }
+        }));
+        match message {
+            AppServerMessage::Notification(ServerNotification::Known(
+                KnownNotification::ItemCompleted(event),
+            )) => {
+                assert_eq!(event.thread_id, "thr_1");
+                assert!(matches!(
+                    event.item,
+                    ThreadItem::Known(KnownThreadItem::AgentMessage { ref text, .. }) if text == "done"
+                ));
+            }
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn classify_keeps_unknown_methods_and_items() {
+        let message = AppServerMessage::classify(&json!({
+            "method": "thread/compacted",
+            "params": { "threadId": "thr_1" }
+        }));
+        assert!(matches!(
+            message,
+            AppServerMessage::Notification(ServerNotification::Unknown(ref unknown))
+                if unknown.method == "thread/compacted"
+        ));
+
+        let item: ThreadItem =
+            serde_json::from_value(json!({ "type": "webSearch", "id": "ws_1", "query": "rust" }))
+                .expect("item");
+        assert_eq!(item.id(), Some("ws_1"));
+        assert!(matches!(item, ThreadItem::Unknown(_)));
+    }
+
+    #[test]
+    fn classify_distinguishes_requests_from_responses() {
+        let request = AppServerMessage::classify(&json!({
+            "id": 9,
+            "method": "item/commandExecution/requestApproval",
+            "params": { "threadId": "t", "turnId": "u", "itemId": "i", "command": "ls" }
+        }));
+        assert!(matches!(
+            request,
+            AppServerMessage::Request {
+                id: 9,
+                request: ServerRequest::Known(KnownServerRequest::CommandExecutionApproval(_))
+            }
+        ));
+        let response = AppServerMessage::classify(&json!({ "id": 9, "result": {} }));
+        assert!(matches!(response, AppServerMessage::Response { id: 9, .. }));
+    }
+}
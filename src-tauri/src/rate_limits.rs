@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::backend::{AgentEvent, AgentHost, SharedHost};
+use crate::codex::WorkspaceSession;
+use crate::protocol::KnownNotification;
+use crate::rpc::RpcError;
+use crate::state::AppState;
+use crate::types::{BackendType, RateLimitWatchSettings};
+
+pub(crate) const RATE_LIMITS_UPDATED_METHOD: &str = "account/rateLimits/updated";
+pub(crate) const RATE_LIMIT_ALERT_METHOD: &str = "rateLimits/alert";
+const MIN_POLL_INTERVAL_SECS: u64 = 30;
+const WATCH_TICK: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum RateLimitWindowKind {
+    Primary,
+    Secondary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum RateLimitAlertKind {
+    /// Usage reached `threshold` percent.
+    Threshold,
+    /// Usage fell back under the lowest threshold, i.e. the window reset.
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct RateLimitAlert {
+    pub(crate) window: RateLimitWindowKind,
+    pub(crate) kind: RateLimitAlertKind,
+    #[serde(default)]
+    pub(crate) threshold: Option<u8>,
+    pub(crate) used_percent: f64,
+    #[serde(default)]
+    pub(crate) window_duration_mins: Option<u64>,
+    /// Seconds since the epoch.
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub(crate) resets_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowReading {
+    #[serde(alias = "used_percent")]
+    used_percent: f64,
+    #[serde(default, alias = "window_duration_mins")]
+    window_duration_mins: Option<u64>,
+    #[serde(default, alias = "resets_at")]
+    resets_at: Option<i64>,
+}
+
+/// The latest `rateLimits` a workspace's app-server reported.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct CachedRateLimits {
+    pub(crate) rate_limits: Value,
+    #[ts(type = "number")]
+    pub(crate) updated_at: u64,
+}
+
+/// Cached snapshots plus the last reading of each window, to spot crossings.
+///
+/// Limits belong to the account, so windows are tracked once for all workspaces.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimitWatcher {
+    latest: HashMap<String, CachedRateLimits>,
+    windows: HashMap<RateLimitWindowKind, WindowReading>,
+}
+
+impl RateLimitWatcher {
+    pub(crate) fn latest(&self, workspace_id: &str) -> Option<CachedRateLimits> {
+        self.latest.get(workspace_id).cloned()
+    }
+
+    /// Caches `rate_limits` and returns the alerts it triggers. The first
+    /// reading of a window only sets the baseline.
+    pub(crate) fn observe(
+        &mut self,
+        workspace_id: &str,
+        rate_limits: &Value,
+        settings: &RateLimitWatchSettings,
+    ) -> Vec<RateLimitAlert> {
+        self.latest.insert(
+            workspace_id.to_string(),
+            CachedRateLimits {
+                rate_limits: rate_limits.clone(),
+                updated_at: now_ms(),
+            },
+        );
+        let lowest = settings
+            .thresholds
+            .iter()
+            .min()
+            .map(|value| f64::from(*value));
+        let mut alerts = Vec::new();
+        for (window, key) in [
+            (RateLimitWindowKind::Primary, "primary"),
+            (RateLimitWindowKind::Secondary, "secondary"),
+        ] {
+            let Some(reading) = rate_limits
+                .get(key)
+                .and_then(|value| serde_json::from_value::<WindowReading>(value.clone()).ok())
+            else {
+                continue;
+            };
+            let Some(previous) = self.windows.insert(window, reading) else {
+                continue;
+            };
+            let alert = |kind, threshold| RateLimitAlert {
+                window,
+                kind,
+                threshold,
+                used_percent: reading.used_percent,
+                window_duration_mins: reading.window_duration_mins,
+                resets_at: reading.resets_at,
+            };
+            let crossed = settings
+                .thresholds
+                .iter()
+                .filter(|threshold| {
+                    let threshold = f64::from(**threshold);
+                    previous.used_percent < threshold && reading.used_percent >= threshold
+                })
+                .max();
+            if let Some(threshold) = crossed {
+                alerts.push(alert(RateLimitAlertKind::Threshold, Some(*threshold)));
+            } else if let Some(lowest) = lowest {
+                if settings.notify_on_reset
+                    && previous.used_percent >= lowest
+                    && reading.used_percent < lowest
+                {
+                    alerts.push(alert(RateLimitAlertKind::Reset, None));
+                }
+            }
+        }
+        alerts
+    }
+}
+
+pub(crate) fn validate_rate_limit_watch(settings: &RateLimitWatchSettings) -> Result<(), String> {
+    if settings.poll_interval_secs < MIN_POLL_INTERVAL_SECS {
+        return Err(format!(
+            "Rate limits can be polled at most every {MIN_POLL_INTERVAL_SECS} seconds."
+        ));
+    }
+    if settings
+        .thresholds
+        .iter()
+        .any(|threshold| *threshold == 0 || *threshold > 100)
+    {
+        return Err("Rate limit thresholds must be between 1 and 100 percent.".to_string());
+    }
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The `rateLimits` object of an `account/rateLimits/read` response.
+fn rate_limits_of(response: &Value) -> Option<&Value> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("rateLimits")
+        .or_else(|| result.get("rate_limits"))
+}
+
+/// Caches a reading and emits any alerts; `forward` also re-emits the snapshot
+/// for readings the UI has not seen as a notification.
+pub(crate) async fn observe_rate_limits(
+    host: &dyn AgentHost,
+    workspace_id: &str,
+    rate_limits: &Value,
+    forward: bool,
+) {
+    let state = host.app_state();
+    let settings = state.app_settings.lock().await.rate_limit_watch.clone();
+    let alerts = state
+        .rate_limits
+        .lock()
+        .await
+        .observe(workspace_id, rate_limits, &settings);
+    if forward {
+        host.emit(AgentEvent::new(
+            workspace_id,
+            BackendType::Codex,
+            RATE_LIMITS_UPDATED_METHOD,
+            json!({ "rateLimits": rate_limits }),
+        ));
+    }
+    if !settings.enabled {
+        return;
+    }
+    for alert in alerts {
+        host.emit(AgentEvent::new(
+            workspace_id,
+            BackendType::Codex,
+            RATE_LIMIT_ALERT_METHOD,
+            json!(alert),
+        ));
+    }
+}
+
+/// Feeds `account/rateLimits/updated` notifications into the watcher.
+pub(crate) async fn observe_codex_rate_limits(
+    host: &dyn AgentHost,
+    workspace_id: &str,
+    notification: &KnownNotification,
+) {
+    if let KnownNotification::AccountRateLimitsUpdated(update) = notification {
+        observe_rate_limits(host, workspace_id, &update.rate_limits, false).await;
+    }
+}
+
+/// Reads rate limits through an app-server and feeds them to the watcher.
+pub(crate) async fn poll_rate_limits(
+    host: &dyn AgentHost,
+    session: &WorkspaceSession,
+) -> Result<Value, RpcError> {
+    let response = session.read_rate_limits().await?;
+    if let Some(rate_limits) = rate_limits_of(&response) {
+        observe_rate_limits(host, &session.entry.id, rate_limits, true).await;
+    }
+    Ok(response)
+}
+
+async fn poll_connected_workspaces(host: &dyn AgentHost) {
+    let state = host.app_state();
+    let sessions: Vec<_> = state
+        .sessions
+        .lock()
+        .await
+        .all()
+        .into_iter()
+        .filter(|session| session.kind() == BackendType::Codex)
+        .filter_map(|session| session.as_any().downcast::<WorkspaceSession>().ok())
+        .collect();
+    for session in sessions {
+        if let Err(error) = poll_rate_limits(host, &session).await {
+            eprintln!(
+                "Failed to read rate limits for {}: {error}",
+                session.entry.id
+            );
+        }
+    }
+}
+
+/// Polls every connected Codex workspace on the configured interval.
+pub(crate) fn start_rate_limit_watcher(host: SharedHost) {
+    tokio::spawn(async move {
+        let mut last_poll: Option<Instant> = None;
+        loop {
+            tokio::time::sleep(WATCH_TICK).await;
+            let settings = host
+                .app_state()
+                .app_settings
+                .lock()
+                .await
+                .rate_limit_watch
+                .clone();
+            let interval =
+                Duration::from_secs(settings.poll_interval_secs.max(MIN_POLL_INTERVAL_SECS));
+            let due = last_poll.is_none_or(|last| last.elapsed() >= interval);
+            if !settings.enabled || !due {
+                continue;
+            }
+            last_poll = Some(Instant::now());
+            poll_connected_workspaces(host.as_ref()).await;
+        }
+    });
+}
+
+pub(crate) async fn get_cached_rate_limits(
+    state: &AppState,
+    workspace_id: String,
+) -> Result<Option<CachedRateLimits>, String> {
+    Ok(state.rate_limits.lock().await.latest(&workspace_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimitAlertKind, RateLimitWatcher, RateLimitWindowKind};
+    use crate::types::RateLimitWatchSettings;
+    use serde_json::json;
+
+    #[test]
+    fn alerts_on_crossing_the_highest_threshold_and_on_reset() {
+        let settings = RateLimitWatchSettings {
+            thresholds: vec![50, 80, 95],
+            ..RateLimitWatchSettings::default()
+        };
+        let mut watcher = RateLimitWatcher::default();
+        let reading = |primary: f64, secondary: f64| {
+            json!({
+                "primary": { "usedPercent": primary, "windowDurationMins": 300 },
+                "secondary": { "used_percent": secondary }
+            })
+        };
+
+        assert!(watcher
+            .observe("ws", &reading(60.0, 10.0), &settings)
+            .is_empty());
+        assert!(watcher
+            .observe("ws", &reading(70.0, 10.0), &settings)
+            .is_empty());
+
+        let alerts = watcher.observe("ws", &reading(96.0, 12.0), &settings);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].window, RateLimitWindowKind::Primary);
+        assert_eq!(alerts[0].kind, RateLimitAlertKind::Threshold);
+        assert_eq!(alerts[0].threshold, Some(95));
+        assert_eq!(alerts[0].window_duration_mins, Some(300));
+
+        let alerts = watcher.observe("other", &reading(2.0, 55.0), &settings);
+        let kinds: Vec<_> = alerts
+            .iter()
+            .map(|alert| (alert.window, alert.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (RateLimitWindowKind::Primary, RateLimitAlertKind::Reset),
+                (
+                    RateLimitWindowKind::Secondary,
+                    RateLimitAlertKind::Threshold
+                ),
+            ]
+        );
+        assert!(watcher.latest("other").is_some());
+    }
+}
@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State as RouteState};
+use axum::http::header::{AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, watch};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::backend::SharedHost;
+use crate::dispatch::{dispatch, error_object, next_event_notification, INVALID_REQUEST};
+use crate::state::AppState;
+use crate::types::{RemoteAccessSettings, RemoteScope, RemoteToken};
+
+/// JSON-RPC error code for a method the token's scope does not allow.
+const FORBIDDEN: i64 = -32003;
+
+/// Never served remotely: they could rewrite the tokens, write files anywhere
+/// on this machine, choose which program an agent launches or pre-approve
+/// what it runs.
+const LOCAL_ONLY_METHODS: &[&str] = &[
+    "update_app_settings",
+    "export_thread",
+    "codex_doctor",
+    "opencode_doctor",
+    "add_workspace",
+    "update_workspace_codex_bin",
+    "update_workspace_acp_agent",
+    "update_workspace_settings",
+];
+
+/// The WebSocket subprotocol `/events` answers with. Browsers cannot set an
+/// `Authorization` header on a WebSocket, so they offer this alongside
+/// `bearer.<token>` in `Sec-WebSocket-Protocol`.
+const EVENTS_PROTOCOL: &str = "codex-monitor";
+const BEARER_PROTOCOL_PREFIX: &str = "bearer.";
+
+/// What a read-only token may call.
+const READ_ONLY_METHODS: &[&str] = &[
+    "get_app_settings",
+    "list_workspaces",
+    "list_workspace_files",
+    "list_threads",
+    "model_list",
+    "backend_health",
+    "list_queued_prompts",
+    "list_pending_requests",
+    "get_thread_snapshot",
+    "query_audit_log",
+    "get_usage_summary",
+    "read_transcript",
+    "search_conversations",
+    "get_git_status",
+    "get_git_diffs",
+    "get_git_log",
+    "get_git_remote",
+    "get_github_issues",
+    "list_git_branches",
+    "get_cached_rate_limits",
+    "get_remote_server_status",
+    "skills_list",
+    "list_opencode_sessions",
+    "get_opencode_messages",
+    "get_opencode_providers",
+];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct RemoteServerStatus {
+    pub(crate) running: bool,
+    pub(crate) address: Option<String>,
+    pub(crate) error: Option<String>,
+}
+
+struct RunningServer {
+    address: String,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// The running server, if any. `changes` ticks whenever remote settings are
+/// applied so open event streams re-check their token.
+pub(crate) struct RemoteServer {
+    running: Option<RunningServer>,
+    error: Option<String>,
+    changes: watch::Sender<()>,
+}
+
+impl Default for RemoteServer {
+    fn default() -> Self {
+        Self {
+            running: None,
+            error: None,
+            changes: watch::channel(()).0,
+        }
+    }
+}
+
+impl RemoteServer {
+    fn status(&self) -> RemoteServerStatus {
+        RemoteServerStatus {
+            running: self.running.is_some(),
+            address: self.running.as_ref().map(|server| server.address.clone()),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// The server speaks plain HTTP, so it only listens on loopback; other
+/// machines reach it through an SSH tunnel rather than sending tokens and
+/// transcripts over the network in the clear.
+fn check_bind_address(address: &str) -> Result<SocketAddr, String> {
+    let parsed = address.parse::<SocketAddr>().map_err(|_| {
+        format!("\"{address}\" is not a valid address; use ip:port, e.g. 127.0.0.1:4732.")
+    })?;
+    if !parsed.ip().is_loopback() {
+        return Err(format!(
+            "Remote access has no TLS, so it only listens on 127.0.0.1 or [::1], not {address}; \
+             use an SSH tunnel to reach it from another machine."
+        ));
+    }
+    Ok(parsed)
+}
+
+pub(crate) fn validate_remote_access(settings: &RemoteAccessSettings) -> Result<(), String> {
+    check_bind_address(&settings.bind_address)?;
+    for (index, token) in settings.tokens.iter().enumerate() {
+        if token.token.len() < 32 {
+            return Err(format!("Remote token \"{}\" is too short.", token.label));
+        }
+        if settings.tokens[..index]
+            .iter()
+            .any(|other| other.id == token.id || other.token == token.token)
+        {
+            return Err(format!("Remote token \"{}\" is duplicated.", token.label));
+        }
+    }
+    Ok(())
+}
+
+/// Compares without returning early, so response times leak nothing about
+/// how much of a guess was right.
+fn secrets_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn token_for<'a>(
+    settings: &'a RemoteAccessSettings,
+    token: Option<&str>,
+) -> Option<&'a RemoteToken> {
+    let token = token?;
+    settings
+        .tokens
+        .iter()
+        .find(|entry| secrets_match(&entry.token, token))
+}
+
+fn check_scope(scope: RemoteScope, method: &str) -> Result<(), String> {
+    if LOCAL_ONLY_METHODS.contains(&method) {
+        return Err(format!("{method} is not available remotely"));
+    }
+    if scope == RemoteScope::ReadOnly && !READ_ONLY_METHODS.contains(&method) {
+        return Err(format!("{method} needs a control token"));
+    }
+    Ok(())
+}
+
+/// Keeps token secrets out of settings read over the network.
+fn redact(method: &str, mut result: Value) -> Value {
+    if method == "get_app_settings" {
+        if let Some(tokens) = result
+            .pointer_mut("/remoteAccess/tokens")
+            .and_then(Value::as_array_mut)
+        {
+            for token in tokens {
+                token["token"] = Value::String(String::new());
+            }
+        }
+    }
+    result
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// The token a browser offered as a `bearer.<token>` WebSocket subprotocol.
+fn protocol_bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|protocol| protocol.trim().strip_prefix(BEARER_PROTOCOL_PREFIX))
+}
+
+async fn authorize(host: &SharedHost, token: Option<&str>) -> Option<RemoteToken> {
+    let settings = host.app_state().app_settings.lock().await;
+    token_for(&settings.remote_access, token).cloned()
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({ "error": "Missing or unknown bearer token" })),
+    )
+        .into_response()
+}
+
+/// `POST /rpc` with `{ "id", "method", "params" }`, answered like the daemon socket.
+async fn handle_rpc(
+    RouteState(host): RouteState<SharedHost>,
+    headers: HeaderMap,
+    Json(request): Json<Value>,
+) -> Response {
+    let Some(token) = authorize(&host, bearer(&headers)).await else {
+        return unauthorized();
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        let error = json!({ "code": INVALID_REQUEST, "message": "Missing method" });
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "jsonrpc": "2.0", "id": id, "error": error })),
+        )
+            .into_response();
+    };
+    if let Err(message) = check_scope(token.scope, method) {
+        let error = json!({ "code": FORBIDDEN, "message": message });
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "jsonrpc": "2.0", "id": id, "error": error })),
+        )
+            .into_response();
+    }
+    let params = request.get("params").cloned();
+    let reply = match dispatch(&host, method, params, Some(&token.label)).await {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": redact(method, result) }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error_object(&error) }),
+    };
+    Json(reply).into_response()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventsQuery {
+    workspace_id: Option<String>,
+}
+
+/// `GET /events` upgrades to a WebSocket carrying `agent-event` notifications.
+async fn handle_events(
+    RouteState(host): RouteState<SharedHost>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let token = bearer(&headers)
+        .or_else(|| protocol_bearer(&headers))
+        .map(str::to_string);
+    if authorize(&host, token.as_deref()).await.is_none() {
+        return unauthorized();
+    }
+    // Echo the fixed protocol name, never the one carrying the token.
+    upgrade
+        .protocols([EVENTS_PROTOCOL])
+        .on_upgrade(move |socket| stream_events(host, socket, token, query.workspace_id))
+}
+
+async fn stream_events(
+    host: SharedHost,
+    mut socket: WebSocket,
+    token: Option<String>,
+    workspace_id: Option<String>,
+) {
+    let state = host.app_state();
+    let mut events = state.events.subscribe();
+    let mut changes = state.remote_server.lock().await.changes.subscribe();
+    loop {
+        tokio::select! {
+            message = next_event_notification(&mut events, workspace_id.as_deref()) => {
+                let Some(message) = message else {
+                    break;
+                };
+                if socket.send(Message::Text(message.to_string().into())).await.is_err() {
+                    return;
+                }
+            }
+            changed = changes.changed() => {
+                // Stop streaming once the server is off or the token was revoked.
+                let running = state.remote_server.lock().await.running.is_some();
+                if changed.is_err() || !running || authorize(&host, token.as_deref()).await.is_none() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                if matches!(incoming, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    return;
+                }
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+fn router(host: SharedHost) -> Router {
+    Router::new()
+        .route("/rpc", post(handle_rpc))
+        .route("/events", get(handle_events))
+        .with_state(host)
+}
+
+/// Starts, stops or moves the server to match the remote access settings.
+pub(crate) async fn apply_remote_access(host: &SharedHost) {
+    let state = host.app_state();
+    let settings = state.app_settings.lock().await.remote_access.clone();
+    let mut server = state.remote_server.lock().await;
+    let wanted = settings.enabled.then_some(settings.bind_address);
+    let current = server.running.as_ref().map(|running| &running.address);
+    if current != wanted.as_ref() {
+        if let Some(running) = server.running.take() {
+            let _ = running.shutdown.send(());
+        }
+        server.error = None;
+        if let Some(address) = wanted {
+            // Settings saved by an older version may still name a public address.
+            match check_bind_address(&address) {
+                Err(error) => server.error = Some(error),
+                Ok(bind) => match TcpListener::bind(bind).await {
+                    Ok(listener) => {
+                        let (shutdown, stopped) = oneshot::channel::<()>();
+                        let router = router(host.clone());
+                        tokio::spawn(async move {
+                            let served = axum::serve(listener, router)
+                                .with_graceful_shutdown(async {
+                                    let _ = stopped.await;
+                                })
+                                .await;
+                            if let Err(error) = served {
+                                eprintln!("Remote access server stopped: {error}");
+                            }
+                        });
+                        server.running = Some(RunningServer { address, shutdown });
+                    }
+                    Err(error) => {
+                        server.error = Some(format!("Failed to listen on {address}: {error}"));
+                    }
+                },
+            }
+        }
+    }
+    server.changes.send_replace(());
+}
+
+pub(crate) fn start_remote_access(host: SharedHost) {
+    tokio::spawn(async move {
+        apply_remote_access(&host).await;
+    });
+}
+
+pub(crate) async fn get_remote_server_status(
+    state: &AppState,
+) -> Result<RemoteServerStatus, String> {
+    Ok(state.remote_server.lock().await.status())
+}
+
+/// Makes a new token; it takes effect once saved into the settings.
+pub(crate) fn generate_remote_token(
+    label: String,
+    scope: RemoteScope,
+) -> Result<RemoteToken, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Give the token a label.".to_string());
+    }
+    Ok(RemoteToken {
+        id: Uuid::new_v4().to_string(),
+        label: label.to_string(),
+        token: format!("cm_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+        scope,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_scope, protocol_bearer, redact, token_for, validate_remote_access};
+    use crate::types::{RemoteAccessSettings, RemoteScope, RemoteToken};
+    use axum::http::header::SEC_WEBSOCKET_PROTOCOL;
+    use axum::http::{HeaderMap, HeaderValue};
+    use serde_json::json;
+
+    fn token(id: &str, secret: &str, scope: RemoteScope) -> RemoteToken {
+        RemoteToken {
+            id: id.to_string(),
+            label: id.to_string(),
+            token: secret.to_string(),
+            scope,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn tokens_grant_their_scope_only() {
+        let settings = RemoteAccessSettings {
+            enabled: true,
+            bind_address: "127.0.0.1:4732".to_string(),
+            tokens: vec![
+                token("laptop", &"r".repeat(32), RemoteScope::ReadOnly),
+                token("ci", &"c".repeat(32), RemoteScope::Control),
+            ],
+        };
+        assert!(validate_remote_access(&settings).is_ok());
+        let laptop = token_for(&settings, Some(&"r".repeat(32))).unwrap();
+        assert_eq!(
+            (laptop.label.as_str(), laptop.scope),
+            ("laptop", RemoteScope::ReadOnly)
+        );
+        assert!(token_for(&settings, Some(&"r".repeat(31))).is_none());
+        assert!(token_for(&settings, None).is_none());
+
+        assert!(check_scope(RemoteScope::ReadOnly, "get_git_status").is_ok());
+        assert!(check_scope(RemoteScope::ReadOnly, "send_user_message").is_err());
+        assert!(check_scope(RemoteScope::Control, "send_user_message").is_ok());
+        assert!(check_scope(RemoteScope::Control, "update_app_settings").is_err());
+        assert!(check_scope(RemoteScope::Control, "update_workspace_codex_bin").is_err());
+        assert!(check_scope(RemoteScope::Control, "add_workspace").is_err());
+
+        let redacted = redact(
+            "get_app_settings",
+            json!({ "remoteAccess": { "tokens": [{ "id": "ci", "token": "secret" }] } }),
+        );
+        assert_eq!(redacted["remoteAccess"]["tokens"][0]["token"], "");
+
+        let mut invalid = settings.clone();
+        invalid.bind_address = "localhost".to_string();
+        assert!(validate_remote_access(&invalid).is_err());
+        invalid.bind_address = "0.0.0.0:4732".to_string();
+        assert!(validate_remote_access(&invalid).is_err());
+        invalid.bind_address = "[::1]:4732".to_string();
+        assert!(validate_remote_access(&invalid).is_ok());
+        invalid = settings;
+        invalid.tokens[1].token = "r".repeat(32);
+        assert!(validate_remote_access(&invalid).is_err());
+    }
+
+    #[test]
+    fn websocket_token_comes_from_the_bearer_subprotocol() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("codex-monitor, bearer.cm_secret"),
+        );
+        assert_eq!(protocol_bearer(&headers), Some("cm_secret"));
+        headers.insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("codex-monitor"),
+        );
+        assert_eq!(protocol_bearer(&headers), None);
+    }
+}
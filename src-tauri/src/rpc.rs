@@ -0,0 +1,451 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::timeout;
+use ts_rs::TS;
+
+/// Errors produced while talking JSON-RPC to an agent process.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum RpcError {
+    /// The peer answered with a JSON-RPC `error` object.
+    #[serde(rename_all = "camelCase")]
+    Server {
+        #[ts(type = "number")]
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Timeout {
+        method: String,
+        #[ts(type = "number")]
+        id: u64,
+        #[ts(type = "number")]
+        after_ms: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Canceled {
+        method: String,
+        #[ts(type = "number")]
+        id: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Disconnected { message: String },
+    #[serde(rename_all = "camelCase")]
+    Transport { message: String },
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Server { code, message, .. } => write!(f, "{message} (code {code})"),
+            RpcError::Timeout {
+                method, after_ms, ..
+            } => write!(f, "{method} timed out after {}", format_millis(*after_ms)),
+            RpcError::Canceled { method, .. } => write!(f, "{method} was canceled"),
+            RpcError::Disconnected { message } => write!(f, "{message}"),
+            RpcError::Transport { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+/// `30s`, `1.5s` or `250ms`: whole seconds where exact, so short timeouts do
+/// not read as `0s`.
+fn format_millis(millis: u64) -> String {
+    if millis < 1000 {
+        format!("{millis}ms")
+    } else if millis.is_multiple_of(1000) {
+        format!("{}s", millis / 1000)
+    } else {
+        format!("{:.1}s", millis as f64 / 1000.0)
+    }
+}
+
+/// What a failed command hands the frontend: a readable `message`, plus the
+/// structured `rpc` error when the failure came from the agent.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub(crate) struct CommandError {
+    pub(crate) message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub(crate) rpc: Option<RpcError>,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<RpcError> for CommandError {
+    fn from(error: RpcError) -> Self {
+        Self {
+            message: error.to_string(),
+            rpc: Some(error),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self { message, rpc: None }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// Turns a response carrying an `error` object into `RpcError::Server`.
+pub(crate) fn check_response(response: Value) -> Result<Value, RpcError> {
+    let Some(error) = response.get("error") else {
+        return Ok(response);
+    };
+    let code = error
+        .get("code")
+        .and_then(|code| code.as_i64())
+        .unwrap_or(0);
+    let message = error
+        .get("message")
+        .and_then(|message| message.as_str())
+        .map(|message| message.to_string())
+        .unwrap_or_else(|| error.to_string());
+    let data = error.get("data").filter(|data| !data.is_null()).cloned();
+    Err(RpcError::Server {
+        code,
+        message,
+        data,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PendingRequestInfo {
+    pub(crate) id: u64,
+    pub(crate) method: String,
+    pub(crate) elapsed_ms: u64,
+}
+
+struct PendingRequest {
+    method: String,
+    started_at: Instant,
+    tx: oneshot::Sender<Result<Value, RpcError>>,
+}
+
+/// In-flight requests keyed by JSON-RPC id, shared by the stdout reader and callers.
+#[derive(Default)]
+pub(crate) struct PendingRequests {
+    entries: Mutex<HashMap<u64, PendingRequest>>,
+}
+
+impl PendingRequests {
+    pub(crate) async fn register(
+        &self,
+        id: u64,
+        method: &str,
+    ) -> oneshot::Receiver<Result<Value, RpcError>> {
+        let (tx, rx) = oneshot::channel();
+        self.entries.lock().await.insert(
+            id,
+            PendingRequest {
+                method: method.to_string(),
+                started_at: Instant::now(),
+                tx,
+            },
+        );
+        rx
+    }
+
+    /// Delivers a response; returns false when nobody is waiting on `id` anymore.
+    pub(crate) async fn resolve(&self, id: u64, value: Value) -> bool {
+        match self.entries.lock().await.remove(&id) {
+            Some(request) => {
+                let _ = request.tx.send(Ok(value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) async fn cancel(&self, id: u64) -> Option<String> {
+        let request = self.entries.lock().await.remove(&id)?;
+        let method = request.method.clone();
+        let _ = request.tx.send(Err(RpcError::Canceled {
+            method: request.method,
+            id,
+        }));
+        Some(method)
+    }
+
+    pub(crate) async fn remove(&self, id: u64) {
+        self.entries.lock().await.remove(&id);
+    }
+
+    pub(crate) async fn fail_all(&self, message: &str) {
+        let mut entries = self.entries.lock().await;
+        for (_, request) in entries.drain() {
+            let _ = request.tx.send(Err(RpcError::Disconnected {
+                message: message.to_string(),
+            }));
+        }
+    }
+
+    pub(crate) async fn snapshot(&self) -> Vec<PendingRequestInfo> {
+        let entries = self.entries.lock().await;
+        let mut list: Vec<PendingRequestInfo> = entries
+            .iter()
+            .map(|(id, request)| PendingRequestInfo {
+                id: *id,
+                method: request.method.clone(),
+                elapsed_ms: request.started_at.elapsed().as_millis() as u64,
+            })
+            .collect();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+}
+
+type RpcWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Line-delimited JSON-RPC client shared by the Codex and ACP sessions.
+///
+/// The Codex app-server omits the `"jsonrpc": "2.0"` marker; ACP agents expect it.
+pub(crate) struct JsonRpcClient {
+    writer: Mutex<RpcWriter>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    versioned: bool,
+}
+
+impl JsonRpcClient {
+    pub(crate) fn new(writer: impl AsyncWrite + Send + Unpin + 'static, versioned: bool) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            pending: PendingRequests::default(),
+            next_id: AtomicU64::new(1),
+            versioned,
+        }
+    }
+
+    pub(crate) fn pending(&self) -> &PendingRequests {
+        &self.pending
+    }
+
+    pub(crate) async fn write_message(&self, mut value: Value) -> Result<(), RpcError> {
+        if self.versioned {
+            if let Some(object) = value.as_object_mut() {
+                object.insert("jsonrpc".to_string(), json!("2.0"));
+            }
+        }
+        let mut line = serde_json::to_string(&value).map_err(|e| RpcError::Transport {
+            message: e.to_string(),
+        })?;
+        line.push('\n');
+        let mut writer = self.writer.lock().await;
+        let transport = |e: std::io::Error| RpcError::Transport {
+            message: format!("Failed to write to agent stdin: {e}"),
+        };
+        writer.write_all(line.as_bytes()).await.map_err(transport)?;
+        writer.flush().await.map_err(transport)
+    }
+
+    /// Sends a request and resolves to the full response message.
+    pub(crate) async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        limit: Option<Duration>,
+    ) -> Result<Value, RpcError> {
+        let (id, rx) = self.start_request(method, params).await?;
+        let response = await_response(&self.pending, rx, id, method, limit).await?;
+        check_response(response)
+    }
+
+    /// Sends a request and hands back the receiver for callers that wait on
+    /// their own terms (e.g. an idle deadline instead of a fixed one).
+    pub(crate) async fn start_request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<(u64, oneshot::Receiver<Result<Value, RpcError>>), RpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let rx = self.pending.register(id, method).await;
+        let message = json!({ "id": id, "method": method, "params": params });
+        if let Err(error) = self.write_message(message).await {
+            self.pending.remove(id).await;
+            return Err(error);
+        }
+        Ok((id, rx))
+    }
+
+    pub(crate) async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), RpcError> {
+        let message = match params {
+            Some(params) => json!({ "method": method, "params": params }),
+            None => json!({ "method": method }),
+        };
+        self.write_message(message).await
+    }
+
+    pub(crate) async fn respond(&self, id: u64, result: Value) -> Result<(), RpcError> {
+        self.write_message(json!({ "id": id, "result": result }))
+            .await
+    }
+
+    pub(crate) async fn respond_error(
+        &self,
+        id: u64,
+        code: i64,
+        message: &str,
+    ) -> Result<(), RpcError> {
+        self.write_message(json!({ "id": id, "error": { "code": code, "message": message } }))
+            .await
+    }
+}
+
+/// Waits for the response to `id`, dropping the pending entry if `limit` elapses first.
+pub(crate) async fn await_response(
+    pending: &PendingRequests,
+    rx: oneshot::Receiver<Result<Value, RpcError>>,
+    id: u64,
+    method: &str,
+    limit: Option<Duration>,
+) -> Result<Value, RpcError> {
+    let closed = || RpcError::Disconnected {
+        message: format!("{method} was dropped before a response arrived"),
+    };
+    let Some(limit) = limit else {
+        return rx.await.map_err(|_| closed())?;
+    };
+    match timeout(limit, rx).await {
+        Ok(result) => result.map_err(|_| closed())?,
+        Err(_) => {
+            pending.remove(id).await;
+            Err(RpcError::Timeout {
+                method: method.to_string(),
+                id,
+                after_ms: limit.as_millis() as u64,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        await_response, check_response, format_millis, CommandError, JsonRpcClient,
+        PendingRequests, RpcError,
+    };
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    #[test]
+    fn check_response_maps_error_objects() {
+        let response = json!({
+            "id": 1,
+            "error": { "code": -32602, "message": "bad params", "data": { "field": "cwd" } }
+        });
+        match check_response(response) {
+            Err(RpcError::Server {
+                code,
+                message,
+                data,
+            }) => {
+                assert_eq!(code, -32602);
+                assert_eq!(message, "bad params");
+                assert_eq!(data, Some(json!({ "field": "cwd" })));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(check_response(json!({ "id": 2, "result": {} })).is_ok());
+    }
+
+    #[test]
+    fn command_errors_keep_the_rpc_error() {
+        let error = CommandError::from(RpcError::Canceled {
+            method: "turn/start".to_string(),
+            id: 4,
+        });
+        assert_eq!(
+            serde_json::to_value(&error).expect("serialize"),
+            json!({
+                "message": "turn/start was canceled",
+                "rpc": { "kind": "canceled", "method": "turn/start", "id": 4 }
+            })
+        );
+        let plain =
+            serde_json::to_value(CommandError::from("workspace not connected")).expect("serialize");
+        assert_eq!(plain, json!({ "message": "workspace not connected" }));
+    }
+
+    #[tokio::test]
+    async fn request_writes_versioned_line_and_surfaces_errors() {
+        let (client_end, agent_end) = tokio::io::duplex(4096);
+        let client = std::sync::Arc::new(JsonRpcClient::new(client_end, true));
+        let caller = {
+            let client = client.clone();
+            tokio::spawn(async move { client.request("session/new", json!({}), None).await })
+        };
+        let mut lines = BufReader::new(agent_end).lines();
+        let line = lines.next_line().await.expect("read").expect("line");
+        let sent: Value = serde_json::from_str(&line).expect("json");
+        assert_eq!(sent["jsonrpc"], "2.0");
+        assert_eq!(sent["method"], "session/new");
+        let id = sent["id"].as_u64().expect("id");
+        client
+            .pending()
+            .resolve(
+                id,
+                json!({ "id": id, "error": { "code": 1, "message": "nope" } }),
+            )
+            .await;
+        let result = caller.await.expect("join");
+        assert!(matches!(result, Err(RpcError::Server { code: 1, .. })));
+    }
+
+    #[tokio::test]
+    async fn await_response_times_out_and_clears_entry() {
+        let pending = PendingRequests::default();
+        let rx = pending.register(7, "model/list").await;
+        let result = await_response(
+            &pending,
+            rx,
+            7,
+            "model/list",
+            Some(Duration::from_millis(10)),
+        )
+        .await;
+        let error = result.expect_err("timed out");
+        assert!(matches!(error, RpcError::Timeout { id: 7, .. }));
+        assert_eq!(error.to_string(), "model/list timed out after 10ms");
+        assert!(pending.snapshot().await.is_empty());
+    }
+
+    #[test]
+    fn format_millis_keeps_sub_second_precision() {
+        assert_eq!(format_millis(250), "250ms");
+        assert_eq!(format_millis(30_000), "30s");
+        assert_eq!(format_millis(1_500), "1.5s");
+    }
+
+    #[tokio::test]
+    async fn cancel_fails_waiter_with_method_name() {
+        let pending = PendingRequests::default();
+        let rx = pending.register(3, "turn/start").await;
+        assert_eq!(pending.cancel(3).await.as_deref(), Some("turn/start"));
+        let result = await_response(&pending, rx, 3, "turn/start", None).await;
+        assert!(matches!(result, Err(RpcError::Canceled { id: 3, .. })));
+        assert!(!pending.resolve(3, json!({})).await);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::protocol::{ApprovalDecision, ApprovalPolicy, SandboxPolicy};
+use crate::types::{
+    builtin_sandbox_profiles, AppSettings, ApprovalRule, ApprovalRuleTarget, SandboxMode,
+    SandboxProfile, WorkspaceSettings,
+};
+use crate::utils::resolve_path;
+
+/// A profile turned into what `turn/start` sends for one workspace.
+///
+/// Codex's sandbox policy cannot carve paths out of a writable root, so
+/// excluded paths are enforced through approvals instead. Only the `untrusted`
+/// policy asks before every write and command; `on-request` and `on-failure`
+/// let sandboxed ones run unasked, so exclusions need workspace-write with
+/// `untrusted`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResolvedSandbox {
+    pub(crate) profile: String,
+    pub(crate) approval_policy: ApprovalPolicy,
+    pub(crate) sandbox_policy: SandboxPolicy,
+    /// Absolute paths whose file changes, and commands run inside, are declined.
+    pub(crate) excluded_paths: Vec<String>,
+}
+
+impl ResolvedSandbox {
+    /// Decline rules that keep file changes and commands out of the excluded paths.
+    pub(crate) fn exclusion_rules(&self) -> Vec<ApprovalRule> {
+        self.excluded_paths
+            .iter()
+            .flat_map(|path| {
+                let name = Some(format!("{} excludes {path}", self.profile));
+                [
+                    ApprovalRule {
+                        name: name.clone(),
+                        decision: ApprovalDecision::Decline,
+                        target: ApprovalRuleTarget::FileChange,
+                        command_prefix: None,
+                        path_glob: Some(format!("{}/**", path.trim_end_matches('/'))),
+                        cwd: None,
+                    },
+                    ApprovalRule {
+                        name,
+                        decision: ApprovalDecision::Decline,
+                        target: ApprovalRuleTarget::Command,
+                        command_prefix: None,
+                        path_glob: None,
+                        cwd: Some(path.clone()),
+                    },
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Why the profile's excluded paths could not be enforced, if it has any.
+fn unenforceable_exclusions(profile: &SandboxProfile) -> Option<String> {
+    if profile.excluded_paths.is_empty() {
+        return None;
+    }
+    let name = profile.name.trim();
+    if profile.mode != SandboxMode::WorkspaceWrite {
+        return Some(format!(
+            "Sandbox profile \"{name}\" lists excluded paths, which only apply to workspace-write."
+        ));
+    }
+    if profile.approval_policy != ApprovalPolicy::UnlessTrusted {
+        return Some(format!(
+            "Sandbox profile \"{name}\" lists excluded paths, which need the \"ask unless trusted\" approval policy; others let sandboxed changes through without asking."
+        ));
+    }
+    None
+}
+
+/// Checks a profile list before it is saved.
+pub(crate) fn validate_sandbox_profiles(profiles: &[SandboxProfile]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for profile in profiles {
+        let name = profile.name.trim();
+        if name.is_empty() {
+            return Err("Sandbox profiles need a name.".to_string());
+        }
+        if !names.insert(name) {
+            return Err(format!("Sandbox profile \"{name}\" is defined twice."));
+        }
+        if profile.mode != SandboxMode::WorkspaceWrite && !profile.writable_roots.is_empty() {
+            return Err(format!(
+                "Sandbox profile \"{name}\" lists writable roots, which only apply to workspace-write."
+            ));
+        }
+        if let Some(error) = unenforceable_exclusions(profile) {
+            return Err(error);
+        }
+        let blank = profile
+            .writable_roots
+            .iter()
+            .chain(&profile.excluded_paths)
+            .any(|path| path.trim().is_empty());
+        if blank {
+            return Err(format!("Sandbox profile \"{name}\" has an empty path."));
+        }
+    }
+    Ok(())
+}
+
+/// Workspace profiles shadow app profiles, which shadow the built-in ones.
+pub(crate) fn find_sandbox_profile(
+    app_settings: &AppSettings,
+    workspace_settings: &WorkspaceSettings,
+    name: &str,
+) -> Option<SandboxProfile> {
+    workspace_settings
+        .sandbox_profiles
+        .iter()
+        .chain(&app_settings.sandbox_profiles)
+        .find(|profile| profile.name == name)
+        .cloned()
+        .or_else(|| {
+            builtin_sandbox_profiles()
+                .into_iter()
+                .find(|profile| profile.name == name)
+        })
+}
+
+/// Resolves the sandbox an `access_mode` names for `workspace_path`.
+pub(crate) fn sandbox_for_access_mode(
+    app_settings: &AppSettings,
+    workspace_settings: &WorkspaceSettings,
+    access_mode: &str,
+    workspace_path: &str,
+) -> Result<ResolvedSandbox, String> {
+    let profile = find_sandbox_profile(app_settings, workspace_settings, access_mode)
+        .ok_or_else(|| format!("Unknown sandbox profile \"{access_mode}\"."))?;
+    resolve_sandbox(&profile, workspace_path)
+}
+
+/// Builds the sandbox policy for `workspace_path`, rejecting writable roots
+/// that fall inside an excluded path.
+pub(crate) fn resolve_sandbox(
+    profile: &SandboxProfile,
+    workspace_path: &str,
+) -> Result<ResolvedSandbox, String> {
+    // Profiles saved before validation covered this still reach us.
+    if let Some(error) = unenforceable_exclusions(profile) {
+        return Err(error);
+    }
+    let workspace = Path::new(workspace_path);
+    let excluded: Vec<_> = profile
+        .excluded_paths
+        .iter()
+        .map(|path| resolve_path(workspace, path))
+        .collect();
+    let sandbox_policy = match profile.mode {
+        SandboxMode::ReadOnly => SandboxPolicy::ReadOnly,
+        SandboxMode::DangerFullAccess => SandboxPolicy::DangerFullAccess,
+        SandboxMode::WorkspaceWrite => {
+            let mut roots = vec![resolve_path(workspace, ".")];
+            for root in &profile.writable_roots {
+                let root = resolve_path(workspace, root);
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+            }
+            for root in &roots {
+                if let Some(path) = excluded.iter().find(|path| root.starts_with(path)) {
+                    return Err(format!(
+                        "Sandbox profile \"{}\" makes {} writable, but it is inside excluded path {}.",
+                        profile.name,
+                        root.display(),
+                        path.display()
+                    ));
+                }
+            }
+            SandboxPolicy::WorkspaceWrite {
+                writable_roots: roots
+                    .iter()
+                    .map(|root| root.to_string_lossy().to_string())
+                    .collect(),
+                network_access: profile.network_access,
+            }
+        }
+    };
+    Ok(ResolvedSandbox {
+        profile: profile.name.clone(),
+        approval_policy: profile.approval_policy,
+        sandbox_policy,
+        excluded_paths: excluded
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_sandbox_profile, resolve_sandbox, validate_sandbox_profiles};
+    use crate::approvals::{evaluate, ApprovalSubject};
+    use crate::protocol::{ApprovalPolicy, SandboxPolicy};
+    use crate::types::{AppSettings, SandboxMode, SandboxProfile, WorkspaceSettings};
+    use std::path::Path;
+
+    fn profile(name: &str) -> SandboxProfile {
+        SandboxProfile {
+            name: name.to_string(),
+            label: None,
+            mode: SandboxMode::WorkspaceWrite,
+            writable_roots: Vec::new(),
+            network_access: false,
+            approval_policy: ApprovalPolicy::UnlessTrusted,
+            excluded_paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn workspace_profiles_shadow_app_and_builtin_profiles() {
+        let app = AppSettings::default();
+        let mut workspace = WorkspaceSettings::default();
+        let mut offline = profile("current");
+        offline.writable_roots = vec!["../shared".to_string()];
+        workspace.sandbox_profiles.push(offline);
+
+        let current = find_sandbox_profile(&app, &workspace, "current").expect("current");
+        let resolved = resolve_sandbox(&current, "/repo/app").expect("resolve");
+        assert_eq!(
+            resolved.sandbox_policy,
+            SandboxPolicy::WorkspaceWrite {
+                writable_roots: vec!["/repo/app".to_string(), "/repo/shared".to_string()],
+                network_access: false,
+            }
+        );
+        let full = find_sandbox_profile(&app, &workspace, "full-access").expect("builtin");
+        assert_eq!(full.approval_policy, ApprovalPolicy::Never);
+        assert!(find_sandbox_profile(&app, &workspace, "missing").is_none());
+    }
+
+    #[test]
+    fn excluded_paths_reject_writable_roots_and_become_rules() {
+        let mut locked = profile("locked");
+        locked.writable_roots = vec!["/repo/vendor/gen".to_string()];
+        locked.excluded_paths = vec!["vendor".to_string()];
+        assert!(resolve_sandbox(&locked, "/repo").is_err());
+
+        locked.writable_roots.clear();
+        let resolved = resolve_sandbox(&locked, "/repo").expect("resolve");
+        assert_eq!(resolved.excluded_paths, vec!["/repo/vendor".to_string()]);
+        let rules = resolved.exclusion_rules();
+        assert_eq!(rules[0].path_glob.as_deref(), Some("/repo/vendor/**"));
+        let change = ApprovalSubject::FileChange {
+            paths: vec!["vendor/lib.rs".to_string()],
+            grant_root: None,
+        };
+        assert!(evaluate(&rules, Path::new("/repo"), &change).is_some());
+        let command = ApprovalSubject::Command {
+            command: "make".to_string(),
+            cwd: Some("/repo/vendor/zlib".to_string()),
+        };
+        assert!(evaluate(&rules, Path::new("/repo"), &command).is_some());
+
+        for policy in [
+            ApprovalPolicy::Never,
+            ApprovalPolicy::OnRequest,
+            ApprovalPolicy::OnFailure,
+        ] {
+            locked.approval_policy = policy;
+            assert!(resolve_sandbox(&locked, "/repo").is_err());
+            assert!(validate_sandbox_profiles(&[locked.clone()]).is_err());
+        }
+        locked.approval_policy = ApprovalPolicy::UnlessTrusted;
+        locked.mode = SandboxMode::DangerFullAccess;
+        assert!(validate_sandbox_profiles(&[locked]).is_err());
+
+        let mut duplicate = vec![profile("a"), profile("a")];
+        assert!(validate_sandbox_profiles(&duplicate).is_err());
+        duplicate[1].name = "b".to_string();
+        duplicate[1].mode = SandboxMode::ReadOnly;
+        duplicate[1].writable_roots = vec!["/tmp".to_string()];
+        assert!(validate_sandbox_profiles(&duplicate).is_err());
+    }
+}
@@ -0,0 +1,988 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ts_rs::TS;
+
+use crate::acp::AcpSession;
+use crate::backend::{AgentBackend, SharedHost};
+use crate::codex::WorkspaceSession;
+use crate::opencode::opencode_sessions;
+use crate::protocol::{KnownThreadItem, ThreadItem};
+use crate::rpc::CommandError;
+use crate::state::AppState;
+use crate::transcripts::{read_journal, unsanitize_id, TranscriptEntry};
+use crate::types::{BackendType, OpenCodeSessionInfo};
+
+const DEFAULT_RESULT_LIMIT: usize = 20;
+const MAX_RESULT_LIMIT: usize = 200;
+const SNIPPET_BEFORE: usize = 60;
+const SNIPPET_AFTER: usize = 140;
+const MAX_TOKEN_LEN: usize = 64;
+/// Command output is indexed, but only its head, to keep build logs from dominating.
+const MAX_OUTPUT_CHARS: usize = 2000;
+/// Pause between background indexing passes.
+const INDEX_INTERVAL: Duration = Duration::from_secs(60);
+const THREAD_LIST_PAGE_SIZE: u32 = 50;
+const MAX_THREAD_LIST_PAGES: usize = 10;
+/// OpenCode histories fetched per workspace and pass, so a long backlog does
+/// not monopolize the agent.
+const MAX_HISTORY_FETCHES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct SearchHit {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) snippet: String,
+    /// Milliseconds since the epoch of the matching text, when known.
+    #[ts(type = "number | null")]
+    pub(crate) timestamp: Option<u64>,
+    pub(crate) score: f64,
+}
+
+/// Where a block of indexed text came from; each origin is replaced independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum SegmentOrigin {
+    /// History reported by the agent (`thread/resume`, `message/list`).
+    Agent,
+    /// Our own transcript journal.
+    Transcript,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Segment {
+    text: String,
+    timestamp: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedThread {
+    title: Option<String>,
+    updated_at: Option<u64>,
+    segments: HashMap<SegmentOrigin, Vec<Segment>>,
+    /// `seq` of the last transcript entry indexed.
+    #[serde(default)]
+    transcript_cursor: Option<u64>,
+    #[serde(skip)]
+    terms: HashMap<String, u32>,
+}
+
+type DocKey = (String, String);
+
+/// One thread as saved in `search_index.json`; postings are rebuilt on load.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredThread<K, T> {
+    workspace_id: K,
+    thread_id: K,
+    #[serde(flatten)]
+    thread: T,
+}
+
+/// New transcript entries for one thread, past its `transcript_cursor`.
+struct TranscriptUpdate {
+    key: DocKey,
+    entries: Vec<TranscriptEntry>,
+}
+
+/// Inverted index over thread titles and message text, one document per
+/// thread. Filled in the background by [`start_search_indexer`] and saved
+/// next to the other app data.
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    /// Where the index is saved; `None` keeps it in memory.
+    path: Option<PathBuf>,
+    threads: HashMap<DocKey, IndexedThread>,
+    postings: HashMap<String, HashSet<DocKey>>,
+    transcript_mtimes: HashMap<PathBuf, SystemTime>,
+    /// Changed since the last save.
+    dirty: bool,
+}
+
+impl SearchIndex {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let stored: Vec<StoredThread<String, IndexedThread>> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let mut index = Self {
+            path: Some(path),
+            ..Self::default()
+        };
+        for StoredThread {
+            workspace_id,
+            thread_id,
+            thread,
+        } in stored
+        {
+            index.update(&workspace_id, &thread_id, |indexed| *indexed = thread);
+        }
+        index.dirty = false;
+        index
+    }
+
+    /// The path and serialized index, if it changed since the last call.
+    fn take_unsaved(&mut self) -> Option<(PathBuf, String)> {
+        let path = self.path.clone().filter(|_| self.dirty)?;
+        let stored: Vec<StoredThread<&str, &IndexedThread>> = self
+            .threads
+            .iter()
+            .map(|((workspace_id, thread_id), thread)| StoredThread {
+                workspace_id: workspace_id.as_str(),
+                thread_id: thread_id.as_str(),
+                thread,
+            })
+            .collect();
+        let data = serde_json::to_string(&stored).ok()?;
+        self.dirty = false;
+        Some((path, data))
+    }
+
+    fn update<F>(&mut self, workspace_id: &str, thread_id: &str, apply: F)
+    where
+        F: FnOnce(&mut IndexedThread),
+    {
+        self.dirty = true;
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        let thread = self.threads.entry(key.clone()).or_default();
+        apply(thread);
+
+        let mut terms: HashMap<String, u32> = HashMap::new();
+        let texts = thread.title.iter().chain(
+            thread
+                .segments
+                .values()
+                .flatten()
+                .map(|segment| &segment.text),
+        );
+        for text in texts {
+            for token in tokenize(text) {
+                *terms.entry(token).or_insert(0) += 1;
+            }
+        }
+        for stale in thread
+            .terms
+            .keys()
+            .filter(|term| !terms.contains_key(*term))
+        {
+            if let Some(docs) = self.postings.get_mut(stale) {
+                docs.remove(&key);
+                if docs.is_empty() {
+                    self.postings.remove(stale);
+                }
+            }
+        }
+        for term in terms.keys() {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        thread.terms = terms;
+    }
+
+    fn set_title(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        title: Option<String>,
+        timestamp: Option<u64>,
+    ) {
+        self.update(workspace_id, thread_id, |thread| {
+            if let Some(title) = title.filter(|title| !title.trim().is_empty()) {
+                thread.title = Some(title);
+            }
+            thread.updated_at = thread.updated_at.max(timestamp);
+        });
+    }
+
+    fn set_segments(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        origin: SegmentOrigin,
+        segments: Vec<Segment>,
+    ) {
+        self.update(workspace_id, thread_id, |thread| {
+            let latest = segments
+                .iter()
+                .filter_map(|segment| segment.timestamp)
+                .max();
+            thread.updated_at = thread.updated_at.max(latest);
+            thread.segments.insert(origin, segments);
+        });
+    }
+
+    /// Indexes a `thread/resume` response.
+    pub(crate) fn index_codex_thread(&mut self, workspace_id: &str, response: &Value) {
+        let Some(thread) = response.get("result").unwrap_or(response).get("thread") else {
+            return;
+        };
+        let Some(thread_id) = thread.get("id").and_then(|id| id.as_str()) else {
+            return;
+        };
+        let created_at = thread
+            .get("createdAt")
+            .and_then(|value| value.as_i64())
+            .map(to_millis);
+        let preview = thread
+            .get("preview")
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string());
+        let segments = thread
+            .get("turns")
+            .and_then(|turns| turns.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|turn| turn.get("items").and_then(|items| items.as_array()))
+            .flatten()
+            .filter_map(|item| ThreadItem::deserialize(item).ok())
+            .filter_map(|item| thread_item_text(&item))
+            .map(|text| Segment {
+                text,
+                timestamp: created_at,
+            })
+            .collect();
+        self.set_title(workspace_id, thread_id, preview, created_at);
+        self.set_segments(workspace_id, thread_id, SegmentOrigin::Agent, segments);
+    }
+
+    /// Indexes the previews of a `thread/list` page.
+    pub(crate) fn index_codex_thread_list(&mut self, workspace_id: &str, response: &Value) {
+        let threads = response
+            .get("result")
+            .unwrap_or(response)
+            .get("data")
+            .and_then(|data| data.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for thread in threads {
+            let Some(thread_id) = thread.get("id").and_then(|id| id.as_str()) else {
+                continue;
+            };
+            let preview = thread
+                .get("preview")
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string());
+            let created_at = thread
+                .get("createdAt")
+                .and_then(|value| value.as_i64())
+                .map(to_millis);
+            self.set_title(workspace_id, thread_id, preview, created_at);
+        }
+    }
+
+    pub(crate) fn index_opencode_sessions(
+        &mut self,
+        workspace_id: &str,
+        sessions: &[OpenCodeSessionInfo],
+    ) {
+        for session in sessions {
+            let timestamp = session.updated_at.or(session.created_at).map(to_millis);
+            self.set_title(workspace_id, &session.id, session.title.clone(), timestamp);
+        }
+    }
+
+    /// Indexes a `message/list` result for one OpenCode session.
+    pub(crate) fn index_opencode_messages(
+        &mut self,
+        workspace_id: &str,
+        session_id: &str,
+        result: &Value,
+    ) {
+        let messages = result
+            .get("messages")
+            .unwrap_or(result)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let segments = messages
+            .iter()
+            .filter_map(|message| {
+                let text = message
+                    .get("parts")
+                    .and_then(|parts| parts.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| {
+                        part.get("text")
+                            .or_else(|| part.get("content"))
+                            .and_then(|text| text.as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let timestamp = message
+                    .get("createdAt")
+                    .and_then(|value| value.as_i64())
+                    .map(to_millis);
+                (!text.trim().is_empty()).then_some(Segment { text, timestamp })
+            })
+            .collect();
+        self.set_segments(workspace_id, session_id, SegmentOrigin::Agent, segments);
+    }
+
+    /// Whether `thread_id` has no agent-reported history yet, or the agent
+    /// reports it changed after what was indexed.
+    fn needs_history(&self, workspace_id: &str, thread_id: &str, updated_at: Option<u64>) -> bool {
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        match self.threads.get(&key) {
+            Some(thread) if thread.segments.contains_key(&SegmentOrigin::Agent) => {
+                updated_at.is_some_and(|updated_at| thread.updated_at < Some(updated_at))
+            }
+            _ => true,
+        }
+    }
+
+    fn transcript_cursors(&self) -> HashMap<DocKey, u64> {
+        self.threads
+            .iter()
+            .filter_map(|(key, thread)| Some((key.clone(), thread.transcript_cursor?)))
+            .collect()
+    }
+
+    /// Adds the transcript entries past the thread's cursor to its text.
+    fn append_transcript(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        entries: &[TranscriptEntry],
+    ) {
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        let cursor = self
+            .threads
+            .get(&key)
+            .and_then(|thread| thread.transcript_cursor);
+        let entries: Vec<&TranscriptEntry> = entries
+            .iter()
+            .filter(|entry| cursor.is_none_or(|cursor| entry.seq > cursor))
+            .collect();
+        let Some(last_seq) = entries.last().map(|entry| entry.seq) else {
+            return;
+        };
+        let segments = transcript_segments(&entries);
+        self.update(workspace_id, thread_id, |thread| {
+            let latest = entries.iter().map(|entry| entry.timestamp).max();
+            thread.updated_at = thread.updated_at.max(latest);
+            thread
+                .segments
+                .entry(SegmentOrigin::Transcript)
+                .or_default()
+                .extend(segments);
+            thread.transcript_cursor = Some(last_seq);
+        });
+    }
+
+    pub(crate) fn search(
+        &self,
+        query: &str,
+        workspace_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Vec<SearchHit> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let total_docs = self.threads.len().max(1) as f64;
+        let mut scores: HashMap<&DocKey, (f64, usize)> = HashMap::new();
+        for term in &terms {
+            let Some(docs) = self.postings.get(term) else {
+                continue;
+            };
+            let idf = (1.0 + total_docs / docs.len() as f64).ln();
+            for key in docs {
+                if !workspace_ids.contains(&key.0) {
+                    continue;
+                }
+                let tf = self
+                    .threads
+                    .get(key)
+                    .and_then(|thread| thread.terms.get(term))
+                    .copied()
+                    .unwrap_or(0) as f64;
+                let score = scores.entry(key).or_insert((0.0, 0));
+                score.0 += idf * (1.0 + tf.ln());
+                score.1 += 1;
+            }
+        }
+        let coverage = |matched: usize| (matched as f64 / terms.len() as f64).powi(2);
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .filter_map(|(key, (score, matched))| {
+                let thread = self.threads.get(key)?;
+                let (snippet, timestamp) = best_snippet(thread, &terms);
+                Some(SearchHit {
+                    workspace_id: key.0.clone(),
+                    thread_id: key.1.clone(),
+                    title: thread.title.clone(),
+                    snippet,
+                    timestamp: timestamp.or(thread.updated_at),
+                    score: score * coverage(matched),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.timestamp.cmp(&a.timestamp))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Indexable text from transcript entries; streamed ACP chunks are joined.
+fn transcript_segments(entries: &[&TranscriptEntry]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut streaming: Option<Segment> = None;
+    for entry in entries {
+        let params = entry.params.as_ref();
+        if entry.method == "session/update" {
+            let update = params.and_then(|params| params.get("update"));
+            let chunk = update
+                .filter(|update| {
+                    update.get("sessionUpdate").and_then(|kind| kind.as_str())
+                        == Some("agent_message_chunk")
+                })
+                .and_then(|update| update.get("content"))
+                .and_then(|content| content.get("text"))
+                .and_then(|text| text.as_str());
+            if let Some(chunk) = chunk {
+                streaming
+                    .get_or_insert_with(|| Segment {
+                        text: String::new(),
+                        timestamp: Some(entry.timestamp),
+                    })
+                    .text
+                    .push_str(chunk);
+                continue;
+            }
+        }
+        segments.extend(streaming.take());
+        let text = match entry.method.as_str() {
+            "turn/start" | "review/start" | "session/prompt" => params
+                .and_then(|params| params.get("input").or_else(|| params.get("prompt")))
+                .and_then(|input| input.as_array())
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .filter_map(|part| part.get("text").and_then(|text| text.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }),
+            "item/completed" => params
+                .and_then(|params| params.get("item"))
+                .and_then(|item| ThreadItem::deserialize(item).ok())
+                .and_then(|item| thread_item_text(&item)),
+            _ => None,
+        };
+        if let Some(text) = text.filter(|text| !text.trim().is_empty()) {
+            segments.push(Segment {
+                text,
+                timestamp: Some(entry.timestamp),
+            });
+        }
+    }
+    segments.extend(streaming);
+    segments
+}
+
+/// Reads the entries past `cursors` from journals whose files changed since
+/// `mtimes`, and returns them with the updated times. Blocking.
+fn read_transcript_updates(
+    root: &Path,
+    workspace_ids: &HashSet<String>,
+    cursors: &HashMap<DocKey, u64>,
+    mut mtimes: HashMap<PathBuf, SystemTime>,
+) -> (Vec<TranscriptUpdate>, HashMap<PathBuf, SystemTime>) {
+    let mut updates = Vec::new();
+    let Ok(workspace_dirs) = fs::read_dir(root) else {
+        return (updates, mtimes);
+    };
+    for workspace_dir in workspace_dirs.filter_map(|entry| entry.ok()) {
+        let name = workspace_dir.file_name().to_string_lossy().to_string();
+        let Some(workspace_id) = unsanitize_id(&name) else {
+            continue;
+        };
+        if !workspace_ids.contains(&workspace_id) {
+            continue;
+        }
+        let Ok(files) = fs::read_dir(workspace_dir.path()) else {
+            continue;
+        };
+        let mut changed: HashSet<String> = HashSet::new();
+        for file in files.filter_map(|entry| entry.ok()) {
+            let name = file.file_name().to_string_lossy().to_string();
+            let Some(stem) = name.strip_suffix(".jsonl") else {
+                continue;
+            };
+            let stem = stem.split('.').next().unwrap_or(stem);
+            if stem.starts_with('_') {
+                continue;
+            }
+            let Some(thread_id) = unsanitize_id(stem) else {
+                continue;
+            };
+            let modified = file.metadata().and_then(|meta| meta.modified()).ok();
+            let path = file.path();
+            if modified.is_some() && mtimes.get(&path) == modified.as_ref() {
+                continue;
+            }
+            if let Some(modified) = modified {
+                mtimes.insert(path, modified);
+            }
+            changed.insert(thread_id);
+        }
+        for thread_id in changed {
+            let key = (workspace_id.clone(), thread_id);
+            let cursor = cursors.get(&key).copied();
+            if let Ok(page) = read_journal(root, &key.0, &key.1, cursor, usize::MAX) {
+                updates.push(TranscriptUpdate {
+                    key,
+                    entries: page.entries,
+                });
+            }
+        }
+    }
+    (updates, mtimes)
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| token.chars().count() >= 2)
+        .map(|token| {
+            token
+                .chars()
+                .take(MAX_TOKEN_LEN)
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .collect()
+}
+
+fn to_millis(timestamp: i64) -> u64 {
+    let timestamp = timestamp.max(0) as u64;
+    // The app-server reports seconds; OpenCode reports milliseconds.
+    if timestamp < 10_000_000_000 {
+        timestamp * 1000
+    } else {
+        timestamp
+    }
+}
+
+fn thread_item_text(item: &ThreadItem) -> Option<String> {
+    let ThreadItem::Known(item) = item else {
+        return None;
+    };
+    let text = match item {
+        KnownThreadItem::UserMessage { content, .. } => content
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|text| text.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        KnownThreadItem::AgentMessage { text, .. } => text.clone(),
+        KnownThreadItem::Reasoning { summary, .. } => summary.join("\n"),
+        KnownThreadItem::CommandExecution {
+            command,
+            aggregated_output,
+            ..
+        } => {
+            let output: String = aggregated_output
+                .as_deref()
+                .unwrap_or_default()
+                .chars()
+                .take(MAX_OUTPUT_CHARS)
+                .collect();
+            format!("{command}\n{output}")
+        }
+        KnownThreadItem::FileChange { changes, .. } => changes
+            .iter()
+            .map(|change| change.path.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        KnownThreadItem::McpToolCall { server, tool, .. } => [server, tool]
+            .iter()
+            .filter_map(|value| value.as_deref())
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Picks the segment containing the most query terms and cuts a window around the first match.
+fn best_snippet(thread: &IndexedThread, terms: &[String]) -> (String, Option<u64>) {
+    let best = thread
+        .segments
+        .values()
+        .flatten()
+        .map(|segment| {
+            let lower = segment.text.to_lowercase();
+            let matched = terms
+                .iter()
+                .filter(|term| lower.contains(term.as_str()))
+                .count();
+            (matched, segment)
+        })
+        .filter(|(matched, _)| *matched > 0)
+        .max_by(|(a_matched, a), (b_matched, b)| {
+            a_matched
+                .cmp(b_matched)
+                .then_with(|| a.timestamp.cmp(&b.timestamp))
+        });
+    let Some((_, segment)) = best else {
+        return (thread.title.clone().unwrap_or_default(), None);
+    };
+    (snippet_around(&segment.text, terms), segment.timestamp)
+}
+
+fn snippet_around(text: &str, terms: &[String]) -> String {
+    let lower = text.to_ascii_lowercase();
+    let position = terms
+        .iter()
+        .filter_map(|term| lower.find(term.as_str()))
+        .min()
+        .unwrap_or(0);
+    let mut start = position.saturating_sub(SNIPPET_BEFORE);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (position + SNIPPET_AFTER).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    let mut snippet = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Indexes transcript entries written since the last refresh. The journals
+/// are read on the blocking pool with no lock held.
+async fn refresh_transcripts(state: &AppState, workspace_ids: HashSet<String>) {
+    let (cursors, mtimes) = {
+        let index = state.search_index.lock().await;
+        (index.transcript_cursors(), index.transcript_mtimes.clone())
+    };
+    let root = state.transcripts_dir.clone();
+    let read = tokio::task::spawn_blocking(move || {
+        read_transcript_updates(&root, &workspace_ids, &cursors, mtimes)
+    })
+    .await;
+    let Ok((updates, mtimes)) = read else {
+        return;
+    };
+    let mut index = state.search_index.lock().await;
+    index.transcript_mtimes.extend(mtimes);
+    for TranscriptUpdate { key, entries } in updates {
+        index.append_transcript(&key.0, &key.1, &entries);
+    }
+}
+
+/// Pages through `thread/list` so every thread's title is indexed. Turns
+/// come from transcripts and from threads the user resumes: resuming loads
+/// and subscribes a thread on the app-server, which the indexer must not do
+/// to idle ones.
+async fn index_codex_history(
+    state: &AppState,
+    session: &WorkspaceSession,
+) -> Result<(), CommandError> {
+    let mut cursor = None;
+    for _ in 0..MAX_THREAD_LIST_PAGES {
+        // `list_threads` indexes each page as it arrives.
+        let response = session
+            .list_threads(state, cursor, Some(THREAD_LIST_PAGE_SIZE))
+            .await?;
+        let result = response.get("result").unwrap_or(&response);
+        cursor = result
+            .get("nextCursor")
+            .and_then(|cursor| cursor.as_str())
+            .map(|cursor| cursor.to_string());
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Indexes the messages of OpenCode sessions that changed since they were
+/// last indexed. Other ACP agents have no history to list.
+async fn index_opencode_history(
+    state: &AppState,
+    session: &AcpSession,
+) -> Result<(), CommandError> {
+    let workspace_id = session.entry.id.as_str();
+    let sessions = opencode_sessions(state, workspace_id).await?;
+    let stale: Vec<String> = {
+        let index = state.search_index.lock().await;
+        sessions
+            .iter()
+            .filter(|info| {
+                let updated_at = info.updated_at.or(info.created_at).map(to_millis);
+                index.needs_history(workspace_id, &info.id, updated_at)
+            })
+            .map(|info| info.id.clone())
+            .collect()
+    };
+    for session_id in stale.into_iter().take(MAX_HISTORY_FETCHES) {
+        let result = session
+            .request("message/list", json!({ "sessionId": session_id }))
+            .await?;
+        state
+            .search_index
+            .lock()
+            .await
+            .index_opencode_messages(workspace_id, &session_id, &result);
+    }
+    Ok(())
+}
+
+/// One indexing pass: new transcript entries for every workspace, then
+/// agent history for the connected ones.
+async fn index_workspaces(state: &AppState) {
+    let workspace_ids: HashSet<String> = state.workspaces.lock().await.keys().cloned().collect();
+    refresh_transcripts(state, workspace_ids).await;
+    let sessions = state.sessions.lock().await.all();
+    for session in sessions {
+        let (workspace_id, result) = match session.kind() {
+            BackendType::Codex => {
+                let Ok(session) = session.as_any().downcast::<WorkspaceSession>() else {
+                    continue;
+                };
+                (
+                    session.entry.id.clone(),
+                    index_codex_history(state, &session).await,
+                )
+            }
+            BackendType::OpenCode => {
+                let Ok(session) = session.as_any().downcast::<AcpSession>() else {
+                    continue;
+                };
+                (
+                    session.entry.id.clone(),
+                    index_opencode_history(state, &session).await,
+                )
+            }
+            BackendType::Acp => continue,
+        };
+        if let Err(error) = result {
+            eprintln!("Failed to index history for {workspace_id}: {error}");
+        }
+    }
+}
+
+async fn save_index(state: &AppState) {
+    let Some((path, data)) = state.search_index.lock().await.take_unsaved() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+    if let Err(error) = tokio::fs::write(&path, data).await {
+        eprintln!(
+            "Failed to save the search index to {}: {error}",
+            path.display()
+        );
+    }
+}
+
+/// Keeps the index current with what the agents and transcripts hold, and
+/// saves it when it changes.
+pub(crate) fn start_search_indexer(host: SharedHost) {
+    tokio::spawn(async move {
+        loop {
+            index_workspaces(host.app_state()).await;
+            save_index(host.app_state()).await;
+            tokio::time::sleep(INDEX_INTERVAL).await;
+        }
+    });
+}
+
+pub(crate) async fn search_conversations(
+    state: &AppState,
+    query: String,
+    workspace_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    let workspace_ids: HashSet<String> = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .keys()
+            .filter(|id| workspace_id.as_ref().is_none_or(|wanted| wanted == *id))
+            .cloned()
+            .collect()
+    };
+    let limit = limit
+        .unwrap_or(DEFAULT_RESULT_LIMIT)
+        .clamp(1, MAX_RESULT_LIMIT);
+    refresh_transcripts(state, workspace_ids.clone()).await;
+    Ok(state
+        .search_index
+        .lock()
+        .await
+        .search(&query, &workspace_ids, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_transcript_updates, snippet_around, tokenize, SearchIndex, SegmentOrigin,
+        TranscriptUpdate,
+    };
+    use crate::transcripts::{TranscriptArchive, TranscriptSource};
+    use serde_json::json;
+    use std::collections::{HashMap, HashSet};
+
+    fn workspaces(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn ranks_threads_matching_all_terms_first() {
+        let mut index = SearchIndex::default();
+        index.index_codex_thread(
+            "ws_a",
+            &json!({ "result": { "thread": {
+                "id": "thr_1",
+                "preview": "Fix the migration",
+                "createdAt": 1_700_000_000,
+                "turns": [{ "items": [
+                    { "type": "agentMessage", "id": "m1", "text": "The migration now adds the index." }
+                ]}]
+            }}}),
+        );
+        index.index_codex_thread_list(
+            "ws_b",
+            &json!({ "result": { "data": [{ "id": "thr_2", "preview": "Plan the migration" }] } }),
+        );
+        index.index_opencode_messages(
+            "ws_b",
+            "ses_3",
+            &json!([{ "id": "m", "sessionId": "ses_3", "role": "assistant",
+                      "parts": [{ "type": "text", "text": "Unrelated refactor" }] }]),
+        );
+
+        let hits = index.search("fixed migration index", &workspaces(&["ws_a", "ws_b"]), 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].thread_id, "thr_1");
+        assert_eq!(hits[0].timestamp, Some(1_700_000_000_000));
+        assert!(hits[0].snippet.contains("migration now adds"));
+
+        let scoped = index.search("migration", &workspaces(&["ws_b"]), 10);
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].thread_id, "thr_2");
+    }
+
+    #[test]
+    fn reindexing_drops_stale_terms() {
+        let mut index = SearchIndex::default();
+        let thread = |text: &str| {
+            json!({ "thread": { "id": "thr_1", "turns": [{ "items": [
+                { "type": "agentMessage", "id": "m1", "text": text }
+            ]}]}})
+        };
+        index.index_codex_thread("ws", &thread("alpha"));
+        index.index_codex_thread("ws", &thread("beta"));
+        assert!(index.search("alpha", &workspaces(&["ws"]), 10).is_empty());
+        assert_eq!(index.search("beta", &workspaces(&["ws"]), 10).len(), 1);
+    }
+
+    #[test]
+    fn saved_index_loads_searchable() {
+        let dir = std::env::temp_dir().join(format!("search-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("search_index.json");
+        let mut index = SearchIndex::load(path.clone());
+        index.index_codex_thread(
+            "ws",
+            &json!({ "thread": { "id": "thr_1", "preview": "Deploy notes", "turns": [{ "items": [
+                { "type": "agentMessage", "id": "m1", "text": "Rollback checklist" }
+            ]}]}}),
+        );
+        let (saved_path, data) = index.take_unsaved().expect("unsaved changes");
+        assert!(index.take_unsaved().is_none());
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(&saved_path, data).expect("write index");
+
+        let reloaded = SearchIndex::load(path);
+        let hits = reloaded.search("rollback", &workspaces(&["ws"]), 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title.as_deref(), Some("Deploy notes"));
+        assert!(!reloaded.needs_history("ws", "thr_1", None));
+        assert!(reloaded.needs_history("ws", "thr_2", None));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn transcripts_are_indexed_past_the_cursor() {
+        let root = std::env::temp_dir().join(format!("search-{}", uuid::Uuid::new_v4()));
+        let mut archive = TranscriptArchive::new(root.clone());
+        let mut index = SearchIndex::default();
+        let prompt = |text: &str| json!({ "threadId": "thr_1", "input": [{ "type": "text", "text": text }] });
+        let refresh = |index: &mut SearchIndex| {
+            let (updates, mtimes) = read_transcript_updates(
+                &root,
+                &workspaces(&["ws"]),
+                &index.transcript_cursors(),
+                HashMap::new(),
+            );
+            index.transcript_mtimes.extend(mtimes);
+            for TranscriptUpdate { key, entries } in updates {
+                index.append_transcript(&key.0, &key.1, &entries);
+            }
+        };
+
+        archive
+            .append(
+                "ws",
+                "thr_1",
+                TranscriptSource::Client,
+                "turn/start",
+                Some(&prompt("first prompt")),
+            )
+            .expect("append");
+        refresh(&mut index);
+        archive
+            .append(
+                "ws",
+                "thr_1",
+                TranscriptSource::Client,
+                "turn/start",
+                Some(&prompt("second prompt")),
+            )
+            .expect("append");
+        refresh(&mut index);
+
+        assert_eq!(
+            index.search("first second", &workspaces(&["ws"]), 10).len(),
+            1
+        );
+        let thread = &index.threads[&("ws".to_string(), "thr_1".to_string())];
+        assert_eq!(thread.segments[&SegmentOrigin::Transcript].len(), 2);
+        assert_eq!(thread.transcript_cursor, Some(2));
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn tokenizer_and_snippet_handle_unicode() {
+        assert_eq!(tokenize("Größe: a/b_c x"), vec!["größe".to_string()]);
+        let text = format!("{}needle{}", "é".repeat(80), "ü".repeat(200));
+        let snippet = snippet_around(&text, &["needle".to_string()]);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+    }
+}
@@ -1,22 +1,50 @@
-use tauri::State;
-
+use crate::backend::SharedHost;
+use crate::rate_limits::validate_rate_limit_watch;
+use crate::remote::{apply_remote_access, validate_remote_access};
+use crate::sandbox::{find_sandbox_profile, validate_sandbox_profiles};
 use crate::state::AppState;
 use crate::storage::write_settings;
-use crate::types::AppSettings;
+use crate::types::{AppSettings, WorkspaceSettings};
+use crate::usage::validate_cost_table;
 
-#[tauri::command]
-pub(crate) async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+pub(crate) async fn get_app_settings(state: &AppState) -> Result<AppSettings, String> {
     let settings = state.app_settings.lock().await;
     Ok(settings.clone())
 }
 
-#[tauri::command]
 pub(crate) async fn update_app_settings(
+    host: &SharedHost,
     settings: AppSettings,
-    state: State<'_, AppState>,
 ) -> Result<AppSettings, String> {
+    let state = host.app_state();
+    validate_sandbox_profiles(&settings.sandbox_profiles)?;
+    validate_cost_table(&settings.cost_table)?;
+    validate_rate_limit_watch(&settings.rate_limit_watch)?;
+    validate_remote_access(&settings.remote_access)?;
+    if find_sandbox_profile(
+        &settings,
+        &WorkspaceSettings::default(),
+        &settings.default_access_mode,
+    )
+    .is_none()
+    {
+        return Err(format!(
+            "Unknown sandbox profile \"{}\".",
+            settings.default_access_mode
+        ));
+    }
     write_settings(&state.settings_path, &settings)?;
-    let mut current = state.app_settings.lock().await;
-    *current = settings.clone();
+    {
+        let mut current = state.app_settings.lock().await;
+        *current = settings.clone();
+    }
+    let sessions: Vec<_> = state.sessions.lock().await.all();
+    for session in sessions {
+        session
+            .apply_settings(&settings)
+            .await
+            .map_err(|error| error.message)?;
+    }
+    apply_remote_access(host).await;
     Ok(settings)
 }
@@ -1,39 +1,151 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+#[cfg(feature = "gui")]
 use tauri::{AppHandle, Manager};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 
+use crate::audit::AuditLog;
+use crate::backend::{AgentEvent, SessionRegistry};
+use crate::prompt_queue::PromptQueue;
+use crate::rate_limits::RateLimitWatcher;
+use crate::remote::RemoteServer;
+use crate::search::SearchIndex;
 use crate::storage::{read_settings, read_workspaces};
+use crate::thread_store::ThreadStore;
+use crate::transcripts::TranscriptArchive;
 use crate::types::{AppSettings, WorkspaceEntry};
+use crate::usage::UsageLedger;
 
 pub(crate) struct AppState {
     pub(crate) workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
-    pub(crate) sessions: Mutex<HashMap<String, Arc<crate::codex::WorkspaceSession>>>,
-    pub(crate) opencode_sessions: Mutex<HashMap<String, Arc<crate::opencode::OpenCodeSession>>>,
+    pub(crate) sessions: Mutex<SessionRegistry>,
     pub(crate) storage_path: PathBuf,
     pub(crate) settings_path: PathBuf,
     pub(crate) app_settings: Mutex<AppSettings>,
+    pub(crate) thread_store: Mutex<ThreadStore>,
+    pub(crate) transcripts_dir: PathBuf,
+    pub(crate) transcripts: Arc<Mutex<TranscriptArchive>>,
+    pub(crate) search_index: Mutex<SearchIndex>,
+    pub(crate) audit_log: Arc<Mutex<AuditLog>>,
+    pub(crate) prompt_queue: Mutex<PromptQueue>,
+    pub(crate) usage: Mutex<UsageLedger>,
+    pub(crate) rate_limits: Mutex<RateLimitWatcher>,
+    pub(crate) remote_server: Mutex<RemoteServer>,
+    /// Every agent event, for listeners outside the webview (the daemon socket).
+    pub(crate) events: broadcast::Sender<AgentEvent>,
+}
+
+const EVENT_BUFFER: usize = 1024;
+/// Must match `identifier` in `tauri.conf.json`, which names the data directory.
+const APP_IDENTIFIER: &str = "com.dimillian.codexmonitor";
+const LOCK_FILE: &str = "codex-monitor.lock";
+
+#[cfg(feature = "gui")]
+pub(crate) fn data_dir(app: &AppHandle) -> PathBuf {
+    app.path()
+        .app_data_dir()
+        .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| ".".into()))
+}
+
+/// The data directory worked out without Tauri, for the daemon and its
+/// clients. Matches what `app_data_dir` resolves to on macOS and Linux.
+#[cfg(unix)]
+pub(crate) fn default_data_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_dir = if cfg!(target_os = "macos") {
+        home?.join("Library/Application Support")
+    } else {
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home?.join(".local/share"),
+        }
+    };
+    Some(data_dir.join(APP_IDENTIFIER))
+}
+
+/// Holds the data directory for this process until it exits.
+pub(crate) struct DataDirLock {
+    _file: File,
+}
+
+/// Claims `data_dir` so the app and the daemon never load and save the same
+/// state at once; `None` while another process holds it.
+pub(crate) fn try_lock_data_dir(data_dir: &Path) -> Result<Option<DataDirLock>, String> {
+    std::fs::create_dir_all(data_dir).map_err(|error| error.to_string())?;
+    let path = data_dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(DataDirLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(error)) => {
+            Err(format!("Failed to lock {}: {error}", path.display()))
+        }
+    }
+}
+
+/// Like `try_lock_data_dir`, but another holder is an error.
+pub(crate) fn lock_data_dir(data_dir: &Path) -> Result<DataDirLock, String> {
+    try_lock_data_dir(data_dir)?.ok_or_else(|| {
+        format!(
+            "{} is in use by another Codex Monitor app or daemon.",
+            data_dir.display()
+        )
+    })
 }
 
 impl AppState {
+    #[cfg(feature = "gui")]
     pub(crate) fn load(app: &AppHandle) -> Self {
-        let data_dir = app
-            .path()
-            .app_data_dir()
-            .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+        Self::load_from(&data_dir(app))
+    }
+
+    /// Loads the state kept under `data_dir`, without a running app.
+    pub(crate) fn load_from(data_dir: &Path) -> Self {
         let storage_path = data_dir.join("workspaces.json");
         let settings_path = data_dir.join("settings.json");
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        let transcripts_dir = data_dir.join("transcripts");
         Self {
             workspaces: Mutex::new(workspaces),
-            sessions: Mutex::new(HashMap::new()),
-            opencode_sessions: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(SessionRegistry::default()),
             storage_path,
             settings_path,
             app_settings: Mutex::new(app_settings),
+            thread_store: Mutex::new(ThreadStore::default()),
+            transcripts: Arc::new(Mutex::new(TranscriptArchive::new(transcripts_dir.clone()))),
+            transcripts_dir,
+            search_index: Mutex::new(SearchIndex::load(data_dir.join("search_index.json"))),
+            audit_log: Arc::new(Mutex::new(AuditLog::new(data_dir.join("audit")))),
+            prompt_queue: Mutex::new(PromptQueue::load(data_dir.join("prompt_queue.json"))),
+            usage: Mutex::new(UsageLedger::new(data_dir.join("usage.jsonl"))),
+            rate_limits: Mutex::new(RateLimitWatcher::default()),
+            remote_server: Mutex::new(RemoteServer::default()),
+            events: broadcast::channel(EVENT_BUFFER).0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{lock_data_dir, try_lock_data_dir};
+
+    #[test]
+    fn data_dir_lock_is_held_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("state-{}", uuid::Uuid::new_v4()));
+        let lock = lock_data_dir(&dir).unwrap();
+        assert!(matches!(try_lock_data_dir(&dir), Ok(None)));
+        assert!(lock_data_dir(&dir).is_err_and(|error| error.contains("in use by another")));
+        drop(lock);
+        assert!(lock_data_dir(&dir).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
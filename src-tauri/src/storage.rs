@@ -36,5 +36,49 @@ pub(crate) fn write_settings(path: &PathBuf, settings: &AppSettings) -> Result<(
         std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
     let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
-    std::fs::write(path, data).map_err(|e| e.to_string())
+    write_private(path, data.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Settings hold remote access tokens, so only the owner may read them.
+#[cfg(unix)]
+fn write_private(path: &PathBuf, data: &[u8]) -> std::io::Result<()> {
+    use std::fs::{OpenOptions, Permissions};
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // `mode` only applies to new files; tighten one written before.
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &PathBuf, data: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, data)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::write_settings;
+    use crate::types::AppSettings;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn settings_are_readable_by_the_owner_only() {
+        let dir = std::env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("settings.json");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_settings(&path, &AppSettings::default()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,126 @@
+//! A small public surface over the session layer for the integration tests in
+//! `tests/`, which drive real sessions against the scripted mock agents. Not
+//! part of the app's API.
+
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+
+use crate::backend::{get_backend, respond_to_request, AgentEvent, MessageRequest, SharedHost};
+use crate::state::AppState;
+use crate::types::WorkspaceEntry;
+use crate::workspaces::connect_saved_workspace;
+
+/// One workspace over a scratch data directory, with no window: events are
+/// read from `AppState::events` instead of reaching a webview.
+pub struct TestSession {
+    host: SharedHost,
+    workspace_id: String,
+    events: broadcast::Receiver<AgentEvent>,
+    dir: PathBuf,
+}
+
+impl TestSession {
+    /// Saves `workspace` (a `WorkspaceEntry` as JSON) without connecting it.
+    pub async fn new(workspace: Value) -> Result<Self, String> {
+        let entry: WorkspaceEntry =
+            serde_json::from_value(workspace).map_err(|e| format!("bad workspace: {e}"))?;
+        let dir = std::env::temp_dir().join(format!("codex-monitor-test-{}", uuid::Uuid::new_v4()));
+        let state = AppState::load_from(&dir);
+        let events = state.events.subscribe();
+        let workspace_id = entry.id.clone();
+        state
+            .workspaces
+            .lock()
+            .await
+            .insert(workspace_id.clone(), entry);
+        let host: SharedHost = Arc::new(state);
+        Ok(Self {
+            host,
+            workspace_id,
+            events,
+            dir,
+        })
+    }
+
+    /// Connects the workspace the way `connect_workspace` does.
+    pub async fn connect(&self) -> Result<(), String> {
+        connect_saved_workspace(&self.workspace_id, &self.host).await
+    }
+
+    pub async fn is_connected(&self) -> bool {
+        get_backend(self.host.app_state(), &self.workspace_id)
+            .await
+            .is_ok()
+    }
+
+    pub async fn start_thread(&self) -> Result<Value, String> {
+        get_backend(self.host.app_state(), &self.workspace_id)
+            .await?
+            .start_thread()
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    pub async fn send_message(&self, thread_id: &str, text: &str) -> Result<Value, String> {
+        let state = self.host.app_state();
+        let request = MessageRequest {
+            thread_id: thread_id.to_string(),
+            text: text.to_string(),
+            model: None,
+            effort: None,
+            access_mode: None,
+            attachments: Vec::new(),
+        };
+        get_backend(state, &self.workspace_id)
+            .await?
+            .send_message(state, request)
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    /// Answers an agent request the way `respond_to_server_request` does.
+    pub async fn respond(&self, request_id: u64, result: Value) -> Result<(), String> {
+        respond_to_request(
+            self.host.app_state(),
+            &self.workspace_id,
+            request_id,
+            result,
+            None,
+            None,
+        )
+        .await
+        .map_err(|error| error.to_string())
+    }
+
+    /// The next event the session published, as the frontend receives it.
+    pub async fn next_event(&mut self) -> Option<Value> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) => return serde_json::to_value(event).ok(),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// The thread as `get_thread_snapshot` reports it.
+    pub async fn thread_snapshot(&self, thread_id: &str) -> Option<Value> {
+        let snapshot = self
+            .host
+            .app_state()
+            .thread_store
+            .lock()
+            .await
+            .snapshot(&self.workspace_id, thread_id)?;
+        serde_json::to_value(snapshot).ok()
+    }
+}
+
+impl Drop for TestSession {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,375 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::protocol::{
+    ItemDelta, KnownNotification, KnownThreadItem, ThreadItem, TurnPlanUpdated, TurnStatus,
+};
+use crate::state::AppState;
+
+/// Live state of one thread, rebuilt from app-server notifications.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ThreadSnapshot {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) items: Vec<ThreadItem>,
+    pub(crate) active_turn_id: Option<String>,
+    pub(crate) turn_status: Option<TurnStatus>,
+    pub(crate) plan: Option<TurnPlanUpdated>,
+    pub(crate) diff: Option<String>,
+    pub(crate) token_usage: Option<Value>,
+    pub(crate) last_error: Option<String>,
+    #[ts(type = "number")]
+    pub(crate) updated_at: u64,
+}
+
+impl ThreadSnapshot {
+    fn new(workspace_id: &str, thread_id: &str) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            items: Vec::new(),
+            active_turn_id: None,
+            turn_status: None,
+            plan: None,
+            diff: None,
+            token_usage: None,
+            last_error: None,
+            updated_at: now_ms(),
+        }
+    }
+
+    fn upsert_item(&mut self, item: ThreadItem) {
+        let existing = item
+            .id()
+            .and_then(|id| self.items.iter().position(|entry| entry.id() == Some(id)));
+        match existing {
+            Some(index) => self.items[index] = item,
+            None => self.items.push(item),
+        }
+    }
+
+    fn item_mut(&mut self, item_id: &str) -> Option<&mut KnownThreadItem> {
+        self.items.iter_mut().find_map(|entry| match entry {
+            ThreadItem::Known(item) if entry_id(item) == item_id => Some(item),
+            _ => None,
+        })
+    }
+
+    fn append_agent_delta(&mut self, delta: &ItemDelta) {
+        if let Some(KnownThreadItem::AgentMessage { text, .. }) = self.item_mut(&delta.item_id) {
+            text.push_str(&delta.delta);
+            return;
+        }
+        self.upsert_item(ThreadItem::Known(KnownThreadItem::AgentMessage {
+            id: delta.item_id.clone(),
+            text: delta.delta.clone(),
+        }));
+    }
+
+    fn append_reasoning_delta(&mut self, delta: &ItemDelta, summary_part: bool) {
+        if self.item_mut(&delta.item_id).is_none() {
+            self.upsert_item(ThreadItem::Known(KnownThreadItem::Reasoning {
+                id: delta.item_id.clone(),
+                summary: Vec::new(),
+                content: Vec::new(),
+            }));
+        }
+        if let Some(KnownThreadItem::Reasoning {
+            summary, content, ..
+        }) = self.item_mut(&delta.item_id)
+        {
+            let parts = if summary_part { summary } else { content };
+            match parts.last_mut() {
+                Some(last) => last.push_str(&delta.delta),
+                None => parts.push(delta.delta.clone()),
+            }
+        }
+    }
+
+    fn append_command_output(&mut self, delta: &ItemDelta) {
+        if let Some(KnownThreadItem::CommandExecution {
+            aggregated_output, ..
+        }) = self.item_mut(&delta.item_id)
+        {
+            aggregated_output
+                .get_or_insert_with(String::new)
+                .push_str(&delta.delta);
+        }
+    }
+}
+
+fn entry_id(item: &KnownThreadItem) -> &str {
+    match item {
+        KnownThreadItem::UserMessage { id, .. }
+        | KnownThreadItem::AgentMessage { id, .. }
+        | KnownThreadItem::Reasoning { id, .. }
+        | KnownThreadItem::CommandExecution { id, .. }
+        | KnownThreadItem::FileChange { id, .. }
+        | KnownThreadItem::McpToolCall { id, .. } => id,
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Per-workspace thread state, keyed by workspace id and then thread id.
+#[derive(Default)]
+pub(crate) struct ThreadStore {
+    workspaces: HashMap<String, HashMap<String, ThreadSnapshot>>,
+}
+
+impl ThreadStore {
+    fn thread_mut(&mut self, workspace_id: &str, thread_id: &str) -> &mut ThreadSnapshot {
+        let thread = self
+            .workspaces
+            .entry(workspace_id.to_string())
+            .or_default()
+            .entry(thread_id.to_string())
+            .or_insert_with(|| ThreadSnapshot::new(workspace_id, thread_id));
+        thread.updated_at = now_ms();
+        thread
+    }
+
+    pub(crate) fn apply(&mut self, workspace_id: &str, notification: &KnownNotification) {
+        match notification {
+            KnownNotification::ThreadStarted(event) => {
+                self.thread_mut(workspace_id, &event.thread.id);
+            }
+            KnownNotification::TurnStarted(event) => {
+                let Some(thread_id) = event.turn.thread_id.as_deref() else {
+                    return;
+                };
+                let thread = self.thread_mut(workspace_id, thread_id);
+                thread.active_turn_id = Some(event.turn.id.clone());
+                thread.turn_status = Some(TurnStatus::InProgress);
+                thread.plan = None;
+                thread.last_error = None;
+            }
+            KnownNotification::TurnCompleted(event) => {
+                let Some(thread_id) = event.turn.thread_id.as_deref() else {
+                    return;
+                };
+                let thread = self.thread_mut(workspace_id, thread_id);
+                thread.active_turn_id = None;
+                thread.turn_status = Some(event.turn.status.unwrap_or(TurnStatus::Completed));
+            }
+            KnownNotification::ItemStarted(event) | KnownNotification::ItemCompleted(event) => {
+                self.thread_mut(workspace_id, &event.thread_id)
+                    .upsert_item(event.item.clone());
+            }
+            KnownNotification::AgentMessageDelta(delta) => {
+                self.thread_mut(workspace_id, &delta.thread_id)
+                    .append_agent_delta(delta);
+            }
+            KnownNotification::ReasoningSummaryTextDelta(delta) => {
+                self.thread_mut(workspace_id, &delta.thread_id)
+                    .append_reasoning_delta(delta, true);
+            }
+            KnownNotification::ReasoningTextDelta(delta) => {
+                self.thread_mut(workspace_id, &delta.thread_id)
+                    .append_reasoning_delta(delta, false);
+            }
+            KnownNotification::CommandExecutionOutputDelta(delta) => {
+                self.thread_mut(workspace_id, &delta.thread_id)
+                    .append_command_output(delta);
+            }
+            // File change progress is superseded by the completed item's `changes`.
+            KnownNotification::FileChangeOutputDelta(_) => {}
+            KnownNotification::TurnPlanUpdated(update) => {
+                self.thread_mut(workspace_id, &update.thread_id).plan = Some(update.clone());
+            }
+            KnownNotification::TurnDiffUpdated(update) => {
+                self.thread_mut(workspace_id, &update.thread_id).diff = Some(update.diff.clone());
+            }
+            KnownNotification::ThreadTokenUsageUpdated(update) => {
+                self.thread_mut(workspace_id, &update.thread_id).token_usage =
+                    Some(update.token_usage.clone());
+            }
+            KnownNotification::AccountRateLimitsUpdated(_) => {}
+            KnownNotification::Error(error) => {
+                let Some(thread_id) = error.thread_id.as_deref() else {
+                    return;
+                };
+                let thread = self.thread_mut(workspace_id, thread_id);
+                thread.last_error = Some(error.error.message.clone());
+                if !error.will_retry {
+                    thread.active_turn_id = None;
+                    thread.turn_status = Some(TurnStatus::Failed);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn snapshot(&self, workspace_id: &str, thread_id: &str) -> Option<ThreadSnapshot> {
+        self.workspaces
+            .get(workspace_id)
+            .and_then(|threads| threads.get(thread_id))
+            .cloned()
+    }
+
+    /// Paths touched by a file change item, for matching approval rules.
+    pub(crate) fn file_change_paths(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        item_id: &str,
+    ) -> Vec<String> {
+        let Some(thread) = self
+            .workspaces
+            .get(workspace_id)
+            .and_then(|threads| threads.get(thread_id))
+        else {
+            return Vec::new();
+        };
+        thread
+            .items
+            .iter()
+            .find_map(|entry| match entry {
+                ThreadItem::Known(KnownThreadItem::FileChange { id, changes, .. })
+                    if id == item_id =>
+                {
+                    Some(changes.iter().map(|change| change.path.clone()).collect())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn remove_thread(&mut self, workspace_id: &str, thread_id: &str) {
+        if let Some(threads) = self.workspaces.get_mut(workspace_id) {
+            threads.remove(thread_id);
+        }
+    }
+
+    pub(crate) fn remove_workspace(&mut self, workspace_id: &str) {
+        self.workspaces.remove(workspace_id);
+    }
+}
+
+pub(crate) async fn get_thread_snapshot(
+    state: &AppState,
+    workspace_id: String,
+    thread_id: String,
+) -> Result<Option<ThreadSnapshot>, String> {
+    let store = state.thread_store.lock().await;
+    Ok(store.snapshot(&workspace_id, &thread_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadStore;
+    use crate::protocol::{KnownNotification, KnownThreadItem, ThreadItem, TurnStatus};
+    use serde_json::{json, Value};
+
+    fn notification(value: Value) -> KnownNotification {
+        serde_json::from_value(value).expect("known notification")
+    }
+
+    #[test]
+    fn deltas_accumulate_into_items() {
+        let mut store = ThreadStore::default();
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "turn/started",
+                "params": { "turn": { "id": "turn_1", "threadId": "thr_1" } }
+            })),
+        );
+        for delta in ["Hel", "lo"] {
+            store.apply(
+                "ws",
+                &notification(json!({
+                    "method": "item/agentMessage/delta",
+                    "params": { "threadId": "thr_1", "itemId": "msg_1", "delta": delta }
+                })),
+            );
+        }
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "item/reasoning/summaryTextDelta",
+                "params": { "threadId": "thr_1", "itemId": "rs_1", "delta": "Plan" }
+            })),
+        );
+
+        let snapshot = store.snapshot("ws", "thr_1").expect("snapshot");
+        assert_eq!(snapshot.active_turn_id.as_deref(), Some("turn_1"));
+        assert_eq!(snapshot.turn_status, Some(TurnStatus::InProgress));
+        assert!(matches!(
+            &snapshot.items[0],
+            ThreadItem::Known(KnownThreadItem::AgentMessage { text, .. }) if text == "Hello"
+        ));
+        assert!(matches!(
+            &snapshot.items[1],
+            ThreadItem::Known(KnownThreadItem::Reasoning { summary, .. }) if summary == &["Plan"]
+        ));
+    }
+
+    #[test]
+    fn completed_items_replace_streamed_ones_and_turns_finish() {
+        let mut store = ThreadStore::default();
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "item/started",
+                "params": {
+                    "threadId": "thr_1",
+                    "item": { "type": "commandExecution", "id": "cmd_1", "command": "ls" }
+                }
+            })),
+        );
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "item/commandExecution/outputDelta",
+                "params": { "threadId": "thr_1", "itemId": "cmd_1", "delta": "a.txt\n" }
+            })),
+        );
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "item/completed",
+                "params": {
+                    "threadId": "thr_1",
+                    "item": {
+                        "type": "commandExecution",
+                        "id": "cmd_1",
+                        "command": "ls",
+                        "aggregatedOutput": "a.txt\nb.txt\n",
+                        "exitCode": 0
+                    }
+                }
+            })),
+        );
+        store.apply(
+            "ws",
+            &notification(json!({
+                "method": "turn/completed",
+                "params": { "turn": { "id": "turn_1", "threadId": "thr_1", "status": "interrupted" } }
+            })),
+        );
+
+        let snapshot = store.snapshot("ws", "thr_1").expect("snapshot");
+        assert_eq!(snapshot.items.len(), 1);
+        assert!(matches!(
+            &snapshot.items[0],
+            ThreadItem::Known(KnownThreadItem::CommandExecution { aggregated_output: Some(output), .. })
+                if output == "a.txt\nb.txt\n"
+        ));
+        assert_eq!(snapshot.active_turn_id, None);
+        assert_eq!(snapshot.turn_status, Some(TurnStatus::Interrupted));
+
+        store.remove_thread("ws", "thr_1");
+        assert!(store.snapshot("ws", "thr_1").is_none());
+    }
+}
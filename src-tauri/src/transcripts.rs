@@ -0,0 +1,513 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::state::AppState;
+
+/// Size at which the active journal is rotated into a numbered segment.
+const SEGMENT_MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// Rotated segments kept per thread; older ones are deleted.
+const MAX_ROTATED_SEGMENTS: usize = 8;
+/// Journal for events that do not belong to a thread (rate limits, approvals, ...).
+const WORKSPACE_JOURNAL: &str = "_workspace";
+/// Journals kept open at once; the least recently written one is closed first.
+const MAX_OPEN_JOURNALS: usize = 32;
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum TranscriptSource {
+    /// Sent by CodexMonitor to the agent.
+    Client,
+    /// Received from the agent.
+    Server,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TranscriptEntry {
+    #[ts(type = "number")]
+    pub(crate) seq: u64,
+    #[ts(type = "number")]
+    pub(crate) timestamp: u64,
+    pub(crate) source: TranscriptSource,
+    pub(crate) method: String,
+    #[serde(default)]
+    pub(crate) params: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TranscriptPage {
+    pub(crate) entries: Vec<TranscriptEntry>,
+    /// Pass back as `cursor` to read the next page; `null` once caught up.
+    #[ts(type = "number | null")]
+    pub(crate) next_cursor: Option<u64>,
+}
+
+struct Journal {
+    file: File,
+    bytes: u64,
+    next_seq: u64,
+    /// `TranscriptArchive::writes` at the last append, for closing idle journals.
+    last_write: u64,
+}
+
+/// Append-only JSONL journals under `<app data>/transcripts/<workspace>/<thread>.jsonl`.
+///
+/// When the active file passes the segment limit it is renamed to
+/// `<thread>.<n>.jsonl`; `seq` keeps increasing across segments so paging cursors
+/// stay valid after rotation. File access is blocking; callers go through
+/// `spawn_blocking` (see [`record_transcript`]).
+pub(crate) struct TranscriptArchive {
+    root: PathBuf,
+    segment_max_bytes: u64,
+    journals: HashMap<(String, String), Journal>,
+    writes: u64,
+}
+
+impl TranscriptArchive {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            segment_max_bytes: SEGMENT_MAX_BYTES,
+            journals: HashMap::new(),
+            writes: 0,
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn thread_dir(&self, workspace_id: &str) -> PathBuf {
+        self.root.join(sanitize_id(workspace_id))
+    }
+
+    pub(crate) fn append(
+        &mut self,
+        workspace_id: &str,
+        thread_id: &str,
+        source: TranscriptSource,
+        method: &str,
+        params: Option<&Value>,
+    ) -> Result<(), String> {
+        let dir = self.thread_dir(workspace_id);
+        let stem = sanitize_id(thread_id);
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        if !self.journals.contains_key(&key) {
+            if self.journals.len() >= MAX_OPEN_JOURNALS {
+                self.close_idle_journal();
+            }
+            let journal = open_journal(&dir, &stem)?;
+            self.journals.insert(key.clone(), journal);
+        }
+        self.writes += 1;
+        let writes = self.writes;
+        let Some(journal) = self.journals.get_mut(&key) else {
+            return Ok(());
+        };
+        journal.last_write = writes;
+
+        if journal.bytes >= self.segment_max_bytes {
+            rotate(&dir, &stem)?;
+            let next_seq = journal.next_seq;
+            *journal = open_journal(&dir, &stem)?;
+            journal.next_seq = next_seq;
+        }
+
+        let entry = TranscriptEntry {
+            seq: journal.next_seq,
+            timestamp: now_ms(),
+            source,
+            method: method.to_string(),
+            params: params.cloned(),
+        };
+        let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        journal
+            .file
+            .write_all(line.as_bytes())
+            .map_err(|e| e.to_string())?;
+        journal.bytes += line.len() as u64;
+        journal.next_seq += 1;
+        Ok(())
+    }
+
+    /// Closes the journal written least recently; it reopens on the next append.
+    fn close_idle_journal(&mut self) {
+        let idle = self
+            .journals
+            .iter()
+            .min_by_key(|(_, journal)| journal.last_write)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = idle {
+            self.journals.remove(&key);
+        }
+    }
+}
+
+/// Reads up to `limit` entries with `seq` greater than `cursor`, oldest
+/// first. Journals are append-only, so readers need no lock on the archive;
+/// a line still being written is skipped until the next read.
+pub(crate) fn read_journal(
+    root: &Path,
+    workspace_id: &str,
+    thread_id: &str,
+    cursor: Option<u64>,
+    limit: usize,
+) -> Result<TranscriptPage, String> {
+    let dir = root.join(sanitize_id(workspace_id));
+    let stem = sanitize_id(thread_id);
+    let mut files: Vec<PathBuf> = rotated_segments(&dir, &stem)?
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    files.push(active_path(&dir, &stem));
+
+    let mut entries = Vec::new();
+    let mut has_more = false;
+    'files: for path in files {
+        let Ok(data) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) else {
+                continue;
+            };
+            if cursor.is_some_and(|cursor| entry.seq <= cursor) {
+                continue;
+            }
+            if entries.len() == limit {
+                has_more = true;
+                break 'files;
+            }
+            entries.push(entry);
+        }
+    }
+    let next_cursor = if has_more {
+        entries.last().map(|entry| entry.seq)
+    } else {
+        None
+    };
+    Ok(TranscriptPage {
+        entries,
+        next_cursor,
+    })
+}
+
+/// A file name for `id`: ASCII letters, digits, `-` and `_` are kept and
+/// every other byte is percent-encoded, so distinct ids never share a file
+/// (and names never contain the `.` that separates rotated segments).
+pub(crate) fn sanitize_id(id: &str) -> String {
+    if id.is_empty() {
+        return "%".to_string();
+    }
+    let mut name = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    name
+}
+
+/// Reverses [`sanitize_id`]; `None` for names it cannot have produced.
+pub(crate) fn unsanitize_id(name: &str) -> Option<String> {
+    if name == "%" {
+        return Some(String::new());
+    }
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn active_path(dir: &Path, stem: &str) -> PathBuf {
+    dir.join(format!("{stem}.jsonl"))
+}
+
+/// Rotated segments for `stem`, oldest first.
+fn rotated_segments(dir: &Path, stem: &str) -> Result<Vec<(u64, PathBuf)>, String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{stem}.");
+    let mut segments: Vec<(u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let index = name
+                .strip_prefix(&prefix)?
+                .strip_suffix(".jsonl")?
+                .parse::<u64>()
+                .ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    segments.sort_by_key(|(index, _)| *index);
+    Ok(segments)
+}
+
+fn rotate(dir: &Path, stem: &str) -> Result<(), String> {
+    let mut segments = rotated_segments(dir, stem)?;
+    let next_index = segments.last().map(|(index, _)| index + 1).unwrap_or(1);
+    let rotated = dir.join(format!("{stem}.{next_index}.jsonl"));
+    fs::rename(active_path(dir, stem), &rotated).map_err(|e| e.to_string())?;
+    segments.push((next_index, rotated));
+    while segments.len() > MAX_ROTATED_SEGMENTS {
+        let (_, oldest) = segments.remove(0);
+        let _ = fs::remove_file(oldest);
+    }
+    Ok(())
+}
+
+fn open_journal(dir: &Path, stem: &str) -> Result<Journal, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = active_path(dir, stem);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    let bytes = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+    let mut candidates = vec![path];
+    candidates.extend(
+        rotated_segments(dir, stem)?
+            .into_iter()
+            .rev()
+            .map(|(_, path)| path),
+    );
+    let next_seq = candidates
+        .iter()
+        .find_map(|path| last_seq(path))
+        .map(|seq| seq + 1)
+        .unwrap_or(1);
+    Ok(Journal {
+        file,
+        bytes,
+        next_seq,
+        last_write: 0,
+    })
+}
+
+fn last_seq(path: &Path) -> Option<u64> {
+    let data = fs::read_to_string(path).ok()?;
+    data.lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<TranscriptEntry>(line).ok())
+        .map(|entry| entry.seq)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Picks the thread (Codex) or session (ACP) a message belongs to.
+pub(crate) fn transcript_thread_id(params: Option<&Value>) -> Option<String> {
+    let params = params?;
+    let direct = ["threadId", "thread_id", "sessionId", "session_id"]
+        .iter()
+        .find_map(|key| params.get(*key).and_then(|value| value.as_str()));
+    let nested = || {
+        params
+            .get("turn")
+            .and_then(|turn| turn.get("threadId"))
+            .or_else(|| params.get("thread").and_then(|thread| thread.get("id")))
+            .and_then(|value| value.as_str())
+    };
+    direct.or_else(nested).map(|id| id.to_string())
+}
+
+/// Appends one message to the matching journal on the blocking pool; failures
+/// are logged, not surfaced.
+pub(crate) async fn record_transcript(
+    state: &AppState,
+    workspace_id: &str,
+    source: TranscriptSource,
+    method: &str,
+    params: Option<&Value>,
+) {
+    let thread_id = transcript_thread_id(params).unwrap_or_else(|| WORKSPACE_JOURNAL.to_string());
+    let archive = Arc::clone(&state.transcripts);
+    let (workspace_id, method, params) = (
+        workspace_id.to_string(),
+        method.to_string(),
+        params.cloned(),
+    );
+    let write = tokio::task::spawn_blocking(move || {
+        let mut archive = archive.blocking_lock();
+        if let Err(error) =
+            archive.append(&workspace_id, &thread_id, source, &method, params.as_ref())
+        {
+            eprintln!(
+                "Failed to write transcript for {workspace_id}/{thread_id} under {}: {error}",
+                archive.root().display()
+            );
+        }
+    });
+    let _ = write.await;
+}
+
+pub(crate) async fn read_transcript(
+    state: &AppState,
+    workspace_id: String,
+    thread_id: String,
+    cursor: Option<u64>,
+    limit: Option<usize>,
+) -> Result<TranscriptPage, String> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let root = state.transcripts_dir.clone();
+    tokio::task::spawn_blocking(move || {
+        read_journal(&root, &workspace_id, &thread_id, cursor, limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_journal, sanitize_id, transcript_thread_id, unsanitize_id, TranscriptArchive,
+        TranscriptSource, MAX_OPEN_JOURNALS,
+    };
+    use serde_json::json;
+
+    fn temp_archive() -> (TranscriptArchive, std::path::PathBuf) {
+        let root = std::env::temp_dir().join(format!("transcripts-{}", uuid::Uuid::new_v4()));
+        let mut archive = TranscriptArchive::new(root.clone());
+        archive.segment_max_bytes = 256;
+        (archive, root)
+    }
+
+    #[test]
+    fn pages_follow_sequence_across_rotation() {
+        let (mut archive, root) = temp_archive();
+        let padding = "x".repeat(100);
+        for index in 0..5 {
+            archive
+                .append(
+                    "ws",
+                    "thr_1",
+                    TranscriptSource::Server,
+                    "item/agentMessage/delta",
+                    Some(&json!({ "index": index, "delta": padding })),
+                )
+                .expect("append");
+        }
+        let segments = std::fs::read_dir(root.join("ws")).expect("dir").count();
+        assert!(segments > 1, "expected rotation, found {segments} file(s)");
+
+        let first = read_journal(&root, "ws", "thr_1", None, 2).expect("read");
+        let seqs: Vec<u64> = first.entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(first.next_cursor, Some(2));
+
+        let rest = read_journal(&root, "ws", "thr_1", first.next_cursor, 10).expect("read");
+        let seqs: Vec<u64> = rest.entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, vec![3, 4, 5]);
+        assert_eq!(rest.next_cursor, None);
+
+        // A fresh archive resumes numbering from disk.
+        let mut reopened = TranscriptArchive::new(root.clone());
+        reopened
+            .append("ws", "thr_1", TranscriptSource::Client, "turn/start", None)
+            .expect("append");
+        let tail = read_journal(&root, "ws", "thr_1", Some(5), 10).expect("read");
+        assert_eq!(tail.entries[0].seq, 6);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn thread_id_is_found_in_common_shapes() {
+        assert_eq!(
+            transcript_thread_id(Some(&json!({ "threadId": "thr_1" }))).as_deref(),
+            Some("thr_1")
+        );
+        assert_eq!(
+            transcript_thread_id(Some(&json!({ "turn": { "id": "t", "threadId": "thr_2" } })))
+                .as_deref(),
+            Some("thr_2")
+        );
+        assert_eq!(
+            transcript_thread_id(Some(&json!({ "sessionId": "ses_3", "update": {} }))).as_deref(),
+            Some("ses_3")
+        );
+        assert_eq!(
+            transcript_thread_id(Some(&json!({ "rateLimits": {} }))),
+            None
+        );
+    }
+
+    #[test]
+    fn ids_map_to_distinct_file_names() {
+        assert_eq!(sanitize_id("../etc"), "%2E%2E%2Fetc");
+        assert_ne!(sanitize_id("a.b"), sanitize_id("a_b"));
+        assert_eq!(sanitize_id("thr_1-x"), "thr_1-x");
+        for id in ["a.b", "a_b", "a%2Eb", "", "ünï/code"] {
+            assert_eq!(unsanitize_id(&sanitize_id(id)).as_deref(), Some(id));
+        }
+        assert_eq!(unsanitize_id("%zz"), None);
+    }
+
+    #[test]
+    fn idle_journals_are_closed() {
+        let (mut archive, root) = temp_archive();
+        for index in 0..=MAX_OPEN_JOURNALS {
+            let thread_id = format!("thr_{index}");
+            archive
+                .append(
+                    "ws",
+                    &thread_id,
+                    TranscriptSource::Server,
+                    "turn/started",
+                    None,
+                )
+                .expect("append");
+        }
+        assert_eq!(archive.journals.len(), MAX_OPEN_JOURNALS);
+        assert!(!archive
+            .journals
+            .contains_key(&("ws".to_string(), "thr_0".to_string())));
+        // A closed journal reopens and keeps numbering.
+        archive
+            .append(
+                "ws",
+                "thr_0",
+                TranscriptSource::Server,
+                "turn/completed",
+                None,
+            )
+            .expect("append");
+        let page = read_journal(&root, "ws", "thr_0", None, 10).expect("read");
+        let seqs: Vec<u64> = page.entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, vec![1, 2]);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
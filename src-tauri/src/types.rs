@@ -1,4 +1,10 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::protocol::{ApprovalDecision, ApprovalPolicy};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct GitFileStatus {
@@ -60,16 +66,41 @@ pub(crate) struct BranchInfo {
 }
 
 /// Backend type for a workspace - determines which CLI to use
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, TS)]
 #[serde(rename_all = "lowercase")]
+#[ts(export)]
 pub(crate) enum BackendType {
+    #[default]
     Codex,
     OpenCode,
+    /// Any CLI speaking the Agent Client Protocol over stdio, as configured
+    /// by the workspace's [`AcpAgentConfig`].
+    Acp,
 }
 
-impl Default for BackendType {
-    fn default() -> Self {
-        BackendType::Codex
+/// Command line for an ACP-compatible agent (e.g. `claude-code-acp`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct AcpAgentConfig {
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    pub(crate) command: String,
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+impl AcpAgentConfig {
+    /// The configured name, or the command's file name.
+    pub(crate) fn display_name(&self) -> String {
+        if let Some(name) = self.name.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+            return name.to_string();
+        }
+        let command = self.command.trim();
+        std::path::Path::new(command)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| command.to_string())
     }
 }
 
@@ -79,10 +110,12 @@ pub(crate) struct WorkspaceEntry {
     pub(crate) name: String,
     pub(crate) path: String,
     pub(crate) codex_bin: Option<String>,
-    #[serde(default)]
+    #[serde(default, alias = "opencodeBin")]
     pub(crate) opencode_bin: Option<String>,
     #[serde(default)]
     pub(crate) backend: BackendType,
+    #[serde(default, rename = "acpAgent")]
+    pub(crate) acp_agent: Option<AcpAgentConfig>,
     #[serde(default)]
     pub(crate) kind: WorkspaceKind,
     #[serde(default, rename = "parentId")]
@@ -104,6 +137,8 @@ pub(crate) struct WorkspaceInfo {
     pub(crate) opencode_bin: Option<String>,
     #[serde(default)]
     pub(crate) backend: BackendType,
+    #[serde(default, rename = "acpAgent")]
+    pub(crate) acp_agent: Option<AcpAgentConfig>,
     #[serde(default)]
     pub(crate) kind: WorkspaceKind,
     #[serde(default, rename = "parentId")]
@@ -114,19 +149,14 @@ pub(crate) struct WorkspaceInfo {
     pub(crate) settings: WorkspaceSettings,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum WorkspaceKind {
+    #[default]
     Main,
     Worktree,
 }
 
-impl Default for WorkspaceKind {
-    fn default() -> Self {
-        WorkspaceKind::Main
-    }
-}
-
 impl WorkspaceKind {
     pub(crate) fn is_worktree(&self) -> bool {
         matches!(self, WorkspaceKind::Worktree)
@@ -144,6 +174,75 @@ pub(crate) struct WorkspaceSettings {
     pub(crate) sidebar_collapsed: bool,
     #[serde(default, rename = "sortOrder")]
     pub(crate) sort_order: Option<u32>,
+    #[serde(default, rename = "approvalRules")]
+    pub(crate) approval_rules: Vec<ApprovalRule>,
+    /// Replace same-named app profiles, or add new ones, for this workspace.
+    #[serde(default, rename = "sandboxProfiles")]
+    pub(crate) sandbox_profiles: Vec<SandboxProfile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum ApprovalRuleTarget {
+    #[default]
+    Any,
+    Command,
+    FileChange,
+}
+
+/// Answers matching approval requests without asking. Every condition that is
+/// set must hold; a rule with none matches every request of its `target`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ApprovalRule {
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    pub(crate) decision: ApprovalDecision,
+    #[serde(default)]
+    pub(crate) target: ApprovalRuleTarget,
+    /// Whole words the command must start with, e.g. `git status`.
+    #[serde(default)]
+    pub(crate) command_prefix: Option<String>,
+    /// Glob over changed paths. Relative patterns see paths relative to the
+    /// workspace; absolute ones see the full path.
+    #[serde(default)]
+    pub(crate) path_glob: Option<String>,
+    /// Directory the request must run in (or below), relative to the workspace.
+    #[serde(default)]
+    pub(crate) cwd: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum SandboxMode {
+    ReadOnly,
+    WorkspaceWrite,
+    DangerFullAccess,
+}
+
+/// A named sandbox a message can run under, selected by `accessMode`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct SandboxProfile {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) label: Option<String>,
+    pub(crate) mode: SandboxMode,
+    /// Writable in addition to the workspace; relative paths resolve against it.
+    #[serde(default)]
+    pub(crate) writable_roots: Vec<String>,
+    #[serde(default = "default_true")]
+    pub(crate) network_access: bool,
+    pub(crate) approval_policy: ApprovalPolicy,
+    /// File changes touching these paths, and commands run inside them, are
+    /// declined without asking. Workspace-write profiles with the `untrusted`
+    /// approval policy only, since the others skip approval for sandboxed work.
+    #[serde(default)]
+    pub(crate) excluded_paths: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -154,18 +253,210 @@ pub(crate) struct AppSettings {
     pub(crate) opencode_bin: Option<String>,
     #[serde(default = "default_access_mode", rename = "defaultAccessMode")]
     pub(crate) default_access_mode: String,
+    #[serde(default = "default_true", rename = "resumeThreadsOnReconnect")]
+    pub(crate) resume_threads_on_reconnect: bool,
+    #[serde(default, rename = "requestTimeouts")]
+    pub(crate) request_timeouts: RequestTimeoutSettings,
+    #[serde(default = "builtin_sandbox_profiles", rename = "sandboxProfiles")]
+    pub(crate) sandbox_profiles: Vec<SandboxProfile>,
+    #[serde(default, rename = "costTable")]
+    pub(crate) cost_table: Vec<ModelPrice>,
+    #[serde(default, rename = "rateLimitWatch")]
+    pub(crate) rate_limit_watch: RateLimitWatchSettings,
+    #[serde(default, rename = "remoteAccess")]
+    pub(crate) remote_access: RemoteAccessSettings,
+}
+
+/// The optional HTTP + WebSocket server for driving the app from elsewhere.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct RemoteAccessSettings {
+    #[serde(default)]
+    pub(crate) enabled: bool,
+    #[serde(default = "default_remote_bind_address")]
+    pub(crate) bind_address: String,
+    #[serde(default)]
+    pub(crate) tokens: Vec<RemoteToken>,
+}
+
+fn default_remote_bind_address() -> String {
+    "127.0.0.1:4732".to_string()
+}
+
+impl Default for RemoteAccessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_remote_bind_address(),
+            tokens: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum RemoteScope {
+    /// Lists, reads and the event stream.
+    ReadOnly,
+    /// Everything a read-only token can do, plus sending, approving and editing.
+    Control,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct RemoteToken {
+    pub(crate) id: String,
+    pub(crate) label: String,
+    /// The bearer secret clients send.
+    pub(crate) token: String,
+    pub(crate) scope: RemoteScope,
+    #[ts(type = "number")]
+    pub(crate) created_at: u64,
+}
+
+/// How often the rate-limit watcher polls and when it raises alerts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct RateLimitWatchSettings {
+    #[serde(default = "default_true")]
+    pub(crate) enabled: bool,
+    #[serde(default = "default_rate_limit_poll_secs")]
+    #[ts(type = "number")]
+    pub(crate) poll_interval_secs: u64,
+    /// Used percentages that raise an alert when a window reaches them.
+    #[serde(default = "default_rate_limit_thresholds")]
+    pub(crate) thresholds: Vec<u8>,
+    #[serde(default = "default_true")]
+    pub(crate) notify_on_reset: bool,
+}
+
+fn default_rate_limit_poll_secs() -> u64 {
+    300
+}
+
+fn default_rate_limit_thresholds() -> Vec<u8> {
+    vec![80, 95]
+}
+
+impl Default for RateLimitWatchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: default_rate_limit_poll_secs(),
+            thresholds: default_rate_limit_thresholds(),
+            notify_on_reset: true,
+        }
+    }
+}
+
+/// A cost table row, in USD per million tokens.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct ModelPrice {
+    /// Model name; a trailing `*` matches every model with that prefix.
+    pub(crate) model: String,
+    pub(crate) input_per_million: f64,
+    /// Defaults to the input price.
+    #[serde(default)]
+    pub(crate) cached_input_per_million: Option<f64>,
+    pub(crate) output_per_million: f64,
+}
+
+/// Per-method response deadlines for app-server requests; `0` disables the deadline.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RequestTimeoutSettings {
+    #[serde(default = "default_request_timeout_secs", rename = "defaultSecs")]
+    pub(crate) default_secs: u64,
+    #[serde(default)]
+    pub(crate) overrides: HashMap<String, u64>,
+}
+
+fn default_request_timeout_secs() -> u64 {
+    120
+}
+
+impl Default for RequestTimeoutSettings {
+    fn default() -> Self {
+        Self {
+            default_secs: default_request_timeout_secs(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl RequestTimeoutSettings {
+    pub(crate) fn for_method(&self, method: &str) -> Option<Duration> {
+        let secs = self
+            .overrides
+            .get(method)
+            .copied()
+            .unwrap_or(self.default_secs);
+        if secs == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(secs))
+        }
+    }
 }
 
 fn default_access_mode() -> String {
     "current".to_string()
 }
 
+fn default_true() -> bool {
+    true
+}
+
+/// The original three access modes, kept resolvable even if removed from settings.
+pub(crate) fn builtin_sandbox_profiles() -> Vec<SandboxProfile> {
+    let profile = |name: &str, label: &str, mode, approval_policy| SandboxProfile {
+        name: name.to_string(),
+        label: Some(label.to_string()),
+        mode,
+        writable_roots: Vec::new(),
+        network_access: true,
+        approval_policy,
+        excluded_paths: Vec::new(),
+    };
+    vec![
+        profile(
+            "read-only",
+            "Read only",
+            SandboxMode::ReadOnly,
+            ApprovalPolicy::OnRequest,
+        ),
+        profile(
+            "current",
+            "On-Request",
+            SandboxMode::WorkspaceWrite,
+            ApprovalPolicy::OnRequest,
+        ),
+        profile(
+            "full-access",
+            "Full access",
+            SandboxMode::DangerFullAccess,
+            ApprovalPolicy::Never,
+        ),
+    ]
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
             codex_bin: None,
             opencode_bin: None,
             default_access_mode: "current".to_string(),
+            resume_threads_on_reconnect: true,
+            request_timeouts: RequestTimeoutSettings::default(),
+            sandbox_profiles: builtin_sandbox_profiles(),
+            cost_table: Vec::new(),
+            rate_limit_watch: RateLimitWatchSettings::default(),
+            remote_access: RemoteAccessSettings::default(),
         }
     }
 }
@@ -181,19 +472,68 @@ pub(crate) struct OpenCodeSessionInfo {
     pub(crate) updated_at: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct OpenCodeMessagePart {
-    #[serde(rename = "type")]
-    pub(crate) part_type: String,
-    #[serde(default)]
-    pub(crate) content: Option<String>,
+/// One piece of an ACP conversation message, folded from `session/update`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[ts(export)]
+pub(crate) enum OpenCodeMessagePart {
+    Text {
+        text: String,
+    },
+    Reasoning {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        kind: Option<String>,
+        #[ts(type = "unknown")]
+        #[serde(default)]
+        input: Value,
+        status: String,
+        #[serde(default)]
+        output: String,
+        #[serde(default)]
+        diffs: Vec<OpenCodeFileDiff>,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    Patch {
+        hash: String,
+        files: Vec<String>,
+    },
+    File {
+        url: String,
+        mime: String,
+    },
+    Plan {
+        entries: Vec<OpenCodePlanEntry>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export)]
+pub(crate) struct OpenCodeFileDiff {
+    pub(crate) path: String,
     #[serde(default)]
-    pub(crate) tool_name: Option<String>,
+    pub(crate) old_text: Option<String>,
+    pub(crate) new_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export)]
+pub(crate) struct OpenCodePlanEntry {
+    pub(crate) content: String,
     #[serde(default)]
-    pub(crate) status: Option<String>,
+    pub(crate) priority: Option<String>,
+    pub(crate) status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export)]
 pub(crate) struct OpenCodeMessage {
     pub(crate) id: String,
     #[serde(rename = "sessionId")]
@@ -202,7 +542,21 @@ pub(crate) struct OpenCodeMessage {
     #[serde(default)]
     pub(crate) parts: Vec<OpenCodeMessagePart>,
     #[serde(rename = "createdAt", default)]
+    #[ts(type = "number | null")]
     pub(crate) created_at: Option<i64>,
+    /// Set once the `session/prompt` that produced the message returns.
+    #[serde(rename = "stopReason", default)]
+    pub(crate) stop_reason: Option<String>,
+}
+
+/// Returned by `send_opencode_message`; the turn finishes with an
+/// `acp/message/completed` event carrying the same `promptId`.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct OpenCodePromptHandle {
+    pub(crate) prompt_id: String,
+    pub(crate) session_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -220,13 +574,28 @@ pub(crate) struct OpenCodeProviderInfo {
 
 #[cfg(test)]
 mod tests {
-    use super::{AppSettings, WorkspaceEntry, WorkspaceKind};
+    use super::{AppSettings, BackendType, RequestTimeoutSettings, WorkspaceEntry, WorkspaceKind};
+    use std::time::Duration;
 
     #[test]
     fn app_settings_defaults_from_empty_json() {
         let settings: AppSettings = serde_json::from_str("{}").expect("settings deserialize");
         assert!(settings.codex_bin.is_none());
         assert_eq!(settings.default_access_mode, "current");
+        assert!(settings.resume_threads_on_reconnect);
+        assert_eq!(settings.request_timeouts.default_secs, 120);
+        assert_eq!(settings.sandbox_profiles.len(), 3);
+    }
+
+    #[test]
+    fn request_timeouts_prefer_overrides_and_zero_disables() {
+        let timeouts: RequestTimeoutSettings = serde_json::from_str(
+            r#"{"defaultSecs":30,"overrides":{"turn/start":0,"model/list":5}}"#,
+        )
+        .expect("timeouts deserialize");
+        assert_eq!(timeouts.for_method("model/list"), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.for_method("thread/list"), Some(Duration::from_secs(30)));
+        assert_eq!(timeouts.for_method("turn/start"), None);
     }
 
     #[test]
@@ -252,4 +621,16 @@ mod tests {
         assert!(matches!(entry.backend, BackendType::OpenCode));
         assert_eq!(entry.opencode_bin, Some("/usr/bin/opencode".to_string()));
     }
+
+    #[test]
+    fn workspace_entry_with_acp_agent() {
+        let entry: WorkspaceEntry = serde_json::from_str(
+            r#"{"id":"1","name":"Test","path":"/tmp","codexBin":null,"backend":"acp","acpAgent":{"command":"/opt/bin/claude-code-acp","args":["--verbose"]}}"#,
+        )
+        .expect("workspace deserialize");
+        assert!(matches!(entry.backend, BackendType::Acp));
+        let agent = entry.acp_agent.expect("acp agent");
+        assert_eq!(agent.args, vec!["--verbose".to_string()]);
+        assert_eq!(agent.display_name(), "claude-code-acp");
+    }
 }
@@ -0,0 +1,628 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ts_rs::TS;
+
+use crate::backend::{AgentEvent, AgentHost};
+use crate::protocol::KnownNotification;
+use crate::state::AppState;
+use crate::types::{BackendType, ModelPrice};
+use crate::utils::civil_from_days;
+
+pub(crate) const USAGE_RECORDED_METHOD: &str = "usage/recorded";
+
+/// Model label for turns whose model neither the request nor the backend named.
+pub(crate) const DEFAULT_MODEL_LABEL: &str = "default";
+
+/// Token counts as reported by Codex (`tokenUsage.last`) or an ACP agent (`usage`).
+///
+/// Cached input is a subset of input, and reasoning a subset of output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct TokenCounts {
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub(crate) input_tokens: u64,
+    #[serde(default, alias = "cachedReadTokens")]
+    #[ts(type = "number")]
+    pub(crate) cached_input_tokens: u64,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub(crate) output_tokens: u64,
+    #[serde(default, alias = "thoughtTokens")]
+    #[ts(type = "number")]
+    pub(crate) reasoning_output_tokens: u64,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub(crate) total_tokens: u64,
+}
+
+impl TokenCounts {
+    fn from_value(value: &Value) -> Option<Self> {
+        let mut counts: Self = serde_json::from_value(value.clone()).ok()?;
+        if counts.total_tokens == 0 {
+            counts.total_tokens = counts.input_tokens + counts.output_tokens;
+        }
+        Some(counts)
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.input_tokens += other.input_tokens;
+        self.cached_input_tokens += other.cached_input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.reasoning_output_tokens += other.reasoning_output_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// USD for `tokens` at `price`; reasoning is billed as part of output.
+fn turn_cost(price: &ModelPrice, tokens: &TokenCounts) -> f64 {
+    let cached = tokens.cached_input_tokens.min(tokens.input_tokens);
+    let uncached = tokens.input_tokens - cached;
+    let cached_price = price
+        .cached_input_per_million
+        .unwrap_or(price.input_per_million);
+    (uncached as f64 * price.input_per_million
+        + cached as f64 * cached_price
+        + tokens.output_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
+/// The exact entry for `model`, else the longest matching `prefix*` entry.
+fn find_price<'a>(prices: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+    prices
+        .iter()
+        .find(|price| price.model == model)
+        .or_else(|| {
+            prices
+                .iter()
+                .filter_map(|price| {
+                    let prefix = price.model.strip_suffix('*')?;
+                    model.starts_with(prefix).then_some((prefix.len(), price))
+                })
+                .max_by_key(|(len, _)| *len)
+                .map(|(_, price)| price)
+        })
+}
+
+pub(crate) fn validate_cost_table(prices: &[ModelPrice]) -> Result<(), String> {
+    let mut seen = Vec::new();
+    for price in prices {
+        let model = price.model.trim();
+        if model.is_empty() {
+            return Err("Cost table entries need a model name.".to_string());
+        }
+        if seen.contains(&model) {
+            return Err(format!(
+                "Model \"{model}\" appears twice in the cost table."
+            ));
+        }
+        seen.push(model);
+        let rates = [
+            Some(price.input_per_million),
+            price.cached_input_per_million,
+            Some(price.output_per_million),
+        ];
+        if rates
+            .into_iter()
+            .flatten()
+            .any(|rate| !rate.is_finite() || rate < 0.0)
+        {
+            return Err(format!("Prices for \"{model}\" must be zero or more."));
+        }
+    }
+    Ok(())
+}
+
+/// Tokens spent by one turn.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct UsageRecord {
+    #[ts(type = "number")]
+    pub(crate) timestamp: u64,
+    pub(crate) workspace_id: String,
+    pub(crate) backend: BackendType,
+    pub(crate) thread_id: String,
+    #[serde(default)]
+    pub(crate) turn_id: Option<String>,
+    pub(crate) model: String,
+    pub(crate) tokens: TokenCounts,
+    /// Cost the agent reported itself, in USD; used instead of the cost table.
+    #[serde(default)]
+    pub(crate) reported_cost: Option<f64>,
+}
+
+impl UsageRecord {
+    /// A record from the `usage` of an ACP `session/prompt` result, if it has one.
+    pub(crate) fn from_acp_result(
+        workspace_id: &str,
+        backend: BackendType,
+        session_id: &str,
+        model: Option<&str>,
+        result: &Value,
+    ) -> Option<Self> {
+        let usage = result.get("usage")?;
+        let tokens = TokenCounts::from_value(usage)?;
+        let reported_cost = usage
+            .get("cost")
+            .and_then(|cost| cost.get("amount").or(Some(cost)))
+            .and_then(|amount| amount.as_f64());
+        if tokens.is_empty() && reported_cost.is_none() {
+            return None;
+        }
+        Some(Self {
+            timestamp: now_ms(),
+            workspace_id: workspace_id.to_string(),
+            backend,
+            thread_id: session_id.to_string(),
+            turn_id: None,
+            model: model.unwrap_or(DEFAULT_MODEL_LABEL).to_string(),
+            tokens,
+            reported_cost,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) enum UsageGroup {
+    Thread,
+    Workspace,
+    Model,
+    Day,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct UsageBucket {
+    pub(crate) key: String,
+    #[ts(type = "number")]
+    pub(crate) turns: u64,
+    pub(crate) tokens: TokenCounts,
+    /// USD for the turns that have a price.
+    pub(crate) cost: f64,
+    /// Turns whose model is missing from the cost table.
+    #[ts(type = "number")]
+    pub(crate) unpriced_turns: u64,
+}
+
+impl UsageBucket {
+    fn add(&mut self, record: &UsageRecord, prices: &[ModelPrice]) {
+        self.turns += 1;
+        self.tokens.add(&record.tokens);
+        let cost = record.reported_cost.or_else(|| {
+            find_price(prices, &record.model).map(|price| turn_cost(price, &record.tokens))
+        });
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_turns += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub(crate) struct UsageSummary {
+    pub(crate) total: UsageBucket,
+    /// Days in order; other groupings by most tokens first.
+    pub(crate) buckets: Vec<UsageBucket>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UsageQuery {
+    pub(crate) workspace_id: Option<String>,
+    pub(crate) thread_id: Option<String>,
+    /// Inclusive lower bound, in milliseconds since the epoch.
+    pub(crate) since: Option<u64>,
+    /// Exclusive upper bound, in milliseconds since the epoch.
+    pub(crate) until: Option<u64>,
+}
+
+impl UsageQuery {
+    fn matches(&self, record: &UsageRecord) -> bool {
+        self.workspace_id
+            .as_ref()
+            .is_none_or(|workspace_id| record.workspace_id == *workspace_id)
+            && self
+                .thread_id
+                .as_ref()
+                .is_none_or(|thread_id| record.thread_id == *thread_id)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+}
+
+/// `YYYY-MM-DD` of `millis`, shifted by the viewer's UTC offset.
+fn day_key(millis: u64, utc_offset_minutes: i32) -> String {
+    let local_ms = millis as i64 + i64::from(utc_offset_minutes) * 60_000;
+    let (year, month, day) = civil_from_days(local_ms.div_euclid(86_400_000));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub(crate) fn summarize(
+    records: &[UsageRecord],
+    group_by: UsageGroup,
+    prices: &[ModelPrice],
+    utc_offset_minutes: i32,
+) -> UsageSummary {
+    let mut total = UsageBucket {
+        key: "total".to_string(),
+        ..UsageBucket::default()
+    };
+    let mut buckets: HashMap<String, UsageBucket> = HashMap::new();
+    for record in records {
+        total.add(record, prices);
+        let key = match group_by {
+            UsageGroup::Thread => record.thread_id.clone(),
+            UsageGroup::Workspace => record.workspace_id.clone(),
+            UsageGroup::Model => record.model.clone(),
+            UsageGroup::Day => day_key(record.timestamp, utc_offset_minutes),
+        };
+        buckets
+            .entry(key.clone())
+            .or_insert_with(|| UsageBucket {
+                key,
+                ..UsageBucket::default()
+            })
+            .add(record, prices);
+    }
+    let mut buckets: Vec<UsageBucket> = buckets.into_values().collect();
+    match group_by {
+        UsageGroup::Day => buckets.sort_by(|a, b| a.key.cmp(&b.key)),
+        _ => buckets.sort_by(|a, b| {
+            b.tokens
+                .total_tokens
+                .cmp(&a.tokens.total_tokens)
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+    }
+    UsageSummary { total, buckets }
+}
+
+/// Tokens seen so far in a Codex turn that has not completed.
+#[derive(Debug, Clone, Default)]
+struct OpenTurn {
+    turn_id: Option<String>,
+    tokens: TokenCounts,
+}
+
+/// Append-only JSONL ledger of per-turn usage at `<app data>/usage.jsonl`.
+pub(crate) struct UsageLedger {
+    path: PathBuf,
+    file: Option<File>,
+    open_turns: HashMap<(String, String), OpenTurn>,
+    /// The model each thread's latest turn was started with.
+    turn_models: HashMap<(String, String), String>,
+    /// The last thread total Codex reported, to drop repeated notifications.
+    last_totals: HashMap<(String, String), TokenCounts>,
+}
+
+impl UsageLedger {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            open_turns: HashMap::new(),
+            turn_models: HashMap::new(),
+            last_totals: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn note_model(&mut self, workspace_id: &str, thread_id: &str, model: Option<&str>) {
+        self.turn_models.insert(
+            (workspace_id.to_string(), thread_id.to_string()),
+            model.unwrap_or(DEFAULT_MODEL_LABEL).to_string(),
+        );
+    }
+
+    /// Folds a Codex notification in; returns the record for a turn that just completed.
+    pub(crate) fn observe(
+        &mut self,
+        workspace_id: &str,
+        notification: &KnownNotification,
+    ) -> Option<UsageRecord> {
+        match notification {
+            KnownNotification::ThreadTokenUsageUpdated(update) => {
+                let key = (workspace_id.to_string(), update.thread_id.clone());
+                let total = update
+                    .token_usage
+                    .get("total")
+                    .and_then(TokenCounts::from_value);
+                if let Some(total) = total {
+                    if self.last_totals.get(&key) == Some(&total) {
+                        return None;
+                    }
+                    self.last_totals.insert(key.clone(), total);
+                }
+                let last = update
+                    .token_usage
+                    .get("last")
+                    .and_then(TokenCounts::from_value)?;
+                let turn = self.open_turns.entry(key).or_default();
+                if update.turn_id.is_some() {
+                    turn.turn_id = update.turn_id.clone();
+                }
+                turn.tokens.add(&last);
+                None
+            }
+            KnownNotification::TurnCompleted(event) => {
+                let key = match event.turn.thread_id.as_ref() {
+                    Some(thread_id) => (workspace_id.to_string(), thread_id.clone()),
+                    None => self
+                        .open_turns
+                        .iter()
+                        .find(|((workspace, _), turn)| {
+                            workspace == workspace_id
+                                && turn.turn_id.as_deref() == Some(event.turn.id.as_str())
+                        })
+                        .map(|(key, _)| key.clone())?,
+                };
+                let turn = self.open_turns.remove(&key)?;
+                self.close_turn(
+                    key,
+                    turn.turn_id.or(Some(event.turn.id.clone())),
+                    turn.tokens,
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Closes the turns of a workspace whose app-server went away.
+    pub(crate) fn workspace_stopped(&mut self, workspace_id: &str) -> Vec<UsageRecord> {
+        let keys: Vec<_> = self
+            .open_turns
+            .keys()
+            .filter(|(workspace, _)| workspace == workspace_id)
+            .cloned()
+            .collect();
+        self.last_totals
+            .retain(|(workspace, _), _| workspace != workspace_id);
+        keys.into_iter()
+            .filter_map(|key| {
+                let turn = self.open_turns.remove(&key)?;
+                self.close_turn(key, turn.turn_id, turn.tokens)
+            })
+            .collect()
+    }
+
+    fn close_turn(
+        &self,
+        key: (String, String),
+        turn_id: Option<String>,
+        tokens: TokenCounts,
+    ) -> Option<UsageRecord> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let model = self
+            .turn_models
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_MODEL_LABEL.to_string());
+        let (workspace_id, thread_id) = key;
+        Some(UsageRecord {
+            timestamp: now_ms(),
+            workspace_id,
+            backend: BackendType::Codex,
+            thread_id,
+            turn_id,
+            model,
+            tokens,
+            reported_cost: None,
+        })
+    }
+
+    pub(crate) fn append(&mut self, record: &UsageRecord) -> Result<(), String> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| e.to_string())?;
+            self.file = Some(file);
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Matching records, oldest first.
+    pub(crate) fn query(&self, query: &UsageQuery) -> Result<Vec<UsageRecord>, String> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut records: Vec<UsageRecord> = data
+            .lines()
+            .filter_map(|line| serde_json::from_str::<UsageRecord>(line).ok())
+            .filter(|record| query.matches(record))
+            .collect();
+        records.sort_by_key(|record| record.timestamp);
+        Ok(records)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Appends to the ledger and tells the UI; failures are logged, not surfaced.
+pub(crate) async fn record_usage(host: &dyn AgentHost, record: UsageRecord) {
+    let state = host.app_state();
+    {
+        let mut ledger = state.usage.lock().await;
+        if let Err(error) = ledger.append(&record) {
+            eprintln!(
+                "Failed to write usage to {}: {error}",
+                ledger.path().display()
+            );
+        }
+    }
+    host.emit(AgentEvent::new(
+        &record.workspace_id,
+        record.backend.clone(),
+        USAGE_RECORDED_METHOD,
+        json!(record),
+    ));
+}
+
+/// Hooked into the Codex reader next to the thread store.
+pub(crate) async fn observe_codex_usage(
+    host: &dyn AgentHost,
+    workspace_id: &str,
+    notification: &KnownNotification,
+) {
+    let record = host
+        .app_state()
+        .usage
+        .lock()
+        .await
+        .observe(workspace_id, notification);
+    if let Some(record) = record {
+        record_usage(host, record).await;
+    }
+}
+
+pub(crate) async fn get_usage_summary(
+    state: &AppState,
+    workspace_id: Option<String>,
+    thread_id: Option<String>,
+    since: Option<u64>,
+    until: Option<u64>,
+    group_by: UsageGroup,
+    utc_offset_minutes: Option<i32>,
+) -> Result<UsageSummary, String> {
+    let query = UsageQuery {
+        workspace_id,
+        thread_id,
+        since,
+        until,
+    };
+    let records = state.usage.lock().await.query(&query)?;
+    let prices = state.app_settings.lock().await.cost_table.clone();
+    Ok(summarize(
+        &records,
+        group_by,
+        &prices,
+        utc_offset_minutes.unwrap_or(0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_price, summarize, TokenCounts, UsageGroup, UsageLedger, UsageQuery};
+    use crate::protocol::KnownNotification;
+    use crate::types::ModelPrice;
+    use serde_json::json;
+
+    fn notification(value: serde_json::Value) -> KnownNotification {
+        serde_json::from_value(value).expect("notification")
+    }
+
+    #[test]
+    fn codex_turns_sum_last_usage_and_skip_repeats() {
+        let root = std::env::temp_dir().join(format!("usage-{}", uuid::Uuid::new_v4()));
+        let mut ledger = UsageLedger::new(root.join("usage.jsonl"));
+        ledger.note_model("ws", "thr_1", Some("gpt-5-codex"));
+        let update = |input: u64, total: u64| {
+            notification(json!({
+                "method": "thread/tokenUsage/updated",
+                "params": {
+                    "threadId": "thr_1",
+                    "turnId": "turn_1",
+                    "tokenUsage": {
+                        "last": { "inputTokens": input, "cachedInputTokens": 100, "outputTokens": 50 },
+                        "total": { "totalTokens": total }
+                    }
+                }
+            }))
+        };
+        assert!(ledger.observe("ws", &update(1000, 1050)).is_none());
+        assert!(ledger.observe("ws", &update(1000, 1050)).is_none());
+        assert!(ledger.observe("ws", &update(2000, 3100)).is_none());
+        let completed = notification(json!({
+            "method": "turn/completed",
+            "params": { "turn": { "id": "turn_1", "threadId": "thr_1", "status": "completed" } }
+        }));
+        let record = ledger.observe("ws", &completed).expect("record");
+        assert_eq!(record.model, "gpt-5-codex");
+        assert_eq!(record.turn_id.as_deref(), Some("turn_1"));
+        assert_eq!(record.tokens.input_tokens, 3000);
+        assert_eq!(record.tokens.total_tokens, 3100);
+        ledger.append(&record).expect("append");
+
+        let records = ledger.query(&UsageQuery::default()).expect("query");
+        let prices = vec![ModelPrice {
+            model: "gpt-5*".to_string(),
+            input_per_million: 1.0,
+            cached_input_per_million: Some(0.5),
+            output_per_million: 10.0,
+        }];
+        let summary = summarize(&records, UsageGroup::Model, &prices, 0);
+        assert_eq!(summary.buckets.len(), 1);
+        assert_eq!(summary.total.unpriced_turns, 0);
+        // 2800 uncached + 200 cached at half price + 100 output.
+        let expected = (2800.0 + 100.0 + 1000.0) / 1_000_000.0;
+        assert!((summary.total.cost - expected).abs() < 1e-12);
+        assert!(ledger.observe("ws", &completed).is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn exact_prices_win_over_prefixes_and_days_follow_the_offset() {
+        let price = |model: &str| ModelPrice {
+            model: model.to_string(),
+            input_per_million: 0.0,
+            cached_input_per_million: None,
+            output_per_million: 0.0,
+        };
+        let prices = vec![price("gpt-*"), price("gpt-5*"), price("gpt-5-mini")];
+        let model = |name: &str| find_price(&prices, name).map(|price| price.model.as_str());
+        assert_eq!(model("gpt-5-mini"), Some("gpt-5-mini"));
+        assert_eq!(model("gpt-5-codex"), Some("gpt-5*"));
+        assert_eq!(model("gpt-4.1"), Some("gpt-*"));
+        assert_eq!(model("claude"), None);
+
+        assert_eq!(super::day_key(0, 0), "1970-01-01");
+        assert_eq!(super::day_key(0, -60), "1969-12-31");
+        let counts = TokenCounts {
+            input_tokens: 3,
+            output_tokens: 4,
+            ..TokenCounts::default()
+        };
+        assert_eq!(
+            TokenCounts::from_value(&json!(counts)).map(|counts| counts.total_tokens),
+            Some(7)
+        );
+    }
+}
@@ -1,13 +1,131 @@
+use std::path::{Component, Path, PathBuf};
+
 pub(crate) fn normalize_git_path(path: &str) -> String {
     path.replace('\\', "/")
 }
 
+/// Resolves `.` and `..` without touching the filesystem.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Joins `path` onto `base` (absolute paths win) and normalizes the result.
+pub(crate) fn resolve_path(base: &Path, path: &str) -> PathBuf {
+    normalize_path(&base.join(path))
+}
+
+/// Resolves symlinks in the absolute `path` and checks that the result lies
+/// under one of `roots` (resolved the same way). A path that does not exist
+/// yet is checked through its nearest existing ancestor; `..` is refused
+/// rather than interpreted.
+pub(crate) async fn confine_path(path: &Path, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("path must be absolute: {}", path.display()));
+    }
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(format!("path must not contain `..`: {}", path.display()));
+    }
+    let mut existing = path;
+    let mut missing = Vec::new();
+    let resolved = loop {
+        match tokio::fs::canonicalize(existing).await {
+            Ok(resolved) => break resolved,
+            Err(_) => {
+                // A dangling symlink would let a new file land wherever it points.
+                if tokio::fs::symlink_metadata(existing).await.is_ok() {
+                    return Err(format!("{} cannot be resolved", existing.display()));
+                }
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(format!("{} does not exist", path.display()));
+                };
+                missing.push(name);
+                existing = parent;
+            }
+        }
+    };
+    let resolved = missing
+        .iter()
+        .rev()
+        .fold(resolved, |resolved, name| resolved.join(name));
+    for root in roots {
+        if let Ok(root) = tokio::fs::canonicalize(root).await {
+            if resolved.starts_with(&root) {
+                return Ok(resolved);
+            }
+        }
+    }
+    Err(format!("{} is outside the workspace", path.display()))
+}
+
+/// `(year, month, day)` for a count of days since 1970-01-01, proleptic Gregorian calendar.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::normalize_git_path;
+    use super::{confine_path, normalize_git_path, resolve_path};
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn normalize_git_path_replaces_backslashes() {
         assert_eq!(normalize_git_path("foo\\bar\\baz"), "foo/bar/baz");
     }
+
+    #[test]
+    fn resolve_path_joins_and_normalizes() {
+        let base = Path::new("/repo/app");
+        assert_eq!(resolve_path(base, "../lib/./x"), PathBuf::from("/repo/lib/x"));
+        assert_eq!(resolve_path(base, "/tmp/out"), PathBuf::from("/tmp/out"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn confine_path_follows_symlinks_and_allows_new_files() {
+        let dir = std::env::temp_dir().join(format!("confine-{}", uuid::Uuid::new_v4()));
+        let root = dir.join("workspace");
+        std::fs::create_dir_all(root.join("src")).expect("create workspace");
+        std::os::unix::fs::symlink("/etc", root.join("escape")).expect("link");
+        std::os::unix::fs::symlink(dir.join("missing"), root.join("dangling")).expect("link");
+        let roots = vec![root.clone()];
+        let root = std::fs::canonicalize(&root).expect("canonical root");
+
+        assert_eq!(
+            confine_path(&root.join("src/new/file.rs"), &roots).await,
+            Ok(root.join("src/new/file.rs"))
+        );
+        assert!(confine_path(&root.join("escape/passwd"), &roots)
+            .await
+            .is_err());
+        assert!(confine_path(&root.join("dangling"), &roots).await.is_err());
+        assert!(confine_path(&root.join("src/../../x"), &roots)
+            .await
+            .is_err());
+        assert!(confine_path(Path::new("src/main.rs"), &roots)
+            .await
+            .is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
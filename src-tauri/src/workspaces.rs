@@ -1,16 +1,16 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use tauri::{AppHandle, State};
 use tokio::process::Command;
 use uuid::Uuid;
 
-use crate::codex::spawn_workspace_session;
-use crate::opencode::spawn_opencode_session;
+use crate::backend::{connect_backend, shutdown_backend, SharedHost};
+use crate::sandbox::validate_sandbox_profiles;
 use crate::state::AppState;
 use crate::storage::write_workspaces;
 use crate::types::{
-    BackendType, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
+    AcpAgentConfig, BackendType, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings,
+    WorktreeInfo,
 };
 use crate::utils::normalize_git_path;
 
@@ -75,7 +75,7 @@ fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
     results
 }
 
-fn sort_workspaces(list: &mut Vec<WorkspaceInfo>) {
+fn sort_workspaces(list: &mut [WorkspaceInfo]) {
     list.sort_by(|a, b| {
         let a_order = a.settings.sort_order.unwrap_or(u32::MAX);
         let b_order = b.settings.sort_order.unwrap_or(u32::MAX);
@@ -118,7 +118,7 @@ async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, St
     Ok(status.success())
 }
 
-fn unique_worktree_path(base_dir: &PathBuf, name: &str) -> PathBuf {
+fn unique_worktree_path(base_dir: &Path, name: &str) -> PathBuf {
     let mut candidate = base_dir.join(name);
     if !candidate.exists() {
         return candidate;
@@ -133,7 +133,7 @@ fn unique_worktree_path(base_dir: &PathBuf, name: &str) -> PathBuf {
     candidate
 }
 
-fn ensure_worktree_ignored(repo_path: &PathBuf) -> Result<(), String> {
+fn ensure_worktree_ignored(repo_path: &Path) -> Result<(), String> {
     let ignore_path = repo_path.join(".gitignore");
     let entry = ".codex-worktrees/";
     let existing = std::fs::read_to_string(&ignore_path).unwrap_or_default();
@@ -154,19 +154,12 @@ fn ensure_worktree_ignored(repo_path: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-pub(crate) async fn list_workspaces(
-    state: State<'_, AppState>,
-) -> Result<Vec<WorkspaceInfo>, String> {
+pub(crate) async fn list_workspaces(state: &AppState) -> Result<Vec<WorkspaceInfo>, String> {
     let workspaces = state.workspaces.lock().await;
-    let codex_sessions = state.sessions.lock().await;
-    let opencode_sessions = state.opencode_sessions.lock().await;
+    let sessions = state.sessions.lock().await;
     let mut result = Vec::new();
     for entry in workspaces.values() {
-        let connected = match entry.backend {
-            BackendType::Codex => codex_sessions.contains_key(&entry.id),
-            BackendType::OpenCode => opencode_sessions.contains_key(&entry.id),
-        };
+        let connected = sessions.contains(&entry.id, &entry.backend);
         result.push(WorkspaceInfo {
             id: entry.id.clone(),
             name: entry.name.clone(),
@@ -174,6 +167,7 @@ pub(crate) async fn list_workspaces(
             codex_bin: entry.codex_bin.clone(),
             opencode_bin: entry.opencode_bin.clone(),
             backend: entry.backend.clone(),
+            acp_agent: entry.acp_agent.clone(),
             connected,
             kind: entry.kind.clone(),
             parent_id: entry.parent_id.clone(),
@@ -185,15 +179,15 @@ pub(crate) async fn list_workspaces(
     Ok(result)
 }
 
-#[tauri::command]
 pub(crate) async fn add_workspace(
+    host: &SharedHost,
     path: String,
     codex_bin: Option<String>,
     opencode_bin: Option<String>,
     backend: Option<String>,
-    state: State<'_, AppState>,
-    app: AppHandle,
+    acp_agent: Option<AcpAgentConfig>,
 ) -> Result<WorkspaceInfo, String> {
+    let state = host.app_state();
     let name = PathBuf::from(&path)
         .file_name()
         .and_then(|s| s.to_str())
@@ -202,8 +196,12 @@ pub(crate) async fn add_workspace(
     
     let backend_type = match backend.as_deref() {
         Some("opencode") => BackendType::OpenCode,
+        Some("acp") => BackendType::Acp,
         _ => BackendType::Codex,
     };
+    if backend_type == BackendType::Acp && acp_agent.is_none() {
+        return Err("An ACP workspace needs an agent command.".to_string());
+    }
 
     let entry = WorkspaceEntry {
         id: Uuid::new_v4().to_string(),
@@ -211,51 +209,26 @@ pub(crate) async fn add_workspace(
         path: path.clone(),
         codex_bin,
         opencode_bin,
-        backend: backend_type.clone(),
+        backend: backend_type,
+        acp_agent,
         kind: WorkspaceKind::Main,
         parent_id: None,
         worktree: None,
         settings: WorkspaceSettings::default(),
     };
 
-    match backend_type {
-        BackendType::Codex => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.codex_bin.clone()
-            };
-            let session = spawn_workspace_session(entry.clone(), default_bin, app).await?;
-            {
-                let mut workspaces = state.workspaces.lock().await;
-                workspaces.insert(entry.id.clone(), entry.clone());
-                let list: Vec<_> = workspaces.values().cloned().collect();
-                write_workspaces(&state.storage_path, &list)?;
-            }
-            state
-                .sessions
-                .lock()
-                .await
-                .insert(entry.id.clone(), session);
-        }
-        BackendType::OpenCode => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.opencode_bin.clone()
-            };
-            let session = spawn_opencode_session(entry.clone(), default_bin, app).await?;
-            {
-                let mut workspaces = state.workspaces.lock().await;
-                workspaces.insert(entry.id.clone(), entry.clone());
-                let list: Vec<_> = workspaces.values().cloned().collect();
-                write_workspaces(&state.storage_path, &list)?;
-            }
-            state
-                .opencode_sessions
-                .lock()
-                .await
-                .insert(entry.id.clone(), session);
-        }
+    let session = connect_backend(entry.clone(), host).await?;
+    {
+        let mut workspaces = state.workspaces.lock().await;
+        workspaces.insert(entry.id.clone(), entry.clone());
+        let list: Vec<_> = workspaces.values().cloned().collect();
+        write_workspaces(&state.storage_path, &list)?;
     }
+    state
+        .sessions
+        .lock()
+        .await
+        .insert(&entry.id, session);
 
     Ok(WorkspaceInfo {
         id: entry.id,
@@ -264,6 +237,7 @@ pub(crate) async fn add_workspace(
         codex_bin: entry.codex_bin,
         opencode_bin: entry.opencode_bin,
         backend: entry.backend,
+        acp_agent: entry.acp_agent,
         connected: true,
         kind: entry.kind,
         parent_id: entry.parent_id,
@@ -272,13 +246,12 @@ pub(crate) async fn add_workspace(
     })
 }
 
-#[tauri::command]
 pub(crate) async fn add_worktree(
+    host: &SharedHost,
     parent_id: String,
     branch: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
+    let state = host.app_state();
     let branch = branch.trim();
     if branch.is_empty() {
         return Err("Branch name is required.".to_string());
@@ -327,6 +300,7 @@ pub(crate) async fn add_worktree(
         codex_bin: parent_entry.codex_bin.clone(),
         opencode_bin: parent_entry.opencode_bin.clone(),
         backend: parent_entry.backend.clone(),
+        acp_agent: parent_entry.acp_agent.clone(),
         kind: WorkspaceKind::Worktree,
         parent_id: Some(parent_entry.id.clone()),
         worktree: Some(WorktreeInfo {
@@ -335,44 +309,18 @@ pub(crate) async fn add_worktree(
         settings: WorkspaceSettings::default(),
     };
 
-    match entry.backend {
-        BackendType::Codex => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.codex_bin.clone()
-            };
-            let session = spawn_workspace_session(entry.clone(), default_bin, app).await?;
-            {
-                let mut workspaces = state.workspaces.lock().await;
-                workspaces.insert(entry.id.clone(), entry.clone());
-                let list: Vec<_> = workspaces.values().cloned().collect();
-                write_workspaces(&state.storage_path, &list)?;
-            }
-            state
-                .sessions
-                .lock()
-                .await
-                .insert(entry.id.clone(), session);
-        }
-        BackendType::OpenCode => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.opencode_bin.clone()
-            };
-            let session = spawn_opencode_session(entry.clone(), default_bin, app).await?;
-            {
-                let mut workspaces = state.workspaces.lock().await;
-                workspaces.insert(entry.id.clone(), entry.clone());
-                let list: Vec<_> = workspaces.values().cloned().collect();
-                write_workspaces(&state.storage_path, &list)?;
-            }
-            state
-                .opencode_sessions
-                .lock()
-                .await
-                .insert(entry.id.clone(), session);
-        }
+    let session = connect_backend(entry.clone(), host).await?;
+    {
+        let mut workspaces = state.workspaces.lock().await;
+        workspaces.insert(entry.id.clone(), entry.clone());
+        let list: Vec<_> = workspaces.values().cloned().collect();
+        write_workspaces(&state.storage_path, &list)?;
     }
+    state
+        .sessions
+        .lock()
+        .await
+        .insert(&entry.id, session);
 
     Ok(WorkspaceInfo {
         id: entry.id,
@@ -381,6 +329,7 @@ pub(crate) async fn add_worktree(
         codex_bin: entry.codex_bin,
         opencode_bin: entry.opencode_bin,
         backend: entry.backend,
+        acp_agent: entry.acp_agent,
         connected: true,
         kind: entry.kind,
         parent_id: entry.parent_id,
@@ -389,11 +338,7 @@ pub(crate) async fn add_worktree(
     })
 }
 
-#[tauri::command]
-pub(crate) async fn remove_workspace(
-    id: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
+pub(crate) async fn remove_workspace(state: &AppState, id: String) -> Result<(), String> {
     let (entry, child_worktrees) = {
         let workspaces = state.workspaces.lock().await;
         let entry = workspaces
@@ -413,20 +358,7 @@ pub(crate) async fn remove_workspace(
 
     let parent_path = PathBuf::from(&entry.path);
     for child in &child_worktrees {
-        match child.backend {
-            BackendType::Codex => {
-                if let Some(session) = state.sessions.lock().await.remove(&child.id) {
-                    let mut child_process = session.child.lock().await;
-                    let _ = child_process.kill().await;
-                }
-            }
-            BackendType::OpenCode => {
-                if let Some(session) = state.opencode_sessions.lock().await.remove(&child.id) {
-                    let mut child_process = session.child.lock().await;
-                    let _ = child_process.kill().await;
-                }
-            }
-        }
+        shutdown_backend(state, &child.id).await;
         let child_path = PathBuf::from(&child.path);
         if child_path.exists() {
             run_git_command(
@@ -438,18 +370,13 @@ pub(crate) async fn remove_workspace(
     }
     let _ = run_git_command(&parent_path, &["worktree", "prune", "--expire", "now"]).await;
 
-    match entry.backend {
-        BackendType::Codex => {
-            if let Some(session) = state.sessions.lock().await.remove(&id) {
-                let mut child = session.child.lock().await;
-                let _ = child.kill().await;
-            }
-        }
-        BackendType::OpenCode => {
-            if let Some(session) = state.opencode_sessions.lock().await.remove(&id) {
-                let mut child = session.child.lock().await;
-                let _ = child.kill().await;
-            }
+    shutdown_backend(state, &id).await;
+
+    {
+        let mut thread_store = state.thread_store.lock().await;
+        thread_store.remove_workspace(&id);
+        for child in &child_worktrees {
+            thread_store.remove_workspace(&child.id);
         }
     }
 
@@ -466,11 +393,7 @@ pub(crate) async fn remove_workspace(
     Ok(())
 }
 
-#[tauri::command]
-pub(crate) async fn remove_worktree(
-    id: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
+pub(crate) async fn remove_worktree(state: &AppState, id: String) -> Result<(), String> {
     let (entry, parent) = {
         let workspaces = state.workspaces.lock().await;
         let entry = workspaces
@@ -491,20 +414,7 @@ pub(crate) async fn remove_worktree(
         (entry, parent)
     };
 
-    match entry.backend {
-        BackendType::Codex => {
-            if let Some(session) = state.sessions.lock().await.remove(&entry.id) {
-                let mut child = session.child.lock().await;
-                let _ = child.kill().await;
-            }
-        }
-        BackendType::OpenCode => {
-            if let Some(session) = state.opencode_sessions.lock().await.remove(&entry.id) {
-                let mut child = session.child.lock().await;
-                let _ = child.kill().await;
-            }
-        }
-    }
+    shutdown_backend(state, &entry.id).await;
 
     let parent_path = PathBuf::from(&parent.path);
     let entry_path = PathBuf::from(&entry.path);
@@ -517,6 +427,7 @@ pub(crate) async fn remove_worktree(
     }
     let _ = run_git_command(&parent_path, &["worktree", "prune", "--expire", "now"]).await;
 
+    state.thread_store.lock().await.remove_workspace(&entry.id);
     {
         let mut workspaces = state.workspaces.lock().await;
         workspaces.remove(&entry.id);
@@ -527,12 +438,12 @@ pub(crate) async fn remove_worktree(
     Ok(())
 }
 
-#[tauri::command]
 pub(crate) async fn update_workspace_settings(
+    state: &AppState,
     id: String,
     settings: WorkspaceSettings,
-    state: State<'_, AppState>,
 ) -> Result<WorkspaceInfo, String> {
+    validate_sandbox_profiles(&settings.sandbox_profiles)?;
     let (entry_snapshot, list) = {
         let mut workspaces = state.workspaces.lock().await;
         let entry_snapshot = match workspaces.get_mut(&id) {
@@ -547,10 +458,11 @@ pub(crate) async fn update_workspace_settings(
     };
     write_workspaces(&state.storage_path, &list)?;
 
-    let connected = match entry_snapshot.backend {
-        BackendType::Codex => state.sessions.lock().await.contains_key(&id),
-        BackendType::OpenCode => state.opencode_sessions.lock().await.contains_key(&id),
-    };
+    let connected = state
+        .sessions
+        .lock()
+        .await
+        .contains(&id, &entry_snapshot.backend);
     Ok(WorkspaceInfo {
         id: entry_snapshot.id,
         name: entry_snapshot.name,
@@ -558,6 +470,7 @@ pub(crate) async fn update_workspace_settings(
         codex_bin: entry_snapshot.codex_bin,
         opencode_bin: entry_snapshot.opencode_bin,
         backend: entry_snapshot.backend,
+        acp_agent: entry_snapshot.acp_agent,
         connected,
         kind: entry_snapshot.kind,
         parent_id: entry_snapshot.parent_id,
@@ -566,11 +479,10 @@ pub(crate) async fn update_workspace_settings(
     })
 }
 
-#[tauri::command]
 pub(crate) async fn update_workspace_codex_bin(
+    state: &AppState,
     id: String,
     codex_bin: Option<String>,
-    state: State<'_, AppState>,
 ) -> Result<WorkspaceInfo, String> {
     let (entry_snapshot, list) = {
         let mut workspaces = state.workspaces.lock().await;
@@ -586,10 +498,11 @@ pub(crate) async fn update_workspace_codex_bin(
     };
     write_workspaces(&state.storage_path, &list)?;
 
-    let connected = match entry_snapshot.backend {
-        BackendType::Codex => state.sessions.lock().await.contains_key(&id),
-        BackendType::OpenCode => state.opencode_sessions.lock().await.contains_key(&id),
-    };
+    let connected = state
+        .sessions
+        .lock()
+        .await
+        .contains(&id, &entry_snapshot.backend);
     Ok(WorkspaceInfo {
         id: entry_snapshot.id,
         name: entry_snapshot.name,
@@ -597,6 +510,7 @@ pub(crate) async fn update_workspace_codex_bin(
         codex_bin: entry_snapshot.codex_bin,
         opencode_bin: entry_snapshot.opencode_bin,
         backend: entry_snapshot.backend,
+        acp_agent: entry_snapshot.acp_agent,
         connected,
         kind: entry_snapshot.kind,
         parent_id: entry_snapshot.parent_id,
@@ -605,45 +519,74 @@ pub(crate) async fn update_workspace_codex_bin(
     })
 }
 
-#[tauri::command]
-pub(crate) async fn connect_workspace(
+pub(crate) async fn update_workspace_acp_agent(
+    state: &AppState,
     id: String,
-    state: State<'_, AppState>,
-    app: AppHandle,
-) -> Result<(), String> {
+    acp_agent: Option<AcpAgentConfig>,
+) -> Result<WorkspaceInfo, String> {
+    let (entry_snapshot, list) = {
+        let mut workspaces = state.workspaces.lock().await;
+        let entry_snapshot = match workspaces.get_mut(&id) {
+            Some(entry) => {
+                if entry.backend == BackendType::Acp && acp_agent.is_none() {
+                    return Err("An ACP workspace needs an agent command.".to_string());
+                }
+                entry.acp_agent = acp_agent.clone();
+                entry.clone()
+            }
+            None => return Err("workspace not found".to_string()),
+        };
+        let list: Vec<_> = workspaces.values().cloned().collect();
+        (entry_snapshot, list)
+    };
+    write_workspaces(&state.storage_path, &list)?;
+
+    // Stop the running agent so the next prompt spawns the new command line.
+    let previous = state.sessions.lock().await.remove(&id, &BackendType::Acp);
+    if let Some(session) = previous {
+        let _ = session.shutdown().await;
+    }
+
+    let connected = state
+        .sessions
+        .lock()
+        .await
+        .contains(&id, &entry_snapshot.backend);
+    Ok(WorkspaceInfo {
+        id: entry_snapshot.id,
+        name: entry_snapshot.name,
+        path: entry_snapshot.path,
+        codex_bin: entry_snapshot.codex_bin,
+        opencode_bin: entry_snapshot.opencode_bin,
+        backend: entry_snapshot.backend,
+        acp_agent: entry_snapshot.acp_agent,
+        connected,
+        kind: entry_snapshot.kind,
+        parent_id: entry_snapshot.parent_id,
+        worktree: entry_snapshot.worktree,
+        settings: entry_snapshot.settings,
+    })
+}
+
+/// Starts the agent for a saved workspace and registers its session.
+pub(crate) async fn connect_saved_workspace(id: &str, host: &SharedHost) -> Result<(), String> {
+    let state = host.app_state();
     let entry = {
         let workspaces = state.workspaces.lock().await;
         workspaces
-            .get(&id)
+            .get(id)
             .cloned()
             .ok_or("workspace not found")?
     };
 
-    match entry.backend {
-        BackendType::Codex => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.codex_bin.clone()
-            };
-            let session = spawn_workspace_session(entry.clone(), default_bin, app).await?;
-            state.sessions.lock().await.insert(entry.id, session);
-        }
-        BackendType::OpenCode => {
-            let default_bin = {
-                let settings = state.app_settings.lock().await;
-                settings.opencode_bin.clone()
-            };
-            let session = spawn_opencode_session(entry.clone(), default_bin, app).await?;
-            state.opencode_sessions.lock().await.insert(entry.id, session);
-        }
-    }
+    let session = connect_backend(entry.clone(), host).await?;
+    state.sessions.lock().await.insert(&entry.id, session);
     Ok(())
 }
 
-#[tauri::command]
 pub(crate) async fn list_workspace_files(
+    state: &AppState,
     workspace_id: String,
-    state: State<'_, AppState>,
 ) -> Result<Vec<String>, String> {
     let workspaces = state.workspaces.lock().await;
     let entry = workspaces
@@ -667,12 +610,13 @@ mod tests {
             codex_bin: None,
             opencode_bin: None,
             backend: BackendType::Codex,
+            acp_agent: None,
             kind: WorkspaceKind::Main,
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings {
-                sidebar_collapsed: false,
                 sort_order,
+                ..WorkspaceSettings::default()
             },
         }
     }
@@ -0,0 +1,94 @@
+//! Codex workspace sessions against the scripted `codex app-server`.
+
+mod common;
+
+use common::{next_event, recv_event, session, MockInstall};
+use serde_json::json;
+
+#[tokio::test]
+async fn turns_stream_into_the_thread_snapshot() {
+    let mock = MockInstall::codex("codex_turn.jsonl");
+    let mut session = session("codex", "codex_bin", &mock).await;
+    session.connect().await.expect("connect workspace");
+    next_event(&mut session, "codex/connected").await;
+
+    let thread = session.start_thread().await.expect("thread/start");
+    assert_eq!(thread["result"]["thread"]["id"], "thr_1");
+    session
+        .send_message("thr_1", "Hi")
+        .await
+        .expect("turn/start");
+    next_event(&mut session, "turn/completed").await;
+
+    let snapshot = session
+        .thread_snapshot("thr_1")
+        .await
+        .expect("thread tracked");
+    assert_eq!(snapshot["turnStatus"], "completed");
+    assert_eq!(snapshot["activeTurnId"], json!(null));
+    assert_eq!(snapshot["items"][0]["type"], "agentMessage");
+    assert_eq!(snapshot["items"][0]["text"], "Hello there");
+}
+
+#[tokio::test]
+async fn approvals_reach_the_client_and_take_answers() {
+    let mock = MockInstall::codex("codex_approval.jsonl");
+    let mut session = session("codex", "codex_bin", &mock).await;
+    session.connect().await.expect("connect workspace");
+    session
+        .send_message("thr_1", "Clean up")
+        .await
+        .expect("turn/start");
+
+    let approval = next_event(&mut session, "item/commandExecution/requestApproval").await;
+    assert_eq!(approval["params"]["command"], "rm -rf build");
+    let id = approval["id"].as_u64().expect("request id");
+    session
+        .respond(id, json!({ "decision": "accept" }))
+        .await
+        .expect("respond");
+    next_event(&mut session, "turn/completed").await;
+    // The script exits 0 only if the answer matched the recording.
+    let disconnected = next_event(&mut session, "codex/disconnected").await;
+    assert_eq!(disconnected["params"]["exitCode"], 0);
+}
+
+#[tokio::test]
+async fn a_crash_is_reported_and_reconnected() {
+    let mock = MockInstall::codex("codex_crash.jsonl");
+    let mut session = session("codex", "codex_bin", &mock).await;
+    session.connect().await.expect("connect workspace");
+    let error = session
+        .start_thread()
+        .await
+        .expect_err("crash fails the request");
+    assert!(error.contains("status 101"), "{error}");
+
+    let disconnected = next_event(&mut session, "codex/disconnected").await;
+    assert_eq!(disconnected["params"]["exitCode"], 101);
+    assert_eq!(disconnected["params"]["willReconnect"], true);
+    assert!(disconnected["params"]["stderr"]
+        .to_string()
+        .contains("panicked"));
+    next_event(&mut session, "codex/reconnecting").await;
+    next_event(&mut session, "codex/connected").await;
+    assert!(session.is_connected().await);
+}
+
+#[tokio::test]
+async fn malformed_lines_are_reported_without_stopping_the_stream() {
+    let mock = MockInstall::codex("codex_malformed.jsonl");
+    let mut session = session("codex", "codex_bin", &mock).await;
+    session.connect().await.expect("connect workspace");
+    let mut parse_errors = 0;
+    let started = loop {
+        let event = recv_event(&mut session).await;
+        match event["method"].as_str() {
+            Some("codex/parseError") => parse_errors += 1,
+            Some("thread/started") => break event,
+            _ => {}
+        }
+    };
+    assert_eq!(parse_errors, 2);
+    assert_eq!(started["params"]["thread"]["id"], "thr_2");
+}
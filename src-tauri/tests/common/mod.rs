@@ -0,0 +1,99 @@
+//! Helpers shared by the session tests, which run the app's sessions against
+//! the scripted agents in `tests/mock_agent/` replaying `tests/fixtures/`.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use codex_monitor_lib::testing::TestSession;
+
+/// How long a test waits on the agent before giving up.
+pub const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Stands for the test's workspace path in fixtures.
+const WORKSPACE_PLACEHOLDER: &str = "{{workspace}}";
+
+/// A mock agent linked into a scratch directory as `name`, beside the script
+/// it replays, so it can stand in for a configured `codex` or `opencode` bin.
+/// Each install has its own `workspace` there, so tests can run in parallel.
+pub struct MockInstall {
+    dir: PathBuf,
+    pub bin: String,
+    pub workspace: String,
+}
+
+impl MockInstall {
+    pub fn codex(fixture: &str) -> Self {
+        Self::new(
+            env!("CARGO_BIN_EXE_mock-codex-app-server"),
+            "codex",
+            fixture,
+        )
+    }
+
+    pub fn opencode(fixture: &str) -> Self {
+        Self::new(env!("CARGO_BIN_EXE_mock-opencode-acp"), "opencode", fixture)
+    }
+
+    fn new(mock: &str, name: &str, fixture: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("mock-agent-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create mock dir");
+        let bin = dir.join(name);
+        std::os::unix::fs::symlink(mock, &bin).expect("link mock agent");
+        // ACP agents are started inside the workspace.
+        let workspace = dir.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        let workspace = workspace.to_string_lossy().into_owned();
+        let script = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture);
+        let script = std::fs::read_to_string(&script).expect("read script");
+        let escaped = json!(workspace).to_string();
+        let script = script.replace(WORKSPACE_PLACEHOLDER, &escaped[1..escaped.len() - 1]);
+        std::fs::write(dir.join(format!("{name}.jsonl")), script).expect("write script");
+        Self {
+            bin: bin.to_string_lossy().into_owned(),
+            dir,
+            workspace,
+        }
+    }
+}
+
+impl Drop for MockInstall {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A session for workspace `ws` whose `bin_key` (`codex_bin`, `opencode_bin`)
+/// points at the mock.
+pub async fn session(backend: &str, bin_key: &str, mock: &MockInstall) -> TestSession {
+    TestSession::new(json!({
+        "id": "ws",
+        "name": "Mock",
+        "path": mock.workspace,
+        "backend": backend,
+        bin_key: mock.bin,
+    }))
+    .await
+    .expect("workspace")
+}
+
+/// The next event the session published.
+pub async fn recv_event(session: &mut TestSession) -> Value {
+    tokio::time::timeout(STEP_TIMEOUT, session.next_event())
+        .await
+        .expect("the session went quiet")
+        .expect("event channel closed")
+}
+
+/// Skips events until one with `method` arrives.
+pub async fn next_event(session: &mut TestSession, method: &str) -> Value {
+    loop {
+        let event = recv_event(session).await;
+        if event["method"] == method {
+            return event;
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! A scripted stand-in for `codex app-server`; see `replay.rs` for the script format.
+
+mod replay;
+
+fn main() {
+    replay::run("app-server", "codex-cli 0.0.0-mock");
+}
@@ -0,0 +1,7 @@
+//! A scripted stand-in for `opencode acp`; see `replay.rs` for the script format.
+
+mod replay;
+
+fn main() {
+    replay::run("acp", "opencode 0.0.0-mock");
+}
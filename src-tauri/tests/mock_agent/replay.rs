@@ -0,0 +1,197 @@
+//! Replays a recorded JSON-RPC conversation over stdio, standing in for an
+//! agent CLI in tests.
+//!
+//! The script is JSONL, one step per line (blank lines and `#` comments are
+//! skipped; the tests fill in `{{workspace}}` before the agent runs), read from `MOCK_AGENT_SCRIPT` or else from `<argv[0]>.jsonl`, so
+//! a symlink named `codex` next to `codex.jsonl` needs no environment:
+//!
+//! - `{"expect": "initialize", "params": {..}, "result": {..}}` waits for the
+//!   client's next message, which must call `expect` with params containing
+//!   `params`, and answers it with `result` or `error` when either is given.
+//! - `{"reply": "session/prompt", "result": {..}}` answers an earlier
+//!   expected request that was left unanswered.
+//! - `{"send": {..}}` writes a notification or server request as-is.
+//! - `{"expectResponse": 0, "result": {..}}` waits for the client's response
+//!   to server request `0`, whose result must contain `result`.
+//! - `{"raw": ".."}` writes a line verbatim, `{"stderr": ".."}` logs one.
+//! - `{"sleepMs": 50}` pauses and `{"exit": 1}` exits immediately.
+//!
+//! Once the script runs out the agent idles until stdin closes. A client
+//! that strays from the script makes the agent exit with status 2.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const SCRIPT_ENV: &str = "MOCK_AGENT_SCRIPT";
+/// Overrides what `--version` prints.
+pub const VERSION_ENV: &str = "MOCK_AGENT_VERSION";
+const MISMATCH_STATUS: i32 = 2;
+
+/// Runs the mock agent for `subcommand` (`app-server`, `acp`).
+pub fn run(subcommand: &str, default_version: &str) -> ! {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("--version") => {
+            let version =
+                std::env::var(VERSION_ENV).unwrap_or_else(|_| default_version.to_string());
+            println!("{version}");
+            std::process::exit(0);
+        }
+        Some(arg) if arg == subcommand => {}
+        _ => {
+            eprintln!("usage: {} {subcommand} | --version", args[0]);
+            std::process::exit(MISMATCH_STATUS);
+        }
+    }
+    let path = std::env::var_os(SCRIPT_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("{}.jsonl", args[0])));
+    let script = match std::fs::read_to_string(&path) {
+        Ok(script) => script,
+        Err(error) => fail(&format!("cannot read {}: {error}", path.display())),
+    };
+    let steps: Vec<Value> = script
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|error| fail(&format!("bad script line {line}: {error}")))
+        })
+        .collect();
+    Replay::default().run(&steps);
+    // Stay up like a real agent until the client hangs up.
+    let mut line = String::new();
+    while std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .is_ok_and(|read| read > 0)
+    {
+        line.clear();
+    }
+    std::process::exit(0);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("mock agent: {message}");
+    std::process::exit(MISMATCH_STATUS);
+}
+
+/// Whether every key of `expected` is present in `actual` with a matching value.
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| contains(actual, value))
+        }),
+        _ => actual == expected,
+    }
+}
+
+#[derive(Default)]
+struct Replay {
+    /// Expected requests left unanswered, by method, for `reply` steps.
+    requests: HashMap<String, Value>,
+}
+
+impl Replay {
+    fn run(&mut self, steps: &[Value]) {
+        for step in steps {
+            if let Some(method) = step.get("expect").and_then(Value::as_str) {
+                self.expect(method, step);
+            } else if let Some(method) = step.get("reply").and_then(Value::as_str) {
+                let Some(request) = self.requests.remove(method) else {
+                    fail(&format!("no {method} request to reply to"));
+                };
+                answer(&request, step);
+            } else if let Some(message) = step.get("send") {
+                write_line(&message.to_string());
+            } else if let Some(id) = step.get("expectResponse") {
+                expect_response(id, step);
+            } else if let Some(raw) = step.get("raw").and_then(Value::as_str) {
+                write_line(raw);
+            } else if let Some(message) = step.get("stderr").and_then(Value::as_str) {
+                eprintln!("{message}");
+            } else if let Some(millis) = step.get("sleepMs").and_then(Value::as_u64) {
+                std::thread::sleep(Duration::from_millis(millis));
+            } else if let Some(code) = step.get("exit").and_then(Value::as_i64) {
+                std::process::exit(code as i32);
+            } else {
+                fail(&format!("unknown script step {step}"));
+            }
+        }
+    }
+
+    fn expect(&mut self, method: &str, step: &Value) {
+        let message = read_message();
+        if message.get("method").and_then(Value::as_str) != Some(method) {
+            fail(&format!("expected {method}, got {message}"));
+        }
+        if let Some(params) = step.get("params") {
+            let actual = message.get("params").unwrap_or(&Value::Null);
+            if !contains(actual, params) {
+                fail(&format!("{method} params {actual} do not contain {params}"));
+            }
+        }
+        if message.get("id").is_none() {
+            return;
+        }
+        if step.get("result").is_some() || step.get("error").is_some() {
+            answer(&message, step);
+        } else {
+            self.requests.insert(method.to_string(), message);
+        }
+    }
+}
+
+fn expect_response(id: &Value, step: &Value) {
+    let message = read_message();
+    if message.get("id") != Some(id) || message.get("method").is_some() {
+        fail(&format!("expected the response to {id}, got {message}"));
+    }
+    if let Some(result) = step.get("result") {
+        let actual = message.get("result").unwrap_or(&Value::Null);
+        if !contains(actual, result) {
+            fail(&format!("response {actual} does not contain {result}"));
+        }
+    }
+}
+
+/// Answers `request` with the step's `result` or `error`, echoing the
+/// request's `jsonrpc` marker like the real agents do.
+fn answer(request: &Value, step: &Value) {
+    let mut response = json!({ "id": request["id"] });
+    if let Some(version) = request.get("jsonrpc") {
+        response["jsonrpc"] = version.clone();
+    }
+    match step.get("error") {
+        Some(error) => response["error"] = error.clone(),
+        None => response["result"] = step.get("result").cloned().unwrap_or(Value::Null),
+    }
+    write_line(&response.to_string());
+}
+
+fn read_message() -> Value {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match std::io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => fail("stdin closed mid-script"),
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => {
+                return serde_json::from_str(line.trim())
+                    .unwrap_or_else(|error| fail(&format!("client sent bad JSON: {error}")))
+            }
+        }
+    }
+}
+
+fn write_line(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
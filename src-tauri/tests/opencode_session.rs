@@ -0,0 +1,31 @@
+//! OpenCode workspace sessions against the scripted `opencode acp`.
+
+mod common;
+
+use common::{next_event, session, MockInstall};
+use serde_json::json;
+
+#[tokio::test]
+async fn prompts_stream_updates_and_ask_permission() {
+    let mock = MockInstall::opencode("opencode_prompt.jsonl");
+    let mut session = session("opencode", "opencode_bin", &mock).await;
+    session.connect().await.expect("spawn session");
+    let thread = session.start_thread().await.expect("session/new");
+    assert_eq!(thread["id"], "ses_1");
+    session
+        .send_message("ses_1", "Clean up")
+        .await
+        .expect("session/prompt");
+
+    let permission = next_event(&mut session, "session/request_permission").await;
+    let id = permission["id"].as_u64().expect("request id");
+    session
+        .respond(id, json!({ "decision": "accept" }))
+        .await
+        .expect("respond");
+    let completed = next_event(&mut session, "acp/message/completed").await;
+    assert_eq!(completed["params"]["stopReason"], "end_turn");
+    assert!(completed["params"]["message"]
+        .to_string()
+        .contains("the build directory."));
+}
@@ -63,8 +63,12 @@ import { useLayoutMode } from "./hooks/useLayoutMode";
 import { useAppSettings } from "./hooks/useAppSettings";
 import { useUpdater } from "./hooks/useUpdater";
 import { useHandoffTldr } from "./hooks/useHandoffTldr";
+import { usePromptQueue } from "./hooks/usePromptQueue";
+import { useRateLimitAlerts } from "./hooks/useRateLimitAlerts";
+import { sandboxProfileOptions } from "./utils/sandboxProfiles";
 import type {
   AccessMode,
+  Attachment,
   BackendType,
   DiffLineReference,
   QueuedMessage,
@@ -109,9 +113,6 @@ function MainApp() {
     "projects" | "codex" | "git" | "log"
   >("codex");
   const tabletTab = activeTab === "projects" ? "codex" : activeTab;
-  const [queuedByThread, setQueuedByThread] = useState<
-    Record<string, QueuedMessage[]>
-  >({});
   const [prefillDraft, setPrefillDraft] = useState<QueuedMessage | null>(null);
   const [composerInsert, setComposerInsert] = useState<QueuedMessage | null>(null);
   const [settingsOpen, setSettingsOpen] = useState(false);
@@ -120,9 +121,6 @@ function MainApp() {
     const stored = localStorage.getItem("reduceTransparency");
     return stored === "true";
   });
-  const [flushingByThread, setFlushingByThread] = useState<Record<string, boolean>>(
-    {},
-  );
   const [worktreePrompt, setWorktreePrompt] = useState<{
     workspace: WorkspaceInfo;
     branch: string;
@@ -140,6 +138,7 @@ function MainApp() {
   } = useDebugLog();
 
   const updater = useUpdater({ onDebug: addDebugEntry });
+  useRateLimitAlerts({ onDebug: addDebugEntry });
 
   const { settings: appSettings, saveSettings, doctor } = useAppSettings();
 
@@ -152,8 +151,10 @@ function MainApp() {
     addWorktreeAgent,
     connectWorkspace,
     markWorkspaceConnected,
+    markWorkspaceDisconnected,
     updateWorkspaceSettings,
     updateWorkspaceCodexBin,
+    updateWorkspaceAcpAgent,
     removeWorkspace,
     removeWorktree,
     hasLoaded,
@@ -166,6 +167,17 @@ function MainApp() {
     );
   }, [appSettings.defaultAccessMode]);
 
+  const sandboxProfiles = useMemo(
+    () => sandboxProfileOptions(appSettings, activeWorkspace?.settings),
+    [appSettings, activeWorkspace?.settings],
+  );
+
+  useEffect(() => {
+    if (!sandboxProfiles.some((profile) => profile.name === accessMode)) {
+      setAccessMode("current");
+    }
+  }, [accessMode, sandboxProfiles]);
+
   useEffect(() => {
     localStorage.setItem("reduceTransparency", String(reduceTransparency));
   }, [reduceTransparency]);
@@ -261,6 +273,7 @@ function MainApp() {
   } = useThreads({
     activeWorkspace,
     onWorkspaceConnected: markWorkspaceConnected,
+    onWorkspaceDisconnected: markWorkspaceDisconnected,
     onDebug: addDebugEntry,
     model: resolvedModel,
     effort: selectedEffort,
@@ -268,6 +281,8 @@ function MainApp() {
     onMessageActivity: refreshGitStatus,
   });
 
+  const promptQueue = usePromptQueue(activeWorkspace?.id ?? null, activeThreadId);
+
   const {
     activeSessionId,
     sessionsByWorkspace,
@@ -378,9 +393,7 @@ function MainApp() {
     hasPendingApproval,
   });
 
-  const activeQueue = activeThreadId
-    ? queuedByThread[activeThreadId] ?? []
-    : [];
+  const activeQueue = activeThreadId ? promptQueue.prompts : [];
   const isWorktreeWorkspace = activeWorkspace?.kind === "worktree";
   const activeParentWorkspace = isWorktreeWorkspace
     ? workspaces.find((entry) => entry.id === activeWorkspace?.parentId) ?? null
@@ -467,7 +480,7 @@ function MainApp() {
     if (!workspace.connected) {
       await connectWorkspace(workspace);
     }
-    if (backend === "opencode") {
+    if (backend === "opencode" || backend === "acp") {
       await startSession();
     } else {
       await startThreadForWorkspace(workspace.id);
@@ -566,7 +579,7 @@ function MainApp() {
     });
   }
 
-  async function handleSend(text: string) {
+  async function handleSend(text: string, attachments: Attachment[] = []) {
     const trimmed = text.trim();
     if (!trimmed) {
       return;
@@ -581,26 +594,31 @@ function MainApp() {
       if (!activeSessionId) {
         const newSessionId = await startSession();
         if (newSessionId) {
-          await sendOpenCodeMessage(trimmed, { sessionId: newSessionId, ...openCodeOptions });
+          await sendOpenCodeMessage(trimmed, {
+            sessionId: newSessionId,
+            ...openCodeOptions,
+            attachments,
+          });
         }
       } else {
-        await sendOpenCodeMessage(trimmed, openCodeOptions);
+        await sendOpenCodeMessage(trimmed, { ...openCodeOptions, attachments });
       }
       return;
     }
-    if (activeThreadId && threadStatusById[activeThreadId]?.isReviewing) {
+    if (isReviewing) {
       return;
     }
     if (isProcessing && activeThreadId) {
-      const item: QueuedMessage = {
-        id: `${Date.now()}-${Math.random().toString(36).slice(2, 8)}`,
-        text: trimmed,
-        createdAt: Date.now(),
-      };
-      setQueuedByThread((prev) => ({
-        ...prev,
-        [activeThreadId]: [...(prev[activeThreadId] ?? []), item],
-      }));
+      // Reviews are not queued; the backend sends queued text as a turn.
+      if (trimmed.startsWith("/review")) {
+        return;
+      }
+      await promptQueue.enqueue(trimmed, {
+        model: resolvedModel,
+        effort: selectedEffort,
+        accessMode,
+        attachments,
+      });
       return;
     }
     if (activeWorkspace && !activeWorkspace.connected) {
@@ -610,52 +628,9 @@ function MainApp() {
       await startReview(trimmed);
       return;
     }
-    await sendUserMessage(trimmed);
+    await sendUserMessage(trimmed, attachments);
   }
 
-  useEffect(() => {
-    if (!activeThreadId || isProcessing || isReviewing) {
-      return;
-    }
-    if (flushingByThread[activeThreadId]) {
-      return;
-    }
-    const queue = queuedByThread[activeThreadId] ?? [];
-    if (queue.length === 0) {
-      return;
-    }
-    const threadId = activeThreadId;
-    const nextItem = queue[0];
-    setFlushingByThread((prev) => ({ ...prev, [threadId]: true }));
-    setQueuedByThread((prev) => ({
-      ...prev,
-      [threadId]: (prev[threadId] ?? []).slice(1),
-    }));
-    (async () => {
-      try {
-        if (nextItem.text.trim().startsWith("/review")) {
-          await startReview(nextItem.text);
-        } else {
-          await sendUserMessage(nextItem.text);
-        }
-      } catch {
-        setQueuedByThread((prev) => ({
-          ...prev,
-          [threadId]: [nextItem, ...(prev[threadId] ?? [])],
-        }));
-      } finally {
-        setFlushingByThread((prev) => ({ ...prev, [threadId]: false }));
-      }
-    })();
-  }, [
-    activeThreadId,
-    flushingByThread,
-    isProcessing,
-    isReviewing,
-    queuedByThread,
-    sendUserMessage,
-  ]);
-
   const handleDebugClick = () => {
     if (isCompact) {
       setActiveTab("log");
@@ -834,27 +809,17 @@ function MainApp() {
         }
       }}
       onEditQueued={(item) => {
-        if (!activeThreadId) {
-          return;
-        }
-        setQueuedByThread((prev) => ({
-          ...prev,
-          [activeThreadId]: (prev[activeThreadId] ?? []).filter(
-            (entry) => entry.id !== item.id,
-          ),
-        }));
+        void promptQueue.cancel(item.id);
         setPrefillDraft(item);
       }}
       onDeleteQueued={(id) => {
-        if (!activeThreadId) {
-          return;
-        }
-        setQueuedByThread((prev) => ({
-          ...prev,
-          [activeThreadId]: (prev[activeThreadId] ?? []).filter(
-            (entry) => entry.id !== id,
-          ),
-        }));
+        void promptQueue.cancel(id);
+      }}
+      onTogglePausedQueued={(item) => {
+        void promptQueue.togglePaused(item);
+      }}
+      onMoveQueued={(id, direction) => {
+        void promptQueue.move(id, direction);
       }}
       models={models}
       selectedModelId={selectedModelId}
@@ -864,6 +829,7 @@ function MainApp() {
       onSelectEffort={setSelectedEffort}
       accessMode={accessMode}
       onSelectAccessMode={setAccessMode}
+      sandboxProfiles={sandboxProfiles}
       skills={skills}
       files={files}
       isOpenCodeMode={isOpenCodeMode}
@@ -1347,6 +1313,12 @@ function MainApp() {
           onUpdateWorkspaceCodexBin={async (id, codexBin) => {
             await updateWorkspaceCodexBin(id, codexBin);
           }}
+          onUpdateWorkspaceAcpAgent={async (id, acpAgent) => {
+            await updateWorkspaceAcpAgent(id, acpAgent);
+          }}
+          onUpdateWorkspaceSettings={async (id, settings) => {
+            await updateWorkspaceSettings(id, settings);
+          }}
         />
       )}
       {openCodePickerOpen && (
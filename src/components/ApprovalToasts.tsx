@@ -24,7 +24,11 @@ export function ApprovalToasts({
       {approvals.map((request) => {
         const workspaceName = workspaceLabels.get(request.workspace_id);
         return (
-          <div key={request.request_id} className="approval-toast" role="alert">
+          <div
+            key={`${request.backend ?? "codex"}-${request.request_id}`}
+            className="approval-toast"
+            role="alert"
+          >
             <div className="approval-toast-header">
               <div className="approval-toast-title">Approval needed</div>
               {workspaceName ? (
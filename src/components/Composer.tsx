@@ -1,12 +1,44 @@
 import { useCallback, useEffect, useRef, useState } from "react";
-import type { QueuedMessage, ThreadTokenUsage } from "../types";
+import type { ClipboardEvent } from "react";
+import type {
+  AccessMode,
+  Attachment,
+  QueuedMessage,
+  QueuedPrompt,
+  ThreadTokenUsage,
+} from "../types";
 import { useComposerAutocompleteState } from "../hooks/useComposerAutocompleteState";
 import { ComposerInput } from "./ComposerInput";
 import { ComposerMetaBar } from "./ComposerMetaBar";
 import { ComposerQueue } from "./ComposerQueue";
+import { pickImagePaths } from "../services/tauri";
+
+function attachmentKey(attachment: Attachment) {
+  return attachment.type === "imageData" ? attachment.data : attachment.path;
+}
+
+function attachmentLabel(attachment: Attachment) {
+  if (attachment.type === "imageData") {
+    return attachment.name ?? "Pasted image";
+  }
+  const parts = attachment.path.replace(/\\/g, "/").split("/").filter(Boolean);
+  return parts.length ? parts[parts.length - 1] : attachment.path;
+}
+
+function readAsBase64(file: File) {
+  return new Promise<string>((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = () => {
+      const result = String(reader.result ?? "");
+      resolve(result.slice(result.indexOf(",") + 1));
+    };
+    reader.onerror = () => reject(reader.error);
+    reader.readAsDataURL(file);
+  });
+}
 
 type ComposerProps = {
-  onSend: (text: string) => void;
+  onSend: (text: string, attachments: Attachment[]) => void;
   onStop: () => void;
   canStop: boolean;
   disabled?: boolean;
@@ -16,14 +48,17 @@ type ComposerProps = {
   reasoningOptions: string[];
   selectedEffort: string | null;
   onSelectEffort: (effort: string) => void;
-  accessMode: "read-only" | "current" | "full-access";
-  onSelectAccessMode: (mode: "read-only" | "current" | "full-access") => void;
+  accessMode: AccessMode;
+  onSelectAccessMode: (mode: AccessMode) => void;
+  sandboxProfiles: { name: string; label: string }[];
   skills: { name: string; description?: string }[];
   files: string[];
   contextUsage?: ThreadTokenUsage | null;
-  queuedMessages?: QueuedMessage[];
-  onEditQueued?: (item: QueuedMessage) => void;
+  queuedMessages?: QueuedPrompt[];
+  onEditQueued?: (item: QueuedPrompt) => void;
   onDeleteQueued?: (id: string) => void;
+  onTogglePausedQueued?: (item: QueuedPrompt) => void;
+  onMoveQueued?: (id: string, direction: "up" | "down") => void;
   sendLabel?: string;
   prefillDraft?: QueuedMessage | null;
   onPrefillHandled?: (id: string) => void;
@@ -47,12 +82,15 @@ export function Composer({
   onSelectEffort,
   accessMode,
   onSelectAccessMode,
+  sandboxProfiles,
   skills,
   files,
   contextUsage = null,
   queuedMessages = [],
   onEditQueued,
   onDeleteQueued,
+  onTogglePausedQueued,
+  onMoveQueued,
   sendLabel = "Send",
   prefillDraft = null,
   onPrefillHandled,
@@ -64,8 +102,51 @@ export function Composer({
 }: ComposerProps) {
   const [text, setText] = useState("");
   const [selectionStart, setSelectionStart] = useState<number | null>(null);
+  const [attachments, setAttachments] = useState<Attachment[]>([]);
   const textareaRef = useRef<HTMLTextAreaElement | null>(null);
 
+  const addAttachments = useCallback((next: Attachment[]) => {
+    setAttachments((prev) => {
+      const seen = new Set(prev.map(attachmentKey));
+      return [...prev, ...next.filter((item) => !seen.has(attachmentKey(item)))];
+    });
+  }, []);
+
+  const removeAttachment = useCallback((key: string) => {
+    setAttachments((prev) => prev.filter((item) => attachmentKey(item) !== key));
+  }, []);
+
+  const handleFileMention = useCallback(
+    (path: string) => addAttachments([{ type: "file", path }]),
+    [addAttachments],
+  );
+
+  const handleAttachImages = useCallback(async () => {
+    const paths = await pickImagePaths();
+    addAttachments(paths.map((path) => ({ type: "imagePath", path })));
+  }, [addAttachments]);
+
+  const handlePaste = useCallback(
+    async (event: ClipboardEvent<HTMLTextAreaElement>) => {
+      const images = Array.from(event.clipboardData.files).filter((file) =>
+        file.type.startsWith("image/"),
+      );
+      if (!images.length) {
+        return;
+      }
+      event.preventDefault();
+      const pasted = await Promise.all(
+        images.map(async (file) => ({
+          type: "imageData" as const,
+          data: await readAsBase64(file),
+          name: file.name || null,
+        })),
+      );
+      addAttachments(pasted);
+    },
+    [addAttachments],
+  );
+
   const handleSend = useCallback(() => {
     if (disabled) {
       return;
@@ -74,9 +155,10 @@ export function Composer({
     if (!trimmed) {
       return;
     }
-    onSend(trimmed);
+    onSend(trimmed, attachments);
     setText("");
-  }, [disabled, onSend, text]);
+    setAttachments([]);
+  }, [attachments, disabled, onSend, text]);
 
   const {
     isAutocompleteOpen,
@@ -96,6 +178,7 @@ export function Composer({
     textareaRef,
     setText,
     setSelectionStart,
+    onFileMention: handleFileMention,
   });
 
   useEffect(() => {
@@ -120,7 +203,35 @@ export function Composer({
         queuedMessages={queuedMessages}
         onEditQueued={onEditQueued}
         onDeleteQueued={onDeleteQueued}
+        onTogglePausedQueued={onTogglePausedQueued}
+        onMoveQueued={onMoveQueued}
       />
+      {attachments.length > 0 && (
+        <div className="composer-attachments">
+          {attachments.map((attachment) => {
+            const key = attachmentKey(attachment);
+            return (
+              <span
+                key={key}
+                className={`composer-attachment is-${attachment.type}`}
+                title={attachment.type === "imageData" ? undefined : attachment.path}
+              >
+                <span className="composer-attachment-label">
+                  {attachmentLabel(attachment)}
+                </span>
+                <button
+                  type="button"
+                  className="composer-attachment-remove"
+                  onClick={() => removeAttachment(key)}
+                  aria-label={`Remove ${attachmentLabel(attachment)}`}
+                >
+                  ×
+                </button>
+              </span>
+            );
+          })}
+        </div>
+      )}
       <ComposerInput
         text={text}
         disabled={disabled}
@@ -128,6 +239,8 @@ export function Composer({
         canStop={canStop}
         onStop={onStop}
         onSend={handleSend}
+        onAttach={() => void handleAttachImages()}
+        onPaste={(event) => void handlePaste(event)}
         onTextChange={handleTextChange}
         onSelectionChange={handleSelectionChange}
         onKeyDown={(event) => {
@@ -157,6 +270,7 @@ export function Composer({
         onSelectEffort={onSelectEffort}
         accessMode={accessMode}
         onSelectAccessMode={onSelectAccessMode}
+        sandboxProfiles={sandboxProfiles}
         contextUsage={contextUsage}
         isOpenCodeMode={isOpenCodeMode}
         openCodeModelLabel={openCodeModelLabel}
@@ -1,5 +1,6 @@
 import { useEffect, useRef } from "react";
-import type { KeyboardEvent, RefObject } from "react";
+import type { ClipboardEvent, KeyboardEvent, RefObject } from "react";
+import { Paperclip } from "lucide-react";
 import type { AutocompleteItem } from "../hooks/useComposerAutocomplete";
 
 type ComposerInputProps = {
@@ -9,6 +10,8 @@ type ComposerInputProps = {
   canStop: boolean;
   onStop: () => void;
   onSend: () => void;
+  onAttach: () => void;
+  onPaste: (event: ClipboardEvent<HTMLTextAreaElement>) => void;
   onTextChange: (next: string, selectionStart: number | null) => void;
   onSelectionChange: (selectionStart: number | null) => void;
   onKeyDown: (event: KeyboardEvent<HTMLTextAreaElement>) => void;
@@ -27,6 +30,8 @@ export function ComposerInput({
   canStop,
   onStop,
   onSend,
+  onAttach,
+  onPaste,
   onTextChange,
   onSelectionChange,
   onKeyDown,
@@ -101,6 +106,7 @@ export function ComposerInput({
           }
           disabled={disabled}
           onKeyDown={onKeyDown}
+          onPaste={onPaste}
         />
         {suggestionsOpen && (
           <div className="composer-suggestions" role="listbox" ref={suggestionListRef}>
@@ -144,6 +150,15 @@ export function ComposerInput({
           </div>
         )}
       </div>
+      <button
+        className="composer-attach"
+        onClick={onAttach}
+        disabled={disabled}
+        aria-label="Attach images"
+        title="Attach images"
+      >
+        <Paperclip size={14} aria-hidden />
+      </button>
       <button
         className="composer-stop"
         onClick={onStop}
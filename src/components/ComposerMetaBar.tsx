@@ -11,6 +11,7 @@ type ComposerMetaBarProps = {
   onSelectEffort: (effort: string) => void;
   accessMode: AccessMode;
   onSelectAccessMode: (mode: AccessMode) => void;
+  sandboxProfiles: { name: string; label: string }[];
   contextUsage?: ThreadTokenUsage | null;
   isOpenCodeMode?: boolean;
   openCodeModelLabel?: string;
@@ -27,6 +28,7 @@ export function ComposerMetaBar({
   onSelectEffort,
   accessMode,
   onSelectAccessMode,
+  sandboxProfiles,
   contextUsage = null,
   isOpenCodeMode = false,
   openCodeModelLabel = "Auto (OpenCode default)",
@@ -209,12 +211,14 @@ export function ComposerMetaBar({
             disabled={disabled}
             value={accessMode}
             onChange={(event) =>
-              onSelectAccessMode(event.target.value as AccessMode)
+              onSelectAccessMode(event.target.value)
             }
           >
-            <option value="read-only">Read only</option>
-            <option value="current">On-Request</option>
-            <option value="full-access">Full access</option>
+            {sandboxProfiles.map((profile) => (
+              <option key={profile.name} value={profile.name}>
+                {profile.label}
+              </option>
+            ))}
           </select>
         </div>
       </div>
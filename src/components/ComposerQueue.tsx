@@ -2,21 +2,37 @@ import { useCallback } from "react";
 import { LogicalPosition } from "@tauri-apps/api/dpi";
 import { Menu, MenuItem } from "@tauri-apps/api/menu";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import type { QueuedMessage } from "../types";
+import type { QueuedPrompt } from "../types";
 
 type ComposerQueueProps = {
-  queuedMessages: QueuedMessage[];
-  onEditQueued?: (item: QueuedMessage) => void;
+  queuedMessages: QueuedPrompt[];
+  onEditQueued?: (item: QueuedPrompt) => void;
   onDeleteQueued?: (id: string) => void;
+  onTogglePausedQueued?: (item: QueuedPrompt) => void;
+  onMoveQueued?: (id: string, direction: "up" | "down") => void;
 };
 
+function scheduleLabel(item: QueuedPrompt) {
+  const schedule = item.schedule;
+  if (schedule.type === "at") {
+    return new Date(schedule.runAt).toLocaleString();
+  }
+  if (schedule.type === "every") {
+    const minutes = Math.round(schedule.intervalSecs / 60);
+    return minutes % 60 === 0 ? `every ${minutes / 60}h` : `every ${minutes}m`;
+  }
+  return null;
+}
+
 export function ComposerQueue({
   queuedMessages,
   onEditQueued,
   onDeleteQueued,
+  onTogglePausedQueued,
+  onMoveQueued,
 }: ComposerQueueProps) {
   const handleQueueMenu = useCallback(
-    async (event: React.MouseEvent, item: QueuedMessage) => {
+    async (event: React.MouseEvent, item: QueuedPrompt) => {
       event.preventDefault();
       event.stopPropagation();
       const { clientX, clientY } = event;
@@ -28,12 +44,26 @@ export function ComposerQueue({
         text: "Delete",
         action: () => onDeleteQueued?.(item.id),
       });
-      const menu = await Menu.new({ items: [editItem, deleteItem] });
+      const pauseItem = await MenuItem.new({
+        text: item.paused ? "Resume" : "Pause",
+        action: () => onTogglePausedQueued?.(item),
+      });
+      const upItem = await MenuItem.new({
+        text: "Move up",
+        action: () => onMoveQueued?.(item.id, "up"),
+      });
+      const downItem = await MenuItem.new({
+        text: "Move down",
+        action: () => onMoveQueued?.(item.id, "down"),
+      });
+      const menu = await Menu.new({
+        items: [editItem, pauseItem, upItem, downItem, deleteItem],
+      });
       const window = getCurrentWindow();
       const position = new LogicalPosition(clientX, clientY);
       await menu.popup(position, window);
     },
-    [onDeleteQueued, onEditQueued],
+    [onDeleteQueued, onEditQueued, onMoveQueued, onTogglePausedQueued],
   );
 
   if (queuedMessages.length === 0) {
@@ -44,18 +74,27 @@ export function ComposerQueue({
     <div className="composer-queue">
       <div className="composer-queue-title">Queued</div>
       <div className="composer-queue-list">
-        {queuedMessages.map((item) => (
-          <div key={item.id} className="composer-queue-item">
-            <span className="composer-queue-text">{item.text}</span>
-            <button
-              className="composer-queue-menu"
-              onClick={(event) => handleQueueMenu(event, item)}
-              aria-label="Queue item menu"
+        {queuedMessages.map((item) => {
+          const schedule = scheduleLabel(item);
+          return (
+            <div
+              key={item.id}
+              className={`composer-queue-item${item.paused ? " is-paused" : ""}`}
+              title={item.lastError ?? undefined}
             >
-              ...
-            </button>
-          </div>
-        ))}
+              <span className="composer-queue-text">{item.text}</span>
+              {schedule && <span className="composer-queue-schedule">{schedule}</span>}
+              {item.paused && <span className="composer-queue-schedule">paused</span>}
+              <button
+                className="composer-queue-menu"
+                onClick={(event) => handleQueueMenu(event, item)}
+                aria-label="Queue item menu"
+              >
+                ...
+              </button>
+            </div>
+          );
+        })}
       </div>
     </div>
   );
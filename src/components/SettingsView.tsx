@@ -1,16 +1,40 @@
 import { useEffect, useMemo, useState } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
 import {
+  BarChart3,
+  Bot,
   ChevronDown,
   ChevronUp,
+  Copy,
+  Globe,
   Laptop2,
   LayoutGrid,
+  Lock,
+  Plus,
+  ShieldCheck,
   Stethoscope,
   TerminalSquare,
   Trash2,
   X,
 } from "lucide-react";
-import type { AppSettings, CodexDoctorResult, WorkspaceInfo } from "../types";
+import type {
+  AcpAgentConfig,
+  AppSettings,
+  ApprovalRule,
+  CodexDoctorResult,
+  ModelPrice,
+  RateLimitWatchSettings,
+  RemoteAccessSettings,
+  RemoteScope,
+  RemoteServerStatus,
+  SandboxProfile,
+  UsageGroup,
+  WorkspaceInfo,
+  WorkspaceSettings,
+} from "../types";
+import { useUsageSummary } from "../hooks/useUsageSummary";
+import { generateRemoteToken, getRemoteServerStatus } from "../services/tauri";
+import { sandboxProfileOptions } from "../utils/sandboxProfiles";
 
 type SettingsViewProps = {
   workspaces: WorkspaceInfo[];
@@ -23,10 +47,150 @@ type SettingsViewProps = {
   onUpdateAppSettings: (next: AppSettings) => Promise<void>;
   onRunDoctor: (codexBin: string | null) => Promise<CodexDoctorResult>;
   onUpdateWorkspaceCodexBin: (id: string, codexBin: string | null) => Promise<void>;
+  onUpdateWorkspaceAcpAgent: (
+    id: string,
+    acpAgent: AcpAgentConfig | null,
+  ) => Promise<void>;
+  onUpdateWorkspaceSettings: (
+    id: string,
+    settings: WorkspaceSettings,
+  ) => Promise<void>;
 };
 
 type SettingsSection = "projects" | "display";
-type CodexSection = SettingsSection | "codex";
+type CodexSection =
+  | SettingsSection
+  | "codex"
+  | "agents"
+  | "approvals"
+  | "sandbox"
+  | "usage"
+  | "remote";
+
+// Drafts for the app-wide profile list live under this key; the rest are workspace ids.
+const APP_PROFILES_KEY = "__app__";
+
+function emptyApprovalRule(): ApprovalRule {
+  return {
+    name: null,
+    decision: "accept",
+    target: "command",
+    commandPrefix: null,
+    pathGlob: null,
+    cwd: null,
+  };
+}
+
+function emptySandboxProfile(): SandboxProfile {
+  return {
+    name: "",
+    label: null,
+    mode: "workspaceWrite",
+    writableRoots: [],
+    networkAccess: true,
+    approvalPolicy: "on-request",
+    excludedPaths: [],
+  };
+}
+
+function emptyModelPrice(): ModelPrice {
+  return {
+    model: "",
+    inputPerMillion: 0,
+    cachedInputPerMillion: null,
+    outputPerMillion: 0,
+  };
+}
+
+const DEFAULT_RATE_LIMIT_WATCH: RateLimitWatchSettings = {
+  enabled: true,
+  pollIntervalSecs: 300,
+  thresholds: [80, 95],
+  notifyOnReset: true,
+};
+
+const DEFAULT_REMOTE_ACCESS: RemoteAccessSettings = {
+  enabled: false,
+  bindAddress: "127.0.0.1:4732",
+  tokens: [],
+};
+
+function parseThresholds(value: string) {
+  return value
+    .split(",")
+    .map((entry) => Number.parseInt(entry.trim(), 10))
+    .filter((entry) => Number.isFinite(entry));
+}
+
+function parsePrice(value: string) {
+  const parsed = Number.parseFloat(value);
+  return Number.isFinite(parsed) ? parsed : 0;
+}
+
+const tokenFormat = new Intl.NumberFormat(undefined, { notation: "compact" });
+const costFormat = new Intl.NumberFormat(undefined, {
+  style: "currency",
+  currency: "USD",
+  maximumFractionDigits: 2,
+});
+
+function splitPaths(value: string) {
+  return value
+    .split(",")
+    .map((entry) => entry.trim())
+    .filter(Boolean);
+}
+
+function optionalText(value: string) {
+  return value.trim() ? value : null;
+}
+
+// Splits a shell-like command line on whitespace, honouring single and double quotes.
+function splitCommandLine(value: string): string[] {
+  const parts: string[] = [];
+  let current = "";
+  let quote: string | null = null;
+  let hasToken = false;
+  for (const char of value) {
+    if (quote) {
+      if (char === quote) {
+        quote = null;
+      } else {
+        current += char;
+      }
+    } else if (char === "'" || char === '"') {
+      quote = char;
+      hasToken = true;
+    } else if (/\s/.test(char)) {
+      if (hasToken) {
+        parts.push(current);
+        current = "";
+        hasToken = false;
+      }
+    } else {
+      current += char;
+      hasToken = true;
+    }
+  }
+  if (hasToken) {
+    parts.push(current);
+  }
+  return parts;
+}
+
+function formatCommandLine(agent: AcpAgentConfig | null | undefined) {
+  if (!agent) {
+    return "";
+  }
+  return [agent.command, ...agent.args]
+    .map((part) => {
+      if (part && !/[\s'"]/.test(part)) {
+        return part;
+      }
+      return part.includes("'") ? `"${part}"` : `'${part}'`;
+    })
+    .join(" ");
+}
 
 function orderValue(workspace: WorkspaceInfo) {
   const value = workspace.settings.sortOrder;
@@ -44,10 +208,42 @@ export function SettingsView({
   onUpdateAppSettings,
   onRunDoctor,
   onUpdateWorkspaceCodexBin,
+  onUpdateWorkspaceAcpAgent,
+  onUpdateWorkspaceSettings,
 }: SettingsViewProps) {
   const [activeSection, setActiveSection] = useState<CodexSection>("projects");
   const [codexPathDraft, setCodexPathDraft] = useState(appSettings.codexBin ?? "");
   const [overrideDrafts, setOverrideDrafts] = useState<Record<string, string>>({});
+  const [agentDrafts, setAgentDrafts] = useState<Record<string, string>>({});
+  const [ruleDrafts, setRuleDrafts] = useState<Record<string, ApprovalRule[]>>({});
+  const [profileDrafts, setProfileDrafts] = useState<Record<string, SandboxProfile[]>>(
+    {},
+  );
+  const [profileError, setProfileError] = useState<string | null>(null);
+  const [usageGroup, setUsageGroup] = useState<UsageGroup>("day");
+  const [usageWorkspaceId, setUsageWorkspaceId] = useState<string | null>(null);
+  const [usageDays, setUsageDays] = useState<number | null>(30);
+  const [priceDrafts, setPriceDrafts] = useState<ModelPrice[] | null>(null);
+  const [priceError, setPriceError] = useState<string | null>(null);
+  const rateLimitWatch = appSettings.rateLimitWatch ?? DEFAULT_RATE_LIMIT_WATCH;
+  const [pollDraft, setPollDraft] = useState(String(rateLimitWatch.pollIntervalSecs));
+  const [thresholdDraft, setThresholdDraft] = useState(
+    rateLimitWatch.thresholds.join(", "),
+  );
+  const [rateLimitError, setRateLimitError] = useState<string | null>(null);
+  const remoteAccess = appSettings.remoteAccess ?? DEFAULT_REMOTE_ACCESS;
+  const [bindDraft, setBindDraft] = useState(remoteAccess.bindAddress);
+  const [tokenLabelDraft, setTokenLabelDraft] = useState("");
+  const [tokenScopeDraft, setTokenScopeDraft] = useState<RemoteScope>("readOnly");
+  const [remoteStatus, setRemoteStatus] = useState<RemoteServerStatus | null>(null);
+  const [remoteError, setRemoteError] = useState<string | null>(null);
+  const usage = useUsageSummary(
+    activeSection === "usage",
+    usageGroup,
+    usageWorkspaceId,
+    usageDays,
+  );
+  const prices = priceDrafts ?? appSettings.costTable ?? [];
   const [doctorState, setDoctorState] = useState<{
     status: "idle" | "running" | "done";
     result: CodexDoctorResult | null;
@@ -67,6 +263,162 @@ export function SettingsView({
       });
   }, [workspaces]);
 
+  const renderProfileEditor = (
+    key: string,
+    title: string,
+    saved: SandboxProfile[],
+    onSave: (profiles: SandboxProfile[]) => Promise<void>,
+  ) => {
+    const profiles = profileDrafts[key] ?? saved;
+    const dirty = profileDrafts[key] !== undefined;
+    const updateProfile = (index: number, patch: Partial<SandboxProfile>) =>
+      setProfileDrafts((prev) => ({
+        ...prev,
+        [key]: profiles.map((profile, profileIndex) =>
+          profileIndex === index ? { ...profile, ...patch } : profile,
+        ),
+      }));
+    return (
+      <div key={key} className="settings-field">
+        <div className="settings-field-label">{title}</div>
+        <div className="settings-overrides">
+          {profiles.map((profile, index) => (
+            <div key={index} className="settings-override-row">
+              <div className="settings-override-actions">
+                <input
+                  className="settings-input settings-input--compact"
+                  value={profile.name}
+                  placeholder="Name"
+                  onChange={(event) => updateProfile(index, { name: event.target.value })}
+                />
+                <input
+                  className="settings-input settings-input--compact"
+                  value={profile.label ?? ""}
+                  placeholder="Label"
+                  onChange={(event) =>
+                    updateProfile(index, { label: optionalText(event.target.value) })
+                  }
+                />
+                <select
+                  className="settings-select"
+                  value={profile.mode}
+                  onChange={(event) =>
+                    updateProfile(index, {
+                      mode: event.target.value as SandboxProfile["mode"],
+                    })
+                  }
+                >
+                  <option value="readOnly">Read only</option>
+                  <option value="workspaceWrite">Workspace write</option>
+                  <option value="dangerFullAccess">Full access</option>
+                </select>
+                <select
+                  className="settings-select"
+                  value={profile.approvalPolicy}
+                  onChange={(event) =>
+                    updateProfile(index, {
+                      approvalPolicy: event.target.value as SandboxProfile["approvalPolicy"],
+                    })
+                  }
+                >
+                  <option value="on-request">Ask on request</option>
+                  <option value="on-failure">Ask on failure</option>
+                  <option value="untrusted">Ask unless trusted</option>
+                  <option value="never">Never ask</option>
+                </select>
+                <label className="settings-checkbox">
+                  <input
+                    type="checkbox"
+                    checked={profile.networkAccess}
+                    onChange={(event) =>
+                      updateProfile(index, { networkAccess: event.target.checked })
+                    }
+                  />
+                  Network
+                </label>
+              </div>
+              <div className="settings-override-actions">
+                <input
+                  className="settings-input settings-input--compact"
+                  value={profile.writableRoots.join(", ")}
+                  placeholder="Extra writable roots"
+                  disabled={profile.mode !== "workspaceWrite"}
+                  onChange={(event) =>
+                    updateProfile(index, { writableRoots: splitPaths(event.target.value) })
+                  }
+                />
+                <input
+                  className="settings-input settings-input--compact"
+                  value={profile.excludedPaths.join(", ")}
+                  placeholder="Excluded paths"
+                  title="Needs workspace-write with Ask unless trusted"
+                  disabled={
+                    profile.excludedPaths.length === 0 &&
+                    (profile.mode !== "workspaceWrite" || profile.approvalPolicy !== "untrusted")
+                  }
+                  onChange={(event) =>
+                    updateProfile(index, { excludedPaths: splitPaths(event.target.value) })
+                  }
+                />
+                <button
+                  type="button"
+                  className="ghost icon-button"
+                  aria-label="Remove profile"
+                  onClick={() =>
+                    setProfileDrafts((prev) => ({
+                      ...prev,
+                      [key]: profiles.filter((_, profileIndex) => profileIndex !== index),
+                    }))
+                  }
+                >
+                  <Trash2 aria-hidden />
+                </button>
+              </div>
+            </div>
+          ))}
+          {profiles.length === 0 && (
+            <div className="settings-empty">No custom profiles.</div>
+          )}
+        </div>
+        <div className="settings-field-actions">
+          <button
+            type="button"
+            className="ghost"
+            onClick={() =>
+              setProfileDrafts((prev) => ({
+                ...prev,
+                [key]: [...profiles, emptySandboxProfile()],
+              }))
+            }
+          >
+            <Plus aria-hidden />
+            Add profile
+          </button>
+          <button
+            type="button"
+            className="primary"
+            disabled={!dirty}
+            onClick={async () => {
+              try {
+                await onSave(profiles);
+                setProfileError(null);
+                setProfileDrafts((prev) => {
+                  const next = { ...prev };
+                  delete next[key];
+                  return next;
+                });
+              } catch (error) {
+                setProfileError(error instanceof Error ? error.message : String(error));
+              }
+            }}
+          >
+            Save
+          </button>
+        </div>
+      </div>
+    );
+  };
+
   useEffect(() => {
     setCodexPathDraft(appSettings.codexBin ?? "");
   }, [appSettings.codexBin]);
@@ -80,8 +432,74 @@ export function SettingsView({
       });
       return next;
     });
+    setAgentDrafts((prev) => {
+      const next: Record<string, string> = {};
+      projects.forEach((workspace) => {
+        next[workspace.id] =
+          prev[workspace.id] ?? formatCommandLine(workspace.acpAgent);
+      });
+      return next;
+    });
   }, [projects]);
 
+  useEffect(() => {
+    setPollDraft(String(rateLimitWatch.pollIntervalSecs));
+    setThresholdDraft(rateLimitWatch.thresholds.join(", "));
+  }, [rateLimitWatch.pollIntervalSecs, rateLimitWatch.thresholds]);
+
+  const saveRateLimitWatch = async (patch: Partial<RateLimitWatchSettings>) => {
+    try {
+      await onUpdateAppSettings({
+        ...appSettings,
+        rateLimitWatch: { ...rateLimitWatch, ...patch },
+      });
+      setRateLimitError(null);
+    } catch (error) {
+      setRateLimitError(error instanceof Error ? error.message : String(error));
+    }
+  };
+
+  useEffect(() => {
+    setBindDraft(remoteAccess.bindAddress);
+  }, [remoteAccess.bindAddress]);
+
+  const refreshRemoteStatus = async () => {
+    try {
+      setRemoteStatus(await getRemoteServerStatus());
+    } catch (error) {
+      setRemoteError(error instanceof Error ? error.message : String(error));
+    }
+  };
+
+  useEffect(() => {
+    if (activeSection === "remote") {
+      void refreshRemoteStatus();
+    }
+  }, [activeSection]);
+
+  const saveRemoteAccess = async (patch: Partial<RemoteAccessSettings>) => {
+    try {
+      await onUpdateAppSettings({
+        ...appSettings,
+        remoteAccess: { ...remoteAccess, ...patch },
+      });
+      setRemoteError(null);
+    } catch (error) {
+      setRemoteError(error instanceof Error ? error.message : String(error));
+    }
+    await refreshRemoteStatus();
+  };
+
+  const handleAddRemoteToken = async () => {
+    try {
+      const token = await generateRemoteToken(tokenLabelDraft, tokenScopeDraft);
+      await saveRemoteAccess({ tokens: [...remoteAccess.tokens, token] });
+      setTokenLabelDraft("");
+    } catch (error) {
+      setRemoteError(error instanceof Error ? error.message : String(error));
+    }
+  };
+
   const codexDirty =
     (codexPathDraft.trim() || null) !== (appSettings.codexBin ?? null);
 
@@ -171,6 +589,46 @@ export function SettingsView({
               <TerminalSquare aria-hidden />
               Codex
             </button>
+            <button
+              type="button"
+              className={`settings-nav ${activeSection === "agents" ? "active" : ""}`}
+              onClick={() => setActiveSection("agents")}
+            >
+              <Bot aria-hidden />
+              Agents
+            </button>
+            <button
+              type="button"
+              className={`settings-nav ${activeSection === "approvals" ? "active" : ""}`}
+              onClick={() => setActiveSection("approvals")}
+            >
+              <ShieldCheck aria-hidden />
+              Approvals
+            </button>
+            <button
+              type="button"
+              className={`settings-nav ${activeSection === "sandbox" ? "active" : ""}`}
+              onClick={() => setActiveSection("sandbox")}
+            >
+              <Lock aria-hidden />
+              Sandbox
+            </button>
+            <button
+              type="button"
+              className={`settings-nav ${activeSection === "usage" ? "active" : ""}`}
+              onClick={() => setActiveSection("usage")}
+            >
+              <BarChart3 aria-hidden />
+              Usage
+            </button>
+            <button
+              type="button"
+              className={`settings-nav ${activeSection === "remote" ? "active" : ""}`}
+              onClick={() => setActiveSection("remote")}
+            >
+              <Globe aria-hidden />
+              Remote
+            </button>
           </aside>
           <div className="settings-content">
             {activeSection === "projects" && (
@@ -347,16 +805,77 @@ export function SettingsView({
                     onChange={(event) =>
                       void onUpdateAppSettings({
                         ...appSettings,
-                        defaultAccessMode: event.target.value as AppSettings["defaultAccessMode"],
+                        defaultAccessMode: event.target.value,
                       })
                     }
                   >
-                    <option value="read-only">Read only</option>
-                    <option value="current">On-request</option>
-                    <option value="full-access">Full access</option>
+                    {sandboxProfileOptions(appSettings).map((profile) => (
+                      <option key={profile.name} value={profile.name}>
+                        {profile.label}
+                      </option>
+                    ))}
                   </select>
                 </div>
 
+                <div className="settings-field">
+                  <div className="settings-field-label">Rate limit alerts</div>
+                  <div className="settings-override-actions">
+                    <label className="settings-checkbox">
+                      <input
+                        type="checkbox"
+                        checked={rateLimitWatch.enabled}
+                        onChange={(event) =>
+                          void saveRateLimitWatch({ enabled: event.target.checked })
+                        }
+                      />
+                      Watch usage
+                    </label>
+                    <input
+                      className="settings-input settings-input--compact"
+                      type="number"
+                      min="30"
+                      value={pollDraft}
+                      aria-label="Poll interval in seconds"
+                      title="Poll interval (seconds)"
+                      onChange={(event) => setPollDraft(event.target.value)}
+                      onBlur={() => {
+                        const secs = Number.parseInt(pollDraft, 10);
+                        if (Number.isFinite(secs) && secs !== rateLimitWatch.pollIntervalSecs) {
+                          void saveRateLimitWatch({ pollIntervalSecs: secs });
+                        }
+                      }}
+                    />
+                    <input
+                      className="settings-input settings-input--compact"
+                      value={thresholdDraft}
+                      placeholder="80, 95"
+                      aria-label="Alert thresholds in percent"
+                      title="Alert thresholds (%)"
+                      onChange={(event) => setThresholdDraft(event.target.value)}
+                      onBlur={() => {
+                        const thresholds = parseThresholds(thresholdDraft);
+                        if (thresholds.join(",") !== rateLimitWatch.thresholds.join(",")) {
+                          void saveRateLimitWatch({ thresholds });
+                        }
+                      }}
+                    />
+                    <label className="settings-checkbox">
+                      <input
+                        type="checkbox"
+                        checked={rateLimitWatch.notifyOnReset}
+                        onChange={(event) =>
+                          void saveRateLimitWatch({ notifyOnReset: event.target.checked })
+                        }
+                      />
+                      On reset
+                    </label>
+                  </div>
+                  <div className="settings-help">
+                    {rateLimitError ??
+                      "Polls connected projects every few minutes and notifies when a window reaches a threshold or resets."}
+                  </div>
+                </div>
+
                 <div className="settings-field">
                   <div className="settings-field-label">Workspace overrides</div>
                   <div className="settings-overrides">
@@ -410,6 +929,584 @@ export function SettingsView({
 
               </section>
             )}
+            {activeSection === "agents" && (
+              <section className="settings-section">
+                <div className="settings-section-title">Agents</div>
+                <div className="settings-section-subtitle">
+                  Run any Agent Client Protocol CLI (for example{" "}
+                  <code>claude-code-acp</code>) in place of OpenCode for a project.
+                </div>
+                <div className="settings-field">
+                  <div className="settings-field-label">ACP agent command</div>
+                  <div className="settings-overrides">
+                    {projects.map((workspace) => (
+                      <div key={workspace.id} className="settings-override-row">
+                        <div className="settings-override-info">
+                          <div className="settings-project-name">{workspace.name}</div>
+                          <div className="settings-project-path">{workspace.path}</div>
+                        </div>
+                        <div className="settings-override-actions">
+                          <input
+                            className="settings-input settings-input--compact"
+                            value={agentDrafts[workspace.id] ?? ""}
+                            placeholder="Use OpenCode"
+                            onChange={(event) =>
+                              setAgentDrafts((prev) => ({
+                                ...prev,
+                                [workspace.id]: event.target.value,
+                              }))
+                            }
+                            onBlur={async () => {
+                              const draft = agentDrafts[workspace.id] ?? "";
+                              if (draft.trim() === formatCommandLine(workspace.acpAgent)) {
+                                return;
+                              }
+                              const [command, ...args] = splitCommandLine(draft);
+                              await onUpdateWorkspaceAcpAgent(
+                                workspace.id,
+                                command
+                                  ? { name: workspace.acpAgent?.name ?? null, command, args }
+                                  : null,
+                              );
+                            }}
+                          />
+                          <button
+                            type="button"
+                            className="ghost"
+                            onClick={async () => {
+                              setAgentDrafts((prev) => ({
+                                ...prev,
+                                [workspace.id]: "",
+                              }));
+                              await onUpdateWorkspaceAcpAgent(workspace.id, null);
+                            }}
+                          >
+                            Clear
+                          </button>
+                        </div>
+                      </div>
+                    ))}
+                    {projects.length === 0 && (
+                      <div className="settings-empty">No projects yet.</div>
+                    )}
+                  </div>
+                </div>
+              </section>
+            )}
+            {activeSection === "approvals" && (
+              <section className="settings-section">
+                <div className="settings-section-title">Approvals</div>
+                <div className="settings-section-subtitle">
+                  Answer Codex approval requests automatically. Decline rules win over
+                  accept rules; anything unmatched still asks you.
+                </div>
+                {projects.map((workspace) => {
+                  const saved = workspace.settings.approvalRules ?? [];
+                  const rules = ruleDrafts[workspace.id] ?? saved;
+                  const dirty = ruleDrafts[workspace.id] !== undefined;
+                  const updateRule = (index: number, patch: Partial<ApprovalRule>) =>
+                    setRuleDrafts((prev) => ({
+                      ...prev,
+                      [workspace.id]: rules.map((rule, ruleIndex) =>
+                        ruleIndex === index ? { ...rule, ...patch } : rule,
+                      ),
+                    }));
+                  return (
+                    <div key={workspace.id} className="settings-field">
+                      <div className="settings-field-label">{workspace.name}</div>
+                      <div className="settings-overrides">
+                        {rules.map((rule, index) => (
+                          <div key={index} className="settings-override-row">
+                            <div className="settings-override-actions">
+                              <select
+                                className="settings-select"
+                                value={rule.decision}
+                                onChange={(event) =>
+                                  updateRule(index, {
+                                    decision: event.target.value as ApprovalRule["decision"],
+                                  })
+                                }
+                              >
+                                <option value="accept">Accept</option>
+                                <option value="decline">Decline</option>
+                              </select>
+                              <select
+                                className="settings-select"
+                                value={rule.target}
+                                onChange={(event) =>
+                                  updateRule(index, {
+                                    target: event.target.value as ApprovalRule["target"],
+                                  })
+                                }
+                              >
+                                <option value="any">Any request</option>
+                                <option value="command">Commands</option>
+                                <option value="fileChange">File changes</option>
+                              </select>
+                            </div>
+                            <div className="settings-override-actions">
+                              <input
+                                className="settings-input settings-input--compact"
+                                value={rule.commandPrefix ?? ""}
+                                placeholder="Command prefix"
+                                onChange={(event) =>
+                                  updateRule(index, {
+                                    commandPrefix: optionalText(event.target.value),
+                                  })
+                                }
+                              />
+                              <input
+                                className="settings-input settings-input--compact"
+                                value={rule.pathGlob ?? ""}
+                                placeholder="Path glob"
+                                onChange={(event) =>
+                                  updateRule(index, {
+                                    pathGlob: optionalText(event.target.value),
+                                  })
+                                }
+                              />
+                              <input
+                                className="settings-input settings-input--compact"
+                                value={rule.cwd ?? ""}
+                                placeholder="Directory"
+                                onChange={(event) =>
+                                  updateRule(index, { cwd: optionalText(event.target.value) })
+                                }
+                              />
+                              <button
+                                type="button"
+                                className="ghost icon-button"
+                                aria-label="Remove rule"
+                                onClick={() =>
+                                  setRuleDrafts((prev) => ({
+                                    ...prev,
+                                    [workspace.id]: rules.filter(
+                                      (_, ruleIndex) => ruleIndex !== index,
+                                    ),
+                                  }))
+                                }
+                              >
+                                <Trash2 aria-hidden />
+                              </button>
+                            </div>
+                          </div>
+                        ))}
+                        {rules.length === 0 && (
+                          <div className="settings-empty">No rules; every request asks.</div>
+                        )}
+                      </div>
+                      <div className="settings-field-actions">
+                        <button
+                          type="button"
+                          className="ghost"
+                          onClick={() =>
+                            setRuleDrafts((prev) => ({
+                              ...prev,
+                              [workspace.id]: [...rules, emptyApprovalRule()],
+                            }))
+                          }
+                        >
+                          <Plus aria-hidden />
+                          Add rule
+                        </button>
+                        <button
+                          type="button"
+                          className="primary"
+                          disabled={!dirty}
+                          onClick={async () => {
+                            await onUpdateWorkspaceSettings(workspace.id, {
+                              ...workspace.settings,
+                              approvalRules: rules,
+                            });
+                            setRuleDrafts((prev) => {
+                              const next = { ...prev };
+                              delete next[workspace.id];
+                              return next;
+                            });
+                          }}
+                        >
+                          Save
+                        </button>
+                      </div>
+                    </div>
+                  );
+                })}
+                {projects.length === 0 && (
+                  <div className="settings-empty">No projects yet.</div>
+                )}
+              </section>
+            )}
+            {activeSection === "sandbox" && (
+              <section className="settings-section">
+                <div className="settings-section-title">Sandbox</div>
+                <div className="settings-section-subtitle">
+                  Named profiles to pick from the composer. Project profiles override app
+                  profiles with the same name; paths may be relative to the project.
+                </div>
+                {profileError && <div className="settings-help">{profileError}</div>}
+                {renderProfileEditor(
+                  APP_PROFILES_KEY,
+                  "All projects",
+                  appSettings.sandboxProfiles ?? [],
+                  (profiles) =>
+                    onUpdateAppSettings({ ...appSettings, sandboxProfiles: profiles }),
+                )}
+                {projects.map((workspace) =>
+                  renderProfileEditor(
+                    workspace.id,
+                    workspace.name,
+                    workspace.settings.sandboxProfiles ?? [],
+                    (profiles) =>
+                      onUpdateWorkspaceSettings(workspace.id, {
+                        ...workspace.settings,
+                        sandboxProfiles: profiles,
+                      }),
+                  ),
+                )}
+              </section>
+            )}
+            {activeSection === "usage" && (
+              <section className="settings-section">
+                <div className="settings-section-title">Usage</div>
+                <div className="settings-section-subtitle">
+                  Tokens spent per turn, priced with the cost table below. Turns started
+                  without a model choice are recorded as "default".
+                </div>
+                <div className="settings-field">
+                  <div className="settings-override-actions">
+                    <select
+                      className="settings-select"
+                      value={usageGroup}
+                      onChange={(event) => setUsageGroup(event.target.value as UsageGroup)}
+                    >
+                      <option value="day">By day</option>
+                      <option value="model">By model</option>
+                      <option value="workspace">By project</option>
+                      <option value="thread">By thread</option>
+                    </select>
+                    <select
+                      className="settings-select"
+                      value={usageWorkspaceId ?? ""}
+                      onChange={(event) => setUsageWorkspaceId(event.target.value || null)}
+                    >
+                      <option value="">All projects</option>
+                      {workspaces.map((workspace) => (
+                        <option key={workspace.id} value={workspace.id}>
+                          {workspace.name}
+                        </option>
+                      ))}
+                    </select>
+                    <select
+                      className="settings-select"
+                      value={usageDays ?? ""}
+                      onChange={(event) =>
+                        setUsageDays(event.target.value ? Number(event.target.value) : null)
+                      }
+                    >
+                      <option value="7">Last 7 days</option>
+                      <option value="30">Last 30 days</option>
+                      <option value="90">Last 90 days</option>
+                      <option value="">All time</option>
+                    </select>
+                  </div>
+                  {usage.error && <div className="settings-help">{usage.error}</div>}
+                  {usage.summary && (
+                    <table className="settings-usage-table">
+                      <thead>
+                        <tr>
+                          <th />
+                          <th>Turns</th>
+                          <th>Input</th>
+                          <th>Cached</th>
+                          <th>Output</th>
+                          <th>Cost</th>
+                        </tr>
+                      </thead>
+                      <tbody>
+                        {[...usage.summary.buckets, usage.summary.total].map((bucket) => (
+                          <tr
+                            key={bucket === usage.summary?.total ? "__total__" : bucket.key}
+                            className={
+                              bucket === usage.summary?.total ? "is-total" : undefined
+                            }
+                          >
+                            <td title={bucket.key}>
+                              {bucket === usage.summary?.total
+                                ? "Total"
+                                : usageGroup === "workspace"
+                                  ? (workspaces.find((entry) => entry.id === bucket.key)
+                                      ?.name ?? bucket.key)
+                                  : bucket.key}
+                            </td>
+                            <td>{bucket.turns}</td>
+                            <td>{tokenFormat.format(bucket.tokens.inputTokens)}</td>
+                            <td>{tokenFormat.format(bucket.tokens.cachedInputTokens)}</td>
+                            <td>{tokenFormat.format(bucket.tokens.outputTokens)}</td>
+                            <td
+                              title={
+                                bucket.unpricedTurns
+                                  ? `${bucket.unpricedTurns} turns have no price`
+                                  : undefined
+                              }
+                            >
+                              {costFormat.format(bucket.cost)}
+                              {bucket.unpricedTurns > 0 ? "*" : ""}
+                            </td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </table>
+                  )}
+                  {usage.summary && usage.summary.total.unpricedTurns > 0 && (
+                    <div className="settings-help">
+                      * Some turns used models that are not in the cost table.
+                    </div>
+                  )}
+                </div>
+                <div className="settings-field">
+                  <div className="settings-field-label">Cost table (USD per million tokens)</div>
+                  {priceError && <div className="settings-help">{priceError}</div>}
+                  <div className="settings-overrides">
+                    {prices.map((price, index) => {
+                      const updatePrice = (patch: Partial<ModelPrice>) =>
+                        setPriceDrafts(
+                          prices.map((entry, entryIndex) =>
+                            entryIndex === index ? { ...entry, ...patch } : entry,
+                          ),
+                        );
+                      return (
+                        <div key={index} className="settings-override-row">
+                          <div className="settings-override-actions">
+                            <input
+                              className="settings-input settings-input--compact"
+                              value={price.model}
+                              placeholder="Model, or prefix*"
+                              onChange={(event) => updatePrice({ model: event.target.value })}
+                            />
+                            <input
+                              className="settings-input settings-input--compact"
+                              type="number"
+                              min="0"
+                              step="0.01"
+                              value={price.inputPerMillion}
+                              aria-label="Input price"
+                              title="Input"
+                              onChange={(event) =>
+                                updatePrice({ inputPerMillion: parsePrice(event.target.value) })
+                              }
+                            />
+                            <input
+                              className="settings-input settings-input--compact"
+                              type="number"
+                              min="0"
+                              step="0.01"
+                              value={price.cachedInputPerMillion ?? ""}
+                              placeholder="Cached"
+                              aria-label="Cached input price"
+                              title="Cached input (defaults to input)"
+                              onChange={(event) =>
+                                updatePrice({
+                                  cachedInputPerMillion: event.target.value
+                                    ? parsePrice(event.target.value)
+                                    : null,
+                                })
+                              }
+                            />
+                            <input
+                              className="settings-input settings-input--compact"
+                              type="number"
+                              min="0"
+                              step="0.01"
+                              value={price.outputPerMillion}
+                              aria-label="Output price"
+                              title="Output"
+                              onChange={(event) =>
+                                updatePrice({ outputPerMillion: parsePrice(event.target.value) })
+                              }
+                            />
+                            <button
+                              type="button"
+                              className="ghost icon-button"
+                              aria-label="Remove price"
+                              onClick={() =>
+                                setPriceDrafts(
+                                  prices.filter((_, entryIndex) => entryIndex !== index),
+                                )
+                              }
+                            >
+                              <Trash2 aria-hidden />
+                            </button>
+                          </div>
+                        </div>
+                      );
+                    })}
+                    {prices.length === 0 && (
+                      <div className="settings-empty">No prices; costs show as $0.</div>
+                    )}
+                  </div>
+                  <div className="settings-field-actions">
+                    <button
+                      type="button"
+                      className="ghost"
+                      onClick={() =>
+                        setPriceDrafts([...prices, emptyModelPrice()])
+                      }
+                    >
+                      <Plus aria-hidden />
+                      Add price
+                    </button>
+                    <button
+                      type="button"
+                      className="primary"
+                      disabled={priceDrafts === null}
+                      onClick={async () => {
+                        try {
+                          await onUpdateAppSettings({
+                            ...appSettings,
+                            costTable: prices,
+                          });
+                          setPriceDrafts(null);
+                          setPriceError(null);
+                          void usage.refresh();
+                        } catch (error) {
+                          setPriceError(
+                            error instanceof Error ? error.message : String(error),
+                          );
+                        }
+                      }}
+                    >
+                      Save
+                    </button>
+                  </div>
+                </div>
+              </section>
+            )}
+            {activeSection === "remote" && (
+              <section className="settings-section">
+                <div className="settings-section-title">Remote access</div>
+                <div className="settings-section-subtitle">
+                  Serve the app over HTTP (<code>POST /rpc</code>) and a WebSocket event
+                  stream (<code>GET /events</code>) for clients holding a token. Traffic is
+                  not encrypted, so the server only listens on loopback. Other machines
+                  connect through an SSH tunnel:{" "}
+                  <code>ssh -L 4732:127.0.0.1:4732 &lt;this host&gt;</code>.
+                </div>
+                <div className="settings-field">
+                  <div className="settings-override-actions">
+                    <label className="settings-checkbox">
+                      <input
+                        type="checkbox"
+                        checked={remoteAccess.enabled}
+                        onChange={(event) =>
+                          void saveRemoteAccess({ enabled: event.target.checked })
+                        }
+                      />
+                      Enable server
+                    </label>
+                    <input
+                      className="settings-input settings-input--compact"
+                      value={bindDraft}
+                      placeholder="127.0.0.1:4732"
+                      aria-label="Bind address"
+                      title="Bind address: 127.0.0.1 or [::1] with a port; LAN addresses are refused"
+                      onChange={(event) => setBindDraft(event.target.value)}
+                      onBlur={() => {
+                        const bindAddress = bindDraft.trim();
+                        if (bindAddress && bindAddress !== remoteAccess.bindAddress) {
+                          void saveRemoteAccess({ bindAddress });
+                        }
+                      }}
+                    />
+                  </div>
+                  <div className="settings-help">
+                    {remoteError ??
+                      remoteStatus?.error ??
+                      (remoteStatus?.running
+                        ? `Listening on ${remoteStatus.address}.`
+                        : "Not running.")}
+                  </div>
+                </div>
+                <div className="settings-field">
+                  <div className="settings-field-label">Tokens</div>
+                  <div className="settings-overrides">
+                    {remoteAccess.tokens.map((token) => (
+                      <div key={token.id} className="settings-override-row">
+                        <div className="settings-override-info">
+                          <div className="settings-project-name">{token.label}</div>
+                          <div className="settings-project-path">
+                            {token.scope === "control" ? "Control" : "Read only"} · created{" "}
+                            {new Date(token.createdAt).toLocaleDateString()}
+                          </div>
+                        </div>
+                        <div className="settings-override-actions">
+                          <button
+                            type="button"
+                            className="ghost icon-button"
+                            aria-label="Copy token"
+                            title="Copy token"
+                            onClick={() => void navigator.clipboard.writeText(token.token)}
+                          >
+                            <Copy aria-hidden />
+                          </button>
+                          <button
+                            type="button"
+                            className="ghost icon-button"
+                            aria-label="Revoke token"
+                            title="Revoke token"
+                            onClick={() =>
+                              void saveRemoteAccess({
+                                tokens: remoteAccess.tokens.filter(
+                                  (entry) => entry.id !== token.id,
+                                ),
+                              })
+                            }
+                          >
+                            <Trash2 aria-hidden />
+                          </button>
+                        </div>
+                      </div>
+                    ))}
+                    {remoteAccess.tokens.length === 0 && (
+                      <div className="settings-empty">No tokens; nobody can connect.</div>
+                    )}
+                  </div>
+                  <div className="settings-field-actions">
+                    <input
+                      className="settings-input settings-input--compact"
+                      value={tokenLabelDraft}
+                      placeholder="Label, e.g. laptop"
+                      onChange={(event) => setTokenLabelDraft(event.target.value)}
+                    />
+                    <select
+                      className="settings-select"
+                      value={tokenScopeDraft}
+                      onChange={(event) =>
+                        setTokenScopeDraft(event.target.value as RemoteScope)
+                      }
+                    >
+                      <option value="readOnly">Read only</option>
+                      <option value="control">Control</option>
+                    </select>
+                    <button
+                      type="button"
+                      className="ghost"
+                      disabled={!tokenLabelDraft.trim()}
+                      onClick={() => void handleAddRemoteToken()}
+                    >
+                      <Plus aria-hidden />
+                      New token
+                    </button>
+                  </div>
+                  <div className="settings-help">
+                    Send it as <code>Authorization: Bearer &lt;token&gt;</code>, or from a
+                    browser as the <code>bearer.&lt;token&gt;</code> WebSocket subprotocol
+                    next to <code>codex-monitor</code>. Read-only tokens can list and read;
+                    control tokens can also send prompts and answer approvals, which the
+                    audit log records under the token's label.
+                  </div>
+                </div>
+              </section>
+            )}
           </div>
         </div>
       </div>
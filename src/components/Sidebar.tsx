@@ -1,10 +1,41 @@
-import type { BackendType, RateLimitSnapshot, SessionSummary, ThreadSummary, WorkspaceInfo } from "../types";
+import type {
+  BackendType,
+  ExportFormat,
+  RateLimitSnapshot,
+  SessionSummary,
+  ThreadSummary,
+  WorkspaceInfo,
+} from "../types";
 import { FolderKanban, Layers, Settings, TerminalSquare } from "lucide-react";
 import { createPortal } from "react-dom";
 import { useCallback, useEffect, useRef, useState } from "react";
-import { Menu, MenuItem } from "@tauri-apps/api/menu";
+import { Menu, MenuItem, Submenu } from "@tauri-apps/api/menu";
 import { LogicalPosition } from "@tauri-apps/api/dpi";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { exportThread } from "../services/tauri";
+
+const EXPORT_FORMATS: { format: ExportFormat; label: string }[] = [
+  { format: "markdown", label: "Markdown…" },
+  { format: "json", label: "JSON…" },
+  { format: "html", label: "HTML report…" },
+];
+
+async function buildExportSubmenu(workspaceId: string, threadId: string) {
+  const items = await Promise.all(
+    EXPORT_FORMATS.map(({ format, label }) =>
+      MenuItem.new({
+        text: label,
+        action: () => {
+          void exportThread(workspaceId, threadId, format).catch((error) => {
+            const message = error instanceof Error ? error.message : String(error);
+            alert(`Failed to export thread.\n\n${message}`);
+          });
+        },
+      }),
+    ),
+  );
+  return Submenu.new({ text: "Export as", items });
+}
 
 type SidebarProps = {
   workspaces: WorkspaceInfo[];
@@ -139,7 +170,8 @@ export function Sidebar({
         await navigator.clipboard.writeText(threadId);
       },
     });
-    const menu = await Menu.new({ items: [copyItem, archiveItem] });
+    const exportItem = await buildExportSubmenu(workspaceId, threadId);
+    const menu = await Menu.new({ items: [copyItem, exportItem, archiveItem] });
     const window = getCurrentWindow();
     const position = new LogicalPosition(event.clientX, event.clientY);
     await menu.popup(position, window);
@@ -162,7 +194,8 @@ export function Sidebar({
         await navigator.clipboard.writeText(sessionId);
       },
     });
-    const menu = await Menu.new({ items: [copyItem, archiveItem] });
+    const exportItem = await buildExportSubmenu(workspaceId, sessionId);
+    const menu = await Menu.new({ items: [copyItem, exportItem, archiveItem] });
     const window = getCurrentWindow();
     const position = new LogicalPosition(event.clientX, event.clientY);
     await menu.popup(position, window);
@@ -401,10 +434,12 @@ export function Sidebar({
                         onClick={(event) => {
                           event.stopPropagation();
                           setAddMenuAnchor(null);
-                          onAddAgent(entry, "opencode");
+                          onAddAgent(entry, entry.acpAgent ? "acp" : "opencode");
                         }}
                       >
-                        New agent (OpenCode)
+                        {entry.acpAgent
+                          ? `New agent (${entry.acpAgent.name || entry.acpAgent.command})`
+                          : "New agent (OpenCode)"}
                       </button>
                       <button
                         className="workspace-add-option"
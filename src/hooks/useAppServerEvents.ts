@@ -1,6 +1,11 @@
 import { useEffect } from "react";
 import { listen } from "@tauri-apps/api/event";
-import type { AppServerEvent, ApprovalRequest } from "../types";
+import type {
+  AgentEvent,
+  AppServerEvent,
+  ApprovalRequest,
+  QueuedPrompt,
+} from "../types";
 
 type AgentDelta = {
   workspaceId: string;
@@ -18,10 +23,12 @@ type AgentCompleted = {
 
 type AppServerEventHandlers = {
   onWorkspaceConnected?: (workspaceId: string) => void;
+  onWorkspaceDisconnected?: (workspaceId: string) => void;
   onApprovalRequest?: (request: ApprovalRequest) => void;
   onAgentMessageDelta?: (event: AgentDelta) => void;
   onAgentMessageCompleted?: (event: AgentCompleted) => void;
   onAppServerEvent?: (event: AppServerEvent) => void;
+  onQueuedPromptDispatched?: (workspaceId: string, prompt: QueuedPrompt) => void;
   onTurnStarted?: (workspaceId: string, threadId: string, turnId: string) => void;
   onTurnCompleted?: (workspaceId: string, threadId: string, turnId: string) => void;
   onTurnError?: (
@@ -58,17 +65,45 @@ export function useAppServerEvents(handlers: AppServerEventHandlers) {
   useEffect(() => {
     let unlisten: (() => void) | null = null;
     let canceled = false;
-    listen<AppServerEvent>("app-server-event", (event) => {
-      handlers.onAppServerEvent?.(event.payload);
-
-      const { workspace_id, message } = event.payload;
-      const method = String(message.method ?? "");
+    listen<AgentEvent>("agent-event", (event) => {
+      const { workspaceId: workspace_id, backend, id, method } = event.payload;
+      if (backend !== "codex") {
+        // ACP agents ask for tool permissions; they share the approval toasts.
+        if (method === "session/request_permission" && id !== null) {
+          handlers.onApprovalRequest?.({
+            workspace_id,
+            request_id: id,
+            method,
+            params: (event.payload.params as Record<string, unknown>) ?? {},
+            backend,
+          });
+        }
+        return;
+      }
+      const message: Record<string, unknown> = { method, params: event.payload.params };
+      if (id !== null) {
+        message.id = id;
+      }
+      handlers.onAppServerEvent?.({ workspace_id, message });
 
       if (method === "codex/connected") {
         handlers.onWorkspaceConnected?.(workspace_id);
         return;
       }
 
+      if (method === "codex/disconnected") {
+        handlers.onWorkspaceDisconnected?.(workspace_id);
+        return;
+      }
+
+      if (method === "queue/dispatched") {
+        const params = message.params as { prompt?: QueuedPrompt };
+        if (params.prompt) {
+          handlers.onQueuedPromptDispatched?.(workspace_id, params.prompt);
+        }
+        return;
+      }
+
       if (method.includes("requestApproval") && typeof message.id === "number") {
         handlers.onApprovalRequest?.({
           workspace_id,
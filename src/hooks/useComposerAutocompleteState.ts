@@ -13,6 +13,7 @@ type UseComposerAutocompleteStateArgs = {
   textareaRef: React.RefObject<HTMLTextAreaElement | null>;
   setText: (next: string) => void;
   setSelectionStart: (next: number | null) => void;
+  onFileMention?: (path: string) => void;
 };
 
 export function useComposerAutocompleteState({
@@ -24,6 +25,7 @@ export function useComposerAutocompleteState({
   textareaRef,
   setText,
   setSelectionStart,
+  onFileMention,
 }: UseComposerAutocompleteStateArgs) {
   const skillItems = useMemo<AutocompleteItem[]>(
     () =>
@@ -87,6 +89,9 @@ export function useComposerAutocompleteState({
       const needsSpace = after.length === 0 ? true : !/^\s/.test(after);
       const nextText = `${before}${actualInsert}${needsSpace ? " " : ""}${after}`;
       setText(nextText);
+      if (triggerChar === "@") {
+        onFileMention?.(actualInsert);
+      }
       closeAutocomplete();
       requestAnimationFrame(() => {
         const textarea = textareaRef.current;
@@ -100,7 +105,15 @@ export function useComposerAutocompleteState({
         setSelectionStart(cursor);
       });
     },
-    [autocompleteRange, closeAutocomplete, setSelectionStart, setText, text, textareaRef],
+    [
+      autocompleteRange,
+      closeAutocomplete,
+      onFileMention,
+      setSelectionStart,
+      setText,
+      text,
+      textareaRef,
+    ],
   );
 
   const handleTextChange = useCallback(
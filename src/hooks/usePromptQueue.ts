@@ -0,0 +1,104 @@
+import { useCallback, useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import type { AgentEvent, Attachment, PromptSchedule, QueuedPrompt } from "../types";
+import {
+  cancelQueuedPrompt,
+  enqueuePrompt,
+  listQueuedPrompts,
+  reorderQueuedPrompts,
+  setQueuedPromptPaused,
+} from "../services/tauri";
+
+type EnqueueOptions = {
+  model?: string | null;
+  effort?: string | null;
+  accessMode?: string;
+  schedule?: PromptSchedule | null;
+  attachments?: Attachment[];
+};
+
+// The backend owns the queue; this mirrors the active thread's part of it.
+export function usePromptQueue(workspaceId: string | null, threadId: string | null) {
+  const [prompts, setPrompts] = useState<QueuedPrompt[]>([]);
+
+  useEffect(() => {
+    setPrompts([]);
+    if (!workspaceId || !threadId) {
+      return;
+    }
+    let active = true;
+    void listQueuedPrompts(workspaceId, threadId).then((items) => {
+      if (active) {
+        setPrompts(items);
+      }
+    });
+    return () => {
+      active = false;
+    };
+  }, [workspaceId, threadId]);
+
+  useEffect(() => {
+    if (!workspaceId || !threadId) {
+      return;
+    }
+    let unlisten: (() => void) | null = null;
+    let canceled = false;
+    listen<AgentEvent>("agent-event", (event) => {
+      const { workspaceId: eventWorkspaceId, method, params } = event.payload;
+      if (method !== "queue/updated" || eventWorkspaceId !== workspaceId) {
+        return;
+      }
+      const payload = params as { threadId?: string; prompts?: QueuedPrompt[] };
+      if (payload.threadId === threadId) {
+        setPrompts(payload.prompts ?? []);
+      }
+    }).then((handler) => {
+      if (canceled) {
+        handler();
+      } else {
+        unlisten = handler;
+      }
+    });
+    return () => {
+      canceled = true;
+      unlisten?.();
+    };
+  }, [workspaceId, threadId]);
+
+  const enqueue = useCallback(
+    async (text: string, options?: EnqueueOptions) => {
+      if (!workspaceId || !threadId) {
+        return null;
+      }
+      return enqueuePrompt(workspaceId, threadId, text, options);
+    },
+    [workspaceId, threadId],
+  );
+
+  const cancel = useCallback(async (id: string) => {
+    await cancelQueuedPrompt(id);
+  }, []);
+
+  const togglePaused = useCallback(async (prompt: QueuedPrompt) => {
+    await setQueuedPromptPaused(prompt.id, !prompt.paused);
+  }, []);
+
+  const move = useCallback(
+    async (id: string, direction: "up" | "down") => {
+      if (!workspaceId || !threadId) {
+        return;
+      }
+      const ids = prompts.map((prompt) => prompt.id);
+      const index = ids.indexOf(id);
+      const target = direction === "up" ? index - 1 : index + 1;
+      if (index < 0 || target < 0 || target >= ids.length) {
+        return;
+      }
+      [ids[index], ids[target]] = [ids[target], ids[index]];
+      await reorderQueuedPrompts(workspaceId, threadId, ids);
+    },
+    [prompts, workspaceId, threadId],
+  );
+
+  return { prompts, enqueue, cancel, togglePaused, move };
+}
@@ -0,0 +1,86 @@
+import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import type { AgentEvent, DebugEntry, RateLimitAlert } from "../types";
+
+type UseRateLimitAlertsOptions = {
+  onDebug?: (entry: DebugEntry) => void;
+};
+
+function windowLabel(alert: RateLimitAlert) {
+  const minutes = alert.windowDurationMins;
+  if (!minutes) {
+    return alert.window === "primary" ? "Session limit" : "Weekly limit";
+  }
+  if (minutes % (60 * 24) === 0) {
+    const days = minutes / (60 * 24);
+    return days === 7 ? "Weekly limit" : `${days}-day limit`;
+  }
+  if (minutes % 60 === 0) {
+    return `${minutes / 60}-hour limit`;
+  }
+  return `${minutes}-minute limit`;
+}
+
+function alertText(alert: RateLimitAlert) {
+  const label = windowLabel(alert);
+  const resets = alert.resetsAt
+    ? ` Resets ${new Date(alert.resetsAt * 1000).toLocaleString()}.`
+    : "";
+  if (alert.kind === "reset") {
+    return {
+      title: `${label} reset`,
+      body: `Usage is back to ${Math.round(alert.usedPercent)}%.`,
+    };
+  }
+  return {
+    title: `${label} at ${Math.round(alert.usedPercent)}%`,
+    body: `Codex usage passed ${alert.threshold ?? Math.round(alert.usedPercent)}%.${resets}`,
+  };
+}
+
+async function notificationsAllowed() {
+  if (typeof Notification === "undefined") {
+    return false;
+  }
+  if (Notification.permission === "default") {
+    return (await Notification.requestPermission()) === "granted";
+  }
+  return Notification.permission === "granted";
+}
+
+// Shows the backend's rate-limit alerts as desktop notifications.
+export function useRateLimitAlerts({ onDebug }: UseRateLimitAlertsOptions = {}) {
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    let canceled = false;
+    listen<AgentEvent>("agent-event", (event) => {
+      if (event.payload.method !== "rateLimits/alert") {
+        return;
+      }
+      const alert = event.payload.params as RateLimitAlert;
+      const { title, body } = alertText(alert);
+      onDebug?.({
+        id: `${Date.now()}-rate-limit-alert`,
+        timestamp: Date.now(),
+        source: "server",
+        label: "rateLimits/alert",
+        payload: alert,
+      });
+      void notificationsAllowed().then((allowed) => {
+        if (allowed) {
+          new Notification(title, { body });
+        }
+      });
+    }).then((handler) => {
+      if (canceled) {
+        handler();
+      } else {
+        unlisten = handler;
+      }
+    });
+    return () => {
+      canceled = true;
+      unlisten?.();
+    };
+  }, [onDebug]);
+}
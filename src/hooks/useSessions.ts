@@ -1,7 +1,11 @@
 import { useCallback, useEffect, useReducer, useRef } from "react";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
+  AgentEvent,
+  Attachment,
   ConversationItem,
+  OpenCodeMessageDelta,
+  OpenCodePlanEntry,
   OpenCodeSessionInfo,
   SessionSummary,
   MessagePart,
@@ -437,76 +441,102 @@ export function saveSessionActivity(activity: SessionActivityMap) {
   } catch {}
 }
 
-export function messagePartsToItems(
+export function planFromEntries(
+  sessionId: string,
+  entries: OpenCodePlanEntry[],
+): TurnPlan {
+  return {
+    turnId: sessionId,
+    explanation: null,
+    steps: entries.map((entry) => ({
+      step: entry.content,
+      status:
+        entry.status === "in_progress"
+          ? "inProgress"
+          : entry.status === "completed"
+            ? "completed"
+            : "pending",
+    })),
+  };
+}
+
+export function messagePartToItem(
   messageId: string,
+  index: number,
   role: "user" | "assistant",
-  parts: MessagePart[],
-): ConversationItem[] {
-  const items: ConversationItem[] = [];
+  part: MessagePart,
+  sessionId: string,
+): ConversationItem | null {
+  const id = `${messageId}-${index}`;
+  switch (part.type) {
+    case "text":
+      return { id, kind: "message", role, text: part.text };
+
+    case "reasoning":
+      return {
+        id,
+        kind: "reasoning",
+        summary: part.text.slice(0, 100),
+        content: part.text,
+      };
 
-  for (const part of parts) {
-    switch (part.type) {
-      case "text":
-        items.push({
-          id: `${messageId}-text`,
-          kind: "message",
-          role,
-          text: part.text,
-        });
-        break;
+    case "tool_use": {
+      const input = part.input ? JSON.stringify(part.input, null, 2) : "";
+      const detail = [part.kind, input].filter(Boolean).join("\n");
+      return {
+        id: `tool-${sessionId}-${part.id}`,
+        kind: "tool",
+        toolType: "acpToolCall",
+        title: part.kind ? `${part.kind}: ${part.name}` : part.name,
+        detail: detail.length > 4000 ? `${detail.slice(0, 4000)}…` : detail,
+        status: part.status,
+        output: part.output,
+        changes: part.diffs.map((diff) => {
+          const body = `--- ${diff.path}\n+++ ${diff.path}\n@@\n-${diff.old_text ?? ""}\n+${diff.new_text}`;
+          return {
+            path: diff.path,
+            kind: diff.old_text === null ? "ADD" : "MOD",
+            diff: body.length > 10000 ? `${body.slice(0, 10000)}…` : body,
+          };
+        }),
+      };
+    }
 
-      case "reasoning":
-        items.push({
-          id: `${messageId}-reasoning`,
-          kind: "reasoning",
-          summary: part.text.slice(0, 100),
-          content: part.text,
-        });
-        break;
+    case "tool_result":
+      return {
+        id: part.tool_use_id,
+        kind: "tool",
+        toolType: "result",
+        title: "Tool Result",
+        detail: "",
+        output: part.content,
+        status: "completed",
+      };
 
-      case "tool_use":
-        items.push({
-          id: part.id,
-          kind: "tool",
-          toolType: part.name,
-          title: part.name,
-          detail: JSON.stringify(part.input, null, 2),
-          status: "running",
-        });
-        break;
+    case "patch":
+      return {
+        id,
+        kind: "diff",
+        title: `Changes to ${part.files.length} file(s)`,
+        diff: part.files.join("\n"),
+        status: "completed",
+      };
 
-      case "tool_result":
-        items.push({
-          id: part.tool_use_id,
-          kind: "tool",
-          toolType: "result",
-          title: "Tool Result",
-          detail: "",
-          output: part.content,
-          status: "completed",
-        });
-        break;
-
-      case "patch":
-        items.push({
-          id: `${messageId}-patch`,
-          kind: "diff",
-          title: `Changes to ${part.files.length} file(s)`,
-          diff: part.files.join("\n"),
-          status: "completed",
-        });
-        break;
-    }
+    default:
+      return null;
   }
-
-  return items;
 }
 
-type OpenCodeEvent = {
-  workspaceId: string;
-  method: string;
-  params?: unknown;
-};
+export function messagePartsToItems(
+  messageId: string,
+  role: "user" | "assistant",
+  parts: MessagePart[],
+  sessionId: string,
+): ConversationItem[] {
+  return parts
+    .map((part, index) => messagePartToItem(messageId, index, role, part, sessionId))
+    .filter((item): item is ConversationItem => item !== null);
+}
 
 export function useSessions(
   activeWorkspace: WorkspaceInfo | null,
@@ -514,7 +544,6 @@ export function useSessions(
 ) {
   const [state, dispatch] = useReducer(sessionReducer, initialState);
   const unlistenRef = useRef<UnlistenFn | null>(null);
-  const activeAssistantMessageIdRef = useRef<Record<string, string>>({});
   const activeReasoningItemIdRef = useRef<Record<string, string>>({});
 
   const workspaceId = activeWorkspace?.id || null;
@@ -563,11 +592,11 @@ export function useSessions(
     dispatch({ type: "clearWorkspaceSessions", workspaceId });
 
     const setupEventListener = async () => {
-      const unlisten = await listen<OpenCodeEvent>("opencode-event", (event) => {
+      const unlisten = await listen<AgentEvent>("agent-event", (event) => {
         try {
-          const { workspaceId: eventWorkspaceId, method, params } = event.payload;
+          const { workspaceId: eventWorkspaceId, backend, method, params } = event.payload;
 
-          if (eventWorkspaceId !== workspaceId) return;
+          if (eventWorkspaceId !== workspaceId || backend === "codex") return;
 
         switch (method) {
           case "session/created": {
@@ -598,217 +627,60 @@ export function useSessions(
             break;
           }
 
-          case "session/update": {
-            const data = params as Record<string, unknown>;
-            const targetSessionId = String(data.sessionId ?? "");
-            if (!targetSessionId) {
-              break;
-            }
-            const update = data.update;
-            if (!update || typeof update !== "object") {
-              break;
-            }
-            const updateRecord = update as Record<string, unknown>;
-            const updateType = String(updateRecord.sessionUpdate ?? "");
-
-            if (updateType === "agent_message_chunk") {
-              const content = updateRecord.content;
-              if (!content || typeof content !== "object") {
-                break;
-              }
-              const contentRecord = content as Record<string, unknown>;
-              const delta = String(contentRecord.text ?? "");
-              if (!delta) {
-                break;
-              }
-              const itemId =
-                activeAssistantMessageIdRef.current[targetSessionId] ?? `assistant-${targetSessionId}`;
+          case "acp/message/delta": {
+            const { sessionId: targetSessionId, messageId, role, index, delta } =
+              params as OpenCodeMessageDelta;
+            const itemId = `${messageId}-${index}`;
+            if (delta.op === "append") {
+              const isReasoning = activeReasoningItemIdRef.current[targetSessionId] === itemId;
               dispatch({
-                type: "appendAssistantDelta",
+                type: isReasoning ? "appendReasoningDelta" : "appendAssistantDelta",
                 sessionId: targetSessionId,
                 itemId,
-                delta,
+                delta: delta.text,
               });
               break;
             }
-
-            if (updateType === "tool_call" || updateType === "tool_call_update") {
-              const toolCallId = String(updateRecord.toolCallId ?? "");
-              if (!toolCallId) {
-                break;
-              }
-              const itemId = `tool-${targetSessionId}-${toolCallId}`;
-
-              if (updateType === "tool_call") {
-                const title = String(updateRecord.title ?? "Tool");
-                const kind = String(updateRecord.kind ?? "");
-                const status = String(updateRecord.status ?? "pending");
-                const rawInput = updateRecord.rawInput;
-                const rawInputText = rawInput ? JSON.stringify(rawInput, null, 2) : "";
-                const detailRaw = [kind, rawInputText].filter(Boolean).join("\n");
-                const detail = detailRaw.length > 4000 ? `${detailRaw.slice(0, 4000)}…` : detailRaw;
-
-                dispatch({
-                  type: "upsertItem",
-                  sessionId: targetSessionId,
-                  item: {
-                    id: itemId,
-                    kind: "tool",
-                    toolType: "acpToolCall",
-                    title: kind ? `${kind}: ${title}` : title,
-                    detail,
-                    status,
-                  },
-                });
-              }
-
-              const status = updateRecord.status;
-              if (typeof status === "string" && status) {
-                dispatch({
-                  type: "setToolStatus",
-                  sessionId: targetSessionId,
-                  itemId,
-                  status,
-                });
-              }
-
-              const content = updateRecord.content;
-              if (Array.isArray(content)) {
-                for (const item of content) {
-                  if (!item || typeof item !== "object") {
-                    continue;
-                  }
-                  const contentItem = item as Record<string, unknown>;
-                  const contentType = String(contentItem.type ?? "");
-
-                  if (contentType === "content") {
-                    const inner = contentItem.content;
-                    if (!inner || typeof inner !== "object") {
-                      continue;
-                    }
-                    const innerRecord = inner as Record<string, unknown>;
-                    if (innerRecord.type !== "text") {
-                      continue;
-                    }
-                    const text = String(innerRecord.text ?? "");
-                    if (!text) {
-                      continue;
-                    }
-                    dispatch({
-                      type: "appendToolOutput",
-                      sessionId: targetSessionId,
-                      itemId,
-                      delta: text,
-                    });
-                  }
-
-                  if (contentType === "diff") {
-                    const path = String(contentItem.path ?? "");
-                    const oldText = String(contentItem.oldText ?? "");
-                    const newText = String(contentItem.newText ?? "");
-                    if (!path) {
-                      continue;
-                    }
-                    const diffBody = `--- ${path}\n+++ ${path}\n@@\n-${oldText}\n+${newText}`;
-                    const diff = diffBody.length > 10000 ? `${diffBody.slice(0, 10000)}…` : diffBody;
-                    dispatch({
-                      type: "appendToolChanges",
-                      sessionId: targetSessionId,
-                      itemId,
-                      changes: [
-                        {
-                          path,
-                          kind: "MOD",
-                          diff,
-                        },
-                      ],
-                    });
-                  }
-
-                  if (contentType === "terminal") {
-                    const terminalId = String(contentItem.terminalId ?? "");
-                    if (terminalId) {
-                      dispatch({
-                        type: "appendToolOutput",
-                        sessionId: targetSessionId,
-                        itemId,
-                        delta: `\n[terminal:${terminalId}]\n`,
-                      });
-                    }
-                  }
-                }
-              }
-
-              break;
-            }
-
-            if (updateType === "plan") {
-              const entries = updateRecord.entries;
-              if (!Array.isArray(entries)) {
-                break;
-              }
-              const steps = entries
-                .map((entry) => {
-                  if (!entry || typeof entry !== "object") {
-                    return null;
-                  }
-                  const record = entry as Record<string, unknown>;
-                  const stepContent = String(record.content ?? "");
-                  const statusRaw = String(record.status ?? "pending");
-                  const stepStatus =
-                    statusRaw === "in_progress"
-                      ? "inProgress"
-                      : statusRaw === "completed"
-                        ? "completed"
-                        : "pending";
-                  return stepContent
-                    ? {
-                        step: stepContent,
-                        status: stepStatus,
-                      }
-                    : null;
-                })
-                .filter(Boolean) as TurnPlan["steps"];
-
+            if (delta.part.type === "plan") {
               dispatch({
                 type: "setSessionPlan",
                 sessionId: targetSessionId,
-                plan: {
-                  turnId: targetSessionId,
-                  explanation: null,
-                  steps,
-                },
+                plan: planFromEntries(targetSessionId, delta.part.entries),
               });
               break;
             }
+            if (delta.part.type === "reasoning") {
+              activeReasoningItemIdRef.current[targetSessionId] = itemId;
+            }
+            const item = messagePartToItem(
+              messageId,
+              index,
+              role === "user" ? "user" : "assistant",
+              delta.part,
+              targetSessionId,
+            );
+            if (item) {
+              dispatch({ type: "upsertItem", sessionId: targetSessionId, item });
+            }
+            break;
+          }
 
-            if (updateType === "agent_thought_chunk") {
-              const content = updateRecord.content;
-              if (!content || typeof content !== "object") {
-                break;
-              }
-              const contentRecord = content as Record<string, unknown>;
-              if (contentRecord.type !== "text") {
-                break;
-              }
-              const delta = String(contentRecord.text ?? "");
-              if (!delta) {
-                break;
-              }
-              let itemId = activeReasoningItemIdRef.current[targetSessionId];
-              if (!itemId) {
-                itemId = `reasoning-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
-                activeReasoningItemIdRef.current[targetSessionId] = itemId;
-              }
+          case "acp/message/completed": {
+            const { sessionId, promptId, error } = params as {
+              sessionId: string;
+              promptId?: string;
+              error?: string | null;
+            };
+            delete activeReasoningItemIdRef.current[sessionId];
+            dispatch({ type: "markProcessing", sessionId, isProcessing: false });
+            if (error) {
               dispatch({
-                type: "appendReasoningDelta",
-                sessionId: targetSessionId,
-                itemId,
-                delta,
+                type: "appendAssistantDelta",
+                sessionId,
+                itemId: `${promptId ?? "prompt"}-error`,
+                delta: `Turn failed: ${error}`,
               });
-              break;
             }
-
             break;
           }
 
@@ -827,14 +699,13 @@ export function useSessions(
 
           case "turn/completed": {
             const { sessionId } = params as { sessionId: string };
-            delete activeAssistantMessageIdRef.current[sessionId];
             delete activeReasoningItemIdRef.current[sessionId];
             dispatch({ type: "markProcessing", sessionId, isProcessing: false });
             break;
           }
         }
         } catch (err) {
-          console.error("[useSessions] Error handling opencode-event:", err);
+          console.error("[useSessions] Error handling agent-event:", err);
         }
       });
 
@@ -889,7 +760,12 @@ export function useSessions(
   const sendMessage = useCallback(
     async (
       text: string,
-      options?: { modelId?: string; providerId?: string; sessionId?: string },
+      options?: {
+        modelId?: string;
+        providerId?: string;
+        sessionId?: string;
+        attachments?: Attachment[];
+      },
     ) => {
       const targetSessionId = options?.sessionId || activeSessionId;
       if (!workspaceId || !targetSessionId) return;
@@ -905,27 +781,21 @@ export function useSessions(
       dispatch({ type: "setSessionPlan", sessionId: targetSessionId, plan: null });
       delete activeReasoningItemIdRef.current[targetSessionId];
 
-      const assistantItemId = `assistant-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
-      activeAssistantMessageIdRef.current[targetSessionId] = assistantItemId;
-      dispatch({
-        type: "upsertItem",
-        sessionId: targetSessionId,
-        item: {
-          id: assistantItemId,
-          kind: "message",
-          role: "assistant",
-          text: "",
-        },
-      });
-
       try {
         await sendOpenCodeMessage(workspaceId, targetSessionId, text, {
           providerId: options?.providerId,
           modelId: options?.modelId,
+          attachments: options?.attachments,
         });
       } catch (error) {
         console.error("[useSessions] Failed to send message:", error);
         dispatch({ type: "markProcessing", sessionId: targetSessionId, isProcessing: false });
+        dispatch({
+          type: "appendAssistantDelta",
+          sessionId: targetSessionId,
+          itemId: `send-error-${Date.now()}`,
+          delta: `Failed to send: ${error instanceof Error ? error.message : String(error)}`,
+        });
       }
     },
     [workspaceId, activeSessionId],
@@ -1,6 +1,9 @@
 import { useCallback, useEffect, useMemo, useReducer, useRef } from "react";
 import type {
+  AccessMode,
   ApprovalRequest,
+  Attachment,
+  QueuedPrompt,
   AppServerEvent,
   ConversationItem,
   DebugEntry,
@@ -20,11 +23,13 @@ import {
   resumeThread as resumeThreadService,
   archiveThread as archiveThreadService,
   getAccountRateLimits,
+  getThreadSnapshot,
   interruptTurn as interruptTurnService,
 } from "../services/tauri";
 import { useAppServerEvents } from "./useAppServerEvents";
 import {
   buildConversationItem,
+  buildConversationItemFromThreadItem,
   buildItemsFromThread,
   getThreadTimestamp,
   isReviewingFromThread,
@@ -73,10 +78,11 @@ function saveThreadActivity(activity: ThreadActivityMap) {
 type UseThreadsOptions = {
   activeWorkspace: WorkspaceInfo | null;
   onWorkspaceConnected: (id: string) => void;
+  onWorkspaceDisconnected?: (id: string) => void;
   onDebug?: (entry: DebugEntry) => void;
   model?: string | null;
   effort?: string | null;
-  accessMode?: "read-only" | "current" | "full-access";
+  accessMode?: AccessMode;
   onMessageActivity?: () => void;
 };
 
@@ -324,6 +330,7 @@ function formatReviewLabel(target: ReturnType<typeof parseReviewTarget>) {
 export function useThreads({
   activeWorkspace,
   onWorkspaceConnected,
+  onWorkspaceDisconnected,
   onDebug,
   model,
   effort,
@@ -479,6 +486,7 @@ export function useThreads({
   const handlers = useMemo(
     () => ({
       onWorkspaceConnected: handleWorkspaceConnected,
+      onWorkspaceDisconnected,
       onApprovalRequest: (approval: ApprovalRequest) => {
         dispatch({ type: "addApproval", approval });
       },
@@ -582,6 +590,14 @@ export function useThreads({
       ) => {
         handleToolOutputDelta(threadId, itemId, delta);
       },
+      onQueuedPromptDispatched: (workspaceId: string, prompt: QueuedPrompt) => {
+        const { threadId } = prompt;
+        dispatch({ type: "ensureThread", workspaceId, threadId });
+        dispatch({ type: "addUserMessage", workspaceId, threadId, text: prompt.text });
+        dispatch({ type: "markProcessing", threadId, isProcessing: true });
+        recordThreadActivity(workspaceId, threadId);
+        safeMessageActivity();
+      },
       onTurnStarted: (workspaceId: string, threadId: string, turnId: string) => {
         dispatch({
           type: "ensureThread",
@@ -662,6 +678,7 @@ export function useThreads({
       activeThreadId,
       activeWorkspaceId,
       handleWorkspaceConnected,
+      onWorkspaceDisconnected,
       handleItemUpdate,
       handleToolOutputDelta,
       onDebug,
@@ -794,6 +811,47 @@ export function useThreads({
             });
           }
         }
+        const snapshot = await getThreadSnapshot(workspaceId, threadId).catch(
+          () => null,
+        );
+        if (snapshot) {
+          snapshot.items.forEach((entry) => {
+            const item = buildConversationItemFromThreadItem(
+              entry as Record<string, unknown>,
+            );
+            if (item) {
+              dispatch({ type: "upsertItem", threadId, item });
+            }
+          });
+          if (snapshot.activeTurnId) {
+            dispatch({ type: "markProcessing", threadId, isProcessing: true });
+            dispatch({
+              type: "setActiveTurnId",
+              threadId,
+              turnId: snapshot.activeTurnId,
+            });
+          }
+          if (snapshot.plan) {
+            dispatch({
+              type: "setThreadPlan",
+              threadId,
+              plan: normalizePlanUpdate(
+                snapshot.plan.turnId ?? snapshot.activeTurnId ?? "",
+                snapshot.plan.explanation,
+                snapshot.plan.plan,
+              ),
+            });
+          }
+          if (snapshot.tokenUsage) {
+            dispatch({
+              type: "setThreadTokenUsage",
+              threadId,
+              tokenUsage: normalizeTokenUsage(
+                snapshot.tokenUsage as Record<string, unknown>,
+              ),
+            });
+          }
+        }
         loadedThreads.current[threadId] = true;
         return threadId;
       } catch (error) {
@@ -943,7 +1001,7 @@ export function useThreads({
   }, [activeWorkspace, activeThreadId, resumeThreadForWorkspace, startThreadForWorkspace]);
 
   const sendUserMessage = useCallback(
-    async (text: string) => {
+    async (text: string, attachments: Attachment[] = []) => {
       if (!activeWorkspace || !text.trim()) {
         return;
       }
@@ -979,6 +1037,7 @@ export function useThreads({
           text: messageText,
           model,
           effort,
+          attachments: attachments.length,
         },
       });
       try {
@@ -987,7 +1046,7 @@ export function useThreads({
           activeWorkspace.id,
           threadId,
           messageText,
-          { model, effort, accessMode },
+          { model, effort, accessMode, attachments },
           )) as Record<string, unknown>;
         onDebug?.({
           id: `${Date.now()}-server-turn-start`,
@@ -1187,8 +1246,13 @@ export function useThreads({
         request.workspace_id,
         request.request_id,
         decision,
+        request.backend,
       );
-      dispatch({ type: "removeApproval", requestId: request.request_id });
+      dispatch({
+        type: "removeApproval",
+        requestId: request.request_id,
+        backend: request.backend,
+      });
     },
     [],
   );
@@ -1,5 +1,6 @@
 import type {
   ApprovalRequest,
+  BackendType,
   ConversationItem,
   RateLimitSnapshot,
   ThreadSummary,
@@ -62,7 +63,7 @@ export type ThreadAction =
       isLoading: boolean;
     }
   | { type: "addApproval"; approval: ApprovalRequest }
-  | { type: "removeApproval"; requestId: number }
+  | { type: "removeApproval"; requestId: number; backend?: BackendType }
   | { type: "setThreadTokenUsage"; threadId: string; tokenUsage: ThreadTokenUsage }
   | {
       type: "setRateLimits";
@@ -443,7 +444,9 @@ export function threadReducer(state: ThreadState, action: ThreadAction): ThreadS
       return {
         ...state,
         approvals: state.approvals.filter(
-          (item) => item.request_id !== action.requestId,
+          (item) =>
+            item.request_id !== action.requestId ||
+            (item.backend ?? "codex") !== (action.backend ?? "codex"),
         ),
       };
     case "setThreads": {
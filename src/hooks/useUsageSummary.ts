@@ -0,0 +1,81 @@
+import { useCallback, useEffect, useRef, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import type { AgentEvent, UsageGroup, UsageSummary } from "../types";
+import { getUsageSummary } from "../services/tauri";
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+
+type UsageSummaryState = {
+  summary: UsageSummary | null;
+  isLoading: boolean;
+  error: string | null;
+};
+
+// Aggregated token usage and cost, refreshed whenever a turn is recorded.
+export function useUsageSummary(
+  enabled: boolean,
+  groupBy: UsageGroup,
+  workspaceId: string | null,
+  days: number | null,
+) {
+  const [state, setState] = useState<UsageSummaryState>({
+    summary: null,
+    isLoading: false,
+    error: null,
+  });
+  const requestIdRef = useRef(0);
+
+  const refresh = useCallback(async () => {
+    const requestId = requestIdRef.current + 1;
+    requestIdRef.current = requestId;
+    setState((prev) => ({ ...prev, isLoading: true, error: null }));
+    try {
+      const summary = await getUsageSummary(groupBy, {
+        workspaceId,
+        since: days ? Date.now() - days * DAY_MS : null,
+      });
+      if (requestIdRef.current === requestId) {
+        setState({ summary, isLoading: false, error: null });
+      }
+    } catch (error) {
+      if (requestIdRef.current === requestId) {
+        setState({
+          summary: null,
+          isLoading: false,
+          error: error instanceof Error ? error.message : String(error),
+        });
+      }
+    }
+  }, [days, groupBy, workspaceId]);
+
+  useEffect(() => {
+    if (enabled) {
+      void refresh();
+    }
+  }, [enabled, refresh]);
+
+  useEffect(() => {
+    if (!enabled) {
+      return;
+    }
+    let unlisten: (() => void) | null = null;
+    let canceled = false;
+    listen<AgentEvent>("agent-event", (event) => {
+      if (event.payload.method === "usage/recorded") {
+        void refresh();
+      }
+    }).then((handler) => {
+      if (canceled) {
+        handler();
+      } else {
+        unlisten = handler;
+      }
+    });
+    return () => {
+      canceled = true;
+      unlisten?.();
+    };
+  }, [enabled, refresh]);
+
+  return { ...state, refresh };
+}
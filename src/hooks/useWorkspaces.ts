@@ -1,6 +1,6 @@
 import { useCallback, useEffect, useMemo, useState } from "react";
 import type { DebugEntry } from "../types";
-import type { WorkspaceInfo, WorkspaceSettings } from "../types";
+import type { AcpAgentConfig, WorkspaceInfo, WorkspaceSettings } from "../types";
 import { ask } from "@tauri-apps/plugin-dialog";
 import {
   addWorkspace as addWorkspaceService,
@@ -10,6 +10,7 @@ import {
   pickWorkspacePath,
   removeWorkspace as removeWorkspaceService,
   removeWorktree as removeWorktreeService,
+  updateWorkspaceAcpAgent as updateWorkspaceAcpAgentService,
   updateWorkspaceCodexBin as updateWorkspaceCodexBinService,
   updateWorkspaceSettings as updateWorkspaceSettingsService,
 } from "../services/tauri";
@@ -142,6 +143,12 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     );
   }
 
+  function markWorkspaceDisconnected(id: string) {
+    setWorkspaces((prev) =>
+      prev.map((entry) => (entry.id === id ? { ...entry, connected: false } : entry)),
+    );
+  }
+
   async function updateWorkspaceSettings(
     workspaceId: string,
     settings: WorkspaceSettings,
@@ -226,6 +233,48 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     }
   }
 
+  async function updateWorkspaceAcpAgent(
+    workspaceId: string,
+    acpAgent: AcpAgentConfig | null,
+  ) {
+    onDebug?.({
+      id: `${Date.now()}-client-update-workspace-acp-agent`,
+      timestamp: Date.now(),
+      source: "client",
+      label: "workspace/acpAgent",
+      payload: { workspaceId, acpAgent },
+    });
+    const previous = workspaces.find((entry) => entry.id === workspaceId) ?? null;
+    if (previous) {
+      setWorkspaces((prev) =>
+        prev.map((entry) =>
+          entry.id === workspaceId ? { ...entry, acpAgent } : entry,
+        ),
+      );
+    }
+    try {
+      const updated = await updateWorkspaceAcpAgentService(workspaceId, acpAgent);
+      setWorkspaces((prev) =>
+        prev.map((entry) => (entry.id === workspaceId ? updated : entry)),
+      );
+      return updated;
+    } catch (error) {
+      if (previous) {
+        setWorkspaces((prev) =>
+          prev.map((entry) => (entry.id === workspaceId ? previous : entry)),
+        );
+      }
+      onDebug?.({
+        id: `${Date.now()}-client-update-workspace-acp-agent-error`,
+        timestamp: Date.now(),
+        source: "error",
+        label: "workspace/acpAgent error",
+        payload: error instanceof Error ? error.message : String(error),
+      });
+      throw error;
+    }
+  }
+
   async function removeWorkspace(workspaceId: string) {
     const workspace = workspaces.find((entry) => entry.id === workspaceId);
     const workspaceName = workspace?.name || "this workspace";
@@ -338,8 +387,10 @@ export function useWorkspaces(options: UseWorkspacesOptions = {}) {
     addWorktreeAgent,
     connectWorkspace,
     markWorkspaceConnected,
+    markWorkspaceDisconnected,
     updateWorkspaceSettings,
     updateWorkspaceCodexBin,
+    updateWorkspaceAcpAgent,
     removeWorkspace,
     removeWorktree,
     hasLoaded,
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Command line for an ACP-compatible agent (e.g. `claude-code-acp`).
+ */
+export type AcpAgentConfig = { name: string | null, command: string, args: Array<string>, };
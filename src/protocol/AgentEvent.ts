@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackendType } from "./BackendType";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * Envelope for everything an agent process pushes to the UI: forwarded
+ * notifications, server requests (with their `id`), and lifecycle events.
+ */
+export type AgentEvent = { workspaceId: string, backend: BackendType, id: number | null, method: string, params: JsonValue | null, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApprovalDecision } from "./ApprovalDecision";
+import type { ApprovalRuleTarget } from "./ApprovalRuleTarget";
+
+/**
+ * Answers matching approval requests without asking. Every condition that is
+ * set must hold; a rule with none matches every request of its `target`.
+ */
+export type ApprovalRule = { name: string | null, decision: ApprovalDecision, target: ApprovalRuleTarget, 
+/**
+ * Whole words the command must start with, e.g. `git status`.
+ */
+commandPrefix: string | null, 
+/**
+ * Glob over changed paths. Relative patterns see paths relative to the
+ * workspace; absolute ones see the full path.
+ */
+pathGlob: string | null, 
+/**
+ * Directory the request must run in (or below), relative to the workspace.
+ */
+cwd: string | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Something sent along with the text of a user message.
+ */
+export type Attachment = { "type": "imagePath", path: string, } | { "type": "imageData", data: string, name: string | null, } | { "type": "file", path: string, };
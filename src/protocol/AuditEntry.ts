@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApprovalPolicy } from "./ApprovalPolicy";
+import type { AuditActor } from "./AuditActor";
+import type { AuditKind } from "./AuditKind";
+import type { BackendType } from "./BackendType";
+import type { SandboxPolicy } from "./SandboxPolicy";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type AuditEntry = { timestamp: number, workspaceId: string, backend: BackendType, kind: AuditKind, threadId: string | null, turnId: string | null, requestId: number | null, method: string | null, 
+/**
+ * `accept`/`decline` for Codex, the chosen option for ACP agents.
+ */
+decision: string | null, actor: AuditActor | null, 
+/**
+ * Label of the remote access token that answered.
+ */
+remoteToken: string | null, accessMode: string | null, approvalPolicy: ApprovalPolicy | null, sandboxPolicy: SandboxPolicy | null, 
+/**
+ * Request params or response result, as sent on the wire.
+ */
+details: JsonValue | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackendType } from "./BackendType";
+
+export type BackendHealth = { backend: BackendType, alive: boolean, pendingRequests: number, stderrTail: Array<string>, };
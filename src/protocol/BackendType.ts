@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Backend type for a workspace - determines which CLI to use
+ */
+export type BackendType = "codex" | "opencode" | "acp";
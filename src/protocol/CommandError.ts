@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RpcError } from "./RpcError";
+
+/**
+ * What a failed command hands the frontend: a readable `message`, plus the
+ * structured `rpc` error when the failure came from the agent.
+ */
+export type CommandError = { message: string, rpc?: RpcError, };
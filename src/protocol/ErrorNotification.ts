@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TurnError } from "./TurnError";
+
+export type ErrorNotification = { threadId: string | null, turnId: string | null, error: TurnError, willRetry: boolean, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PlanStep } from "./PlanStep";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type ExportEntry = { "kind": "userMessage", text: string, } | { "kind": "agentMessage", text: string, } | { "kind": "reasoning", summary: string, } | { "kind": "command", command: string, cwd: string | null, exitCode: number | null, output: string | null, } | { "kind": "fileChange", path: string, diff: string | null, } | { "kind": "toolCall", name: string, status: string | null, input: JsonValue | null, output: string | null, } | { "kind": "plan", explanation: string | null, steps: Array<PlanStep>, };
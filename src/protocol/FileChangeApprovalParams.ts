@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FileChangeApprovalParams = { threadId: string, turnId: string, itemId: string, reason: string | null, grantRoot: string | null, };
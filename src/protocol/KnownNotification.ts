@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AccountRateLimitsUpdated } from "./AccountRateLimitsUpdated";
+import type { ErrorNotification } from "./ErrorNotification";
+import type { ItemDelta } from "./ItemDelta";
+import type { ItemEvent } from "./ItemEvent";
+import type { ThreadEvent } from "./ThreadEvent";
+import type { ThreadTokenUsageUpdated } from "./ThreadTokenUsageUpdated";
+import type { TurnDiffUpdated } from "./TurnDiffUpdated";
+import type { TurnEvent } from "./TurnEvent";
+import type { TurnPlanUpdated } from "./TurnPlanUpdated";
+
+export type KnownNotification = { "method": "thread/started", "params": ThreadEvent } | { "method": "turn/started", "params": TurnEvent } | { "method": "turn/completed", "params": TurnEvent } | { "method": "item/started", "params": ItemEvent } | { "method": "item/completed", "params": ItemEvent } | { "method": "item/agentMessage/delta", "params": ItemDelta } | { "method": "item/reasoning/summaryTextDelta", "params": ItemDelta } | { "method": "item/reasoning/textDelta", "params": ItemDelta } | { "method": "item/commandExecution/outputDelta", "params": ItemDelta } | { "method": "item/fileChange/outputDelta", "params": ItemDelta } | { "method": "turn/plan/updated", "params": TurnPlanUpdated } | { "method": "turn/diff/updated", "params": TurnDiffUpdated } | { "method": "thread/tokenUsage/updated", "params": ThreadTokenUsageUpdated } | { "method": "account/rateLimits/updated", "params": AccountRateLimitsUpdated } | { "method": "error", "params": ErrorNotification };
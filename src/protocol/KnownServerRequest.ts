@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CommandExecutionApprovalParams } from "./CommandExecutionApprovalParams";
+import type { FileChangeApprovalParams } from "./FileChangeApprovalParams";
+
+export type KnownServerRequest = { "method": "item/commandExecution/requestApproval", "params": CommandExecutionApprovalParams } | { "method": "item/fileChange/requestApproval", "params": FileChangeApprovalParams };
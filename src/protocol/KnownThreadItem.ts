@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileUpdateChange } from "./FileUpdateChange";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type KnownThreadItem = { "type": "userMessage", id: string, content: Array<JsonValue>, } | { "type": "agentMessage", id: string, text: string, } | { "type": "reasoning", id: string, summary: Array<string>, content: Array<string>, } | { "type": "commandExecution", id: string, command: string, cwd: string | null, status: string | null, aggregatedOutput: string | null, exitCode: number | null, } | { "type": "fileChange", id: string, changes: Array<FileUpdateChange>, status: string | null, } | { "type": "mcpToolCall", id: string, server: string | null, tool: string | null, status: string | null, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A cost table row, in USD per million tokens.
+ */
+export type ModelPrice = { 
+/**
+ * Model name; a trailing `*` matches every model with that prefix.
+ */
+model: string, inputPerMillion: number, 
+/**
+ * Defaults to the input price.
+ */
+cachedInputPerMillion: number | null, outputPerMillion: number, };
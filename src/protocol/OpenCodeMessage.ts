@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OpenCodeMessagePart } from "./OpenCodeMessagePart";
+
+export type OpenCodeMessage = { id: string, sessionId: string, role: string, parts: Array<OpenCodeMessagePart>, createdAt: number | null, 
+/**
+ * Set once the `session/prompt` that produced the message returns.
+ */
+stopReason: string | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PartDelta } from "./PartDelta";
+
+export type OpenCodeMessageDelta = { sessionId: string, messageId: string, role: string, index: number, delta: PartDelta, };
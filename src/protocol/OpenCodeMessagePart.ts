@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OpenCodeFileDiff } from "./OpenCodeFileDiff";
+import type { OpenCodePlanEntry } from "./OpenCodePlanEntry";
+
+/**
+ * One piece of an ACP conversation message, folded from `session/update`.
+ */
+export type OpenCodeMessagePart = { "type": "text", text: string, } | { "type": "reasoning", text: string, } | { "type": "tool_use", id: string, name: string, kind: string | null, input: unknown, status: string, output: string, diffs: Array<OpenCodeFileDiff>, } | { "type": "tool_result", tool_use_id: string, content: string, } | { "type": "patch", hash: string, files: Array<string>, } | { "type": "file", url: string, mime: string, } | { "type": "plan", entries: Array<OpenCodePlanEntry>, };
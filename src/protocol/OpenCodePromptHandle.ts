@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Returned by `send_opencode_message`; the turn finishes with an
+ * `acp/message/completed` event carrying the same `promptId`.
+ */
+export type OpenCodePromptHandle = { promptId: string, sessionId: string, };
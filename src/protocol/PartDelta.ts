@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OpenCodeMessagePart } from "./OpenCodeMessagePart";
+
+/**
+ * How a part of the open message changed.
+ */
+export type PartDelta = { "op": "append", text: string, } | { "op": "upsert", part: OpenCodeMessagePart, };
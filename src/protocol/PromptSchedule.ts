@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * When a queued prompt may be sent. Prompts only go out while their thread is idle.
+ */
+export type PromptSchedule = { "type": "next" } | { "type": "at", runAt: number, } | { "type": "every", intervalSecs: number, nextRunAt: number, };
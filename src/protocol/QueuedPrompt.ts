@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Attachment } from "./Attachment";
+import type { PromptSchedule } from "./PromptSchedule";
+
+export type QueuedPrompt = { id: string, workspaceId: string, threadId: string, text: string, model: string | null, effort: string | null, accessMode: string | null, attachments: Array<Attachment>, schedule: PromptSchedule, paused: boolean, 
+/**
+ * Why the last dispatch failed; failed prompts are paused until resumed.
+ */
+lastError: string | null, createdAt: number, };
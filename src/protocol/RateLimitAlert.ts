@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RateLimitAlertKind } from "./RateLimitAlertKind";
+import type { RateLimitWindowKind } from "./RateLimitWindowKind";
+
+export type RateLimitAlert = { window: RateLimitWindowKind, kind: RateLimitAlertKind, threshold: number | null, usedPercent: number, windowDurationMins: bigint | null, 
+/**
+ * Seconds since the epoch.
+ */
+resetsAt: number | null, };
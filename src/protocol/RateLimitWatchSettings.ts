@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How often the rate-limit watcher polls and when it raises alerts.
+ */
+export type RateLimitWatchSettings = { enabled: boolean, pollIntervalSecs: number, 
+/**
+ * Used percentages that raise an alert when a window reaches them.
+ */
+thresholds: Array<number>, notifyOnReset: boolean, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RemoteToken } from "./RemoteToken";
+
+/**
+ * The optional HTTP + WebSocket server for driving the app from elsewhere.
+ */
+export type RemoteAccessSettings = { enabled: boolean, bindAddress: string, tokens: Array<RemoteToken>, };
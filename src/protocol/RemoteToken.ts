@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RemoteScope } from "./RemoteScope";
+
+export type RemoteToken = { id: string, label: string, 
+/**
+ * The bearer secret clients send.
+ */
+token: string, scope: RemoteScope, createdAt: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * Errors produced while talking JSON-RPC to an agent process.
+ */
+export type RpcError = { "kind": "server", code: number, message: string, data: JsonValue | null, } | { "kind": "timeout", method: string, id: number, afterMs: number, } | { "kind": "canceled", method: string, id: number, } | { "kind": "disconnected", message: string, } | { "kind": "transport", message: string, };
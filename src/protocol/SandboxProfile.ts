@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApprovalPolicy } from "./ApprovalPolicy";
+import type { SandboxMode } from "./SandboxMode";
+
+/**
+ * A named sandbox a message can run under, selected by `accessMode`.
+ */
+export type SandboxProfile = { name: string, label: string | null, mode: SandboxMode, 
+/**
+ * Writable in addition to the workspace; relative paths resolve against it.
+ */
+writableRoots: Array<string>, networkAccess: boolean, approvalPolicy: ApprovalPolicy, 
+/**
+ * File changes touching these paths, and commands run inside them, are
+ * declined without asking. Workspace-write profiles with the `untrusted`
+ * approval policy only, since the others skip approval for sandboxed work.
+ */
+excludedPaths: Array<string>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SearchHit = { workspaceId: string, threadId: string, title: string | null, snippet: string, 
+/**
+ * Milliseconds since the epoch of the matching text, when known.
+ */
+timestamp: number | null, score: number, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KnownNotification } from "./KnownNotification";
+import type { UnknownMessage } from "./UnknownMessage";
+
+/**
+ * A server notification; methods this module does not model are kept as-is.
+ */
+export type ServerNotification = KnownNotification | UnknownMessage;
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KnownServerRequest } from "./KnownServerRequest";
+import type { UnknownMessage } from "./UnknownMessage";
+
+export type ServerRequest = KnownServerRequest | UnknownMessage;
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ExportEntry } from "./ExportEntry";
+
+/**
+ * The stable JSON export; Markdown and HTML are rendered from the same data.
+ */
+export type ThreadExport = { schemaVersion: number, backend: string, workspaceId: string, workspaceName: string, threadId: string, title: string | null, exportedAt: number, entries: Array<ExportEntry>, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { KnownThreadItem } from "./KnownThreadItem";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * A thread item; unrecognised item types are preserved verbatim.
+ */
+export type ThreadItem = KnownThreadItem | JsonValue;
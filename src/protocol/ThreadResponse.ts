@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Thread } from "./Thread";
+
+/**
+ * Result of `thread/start` and `thread/resume`.
+ */
+export type ThreadResponse = { thread: Thread, 
+/**
+ * The model the thread's turns use unless `turn/start` names one.
+ */
+model: string | null, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThreadItem } from "./ThreadItem";
+import type { TurnPlanUpdated } from "./TurnPlanUpdated";
+import type { TurnStatus } from "./TurnStatus";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * Live state of one thread, rebuilt from app-server notifications.
+ */
+export type ThreadSnapshot = { workspaceId: string, threadId: string, items: Array<ThreadItem>, activeTurnId: string | null, turnStatus: TurnStatus | null, plan: TurnPlanUpdated | null, diff: string | null, tokenUsage: JsonValue | null, lastError: string | null, updatedAt: number, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApprovalPolicy } from "./ApprovalPolicy";
+
+export type ThreadStartParams = { cwd: string, approvalPolicy: ApprovalPolicy, };
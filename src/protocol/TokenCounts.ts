@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Token counts as reported by Codex (`tokenUsage.last`) or an ACP agent (`usage`).
+ *
+ * Cached input is a subset of input, and reasoning a subset of output.
+ */
+export type TokenCounts = { inputTokens: number, cachedInputTokens: number, outputTokens: number, reasoningOutputTokens: number, totalTokens: number, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TranscriptSource } from "./TranscriptSource";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type TranscriptEntry = { seq: number, timestamp: number, source: TranscriptSource, method: string, params: JsonValue | null, };
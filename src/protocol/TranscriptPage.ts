@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TranscriptEntry } from "./TranscriptEntry";
+
+export type TranscriptPage = { entries: Array<TranscriptEntry>, 
+/**
+ * Pass back as `cursor` to read the next page; `null` once caught up.
+ */
+nextCursor: number | null, };
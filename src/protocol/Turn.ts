@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TurnStatus } from "./TurnStatus";
+
+export type Turn = { id: string, threadId: string | null, status: TurnStatus | null, };
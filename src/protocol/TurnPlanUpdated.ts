@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PlanStep } from "./PlanStep";
+
+export type TurnPlanUpdated = { threadId: string, turnId: string | null, explanation: string | null, plan: Array<PlanStep>, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApprovalPolicy } from "./ApprovalPolicy";
+import type { SandboxPolicy } from "./SandboxPolicy";
+import type { UserInput } from "./UserInput";
+
+export type TurnStartParams = { threadId: string, input: Array<UserInput>, cwd: string, approvalPolicy: ApprovalPolicy, sandboxPolicy: SandboxPolicy, model: string | null, effort: string | null, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TokenCounts } from "./TokenCounts";
+
+export type UsageBucket = { key: string, turns: number, tokens: TokenCounts, 
+/**
+ * USD for the turns that have a price.
+ */
+cost: number, 
+/**
+ * Turns whose model is missing from the cost table.
+ */
+unpricedTurns: number, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackendType } from "./BackendType";
+import type { TokenCounts } from "./TokenCounts";
+
+/**
+ * Tokens spent by one turn.
+ */
+export type UsageRecord = { timestamp: number, workspaceId: string, backend: BackendType, threadId: string, turnId: string | null, model: string, tokens: TokenCounts, 
+/**
+ * Cost the agent reported itself, in USD; used instead of the cost table.
+ */
+reportedCost: number | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UsageBucket } from "./UsageBucket";
+
+export type UsageSummary = { total: UsageBucket, 
+/**
+ * Days in order; other groupings by most tokens first.
+ */
+buckets: Array<UsageBucket>, };
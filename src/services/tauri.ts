@@ -1,10 +1,18 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke as invokeCommand, type InvokeArgs } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import type {
+  AccessMode,
+  AcpAgentConfig,
   AppSettings,
+  BackendHealth,
+  BackendType,
   CodexDoctorResult,
+  CommandError,
+  RpcError,
+  PendingRequestInfo,
   OpenCodeDoctorResult,
   OpenCodeMessage,
+  OpenCodePromptHandle,
   OpenCodeSessionInfo,
   MessagePart,
   ProviderInfo,
@@ -12,13 +20,53 @@ import type {
   WorkspaceSettings,
 } from "../types";
 import type {
+  Attachment,
+  AuditEntry,
   GitFileDiff,
   GitFileStatus,
   GitHubIssuesResponse,
   GitLogResponse,
+  ExportFormat,
+  PromptSchedule,
+  QueuedPrompt,
+  RemoteScope,
+  RemoteServerStatus,
+  RemoteToken,
   ReviewTarget,
+  SearchHit,
+  ThreadSnapshot,
+  TranscriptPage,
+  UsageGroup,
+  UsageSummary,
 } from "../types";
 
+/** A failed command; `rpc` is set when the agent itself returned the error. */
+export class BackendError extends Error {
+  readonly rpc: RpcError | null;
+
+  constructor(error: CommandError) {
+    super(error.message);
+    this.name = "BackendError";
+    this.rpc = error.rpc ?? null;
+  }
+}
+
+function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    typeof (error as CommandError).message === "string"
+  );
+}
+
+async function invoke<T = unknown>(command: string, args?: InvokeArgs): Promise<T> {
+  try {
+    return await invokeCommand<T>(command, args);
+  } catch (error) {
+    throw isCommandError(error) ? new BackendError(error) : error;
+  }
+}
+
 // ============================================================================
 // File Picker
 // ============================================================================
@@ -31,6 +79,17 @@ export async function pickWorkspacePath(): Promise<string | null> {
   return selection;
 }
 
+export async function pickImagePaths(): Promise<string[]> {
+  const selection = await open({
+    multiple: true,
+    filters: [{ name: "Images", extensions: ["png", "jpg", "jpeg", "gif", "webp"] }],
+  });
+  if (!selection) {
+    return [];
+  }
+  return Array.isArray(selection) ? selection : [selection];
+}
+
 export async function listWorkspaces(): Promise<WorkspaceInfo[]> {
   return invoke<WorkspaceInfo[]>("list_workspaces");
 }
@@ -66,6 +125,13 @@ export async function updateWorkspaceCodexBin(
   return invoke<WorkspaceInfo>("update_workspace_codex_bin", { id, codex_bin });
 }
 
+export async function updateWorkspaceAcpAgent(
+  id: string,
+  acpAgent: AcpAgentConfig | null,
+): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("update_workspace_acp_agent", { id, acpAgent });
+}
+
 export async function removeWorkspace(id: string): Promise<void> {
   return invoke("remove_workspace", { id });
 }
@@ -89,7 +155,8 @@ export async function sendUserMessage(
   options?: {
     model?: string | null;
     effort?: string | null;
-    accessMode?: "read-only" | "current" | "full-access";
+    accessMode?: AccessMode;
+    attachments?: Attachment[];
   },
 ) {
   return invoke("send_user_message", {
@@ -99,9 +166,64 @@ export async function sendUserMessage(
     model: options?.model ?? null,
     effort: options?.effort ?? null,
     accessMode: options?.accessMode ?? null,
+    attachments: options?.attachments ?? null,
+  });
+}
+
+export async function enqueuePrompt(
+  workspaceId: string,
+  threadId: string,
+  text: string,
+  options?: {
+    model?: string | null;
+    effort?: string | null;
+    accessMode?: AccessMode;
+    attachments?: Attachment[];
+    schedule?: PromptSchedule | null;
+  },
+): Promise<QueuedPrompt> {
+  return invoke<QueuedPrompt>("enqueue_prompt", {
+    workspaceId,
+    threadId,
+    text,
+    model: options?.model ?? null,
+    effort: options?.effort ?? null,
+    accessMode: options?.accessMode ?? null,
+    attachments: options?.attachments ?? null,
+    schedule: options?.schedule ?? null,
   });
 }
 
+export async function listQueuedPrompts(
+  workspaceId: string | null,
+  threadId: string | null,
+): Promise<QueuedPrompt[]> {
+  return invoke<QueuedPrompt[]>("list_queued_prompts", { workspaceId, threadId });
+}
+
+export async function reorderQueuedPrompts(
+  workspaceId: string,
+  threadId: string,
+  promptIds: string[],
+): Promise<QueuedPrompt[]> {
+  return invoke<QueuedPrompt[]>("reorder_queued_prompts", {
+    workspaceId,
+    threadId,
+    promptIds,
+  });
+}
+
+export async function setQueuedPromptPaused(
+  promptId: string,
+  paused: boolean,
+): Promise<QueuedPrompt> {
+  return invoke<QueuedPrompt>("set_queued_prompt_paused", { promptId, paused });
+}
+
+export async function cancelQueuedPrompt(promptId: string) {
+  return invoke("cancel_queued_prompt", { promptId });
+}
+
 export async function interruptTurn(
   workspaceId: string,
   threadId: string,
@@ -127,14 +249,112 @@ export async function respondToServerRequest(
   workspaceId: string,
   requestId: number,
   decision: "accept" | "decline",
+  backend?: BackendType,
 ) {
   return invoke("respond_to_server_request", {
     workspaceId,
     requestId,
     result: { decision },
+    backend: backend ?? null,
+  });
+}
+
+export async function cancelRequest(workspaceId: string, requestId: number) {
+  return invoke("cancel_request", { workspaceId, requestId });
+}
+
+export async function getThreadSnapshot(
+  workspaceId: string,
+  threadId: string,
+): Promise<ThreadSnapshot | null> {
+  return invoke<ThreadSnapshot | null>("get_thread_snapshot", {
+    workspaceId,
+    threadId,
   });
 }
 
+export async function readTranscript(
+  workspaceId: string,
+  threadId: string,
+  cursor?: number | null,
+  limit?: number,
+): Promise<TranscriptPage> {
+  return invoke<TranscriptPage>("read_transcript", {
+    workspaceId,
+    threadId,
+    cursor: cursor ?? null,
+    limit: limit ?? null,
+  });
+}
+
+export async function searchConversations(
+  query: string,
+  options?: { workspaceId?: string | null; limit?: number },
+): Promise<SearchHit[]> {
+  return invoke<SearchHit[]>("search_conversations", {
+    query,
+    workspaceId: options?.workspaceId ?? null,
+    limit: options?.limit ?? null,
+  });
+}
+
+export async function exportThread(
+  workspaceId: string,
+  threadId: string,
+  format: ExportFormat,
+): Promise<string | null> {
+  return invoke<string | null>("export_thread", {
+    workspaceId,
+    threadId,
+    format,
+    path: null,
+  });
+}
+
+export async function queryAuditLog(filter: {
+  workspaceId?: string | null;
+  threadId?: string | null;
+  decision?: string | null;
+  since?: number | null;
+  until?: number | null;
+  limit?: number | null;
+}): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>("query_audit_log", {
+    workspaceId: filter.workspaceId ?? null,
+    threadId: filter.threadId ?? null,
+    decision: filter.decision ?? null,
+    since: filter.since ?? null,
+    until: filter.until ?? null,
+    limit: filter.limit ?? null,
+  });
+}
+
+export async function getUsageSummary(
+  groupBy: UsageGroup,
+  filter: {
+    workspaceId?: string | null;
+    threadId?: string | null;
+    since?: number | null;
+    until?: number | null;
+  } = {},
+): Promise<UsageSummary> {
+  return invoke<UsageSummary>("get_usage_summary", {
+    groupBy,
+    workspaceId: filter.workspaceId ?? null,
+    threadId: filter.threadId ?? null,
+    since: filter.since ?? null,
+    until: filter.until ?? null,
+    // Date#getTimezoneOffset is minutes *behind* UTC.
+    utcOffsetMinutes: -new Date().getTimezoneOffset(),
+  });
+}
+
+export async function listPendingRequests(
+  workspaceId: string,
+): Promise<PendingRequestInfo[]> {
+  return invoke<PendingRequestInfo[]>("list_pending_requests", { workspaceId });
+}
+
 export async function getGitStatus(workspace_id: string): Promise<{
   branchName: string;
   files: GitFileStatus[];
@@ -167,6 +387,10 @@ export async function getGitHubIssues(
   return invoke("get_github_issues", { workspaceId: workspace_id });
 }
 
+export async function getBackendHealth(workspaceId: string): Promise<BackendHealth> {
+  return invoke<BackendHealth>("backend_health", { workspaceId });
+}
+
 export async function getModelList(workspaceId: string) {
   return invoke<any>("model_list", { workspaceId });
 }
@@ -175,6 +399,18 @@ export async function getAccountRateLimits(workspaceId: string) {
   return invoke<any>("account_rate_limits", { workspaceId });
 }
 
+export async function getRemoteServerStatus(): Promise<RemoteServerStatus> {
+  return invoke<RemoteServerStatus>("get_remote_server_status");
+}
+
+// Generates a token; it only grants access once saved into the app settings.
+export async function generateRemoteToken(
+  label: string,
+  scope: RemoteScope,
+): Promise<RemoteToken> {
+  return invoke<RemoteToken>("generate_remote_token", { label, scope });
+}
+
 export async function getSkillsList(workspaceId: string) {
   return invoke<any>("skills_list", { workspaceId });
 }
@@ -263,14 +499,16 @@ export async function sendOpenCodeMessage(
   options?: {
     providerId?: string;
     modelId?: string;
+    attachments?: Attachment[];
   }
-): Promise<void> {
-  return invoke("send_opencode_message", {
+): Promise<OpenCodePromptHandle> {
+  return invoke<OpenCodePromptHandle>("send_opencode_message", {
     workspaceId,
     sessionId,
     text,
     providerId: options?.providerId,
     modelId: options?.modelId,
+    attachments: options?.attachments ?? null,
   });
 }
 
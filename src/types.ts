@@ -1,11 +1,30 @@
 export type WorkspaceSettings = {
   sidebarCollapsed: boolean;
   sortOrder?: number | null;
+  approvalRules?: ApprovalRule[];
+  sandboxProfiles?: SandboxProfile[];
 };
 
 export type WorkspaceKind = "main" | "worktree";
 
-export type BackendType = "codex" | "opencode";
+import type { BackendType } from "./protocol/BackendType";
+import type { AcpAgentConfig } from "./protocol/AcpAgentConfig";
+import type { ApprovalRule } from "./protocol/ApprovalRule";
+import type { SandboxProfile } from "./protocol/SandboxProfile";
+import type { ModelPrice } from "./protocol/ModelPrice";
+import type { RateLimitWatchSettings } from "./protocol/RateLimitWatchSettings";
+import type { RemoteAccessSettings } from "./protocol/RemoteAccessSettings";
+
+export type {
+  AcpAgentConfig,
+  ApprovalRule,
+  BackendType,
+  ModelPrice,
+  RateLimitWatchSettings,
+  RemoteAccessSettings,
+  SandboxProfile,
+};
+export type { SandboxMode } from "./protocol/SandboxMode";
 
 export type WorktreeInfo = {
   branch: string;
@@ -19,6 +38,7 @@ export type WorkspaceInfo = {
   codex_bin?: string | null;
   opencode_bin?: string | null;
   backend?: BackendType;
+  acpAgent?: AcpAgentConfig | null;
   kind?: WorkspaceKind;
   parentId?: string | null;
   worktree?: WorktreeInfo | null;
@@ -57,18 +77,53 @@ export type ThreadSummary = {
   name: string;
 };
 
-export type ReviewTarget =
-  | { type: "uncommittedChanges" }
-  | { type: "baseBranch"; branch: string }
-  | { type: "commit"; sha: string; title?: string }
-  | { type: "custom"; instructions: string };
-
-export type AccessMode = "read-only" | "current" | "full-access";
+export type { AgentEvent } from "./protocol/AgentEvent";
+export type { BackendHealth } from "./protocol/BackendHealth";
+export type { ReviewTarget } from "./protocol/ReviewTarget";
+export type { ExportFormat } from "./protocol/ExportFormat";
+export type { PromptSchedule } from "./protocol/PromptSchedule";
+export type { QueuedPrompt } from "./protocol/QueuedPrompt";
+export type { Attachment } from "./protocol/Attachment";
+export type { AuditEntry } from "./protocol/AuditEntry";
+export type { SearchHit } from "./protocol/SearchHit";
+export type { ThreadSnapshot } from "./protocol/ThreadSnapshot";
+export type { TranscriptEntry } from "./protocol/TranscriptEntry";
+export type { TranscriptPage } from "./protocol/TranscriptPage";
+export type { TokenCounts } from "./protocol/TokenCounts";
+export type { UsageBucket } from "./protocol/UsageBucket";
+export type { UsageGroup } from "./protocol/UsageGroup";
+export type { UsageSummary } from "./protocol/UsageSummary";
+export type { RateLimitAlert } from "./protocol/RateLimitAlert";
+export type { RemoteScope } from "./protocol/RemoteScope";
+export type { RemoteToken } from "./protocol/RemoteToken";
+export type { RemoteServerStatus } from "./protocol/RemoteServerStatus";
+export type { CommandError } from "./protocol/CommandError";
+export type { RpcError } from "./protocol/RpcError";
+
+// The name of a sandbox profile; "read-only", "current" and "full-access" are built in.
+export type AccessMode = string;
 
 export type AppSettings = {
   codexBin: string | null;
   opencodeBin: string | null;
   defaultAccessMode: AccessMode;
+  resumeThreadsOnReconnect?: boolean;
+  requestTimeouts?: RequestTimeoutSettings;
+  sandboxProfiles?: SandboxProfile[];
+  costTable?: ModelPrice[];
+  rateLimitWatch?: RateLimitWatchSettings;
+  remoteAccess?: RemoteAccessSettings;
+};
+
+export type RequestTimeoutSettings = {
+  defaultSecs: number;
+  overrides: Record<string, number>;
+};
+
+export type PendingRequestInfo = {
+  id: number;
+  method: string;
+  elapsedMs: number;
 };
 
 export type CodexDoctorResult = {
@@ -88,6 +143,7 @@ export type ApprovalRequest = {
   request_id: number;
   method: string;
   params: Record<string, unknown>;
+  backend?: BackendType;
 };
 
 export type GitFileStatus = {
@@ -235,21 +291,11 @@ export type SessionSummary = {
   title: string;
 };
 
-export type OpenCodeMessage = {
-  id: string;
-  sessionId: string;
-  role: "user" | "assistant" | "system";
-  parts: MessagePart[];
-  createdAt?: number | null;
-};
-
-export type MessagePart =
-  | { type: "text"; text: string }
-  | { type: "reasoning"; text: string }
-  | { type: "tool_use"; id: string; name: string; input: unknown }
-  | { type: "tool_result"; tool_use_id: string; content: string }
-  | { type: "patch"; hash: string; files: string[] }
-  | { type: "file"; url: string; mime: string };
+export type { OpenCodeMessage } from "./protocol/OpenCodeMessage";
+export type { OpenCodeMessageDelta } from "./protocol/OpenCodeMessageDelta";
+export type { OpenCodePromptHandle } from "./protocol/OpenCodePromptHandle";
+export type { OpenCodePlanEntry } from "./protocol/OpenCodePlanEntry";
+export type { OpenCodeMessagePart as MessagePart } from "./protocol/OpenCodeMessagePart";
 
 export type ProviderModel = {
   id: string;
@@ -0,0 +1,25 @@
+import type { AppSettings, SandboxProfile, WorkspaceSettings } from "../types";
+
+const BUILTIN_PROFILES: { name: string; label: string }[] = [
+  { name: "read-only", label: "Read only" },
+  { name: "current", label: "On-Request" },
+  { name: "full-access", label: "Full access" },
+];
+
+// Mirrors the backend lookup: workspace profiles shadow app profiles, which
+// shadow the built-in ones.
+export function sandboxProfileOptions(
+  appSettings: AppSettings,
+  workspaceSettings?: WorkspaceSettings | null,
+) {
+  const options = new Map<string, string>();
+  const profiles: SandboxProfile[] = [
+    ...(appSettings.sandboxProfiles ?? []),
+    ...(workspaceSettings?.sandboxProfiles ?? []),
+  ];
+  BUILTIN_PROFILES.forEach((profile) => options.set(profile.name, profile.label));
+  profiles.forEach((profile) =>
+    options.set(profile.name, profile.label?.trim() || profile.name),
+  );
+  return Array.from(options, ([name, label]) => ({ name, label }));
+}